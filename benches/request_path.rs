@@ -0,0 +1,116 @@
+//! Benchmarks for the hot paths of a request: authenticating the caller, validating the
+//! payload and serializing the response.
+//!
+//! Claims extraction fetches the JWKS from a local mock server, the same one used by the
+//! integration tests, so the numbers include the HTTP round trip but not network latency.
+#[path = "../tests/common/auth.rs"]
+#[allow(dead_code)]
+mod auth;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, Request},
+};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use rust_web_app::http::{
+    auth::Claims,
+    person::{NewPerson, Person},
+};
+use time::{macros::date, Date, OffsetDateTime};
+use uuid::Uuid;
+use validator::ValidationError;
+
+fn people(count: usize) -> Vec<Person> {
+    (0..count)
+        .map(|i| Person {
+            id: Uuid::new_v4(),
+            first_name: format!("First {i}"),
+            family_name: format!("Family {i}"),
+            date_of_birth: date!(1990 - 01 - 01),
+            created: OffsetDateTime::now_utc(),
+            last_edited: OffsetDateTime::now_utc(),
+            version: 1,
+        })
+        .collect()
+}
+
+fn list_people_serialization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("list_people serialization");
+
+    for count in [10, 1_000, 10_000] {
+        let people = people(count);
+        group.bench_function(count.to_string(), |b| {
+            b.iter(|| serde_json::to_vec(black_box(&people)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn claims_extraction(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(auth::mock_jwks());
+    let token = auth::token(&["read", "write"]);
+
+    c.bench_function("claims extraction", |b| {
+        b.to_async(&runtime).iter_batched(
+            || {
+                let (parts, _) = Request::get("/api/v1/person")
+                    .header(AUTHORIZATION, format!("Bearer {token}"))
+                    .body(())
+                    .unwrap()
+                    .into_parts();
+                parts
+            },
+            |mut parts| async move {
+                Claims::from_request_parts(&mut parts, &())
+                    .await
+                    .unwrap_or_else(|_| panic!("Token should be valid"))
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn validation(c: &mut Criterion) {
+    fn new_person(
+        first_name: String,
+        family_name: String,
+        date_of_birth: Date,
+    ) -> Result<NewPerson, ValidationError> {
+        Ok(NewPerson {
+            first_name: first_name.try_into()?,
+            family_name: family_name.try_into()?,
+            date_of_birth: date_of_birth.try_into()?,
+        })
+    }
+
+    let mut group = c.benchmark_group("new person validation");
+    group.bench_function("valid", |b| {
+        b.iter(|| {
+            new_person(
+                black_box("John".to_owned()),
+                black_box("Doe".to_owned()),
+                black_box(date!(1990 - 01 - 01)),
+            )
+        })
+    });
+    group.bench_function("invalid", |b| {
+        b.iter(|| {
+            new_person(
+                black_box("J".repeat(65)),
+                black_box(String::new()),
+                black_box(date!(2999 - 01 - 01)),
+            )
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    list_people_serialization,
+    claims_extraction,
+    validation
+);
+criterion_main!(benches);
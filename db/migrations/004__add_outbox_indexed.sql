@@ -0,0 +1,3 @@
+ALTER TABLE outbox ADD COLUMN IF NOT EXISTS indexed TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS outbox_unindexed ON outbox (id) WHERE indexed IS NULL;
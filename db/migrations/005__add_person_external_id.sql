@@ -0,0 +1 @@
+ALTER TABLE person ADD COLUMN IF NOT EXISTS external_id TEXT UNIQUE;
@@ -0,0 +1,3 @@
+ALTER TABLE person ADD COLUMN IF NOT EXISTS user_name TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS person_user_name ON person (lower(user_name));
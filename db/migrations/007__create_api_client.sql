@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS api_client (
+    id BIGSERIAL PRIMARY KEY,
+    uuid UUID UNIQUE NOT NULL DEFAULT gen_random_uuid(),
+    created TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_edited TIMESTAMPTZ NOT NULL DEFAULT now(),
+    name TEXT NOT NULL,
+    scopes TEXT[] NOT NULL,
+    key_prefix TEXT NOT NULL,
+    key_hash BYTEA NOT NULL,
+    expires TIMESTAMPTZ,
+    disabled TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS api_client_name ON api_client (lower(name));
+CREATE UNIQUE INDEX IF NOT EXISTS api_client_key_hash ON api_client (key_hash);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS client_usage (
+    client TEXT NOT NULL,
+    day DATE NOT NULL,
+    requests BIGINT NOT NULL,
+    PRIMARY KEY (client, day)
+);
+
+ALTER TABLE api_client ADD COLUMN IF NOT EXISTS daily_quota BIGINT;
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS person_employment (
+    id BIGSERIAL PRIMARY KEY,
+    uuid UUID UNIQUE NOT NULL DEFAULT gen_random_uuid(),
+    created TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_edited TIMESTAMPTZ NOT NULL DEFAULT now(),
+    person UUID NOT NULL REFERENCES person (uuid) ON DELETE CASCADE,
+    employer TEXT NOT NULL,
+    role TEXT NOT NULL,
+    start_date DATE NOT NULL,
+    end_date DATE,
+    CHECK (end_date IS NULL OR end_date >= start_date)
+);
+
+CREATE INDEX IF NOT EXISTS person_employment_person ON person_employment (person, start_date);
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS person_emergency_contact (
+    id BIGSERIAL PRIMARY KEY,
+    uuid UUID UNIQUE NOT NULL DEFAULT gen_random_uuid(),
+    created TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_edited TIMESTAMPTZ NOT NULL DEFAULT now(),
+    person UUID NOT NULL REFERENCES person (uuid) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    relationship TEXT NOT NULL,
+    phone TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS person_emergency_contact_person ON person_emergency_contact (person, created);
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS person_consent (
+    id BIGSERIAL PRIMARY KEY,
+    uuid UUID UNIQUE NOT NULL DEFAULT gen_random_uuid(),
+    recorded TIMESTAMPTZ NOT NULL DEFAULT now(),
+    person UUID NOT NULL REFERENCES person (uuid) ON DELETE CASCADE,
+    purpose TEXT NOT NULL CHECK (purpose IN ('email', 'sms', 'phone', 'post', 'marketing', 'research')),
+    granted BOOLEAN NOT NULL,
+    channel TEXT NOT NULL CHECK (channel IN ('web', 'email', 'phone', 'paper', 'verbal'))
+);
+
+CREATE INDEX IF NOT EXISTS person_consent_person ON person_consent (person, purpose, recorded);
@@ -0,0 +1 @@
+ALTER TABLE person ADD COLUMN IF NOT EXISTS legal_hold BOOLEAN NOT NULL DEFAULT false;
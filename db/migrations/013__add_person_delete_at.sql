@@ -0,0 +1,3 @@
+ALTER TABLE person ADD COLUMN IF NOT EXISTS delete_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS person_delete_at ON person (delete_at) WHERE delete_at IS NOT NULL;
@@ -0,0 +1,20 @@
+-- People moved out of the person table after a long time without changes. Their employment,
+-- emergency contacts and consent are kept alongside as JSON, to be put back on restore.
+CREATE TABLE IF NOT EXISTS person_archive (
+    id INTEGER PRIMARY KEY,
+    uuid UUID UNIQUE NOT NULL,
+    created TIMESTAMPTZ NOT NULL,
+    last_edited TIMESTAMPTZ NOT NULL,
+    first_name TEXT NOT NULL,
+    family_name TEXT NOT NULL,
+    date_of_birth DATE NOT NULL,
+    address UUID REFERENCES address (uuid),
+    external_id TEXT,
+    user_name TEXT,
+    legal_hold BOOLEAN NOT NULL,
+    related JSONB NOT NULL,
+    archived TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS person_archive_archived ON person_archive (archived);
+CREATE INDEX IF NOT EXISTS person_last_edited ON person (last_edited);
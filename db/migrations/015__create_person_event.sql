@@ -0,0 +1,13 @@
+-- Every change to a person, in order, when event sourcing is enabled. Each event holds the
+-- person as they were left by it, or nothing once they were deleted or archived, so the person
+-- table can be rebuilt from the stream. The stream outlives the person on purpose.
+CREATE TABLE IF NOT EXISTS person_event (
+    id BIGSERIAL PRIMARY KEY,
+    person UUID NOT NULL,
+    version INTEGER NOT NULL,
+    event_type TEXT NOT NULL,
+    data JSONB,
+    actor TEXT NOT NULL,
+    recorded TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (person, version)
+);
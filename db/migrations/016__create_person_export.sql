@@ -0,0 +1,17 @@
+-- Exports of people generated in the background, holding the file until its download expires
+CREATE TABLE IF NOT EXISTS person_export (
+    id BIGSERIAL PRIMARY KEY,
+    uuid UUID UNIQUE NOT NULL DEFAULT gen_random_uuid(),
+    created TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_edited TIMESTAMPTZ NOT NULL DEFAULT now(),
+    requested_by TEXT NOT NULL,
+    format TEXT NOT NULL CHECK (format IN ('csv', 'json', 'xlsx')),
+    filter TEXT,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'completed', 'failed')),
+    row_count BIGINT,
+    content BYTEA,
+    expires TIMESTAMPTZ,
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS person_export_expires ON person_export (expires) WHERE content IS NOT NULL;
@@ -0,0 +1,2 @@
+-- The order people are listed in, so pages after a cursor are found without a sort
+CREATE INDEX IF NOT EXISTS person_created ON person (created, uuid);
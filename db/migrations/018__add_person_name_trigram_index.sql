@@ -0,0 +1,6 @@
+-- Matching names by their trigrams, to search for people allowing for typos when there's no
+-- search index. The extension lives in public so every schema can use it.
+CREATE EXTENSION IF NOT EXISTS pg_trgm WITH SCHEMA public;
+
+CREATE INDEX IF NOT EXISTS person_name_trigram
+    ON person USING gin ((first_name || ' ' || family_name) public.gin_trgm_ops);
@@ -0,0 +1,3 @@
+ALTER TABLE person ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS person_deleted_at ON person (deleted_at) WHERE deleted_at IS NOT NULL;
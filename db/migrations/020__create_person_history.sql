@@ -0,0 +1,14 @@
+-- An audit of every change to a person, whatever way it was made, kept whether or not event
+-- sourcing is enabled. Each entry holds the person as it left them, to compare the next change
+-- against, and the fields it changed. Entries outlive the person on purpose.
+CREATE TABLE IF NOT EXISTS person_history (
+    id BIGSERIAL PRIMARY KEY,
+    person UUID NOT NULL,
+    change_type TEXT NOT NULL,
+    data JSONB,
+    changes JSONB NOT NULL,
+    actor TEXT NOT NULL,
+    changed TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS person_history_person ON person_history (person, id);
@@ -0,0 +1,4 @@
+-- Counts the changes made to a person, for clients to say which version they're changing so
+-- they can't overwrite each other's changes unknowingly. Archived people keep theirs.
+ALTER TABLE person ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE person_archive ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
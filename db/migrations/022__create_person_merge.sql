@@ -0,0 +1,16 @@
+-- An audit of duplicate people merged together, the source being deleted once everything related
+-- to them has been moved to the target. Entries outlive both people on purpose.
+CREATE TABLE IF NOT EXISTS person_merge (
+    id BIGSERIAL PRIMARY KEY,
+    uuid UUID UNIQUE NOT NULL DEFAULT gen_random_uuid(),
+    target UUID NOT NULL,
+    source UUID NOT NULL,
+    address_moved BOOLEAN NOT NULL,
+    employments_moved BIGINT NOT NULL,
+    emergency_contacts_moved BIGINT NOT NULL,
+    consents_moved BIGINT NOT NULL,
+    merged_by TEXT NOT NULL,
+    merged TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS person_merge_target ON person_merge (target, id);
@@ -0,0 +1,25 @@
+-- A person's email addresses and phone numbers, served together as their contact details
+CREATE TABLE IF NOT EXISTS person_email_address (
+    id BIGSERIAL PRIMARY KEY,
+    uuid UUID UNIQUE NOT NULL DEFAULT gen_random_uuid(),
+    created TIMESTAMPTZ NOT NULL DEFAULT now(),
+    person UUID NOT NULL REFERENCES person (uuid) ON DELETE CASCADE,
+    address TEXT NOT NULL,
+    label TEXT
+);
+
+-- the same address can't be recorded twice for one person, whatever its case
+CREATE UNIQUE INDEX IF NOT EXISTS person_email_address_person ON person_email_address (person, lower(address));
+
+CREATE TABLE IF NOT EXISTS person_phone_number (
+    id BIGSERIAL PRIMARY KEY,
+    uuid UUID UNIQUE NOT NULL DEFAULT gen_random_uuid(),
+    created TIMESTAMPTZ NOT NULL DEFAULT now(),
+    person UUID NOT NULL REFERENCES person (uuid) ON DELETE CASCADE,
+    number TEXT NOT NULL,
+    label TEXT
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS person_phone_number_person ON person_phone_number (person, number);
+
+ALTER TABLE person_merge ADD COLUMN IF NOT EXISTS contact_details_moved BIGINT NOT NULL DEFAULT 0;
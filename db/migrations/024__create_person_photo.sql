@@ -0,0 +1,9 @@
+-- A person's profile photo, held alongside them much as export files are
+CREATE TABLE IF NOT EXISTS person_photo (
+    id BIGSERIAL PRIMARY KEY,
+    person UUID UNIQUE NOT NULL REFERENCES person (uuid) ON DELETE CASCADE,
+    content_type TEXT NOT NULL CHECK (content_type IN ('image/jpeg', 'image/png', 'image/webp')),
+    content BYTEA NOT NULL,
+    uploaded_by TEXT NOT NULL,
+    uploaded TIMESTAMPTZ NOT NULL DEFAULT now()
+);
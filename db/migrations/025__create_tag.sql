@@ -0,0 +1,19 @@
+-- Tags grouping people into ad-hoc cohorts, kept once each however many people have them
+CREATE TABLE IF NOT EXISTS tag (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT UNIQUE NOT NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS person_tag (
+    person UUID NOT NULL REFERENCES person (uuid) ON DELETE CASCADE,
+    tag BIGINT NOT NULL REFERENCES tag (id),
+    tagged_by TEXT NOT NULL,
+    tagged TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (person, tag)
+);
+
+-- finds the people with a tag when the person list is filtered by it
+CREATE INDEX IF NOT EXISTS person_tag_tag ON person_tag (tag, person);
+
+ALTER TABLE person_merge ADD COLUMN IF NOT EXISTS tags_moved BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,13 @@
+-- Free-text case notes about a person, written by a client and never changed once written
+CREATE TABLE IF NOT EXISTS person_note (
+    id BIGSERIAL PRIMARY KEY,
+    uuid UUID UNIQUE NOT NULL DEFAULT gen_random_uuid(),
+    person UUID NOT NULL REFERENCES person (uuid) ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    author TEXT NOT NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS person_note_person ON person_note (person, created DESC, id DESC);
+
+ALTER TABLE person_merge ADD COLUMN IF NOT EXISTS notes_moved BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,23 @@
+-- Typed links between two people, each read as the related person being the person's parent,
+-- guardian, spouse, sibling or next of kin. Spouses and siblings are linked both ways at once.
+CREATE TABLE IF NOT EXISTS relationship (
+    id BIGSERIAL PRIMARY KEY,
+    uuid UUID UNIQUE NOT NULL DEFAULT gen_random_uuid(),
+    person UUID NOT NULL REFERENCES person (uuid) ON DELETE CASCADE,
+    related UUID NOT NULL REFERENCES person (uuid) ON DELETE CASCADE,
+    kind TEXT NOT NULL CHECK (kind IN ('parent', 'guardian', 'spouse', 'sibling', 'next_of_kin')),
+    created_by TEXT NOT NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (person <> related)
+);
+
+-- the same link can't be made twice, whichever way round it was made for those going both ways
+CREATE UNIQUE INDEX IF NOT EXISTS relationship_link ON relationship (person, related, kind);
+CREATE UNIQUE INDEX IF NOT EXISTS relationship_mutual
+    ON relationship (LEAST(person, related), GREATEST(person, related), kind)
+    WHERE kind IN ('spouse', 'sibling');
+
+-- finds the links made from the other person's side
+CREATE INDEX IF NOT EXISTS relationship_related ON relationship (related);
+
+ALTER TABLE person_merge ADD COLUMN IF NOT EXISTS relationships_moved BIGINT NOT NULL DEFAULT 0;
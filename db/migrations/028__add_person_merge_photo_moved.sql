@@ -0,0 +1,3 @@
+-- Whether the source's photo went to the target on merging, which happens when the target has
+-- none. Merges recorded before photos were moved keep false.
+ALTER TABLE person_merge ADD COLUMN IF NOT EXISTS photo_moved BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,149 @@
+//! A typed client for the API, enabled with the `client` feature.
+//!
+//! Requests and responses use the same DTOs as the server so the two can't drift apart.
+
+use reqwest::{
+    header::{CONTENT_TYPE, IF_MATCH},
+    RequestBuilder, Response, StatusCode,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+pub use crate::http::address::{Address, NewAddress};
+pub use crate::http::fields::{DateOfBirth, PersonName, Postcode};
+pub use crate::http::person::{NewPerson, Person, UpdatePerson};
+
+use crate::http::{merge_patch::MERGE_PATCH, precondition::entity_tag};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ClientError {
+    #[error("{0}")]
+    Http(#[from] reqwest::Error),
+    #[error("{status}: {message}")]
+    Api { status: StatusCode, message: String },
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    message: String,
+}
+
+/// Client for the person resource of version 1 of the API
+#[derive(Clone, Debug)]
+pub struct PersonClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl PersonClient {
+    /// Creates a client for the service hosted at `base_url`, e.g. `http://localhost:8080`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(reqwest::Client::new(), base_url)
+    }
+
+    /// Creates a client reusing an existing `reqwest::Client` and its connection pool
+    pub fn with_http_client(http: reqwest::Client, base_url: impl Into<String>) -> Self {
+        PersonClient {
+            http,
+            base_url: base_url.into().trim_end_matches('/').to_owned(),
+            token: None,
+        }
+    }
+
+    /// Sends the given JWT as a bearer token on every request
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub async fn create(&self, person: &NewPerson) -> Result<Person, ClientError> {
+        let response = self
+            .send(self.http.post(self.url("/person")).json(person))
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    pub async fn list(&self) -> Result<Vec<Person>, ClientError> {
+        let response = self.send(self.http.get(self.url("/person"))).await?;
+        Ok(response.json().await?)
+    }
+
+    pub async fn get(&self, person_uuid: Uuid) -> Result<Person, ClientError> {
+        let response = self
+            .send(self.http.get(self.url(&format!("/person/{person_uuid}"))))
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    /// Changes the person, failing with `412 Precondition Failed` if they're no longer at the
+    /// `version` the changes were made to
+    pub async fn update(
+        &self,
+        person_uuid: Uuid,
+        version: i32,
+        changes: &UpdatePerson,
+    ) -> Result<Person, ClientError> {
+        let response = self
+            .send(
+                self.http
+                    .patch(self.url(&format!("/person/{person_uuid}")))
+                    .header(IF_MATCH, entity_tag(version))
+                    .header(CONTENT_TYPE, MERGE_PATCH)
+                    .json(changes),
+            )
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    /// Deletes the person, as for [`update`](Self::update)
+    pub async fn delete(&self, person_uuid: Uuid, version: i32) -> Result<(), ClientError> {
+        self.send(
+            self.http
+                .delete(self.url(&format!("/person/{person_uuid}")))
+                .header(IF_MATCH, entity_tag(version)),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn add_address(
+        &self,
+        person_uuid: Uuid,
+        address: &NewAddress,
+    ) -> Result<Address, ClientError> {
+        let response = self
+            .send(
+                self.http
+                    .post(self.url(&format!("/person/{person_uuid}/address")))
+                    .json(address),
+            )
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}{}", self.base_url, crate::http::v1::PREFIX, path)
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, ClientError> {
+        let request = match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+
+        let response = request.send().await?;
+        let status = response.status();
+
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let message = match response.json::<ErrorBody>().await {
+            Ok(body) => body.message,
+            Err(_) => status.canonical_reason().unwrap_or_default().to_owned(),
+        };
+
+        Err(ClientError::Api { status, message })
+    }
+}
@@ -0,0 +1,82 @@
+//! The time as far as the application is concerned, used when checking dates of birth and when
+//! stamping when people and addresses were created or edited.
+//!
+//! The [`Clock`] is part of the app's state, and [`scope`] makes it the current one for each
+//! request, so anything handling the request reads the time with [`now`] or [`today`] rather
+//! than asking the system. Tests can then freeze time with a [`FixedClock`]. Outside of a
+//! request, such as in background jobs, the system clock is used.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use time::{Date, OffsetDateTime};
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> OffsetDateTime;
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The real time, as told by the system
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
+
+/// A clock stopped at the given time
+#[derive(Clone, Copy, Debug)]
+pub struct FixedClock(pub OffsetDateTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> OffsetDateTime {
+        self.0
+    }
+}
+
+tokio::task_local! {
+    static CURRENT: SharedClock;
+}
+
+/// Makes the app's clock the current one while the request is handled
+pub async fn scope(State(clock): State<SharedClock>, request: Request, next: Next) -> Response {
+    CURRENT.scope(clock, next.run(request)).await
+}
+
+/// The current time, by the clock of the request being handled
+pub fn now() -> OffsetDateTime {
+    CURRENT
+        .try_with(|clock| clock.now())
+        .unwrap_or_else(|_| OffsetDateTime::now_utc())
+}
+
+/// Today's date in UTC, by the clock of the request being handled
+pub fn today() -> Date {
+    now().date()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use time::macros::datetime;
+
+    use super::{today, FixedClock, SharedClock, CURRENT};
+
+    #[tokio::test]
+    async fn the_current_clock_is_used_within_its_scope() {
+        let clock: SharedClock = Arc::new(FixedClock(datetime!(2000-01-01 12:00 UTC)));
+
+        let date = CURRENT.scope(clock, async { today() }).await;
+
+        assert_eq!(date, datetime!(2000-01-01 0:00 UTC).date());
+        assert_ne!(today(), date, "Should use the system clock outside a scope");
+    }
+}
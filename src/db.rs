@@ -3,33 +3,92 @@ use std::{
     time::Duration,
 };
 
+use futures::future::try_join_all;
+use rust_web_app::failover;
 use sqlx::{
     migrate::MigrateError,
     postgres::{PgConnectOptions, PgPoolOptions},
     ConnectOptions, PgPool,
 };
-use tracing::log::LevelFilter;
+use tracing::{info, log::LevelFilter};
+
+const MAX_CONNECTIONS: u32 = 20;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("{0}")]
     Configuration(#[from] VarError),
+    #[error("Invalid DATABASE_HOSTS: {0}")]
+    Hosts(String),
     #[error("{0}")]
     Database(#[from] sqlx::Error),
     #[error("{0}")]
     Migrate(#[from] MigrateError),
 }
 
+/// How many prepared statements each connection keeps, from `DATABASE_STATEMENT_CACHE_CAPACITY`
+/// (default 100), with zero disabling the cache
+fn statement_cache_capacity() -> usize {
+    env::var("DATABASE_STATEMENT_CACHE_CAPACITY")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(100)
+}
+
+/// How many connections are kept open even when idle, from `DATABASE_MIN_CONNECTIONS`
+/// (default 0)
+fn min_connections() -> u32 {
+    env::var("DATABASE_MIN_CONNECTIONS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0)
+}
+
+/// How long a query waits for a free connection before giving up, from
+/// `DATABASE_ACQUIRE_TIMEOUT_SECONDS` (default 3)
+fn acquire_timeout() -> Duration {
+    env::var("DATABASE_ACQUIRE_TIMEOUT_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(3))
+}
+
+/// Opens `count` connections at once and checks each with a trivial query, so the first
+/// requests don't wait on connecting, TLS and authentication
+async fn warm_up(pool: &PgPool, count: u32) -> Result<(), sqlx::Error> {
+    let connections = try_join_all((0..count).map(|_| pool.acquire())).await?;
+
+    try_join_all(connections.into_iter().map(|mut connection| async move {
+        sqlx::query("SELECT 1").execute(&mut *connection).await
+    }))
+    .await?;
+
+    info!("Warmed up {count} database connection(s)");
+
+    Ok(())
+}
+
 pub async fn init() -> Result<PgPool, Error> {
     let connect_options = env::var("DATABASE_URL")?
         .parse::<PgConnectOptions>()?
         .log_statements(LevelFilter::Debug)
-        .log_slow_statements(LevelFilter::Warn, Duration::from_millis(100));
+        .log_slow_statements(LevelFilter::Warn, Duration::from_millis(100))
+        .statement_cache_capacity(statement_cache_capacity());
+
+    let hosts = failover::hosts_from_env(connect_options.get_port()).map_err(Error::Hosts)?;
+    let connect_options = failover::connect_options(connect_options, hosts).await?;
 
     let schema_name = env::var("DATABASE_SCHEMA").unwrap_or_else(|_| "public".to_owned());
 
+    let min_connections = min_connections().min(MAX_CONNECTIONS);
+
     let pool = PgPoolOptions::new()
-        .max_connections(20)
+        .max_connections(MAX_CONNECTIONS)
+        .min_connections(min_connections)
+        .acquire_timeout(acquire_timeout())
+        // connections to the old primary are dropped once failed over
+        .before_acquire(|_, meta| Box::pin(async move { Ok(!failover::predates_failover(&meta)) }))
         .connect_with(connect_options.clone())
         .await?;
 
@@ -39,5 +98,8 @@ pub async fn init() -> Result<PgPool, Error> {
 
     sqlx::migrate!("db/migrations").run(&pool).await?;
 
+    // the server only starts listening once this returns
+    warm_up(&pool, min_connections).await?;
+
     Ok(pool)
 }
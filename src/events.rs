@@ -0,0 +1,332 @@
+use std::{env, sync::Arc, sync::OnceLock};
+
+use lapin::{
+    options::{BasicPublishOptions, ConfirmSelectOptions, ExchangeDeclareOptions},
+    types::FieldTable,
+    BasicProperties, Channel, Connection, ConnectionProperties, ExchangeKind,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::OffsetDateTime;
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::http::{consent::ConsentPurpose, person::Person, timestamp};
+
+#[derive(thiserror::Error, Debug)]
+pub enum EventError {
+    #[error("{0}")]
+    Configuration(String),
+    #[error("{0}")]
+    Amqp(#[from] lapin::Error),
+    #[error("{0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Something that happened to a resource, recorded in the outbox alongside the change
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all_fields = "camelCase")]
+pub enum Event {
+    #[serde(rename = "person.created")]
+    PersonCreated { person: Person },
+    #[serde(rename = "person.updated")]
+    PersonUpdated { person: Person },
+    #[serde(rename = "person.deleted")]
+    PersonDeleted { person_id: Uuid },
+    #[serde(rename = "address.added")]
+    AddressAdded { person_id: Uuid, address_id: Uuid },
+    #[serde(rename = "address.removed")]
+    AddressRemoved { address_id: Uuid },
+    #[serde(rename = "employment.added")]
+    EmploymentAdded {
+        person_id: Uuid,
+        employment_id: Uuid,
+    },
+    #[serde(rename = "employment.updated")]
+    EmploymentUpdated {
+        person_id: Uuid,
+        employment_id: Uuid,
+    },
+    #[serde(rename = "employment.removed")]
+    EmploymentRemoved {
+        person_id: Uuid,
+        employment_id: Uuid,
+    },
+    #[serde(rename = "emergency_contact.added")]
+    EmergencyContactAdded { person_id: Uuid, contact_id: Uuid },
+    #[serde(rename = "emergency_contact.updated")]
+    EmergencyContactUpdated { person_id: Uuid, contact_id: Uuid },
+    #[serde(rename = "emergency_contact.removed")]
+    EmergencyContactRemoved { person_id: Uuid, contact_id: Uuid },
+    #[serde(rename = "contact_detail.added")]
+    ContactDetailAdded { person_id: Uuid, detail_id: Uuid },
+    #[serde(rename = "contact_detail.removed")]
+    ContactDetailRemoved { person_id: Uuid, detail_id: Uuid },
+    #[serde(rename = "person.photo_updated")]
+    PhotoUpdated { person_id: Uuid },
+    #[serde(rename = "person.tagged")]
+    PersonTagged { person_id: Uuid, tag: String },
+    #[serde(rename = "person.untagged")]
+    PersonUntagged { person_id: Uuid, tag: String },
+    #[serde(rename = "note.added")]
+    NoteAdded { person_id: Uuid, note_id: Uuid },
+    #[serde(rename = "relationship.added")]
+    RelationshipAdded {
+        person_id: Uuid,
+        relationship_id: Uuid,
+    },
+    #[serde(rename = "relationship.removed")]
+    RelationshipRemoved {
+        person_id: Uuid,
+        relationship_id: Uuid,
+    },
+    #[serde(rename = "person.archived")]
+    PersonArchived { person_id: Uuid },
+    #[serde(rename = "person.restored")]
+    PersonRestored { person: Person },
+    #[serde(rename = "person.merged")]
+    PersonMerged { person_id: Uuid, merged_id: Uuid },
+    #[serde(rename = "person.deletion_scheduled")]
+    DeletionScheduled {
+        person_id: Uuid,
+        #[serde(with = "timestamp")]
+        delete_at: OffsetDateTime,
+    },
+    #[serde(rename = "person.deletion_cancelled")]
+    DeletionCancelled { person_id: Uuid },
+    #[serde(rename = "person.legal_hold_placed")]
+    LegalHoldPlaced { person_id: Uuid, reason: String },
+    #[serde(rename = "person.legal_hold_lifted")]
+    LegalHoldLifted { person_id: Uuid },
+    #[serde(rename = "consent.granted")]
+    ConsentGranted {
+        person_id: Uuid,
+        consent_id: Uuid,
+        purpose: ConsentPurpose,
+    },
+    #[serde(rename = "consent.revoked")]
+    ConsentRevoked {
+        person_id: Uuid,
+        consent_id: Uuid,
+        purpose: ConsentPurpose,
+    },
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::PersonCreated { .. } => "person.created",
+            Event::PersonUpdated { .. } => "person.updated",
+            Event::PersonDeleted { .. } => "person.deleted",
+            Event::AddressAdded { .. } => "address.added",
+            Event::AddressRemoved { .. } => "address.removed",
+            Event::EmploymentAdded { .. } => "employment.added",
+            Event::EmploymentUpdated { .. } => "employment.updated",
+            Event::EmploymentRemoved { .. } => "employment.removed",
+            Event::EmergencyContactAdded { .. } => "emergency_contact.added",
+            Event::EmergencyContactUpdated { .. } => "emergency_contact.updated",
+            Event::EmergencyContactRemoved { .. } => "emergency_contact.removed",
+            Event::ContactDetailAdded { .. } => "contact_detail.added",
+            Event::ContactDetailRemoved { .. } => "contact_detail.removed",
+            Event::PhotoUpdated { .. } => "person.photo_updated",
+            Event::PersonTagged { .. } => "person.tagged",
+            Event::PersonUntagged { .. } => "person.untagged",
+            Event::NoteAdded { .. } => "note.added",
+            Event::RelationshipAdded { .. } => "relationship.added",
+            Event::RelationshipRemoved { .. } => "relationship.removed",
+            Event::PersonArchived { .. } => "person.archived",
+            Event::PersonRestored { .. } => "person.restored",
+            Event::PersonMerged { .. } => "person.merged",
+            Event::DeletionScheduled { .. } => "person.deletion_scheduled",
+            Event::DeletionCancelled { .. } => "person.deletion_cancelled",
+            Event::LegalHoldPlaced { .. } => "person.legal_hold_placed",
+            Event::LegalHoldLifted { .. } => "person.legal_hold_lifted",
+            Event::ConsentGranted { .. } => "consent.granted",
+            Event::ConsentRevoked { .. } => "consent.revoked",
+        }
+    }
+}
+
+const CLOUDEVENTS_CONTENT_TYPE: &str = "application/cloudevents+json";
+
+/// An event wrapped in a [CloudEvents 1.0](https://cloudevents.io) envelope, as published in
+/// structured mode
+#[derive(Debug, Serialize)]
+pub struct CloudEvent<'a> {
+    pub specversion: &'static str,
+    pub id: Uuid,
+    pub source: &'static str,
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: OffsetDateTime,
+    pub datacontenttype: &'static str,
+    /// Provides the `type` and `data` attributes
+    #[serde(flatten)]
+    pub event: &'a Event,
+}
+
+impl<'a> CloudEvent<'a> {
+    /// Wraps an event which has just happened, taking its `source` from `EVENT_SOURCE`
+    /// (default `/rust-web-app`)
+    pub fn new(event: &'a Event) -> Self {
+        static SOURCE: OnceLock<&'static str> = OnceLock::new();
+        let source = SOURCE.get_or_init(|| {
+            env::var("EVENT_SOURCE")
+                .map(|s| &*s.leak())
+                .unwrap_or("/rust-web-app")
+        });
+
+        CloudEvent {
+            specversion: "1.0",
+            id: Uuid::new_v4(),
+            source,
+            time: OffsetDateTime::now_utc(),
+            datacontenttype: "application/json",
+            event,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AmqpPublisher {
+    // kept so the connection lives as long as the channel using it
+    _connection: Connection,
+    channel: Channel,
+    exchange: String,
+    routing_key_prefix: String,
+}
+
+impl AmqpPublisher {
+    /// Connects to `AMQP_URL` and declares the topic exchange `AMQP_EXCHANGE` events are
+    /// published to. Each event is routed with the key `<AMQP_ROUTING_KEY_PREFIX>.<event>`,
+    /// e.g. `rust-web-app.person.created`.
+    pub async fn from_env() -> Result<Self, EventError> {
+        let url = env::var("AMQP_URL").map_err(|_| {
+            EventError::Configuration("AMQP_URL is required for the amqp transport".to_owned())
+        })?;
+        let exchange = env::var("AMQP_EXCHANGE").unwrap_or_else(|_| "rust-web-app".to_owned());
+        let routing_key_prefix =
+            env::var("AMQP_ROUTING_KEY_PREFIX").unwrap_or_else(|_| "rust-web-app".to_owned());
+
+        let connection = Connection::connect(&url, ConnectionProperties::default()).await?;
+        let channel = connection.create_channel().await?;
+
+        channel
+            .confirm_select(ConfirmSelectOptions::default())
+            .await?;
+        channel
+            .exchange_declare(
+                &exchange,
+                ExchangeKind::Topic,
+                ExchangeDeclareOptions {
+                    durable: true,
+                    ..ExchangeDeclareOptions::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+
+        Ok(AmqpPublisher {
+            _connection: connection,
+            channel,
+            exchange,
+            routing_key_prefix,
+        })
+    }
+
+    fn routing_key(&self, event_type: &str) -> String {
+        format!("{}.{}", self.routing_key_prefix, event_type)
+    }
+
+    async fn publish(&self, event_type: &str, payload: &Value) -> Result<(), EventError> {
+        let payload = serde_json::to_vec(payload)?;
+
+        self.channel
+            .basic_publish(
+                &self.exchange,
+                &self.routing_key(event_type),
+                BasicPublishOptions::default(),
+                &payload,
+                BasicProperties::default()
+                    .with_content_type(CLOUDEVENTS_CONTENT_TYPE.into())
+                    .with_delivery_mode(2),
+            )
+            .await?
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Where events are published to, selected with `EVENT_TRANSPORT`
+#[derive(Clone, Debug)]
+pub enum EventPublisher {
+    /// Events are only logged, the default when `EVENT_TRANSPORT` is unset or `none`
+    Disabled,
+    /// Events are published to a RabbitMQ exchange, when `EVENT_TRANSPORT` is `amqp`
+    Amqp(Arc<AmqpPublisher>),
+}
+
+impl EventPublisher {
+    pub async fn from_env() -> Result<Self, EventError> {
+        match env::var("EVENT_TRANSPORT").as_deref() {
+            Err(_) | Ok("none") => Ok(EventPublisher::Disabled),
+            Ok("amqp") => Ok(EventPublisher::Amqp(Arc::new(
+                AmqpPublisher::from_env().await?,
+            ))),
+            Ok(other) => Err(EventError::Configuration(format!(
+                "Unknown EVENT_TRANSPORT '{other}', expected one of: none, amqp"
+            ))),
+        }
+    }
+
+    /// Publishes a serialized `CloudEvent`, as read back from the outbox
+    pub async fn publish(&self, event_type: &str, payload: &Value) -> Result<(), EventError> {
+        match self {
+            EventPublisher::Disabled => {
+                debug!("Event '{event_type}' not published, no transport configured");
+                Ok(())
+            }
+            EventPublisher::Amqp(amqp) => amqp.publish(event_type, payload).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::{CloudEvent, Event};
+
+    #[test]
+    fn events_are_tagged_with_their_name() {
+        let person_id = Uuid::new_v4();
+        let event = Event::PersonDeleted { person_id };
+
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({ "type": "person.deleted", "data": { "personId": person_id } })
+        );
+        assert_eq!(event.name(), "person.deleted");
+    }
+
+    #[test]
+    fn cloud_events_carry_the_required_attributes() {
+        let person_id = Uuid::new_v4();
+        let event = Event::PersonDeleted { person_id };
+        let cloud_event = CloudEvent::new(&event);
+
+        let value = serde_json::to_value(&cloud_event).unwrap();
+
+        assert_eq!(value["specversion"], "1.0");
+        assert_eq!(value["id"], json!(cloud_event.id));
+        assert_eq!(value["source"], "/rust-web-app");
+        assert_eq!(value["type"], "person.deleted");
+        assert_eq!(value["datacontenttype"], "application/json");
+        assert_eq!(value["data"], json!({ "personId": person_id }));
+        assert!(
+            value["time"].as_str().unwrap().ends_with('Z'),
+            "time should be an RFC 3339 UTC timestamp"
+        );
+    }
+}
@@ -0,0 +1,334 @@
+//! Following the primary of a replicated database across failovers.
+//!
+//! `DATABASE_HOSTS` lists the hosts of the cluster as comma separated `host[:port]`s, in place of
+//! the host of `DATABASE_URL`. The pool connects to whichever of them is the primary, found by
+//! asking each in turn whether it's in recovery. [`watch`] checks every
+//! `DATABASE_FAILOVER_CHECK_SECONDS` (default 5) that it still is. Once it stops answering or has
+//! become a standby, the hosts are searched for the new primary, the pool is pointed at it and
+//! every connection opened before is discarded.
+//!
+//! Until the new primary is found, [`retry_reads`] retries reads which failed to reach the
+//! database, and the readiness check reports the database as failing over.
+
+use std::{
+    env, fmt,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use axum::{body::Body, extract::Request, http::Method, middleware::Next, response::Response};
+use serde::Serialize;
+use sqlx::{pool::PoolConnectionMetadata, postgres::PgConnectOptions, ConnectOptions, PgPool};
+use time::OffsetDateTime;
+use tokio::{sync::Notify, time::timeout};
+use tracing::{info, warn};
+
+use crate::http::timestamp;
+
+/// How long a host has to answer whether it's the primary
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long to wait before retrying a read
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// A host of the database cluster
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Host {
+    pub name: String,
+    pub port: u16,
+}
+
+impl Host {
+    fn parse(host: &str, default_port: u16) -> Result<Host, String> {
+        let (name, port) = match host.rsplit_once(':') {
+            Some((name, port)) => {
+                let port = port
+                    .parse()
+                    .map_err(|_| format!("'{port}' is not a valid port of '{host}'"))?;
+                (name, port)
+            }
+            None => (host, default_port),
+        };
+
+        if name.is_empty() {
+            return Err(format!("'{host}' has no host name"));
+        }
+
+        Ok(Host {
+            name: name.to_owned(),
+            port,
+        })
+    }
+
+    fn options(&self, options: &PgConnectOptions) -> PgConnectOptions {
+        options.clone().host(&self.name).port(self.port)
+    }
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.name, self.port)
+    }
+}
+
+/// The hosts in `DATABASE_HOSTS`, those without a port using `default_port`. None means the host
+/// of `DATABASE_URL` is used as it is.
+pub fn hosts_from_env(default_port: u16) -> Result<Vec<Host>, String> {
+    let Ok(hosts) = env::var("DATABASE_HOSTS") else {
+        return Ok(vec![]);
+    };
+
+    hosts
+        .split(',')
+        .map(str::trim)
+        .filter(|host| !host.is_empty())
+        .map(|host| Host::parse(host, default_port))
+        .collect()
+}
+
+/// How often the primary is checked, from `DATABASE_FAILOVER_CHECK_SECONDS` (default 5)
+fn check_interval() -> Duration {
+    env::var("DATABASE_FAILOVER_CHECK_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(5))
+}
+
+/// How long reads are retried for while the database can't be reached, from
+/// `DATABASE_FAILOVER_RETRY_SECONDS` (default 10), with zero disabling retries
+fn retry_window() -> Duration {
+    env::var("DATABASE_FAILOVER_RETRY_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(10))
+}
+
+struct State {
+    hosts: Vec<Host>,
+    primary: Option<Host>,
+    failing_over_since: Option<Instant>,
+    last_failover: Option<(Instant, OffsetDateTime)>,
+    failovers: u64,
+}
+
+static STATE: RwLock<State> = RwLock::new(State {
+    hosts: Vec::new(),
+    primary: None,
+    failing_over_since: None,
+    last_failover: None,
+    failovers: 0,
+});
+
+/// Wakes the watcher to check the primary straight away
+static CHECK_NOW: Notify = Notify::const_new();
+
+/// Marks a response to a request which failed because the database couldn't be reached
+#[derive(Clone, Copy, Debug)]
+pub struct Unreachable;
+
+/// How the database cluster is doing, for the readiness check
+#[serde_with::skip_serializing_none]
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Status {
+    /// The host connected to, when `DATABASE_HOSTS` is set
+    pub primary: Option<String>,
+    pub failing_over: bool,
+    #[serde(with = "timestamp::option")]
+    pub last_failover: Option<OffsetDateTime>,
+    pub failovers: u64,
+}
+
+pub fn status() -> Status {
+    let state = STATE.read().unwrap();
+
+    Status {
+        primary: state.primary.as_ref().map(Host::to_string),
+        failing_over: state.failing_over_since.is_some(),
+        last_failover: state.last_failover.map(|(_, at)| at),
+        failovers: state.failovers,
+    }
+}
+
+/// Whether the host answers as the primary, rather than a standby
+async fn is_primary(options: &PgConnectOptions, host: &Host) -> bool {
+    let check = async {
+        let mut connection = host.options(options).connect().await?;
+        sqlx::query_scalar::<_, bool>("SELECT NOT pg_is_in_recovery()")
+            .fetch_one(&mut connection)
+            .await
+    };
+
+    matches!(timeout(CHECK_TIMEOUT, check).await, Ok(Ok(true)))
+}
+
+async fn find_primary(options: &PgConnectOptions, hosts: &[Host]) -> Option<Host> {
+    for host in hosts {
+        if is_primary(options, host).await {
+            return Some(host.clone());
+        }
+    }
+
+    None
+}
+
+/// The options to connect to the primary of `hosts` with, or `options` as they are when there
+/// are no hosts to choose from
+pub async fn connect_options(
+    options: PgConnectOptions,
+    hosts: Vec<Host>,
+) -> Result<PgConnectOptions, sqlx::Error> {
+    if hosts.is_empty() {
+        return Ok(options);
+    }
+
+    let primary = find_primary(&options, &hosts).await.ok_or_else(|| {
+        sqlx::Error::Configuration("None of the DATABASE_HOSTS is a reachable primary".into())
+    })?;
+
+    info!("Connecting to the database primary '{primary}'");
+
+    let options = primary.options(&options);
+
+    let mut state = STATE.write().unwrap();
+    state.hosts = hosts;
+    state.primary = Some(primary);
+
+    Ok(options)
+}
+
+/// Whether a connection was opened before the last failover, and so is to the old primary
+pub fn predates_failover(meta: &PoolConnectionMetadata) -> bool {
+    STATE
+        .read()
+        .unwrap()
+        .last_failover
+        .is_some_and(|(at, _)| meta.age > at.elapsed())
+}
+
+/// Asks the watcher to check the primary now, as a request couldn't reach it
+pub fn suspect() {
+    CHECK_NOW.notify_one();
+}
+
+/// Checks the primary is still the primary, pointing the pool at the new one after a failover
+pub async fn watch(pool: PgPool) {
+    let (hosts, mut primary) = {
+        let state = STATE.read().unwrap();
+        match &state.primary {
+            Some(primary) => (state.hosts.clone(), primary.clone()),
+            // there's only the one host to connect to
+            None => return,
+        }
+    };
+
+    let interval = check_interval();
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = CHECK_NOW.notified() => {}
+        }
+
+        let options = pool.connect_options();
+
+        if is_primary(&options, &primary).await {
+            continue;
+        }
+
+        let failing_over = STATE.read().unwrap().failing_over_since.is_some();
+        if !failing_over {
+            warn!("The database primary '{primary}' is unavailable, looking for a new one");
+            STATE.write().unwrap().failing_over_since = Some(Instant::now());
+        }
+
+        let Some(found) = find_primary(&options, &hosts).await else {
+            continue;
+        };
+
+        let mut state = STATE.write().unwrap();
+        let since = state.failing_over_since.take();
+
+        if found != primary {
+            pool.set_connect_options(found.options(&options));
+            state.primary = Some(found.clone());
+            state.last_failover = Some((Instant::now(), OffsetDateTime::now_utc()));
+            state.failovers += 1;
+
+            info!(
+                "Failed over from '{primary}' to '{found}' in {:?}",
+                since.map(|since| since.elapsed()).unwrap_or_default()
+            );
+
+            primary = found;
+        }
+    }
+}
+
+/// Retries `GET` and `HEAD` requests which failed because the database couldn't be reached,
+/// for up to `DATABASE_FAILOVER_RETRY_SECONDS`, so reads ride out a failover. Other requests
+/// aren't retried, as they may have changed something before failing.
+pub async fn retry_reads(request: Request, next: Next) -> Response {
+    if !matches!(*request.method(), Method::GET | Method::HEAD) {
+        let response = next.run(request).await;
+        if response.extensions().get::<Unreachable>().is_some() {
+            suspect();
+        }
+        return response;
+    }
+
+    let deadline = Instant::now() + retry_window();
+    let (parts, body) = request.into_parts();
+    let mut response = next
+        .clone()
+        .run(Request::from_parts(parts.clone(), body))
+        .await;
+
+    while response.extensions().get::<Unreachable>().is_some() {
+        suspect();
+
+        if Instant::now() + RETRY_DELAY > deadline {
+            break;
+        }
+        tokio::time::sleep(RETRY_DELAY).await;
+
+        // reads don't have bodies worth sending again
+        response = next
+            .clone()
+            .run(Request::from_parts(parts.clone(), Body::empty()))
+            .await;
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Host;
+
+    #[test]
+    fn hosts_are_given_the_default_port() {
+        assert_eq!(
+            Host::parse("db-1", 5432),
+            Ok(Host {
+                name: "db-1".to_owned(),
+                port: 5432
+            })
+        );
+        assert_eq!(
+            Host::parse("db-2:6432", 5432),
+            Ok(Host {
+                name: "db-2".to_owned(),
+                port: 6432
+            })
+        );
+    }
+
+    #[test]
+    fn hosts_with_an_invalid_port_are_rejected() {
+        assert!(Host::parse("db-1:primary", 5432).is_err());
+        assert!(Host::parse(":5432", 5432).is_err());
+    }
+}
@@ -1,28 +1,82 @@
+use async_graphql::{InputObject, SimpleObject};
 use axum::{
     extract::Path,
     routing::{delete, post},
-    Extension, Json, Router,
+    Router,
 };
-use hyper::StatusCode;
-use serde::Deserialize;
-use sqlx::PgPool;
-use tracing::info;
+use hyper::{
+    header::{HeaderName, LOCATION},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
 use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
 
-use super::{auth::WriteUser, error::ApiError};
+use super::{
+    auth::WriteUser,
+    content::{Format, Link, Negotiated, Resource, ValidatedPayload},
+    error::ApiError,
+    fields::{self, Postcode},
+    response::Deleted,
+    timestamp, v1,
+};
+use crate::service::address::AddressService;
 
-#[derive(Debug, Validate, Deserialize, ToSchema)]
+// the snake_case alias keeps clients written before the fields were camelCase working
+#[derive(Debug, Validate, Serialize, Deserialize, ToSchema, InputObject)]
+#[serde(rename_all = "camelCase")]
 pub struct NewAddress {
     #[validate(length(min = 1, max = 64))]
-    building: String,
+    #[schema(min_length = 1, max_length = 64)]
+    #[serde(deserialize_with = "fields::normalized")]
+    #[graphql(process_with = "fields::normalize_input")]
+    pub building: String,
     #[validate(length(min = 1, max = 64))]
-    street: Option<String>,
+    #[schema(min_length = 1, max_length = 64)]
+    #[serde(default, deserialize_with = "fields::normalized")]
+    #[graphql(process_with = "fields::normalize_input")]
+    pub street: Option<String>,
     #[validate(length(min = 1, max = 64))]
-    town_or_city: Option<String>,
-    #[validate(length(min = 1, max = 8))]
-    postcode: String,
+    #[schema(min_length = 1, max_length = 64)]
+    #[serde(
+        default,
+        alias = "town_or_city",
+        deserialize_with = "fields::normalized"
+    )]
+    #[graphql(process_with = "fields::normalize_input")]
+    pub town_or_city: Option<String>,
+    #[schema(value_type = String, min_length = 1, max_length = 8)]
+    pub postcode: Postcode,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, SimpleObject)]
+#[serde(rename_all = "camelCase")]
+pub struct Address {
+    pub id: Uuid,
+    pub building: String,
+    pub street: Option<String>,
+    pub town_or_city: Option<String>,
+    pub postcode: String,
+    /// When the address was created, as an RFC 3339 timestamp in UTC, or milliseconds since the
+    /// Unix epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub created: OffsetDateTime,
+    /// When the address was last edited, in the same format as `created`
+    #[serde(with = "timestamp")]
+    pub last_edited: OffsetDateTime,
+}
+
+impl Resource for Address {
+    const ELEMENT: &'static str = "address";
+    const COLLECTION: &'static str = "addresses";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        let address = format!("{}/address/{}", v1::PREFIX, self.id);
+
+        vec![("self", Link::to(&address)), ("delete", Link::to(&address))]
+    }
 }
 
 /// Create an address for a person
@@ -37,7 +91,9 @@ pub struct NewAddress {
         ("person_uuid" = Uuid, Path, description = "The UUID of the person to create an address for")
     ),
     responses(
-        (status = 201, description = "Address created successfully"),
+        (status = 201, description = "Address created successfully", body = Address,
+            headers(("location" = String, description = "The URL of the person's address"))),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
         (status = 404, description = "Person not found", body = ErrorResponse),
     ),
     security(
@@ -46,46 +102,20 @@ pub struct NewAddress {
 )]
 pub async fn add_address(
     user: WriteUser,
-    db: Extension<PgPool>,
+    addresses: AddressService,
+    format: Format,
     Path(person_uuid): Path<Uuid>,
-    Json(request): Json<NewAddress>,
-) -> Result<StatusCode, ApiError> {
-    request.validate()?;
+    ValidatedPayload(request): ValidatedPayload<NewAddress>,
+) -> Result<(StatusCode, [(HeaderName, String); 1], Negotiated<Address>), ApiError> {
+    let address = addresses.add(&user.username, person_uuid, &request).await?;
 
-    sqlx::query!(
-        r#"
-            WITH new_address AS (
-                INSERT INTO address(building, street, town_or_city, postcode)
-                VALUES ($1, $2, $3, $4)
-                RETURNING uuid, created, last_edited, building, street, town_or_city, postcode
-            )
-            UPDATE person p
-            SET address = new_address.uuid, last_edited = now()
-            FROM new_address
-            WHERE p.uuid = $5
-            RETURNING p.uuid as id;
-        "#,
-        request.building,
-        request.street,
-        request.town_or_city,
-        request.postcode,
-        person_uuid,
-    )
-    .fetch_one(&*db)
-    .await
-    .map_err(|e| match e {
-        sqlx::Error::RowNotFound => {
-            ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}"))
-        }
-        _ => ApiError::DatabaseError(e),
-    })?;
-
-    info!(
-        "Client '{}' created an address for the person '{}'",
-        user.username, person_uuid
-    );
+    let location = format!("{}/person/{person_uuid}/address", v1::PREFIX);
 
-    Ok(StatusCode::CREATED)
+    Ok((
+        StatusCode::CREATED,
+        [(LOCATION, location)],
+        Negotiated(format, address),
+    ))
 }
 
 /// Remove an address
@@ -99,7 +129,7 @@ pub async fn add_address(
         ("address_uuid" = Uuid, Path, description = "The UUID of the address to remove")
     ),
     responses(
-        (status = 200, description = "Address deleted successfully"),
+        (status = 204, description = "Address deleted successfully"),
         (status = 404, description = "Address not found", body = ErrorResponse),
     ),
     security(
@@ -108,55 +138,52 @@ pub async fn add_address(
 )]
 pub async fn remove_address(
     user: WriteUser,
-    db: Extension<PgPool>,
+    addresses: AddressService,
     Path(address_uuid): Path<Uuid>,
-) -> Result<(), ApiError> {
-    let mut tx = db.begin().await?;
+) -> Result<Deleted, ApiError> {
+    addresses.remove(&user.username, address_uuid).await?;
 
-    sqlx::query!(
-        r#"
-            UPDATE person SET address = NULL WHERE address = $1
-            RETURNING id;
-        "#,
-        address_uuid
-    )
-    .fetch_all(&mut *tx)
-    .await
-    .map_err(|e| match e {
-        sqlx::Error::RowNotFound => {
-            ApiError::NotFound(format!("Person not found with the address: {address_uuid}"))
-        }
-        _ => ApiError::DatabaseError(e),
-    })?;
+    Ok(Deleted)
+}
 
-    sqlx::query!(
-        r#"
-            DELETE FROM address WHERE uuid = $1
-            RETURNING id;
-        "#,
-        address_uuid
+/// Remove a person's address
+///
+/// Removes the person's current address as removing it by its own UUID does, for callers who
+/// only know the person.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    delete,
+    tag = "address",
+    path = "/person/{person_uuid}/address",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person whose address to remove")
+    ),
+    responses(
+        (status = 204, description = "Address deleted successfully"),
+        (status = 404, description = "Person not found, or they have no address", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
     )
-    .fetch_one(&mut *tx)
-    .await
-    .map_err(|e| match e {
-        sqlx::Error::RowNotFound => {
-            ApiError::NotFound(format!("Address not found for the UUID: {address_uuid}"))
-        }
-        _ => ApiError::DatabaseError(e),
-    })?;
-
-    tx.commit().await?;
-
-    info!(
-        "Client '{}' deleted the address '{}'",
-        user.username, address_uuid
-    );
+)]
+pub async fn remove_person_address(
+    user: WriteUser,
+    addresses: AddressService,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Deleted, ApiError> {
+    addresses
+        .remove_for_person(&user.username, person_uuid)
+        .await?;
 
-    Ok(())
+    Ok(Deleted)
 }
 
 pub fn router() -> Router {
     Router::new()
-        .route("/person/:person_uuid/address", post(add_address))
+        .route(
+            "/person/:person_uuid/address",
+            post(add_address).delete(remove_person_address),
+        )
         .route("/address/:address_uuid", delete(remove_address))
 }
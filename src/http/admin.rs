@@ -0,0 +1,123 @@
+use axum::{routing::get, Extension, Json, Router};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::{
+    auth::AdminUser, error::ApiError, limit::LimitQuery, query::ValidatedQuery, timestamp,
+};
+use crate::scheduler::{ScheduledTaskStatus, Scheduler};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobSummary {
+    pub id: Uuid,
+    pub kind: String,
+    pub status: JobStatus,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    #[serde(with = "timestamp")]
+    pub run_at: OffsetDateTime,
+    pub last_error: Option<String>,
+    /// When the job was created, as an RFC 3339 timestamp in UTC, or milliseconds since the
+    /// Unix epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub created: OffsetDateTime,
+    /// When the job was last edited, in the same format as `created`
+    #[serde(with = "timestamp")]
+    pub last_edited: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct JobFilter {
+    /// Only return jobs with this status
+    status: Option<JobStatus>,
+}
+
+/// List background jobs
+///
+/// Requires the scope `admin`
+#[utoipa::path(
+    get,
+    tag = "admin",
+    path = "/admin/jobs",
+    params(JobFilter, LimitQuery),
+    responses(
+        (status = 200, description = "The most recent background jobs, newest first", body = [JobSummary]),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+async fn list_jobs(
+    user: AdminUser,
+    db: Extension<PgPool>,
+    ValidatedQuery(filter): ValidatedQuery<JobFilter>,
+    ValidatedQuery(page): ValidatedQuery<LimitQuery>,
+) -> Result<Json<Vec<JobSummary>>, ApiError> {
+    let jobs = sqlx::query_as!(
+        JobSummary,
+        r#"
+            SELECT uuid AS id, kind, status AS "status: JobStatus", attempts, max_attempts,
+                run_at, last_error, created, last_edited
+            FROM job
+            WHERE $1::TEXT IS NULL OR status = $1
+            ORDER BY created DESC
+            LIMIT $2;
+        "#,
+        filter.status as Option<JobStatus>,
+        page.limit().get()
+    )
+    .fetch_all(&*db)
+    .await?;
+
+    info!("Client '{}' retrieved {} job(s)", user.username, jobs.len());
+
+    Ok(Json(jobs))
+}
+
+/// List scheduled tasks
+///
+/// Reports when each periodic task enabled on this instance last ran and will next run.
+///
+/// Requires the scope `admin`
+#[utoipa::path(
+    get,
+    tag = "admin",
+    path = "/admin/schedule",
+    responses(
+        (status = 200, description = "The tasks scheduled on this instance", body = [ScheduledTaskStatus]),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+async fn list_scheduled_tasks(
+    user: AdminUser,
+    scheduler: Extension<Scheduler>,
+) -> Json<Vec<ScheduledTaskStatus>> {
+    info!("Client '{}' retrieved the schedule", user.username);
+
+    Json(scheduler.status())
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/admin/jobs", get(list_jobs))
+        .route("/admin/schedule", get(list_scheduled_tasks))
+}
@@ -0,0 +1,378 @@
+//! A small admin interface for operators to browse, search and edit people and their addresses
+//! from a browser, served under `/admin` outside the versioned API.
+//!
+//! Operators sign in with an API key granted the `admin` scope, which is kept in an `HttpOnly`,
+//! `SameSite=Strict` cookie for the rest of the session. Browsers only send a strict cookie with
+//! requests from the interface's own pages, so other sites can't submit its forms on an
+//! operator's behalf. Pages are rendered from the Handlebars templates in `templates/admin`,
+//! which escape everything they're given.
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, Query},
+    http::{
+        header::{COOKIE, SET_COOKIE},
+        request::Parts,
+        HeaderMap, StatusCode,
+    },
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Extension, Form, Router,
+};
+use handlebars::Handlebars;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Map, Value};
+use sqlx::PgPool;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use super::{
+    address::NewAddress,
+    api_client,
+    error::{ApiError, Context},
+    filter::Filter,
+    limit::Limit,
+    person::UpdatePerson,
+    precondition::IfMatch,
+};
+use crate::service::{address::AddressService, person::PersonService};
+
+/// The cookie holding the signed in operator's key
+const SESSION_COOKIE: &str = "admin_session";
+
+/// How many people are listed on a page
+const PAGE_SIZE: i64 = 50;
+
+fn templates() -> &'static Handlebars<'static> {
+    static TEMPLATES: OnceLock<Handlebars<'static>> = OnceLock::new();
+
+    TEMPLATES.get_or_init(|| {
+        let mut templates = Handlebars::new();
+
+        for (name, template) in [
+            ("layout", include_str!("../../templates/admin/layout.hbs")),
+            ("login", include_str!("../../templates/admin/login.hbs")),
+            ("people", include_str!("../../templates/admin/people.hbs")),
+            ("person", include_str!("../../templates/admin/person.hbs")),
+            ("error", include_str!("../../templates/admin/error.hbs")),
+        ] {
+            templates
+                .register_template_string(name, template)
+                .expect("Invalid admin template");
+        }
+
+        templates
+    })
+}
+
+fn render(template: &str, data: Value) -> Response {
+    match templates().render(template, &data) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            error!("Failed to render the admin page '{template}': {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// A failure shown to the operator as a page, rather than the JSON the API responds with
+struct PageError(ApiError);
+
+impl From<ApiError> for PageError {
+    fn from(e: ApiError) -> Self {
+        PageError(e)
+    }
+}
+
+impl IntoResponse for PageError {
+    fn into_response(self) -> Response {
+        self.0.log();
+
+        let mut response = render("error", json!({ "error": self.0.to_string() }));
+        *response.status_mut() = self.0.status_code();
+
+        response
+    }
+}
+
+/// Reads a form as `T`, leaving out the fields left blank
+fn from_form<T: DeserializeOwned>(form: HashMap<String, String>) -> Result<T, ApiError> {
+    let fields: Map<String, Value> = form
+        .into_iter()
+        .filter(|(_, value)| !value.trim().is_empty())
+        .map(|(name, value)| (name, Value::String(value)))
+        .collect();
+
+    serde_json::from_value(Value::Object(fields))
+        .map_err(|e| ApiError::UnreadableBody(e.to_string()))
+}
+
+/// Percent encodes a query parameter's value
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn session_key(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|cookie| cookie.to_str().ok())
+        .flat_map(|cookie| cookie.split(';'))
+        .find_map(|pair| pair.trim().strip_prefix(SESSION_COOKIE)?.strip_prefix('='))
+        .map(str::to_owned)
+}
+
+/// The name of the client with the key, if it's valid and has the `admin` scope
+async fn operator_with_key(db: &PgPool, key: &str) -> Result<Option<String>, ApiError> {
+    let holder = api_client::authenticate(db, key)
+        .await
+        .context("Failed to look up an API key")?;
+
+    Ok(holder
+        .filter(|holder| holder.scopes.iter().any(|scope| scope == "admin"))
+        .map(|holder| holder.name))
+}
+
+/// The operator signed in to the admin interface, sent to sign in when there's none
+pub struct Operator {
+    pub username: String,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Operator
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let sign_in = || Redirect::to("/admin/login").into_response();
+
+        let key = session_key(&parts.headers).ok_or_else(sign_in)?;
+
+        let Extension(db) = Extension::<PgPool>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| {
+                PageError(ApiError::Unavailable(
+                    "The database is not available".to_owned(),
+                ))
+                .into_response()
+            })?;
+
+        match operator_with_key(&db, &key).await {
+            Ok(Some(username)) => Ok(Operator { username }),
+            Ok(None) => Err(sign_in()),
+            Err(e) => Err(PageError(e).into_response()),
+        }
+    }
+}
+
+async fn home() -> Redirect {
+    Redirect::to("/admin/people")
+}
+
+async fn login_page() -> Response {
+    render("login", json!({}))
+}
+
+#[derive(Deserialize)]
+struct Login {
+    key: String,
+}
+
+async fn login(
+    Extension(db): Extension<PgPool>,
+    Form(login): Form<Login>,
+) -> Result<Response, PageError> {
+    let Some(username) = operator_with_key(&db, &login.key).await? else {
+        let mut response = render(
+            "login",
+            json!({ "error": "The key isn't valid or doesn't have the admin scope" }),
+        );
+        *response.status_mut() = StatusCode::UNAUTHORIZED;
+        return Ok(response);
+    };
+
+    info!("Client '{username}' signed in to the admin interface");
+
+    let cookie = format!(
+        "{SESSION_COOKIE}={}; Path=/admin; HttpOnly; Secure; SameSite=Strict",
+        login.key
+    );
+
+    Ok(([(SET_COOKIE, cookie)], Redirect::to("/admin/people")).into_response())
+}
+
+async fn logout() -> impl IntoResponse {
+    let cookie =
+        format!("{SESSION_COOKIE}=; Path=/admin; HttpOnly; Secure; SameSite=Strict; Max-Age=0");
+
+    ([(SET_COOKIE, cookie)], Redirect::to("/admin/login"))
+}
+
+#[derive(Deserialize)]
+struct PeopleQuery {
+    #[serde(default)]
+    q: String,
+    page: Option<i64>,
+}
+
+async fn people_page(
+    operator: Operator,
+    people: PersonService,
+    Query(query): Query<PeopleQuery>,
+) -> Result<Response, PageError> {
+    let search = query.q.trim();
+    let page = query.page.unwrap_or(1).max(1);
+
+    // either name containing the search, ignoring case
+    let filter = (!search.is_empty())
+        .then(|| {
+            let quoted = search.replace('\\', "\\\\").replace('"', "\\\"");
+            Filter::parse(&format!(
+                "firstName==\"*{quoted}*\",familyName==\"*{quoted}*\""
+            ))
+        })
+        .transpose()?;
+
+    let total = people.count(filter.as_ref()).await?;
+    let found = people
+        .list_from(
+            filter.as_ref(),
+            &[],
+            (page - 1).saturating_mul(PAGE_SIZE),
+            Limit::new(Some(PAGE_SIZE)),
+        )
+        .await?;
+
+    let link = |page: i64| format!("/admin/people?q={}&page={page}", encode(search));
+
+    Ok(render(
+        "people",
+        json!({
+            "operator": operator.username,
+            "q": search,
+            "total": total,
+            "people": found,
+            "page": page,
+            "previous": (page > 1).then(|| link(page - 1)),
+            "next": (page.saturating_mul(PAGE_SIZE) < total).then(|| link(page + 1)),
+        }),
+    ))
+}
+
+async fn person_page(
+    operator: Operator,
+    people: PersonService,
+    addresses: AddressService,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Response, PageError> {
+    let person = people.find(person_uuid).await?;
+    let address = addresses.find_for_person(person_uuid).await?;
+
+    Ok(render(
+        "person",
+        json!({
+            "operator": operator.username,
+            "person": person,
+            "address": address,
+        }),
+    ))
+}
+
+fn back_to(person_uuid: Uuid) -> Redirect {
+    Redirect::to(&format!("/admin/people/{person_uuid}"))
+}
+
+async fn update_person(
+    operator: Operator,
+    people: PersonService,
+    Path(person_uuid): Path<Uuid>,
+    Form(mut form): Form<HashMap<String, String>>,
+) -> Result<Redirect, PageError> {
+    // the version the form was rendered with, so changes made since aren't overwritten
+    let version = form
+        .remove("version")
+        .and_then(|version| version.parse().ok())
+        .ok_or_else(|| {
+            ApiError::PreconditionRequired(
+                "The version of the person being changed must be given".to_owned(),
+            )
+        })?;
+
+    let changes: UpdatePerson = from_form(form)?;
+    people
+        .update(
+            &operator.username,
+            person_uuid,
+            changes,
+            &IfMatch::Versions(vec![version]),
+        )
+        .await?;
+
+    Ok(back_to(person_uuid))
+}
+
+async fn replace_address(
+    operator: Operator,
+    addresses: AddressService,
+    Path(person_uuid): Path<Uuid>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Result<Redirect, PageError> {
+    let address: NewAddress = from_form(form)?;
+    addresses
+        .add(&operator.username, person_uuid, &address)
+        .await?;
+
+    Ok(back_to(person_uuid))
+}
+
+async fn remove_address(
+    operator: Operator,
+    addresses: AddressService,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Redirect, PageError> {
+    addresses
+        .remove_for_person(&operator.username, person_uuid)
+        .await?;
+
+    Ok(back_to(person_uuid))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/admin", get(home))
+        .route("/admin/login", get(login_page).post(login))
+        .route("/admin/logout", post(logout))
+        .route("/admin/people", get(people_page))
+        .route(
+            "/admin/people/:person_uuid",
+            get(person_page).post(update_person),
+        )
+        .route("/admin/people/:person_uuid/address", post(replace_address))
+        .route(
+            "/admin/people/:person_uuid/address/delete",
+            post(remove_address),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::encode;
+
+    #[test]
+    fn query_values_are_percent_encoded() {
+        assert_eq!(encode("O'Brien & co"), "O%27Brien%20%26%20co");
+        assert_eq!(encode("Zoë"), "Zo%C3%AB");
+    }
+}
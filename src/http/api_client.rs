@@ -0,0 +1,328 @@
+//! Clients calling the API with a key rather than a token from the auth server, such as batch
+//! jobs and integrations without a user behind them.
+//!
+//! Keys are generated when a client is created and only ever shown then. Just a SHA-256 hash
+//! of each is kept, which is enough for keys of 256 random bits, along with the first few
+//! characters so a key can be recognised in the list of clients. A key is sent in the
+//! `X-Api-Key` header and grants the client's scopes until it expires or is disabled.
+
+use axum::{
+    extract::Path,
+    http::HeaderName,
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use hyper::StatusCode;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::{
+    auth::AdminUser,
+    content::ValidatedPayload,
+    error::{ApiError, Context},
+    fields, timestamp,
+};
+use crate::clock;
+
+/// The header clients send their key in
+pub const API_KEY: HeaderName = HeaderName::from_static("x-api-key");
+
+/// Marks a string as one of our keys, e.g. to secret scanners
+const KEY_PREFIX: &str = "rwa_";
+
+/// How many characters of a key, after [`KEY_PREFIX`], are kept to recognise it by
+const RECOGNISABLE_LENGTH: usize = 8;
+
+/// The scopes a client may be granted
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Write,
+    Admin,
+}
+
+impl Scope {
+    fn as_str(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiClient {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// The start of the client's key, to recognise it by
+    pub key_prefix: String,
+    /// When the key stops working, if ever
+    #[serde(with = "timestamp::option")]
+    pub expires: Option<OffsetDateTime>,
+    /// How many requests the client may make a day (UTC), if limited
+    pub daily_quota: Option<i64>,
+    /// When the client was disabled, if it has been
+    #[serde(with = "timestamp::option")]
+    pub disabled: Option<OffsetDateTime>,
+    /// When the client was created, as an RFC 3339 timestamp in UTC, or milliseconds since the
+    /// Unix epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub created: OffsetDateTime,
+    /// When the client was last edited, in the same format as `created`
+    #[serde(with = "timestamp")]
+    pub last_edited: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct NewApiClient {
+    #[validate(length(min = 1, max = 64))]
+    #[schema(min_length = 1, max_length = 64)]
+    pub name: String,
+    #[validate(length(min = 1))]
+    #[schema(min_items = 1)]
+    pub scopes: Vec<Scope>,
+    /// When the key should stop working, if ever
+    #[serde(default, with = "timestamp::option")]
+    #[validate(custom(function = "fields::in_the_future"))]
+    pub expires: Option<OffsetDateTime>,
+    /// How many requests the client may make a day (UTC), if limited
+    #[validate(range(min = 1))]
+    #[schema(minimum = 1)]
+    pub daily_quota: Option<i64>,
+}
+
+/// A newly created client, along with its key
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreatedApiClient {
+    #[serde(flatten)]
+    pub client: ApiClient,
+    /// The key to call the API with, which can't be retrieved again
+    pub key: String,
+}
+
+/// A new random key
+fn generate_key() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!("{KEY_PREFIX}{hex}")
+}
+
+fn hash(key: &str) -> Vec<u8> {
+    Sha256::digest(key.as_bytes()).to_vec()
+}
+
+/// The client holding a valid key
+pub(crate) struct KeyHolder {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub daily_quota: Option<i64>,
+}
+
+/// The client with the given key, if it's still valid
+pub(crate) async fn authenticate(db: &PgPool, key: &str) -> Result<Option<KeyHolder>, sqlx::Error> {
+    let client = sqlx::query!(
+        r#"
+            SELECT name, scopes AS "scopes: Vec<Scope>", daily_quota FROM api_client
+            WHERE key_hash = $1 AND disabled IS NULL AND (expires IS NULL OR expires > $2);
+        "#,
+        hash(key),
+        clock::now()
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(client.map(|client| KeyHolder {
+        name: client.name,
+        scopes: client
+            .scopes
+            .iter()
+            .map(|scope| scope.as_str().to_owned())
+            .collect(),
+        daily_quota: client.daily_quota,
+    }))
+}
+
+/// Create an API client
+///
+/// Creates a client and generates its key. The key is only returned here, so should be kept
+/// safe straight away.
+///
+/// Requires the scope `admin`
+#[utoipa::path(
+    post,
+    tag = "admin",
+    path = "/admin/clients",
+    request_body = NewApiClient,
+    responses(
+        (status = 201, description = "The client and its key", body = CreatedApiClient),
+        (status = 400, description = "Invalid client", body = ErrorResponse),
+        (status = 409, description = "A client with the same name already exists", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+async fn create_client(
+    user: AdminUser,
+    db: Extension<PgPool>,
+    ValidatedPayload(request): ValidatedPayload<NewApiClient>,
+) -> Result<(StatusCode, Json<CreatedApiClient>), ApiError> {
+    let key = generate_key();
+    let key_prefix = &key[..KEY_PREFIX.len() + RECOGNISABLE_LENGTH];
+
+    let client = sqlx::query_as!(
+        ApiClient,
+        r#"
+            INSERT INTO api_client (name, scopes, key_prefix, key_hash, expires, daily_quota, created, last_edited)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+            RETURNING uuid AS id, name, scopes AS "scopes: Vec<Scope>", key_prefix, expires, daily_quota, disabled, created, last_edited;
+        "#,
+        request.name,
+        &request.scopes as &[Scope],
+        key_prefix,
+        hash(&key),
+        request.expires,
+        request.daily_quota,
+        clock::now()
+    )
+    .fetch_one(&*db)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(dbe) if dbe.is_unique_violation() => ApiError::Conflict(format!(
+            "An API client named '{}' already exists",
+            request.name
+        )),
+        _ => ApiError::from(e).context("Failed to insert an API client"),
+    })?;
+
+    info!(
+        "Client '{}' created API client '{}' with the scopes {:?}",
+        user.username, client.id, client.scopes
+    );
+
+    Ok((StatusCode::CREATED, Json(CreatedApiClient { client, key })))
+}
+
+/// List API clients
+///
+/// Requires the scope `admin`
+#[utoipa::path(
+    get,
+    tag = "admin",
+    path = "/admin/clients",
+    responses(
+        (status = 200, description = "Every API client, oldest first", body = [ApiClient]),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+async fn list_clients(
+    user: AdminUser,
+    db: Extension<PgPool>,
+) -> Result<Json<Vec<ApiClient>>, ApiError> {
+    let clients = sqlx::query_as!(
+        ApiClient,
+        r#"
+            SELECT uuid AS id, name, scopes AS "scopes: Vec<Scope>", key_prefix, expires, daily_quota, disabled, created, last_edited
+            FROM api_client
+            ORDER BY created, uuid;
+        "#
+    )
+    .fetch_all(&*db)
+    .await
+    .context("Failed to list API clients")?;
+
+    info!(
+        "Client '{}' retrieved {} API client(s)",
+        user.username,
+        clients.len()
+    );
+
+    Ok(Json(clients))
+}
+
+/// Disable an API client
+///
+/// Its key stops working straight away. Disabling a client again leaves it as it was.
+///
+/// Requires the scope `admin`
+#[utoipa::path(
+    post,
+    tag = "admin",
+    path = "/admin/clients/{client_uuid}/disable",
+    params(
+        ("client_uuid" = Uuid, Path, description = "The UUID of the API client")
+    ),
+    responses(
+        (status = 200, description = "The disabled client", body = ApiClient),
+        (status = 404, description = "API client not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+async fn disable_client(
+    user: AdminUser,
+    db: Extension<PgPool>,
+    Path(client_uuid): Path<Uuid>,
+) -> Result<Json<ApiClient>, ApiError> {
+    let client = sqlx::query_as!(
+        ApiClient,
+        r#"
+            UPDATE api_client
+            SET disabled = COALESCE(disabled, $1), last_edited = CASE WHEN disabled IS NULL THEN $1 ELSE last_edited END
+            WHERE uuid = $2
+            RETURNING uuid AS id, name, scopes AS "scopes: Vec<Scope>", key_prefix, expires, daily_quota, disabled, created, last_edited;
+        "#,
+        clock::now(),
+        client_uuid
+    )
+    .fetch_optional(&*db)
+    .await
+    .with_context(|| format!("Failed to disable API client '{client_uuid}'"))?
+    .ok_or_else(|| ApiError::NotFound(format!("API client not found for the UUID: {client_uuid}")))?;
+
+    info!(
+        "Client '{}' disabled API client '{}'",
+        user.username, client_uuid
+    );
+
+    Ok(Json(client))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/admin/clients", get(list_clients).post(create_client))
+        .route("/admin/clients/:client_uuid/disable", post(disable_client))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_key, hash, KEY_PREFIX};
+
+    #[test]
+    fn keys_are_random_and_recognisable() {
+        let key = generate_key();
+
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_eq!(key.len(), KEY_PREFIX.len() + 64);
+        assert_ne!(key, generate_key());
+        assert_ne!(hash(&key), hash(&generate_key()));
+    }
+}
@@ -0,0 +1,222 @@
+use axum::{
+    extract::Path,
+    routing::{get, post},
+    Router,
+};
+use hyper::{
+    header::{HeaderName, LOCATION},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime};
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{
+    auth::{ReadUser, WriteUser},
+    content::{Format, Link, Negotiated, Resource},
+    error::ApiError,
+    limit::LimitQuery,
+    person::Person,
+    query::ValidatedQuery,
+    timestamp, v1,
+};
+use crate::service::archive::ArchiveService;
+
+/// A person moved out of the way after a long time without changes, who can be restored
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedPerson {
+    pub id: Uuid,
+    pub first_name: String,
+    pub family_name: String,
+    pub date_of_birth: Date,
+    /// When the person was created, as an RFC 3339 timestamp in UTC, or milliseconds since the
+    /// Unix epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub created: OffsetDateTime,
+    /// When the person was last edited before being archived, in the same format as `created`
+    #[serde(with = "timestamp")]
+    pub last_edited: OffsetDateTime,
+    /// When the person was archived, in the same format as `created`
+    #[serde(with = "timestamp")]
+    pub archived: OffsetDateTime,
+}
+
+impl Resource for ArchivedPerson {
+    const ELEMENT: &'static str = "archivedPerson";
+    const COLLECTION: &'static str = "archivedPeople";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        let archived = format!("{}/person/archive/{}", v1::PREFIX, self.id);
+
+        vec![
+            ("self", Link::to(&archived)),
+            ("restore", Link::to(format!("{archived}/restore"))),
+        ]
+    }
+}
+
+/// List archived people
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "archive",
+    path = "/person/archive",
+    params(LimitQuery),
+    responses(
+        (status = 200, description = "The most recently archived people, latest first", body = [ArchivedPerson]),
+        (status = 400, description = "Invalid query", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn list_archived_people(
+    user: ReadUser,
+    archive: ArchiveService,
+    format: Format,
+    ValidatedQuery(page): ValidatedQuery<LimitQuery>,
+) -> Result<Negotiated<Vec<ArchivedPerson>>, ApiError> {
+    let people = archive.list(page.limit()).await?;
+
+    info!(
+        "Client '{}' retrieved {} archived person(s)",
+        user.username,
+        people.len()
+    );
+
+    Ok(Negotiated(format, people))
+}
+
+/// Get an archived person
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "archive",
+    path = "/person/archive/{archived_uuid}",
+    params(
+        ("archived_uuid" = Uuid, Path, description = "The UUID of the archived person")
+    ),
+    responses(
+        (status = 200, description = "The archived person matching the given UUID", body = ArchivedPerson),
+        (status = 404, description = "Archived person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn get_archived_person(
+    user: ReadUser,
+    archive: ArchiveService,
+    format: Format,
+    Path(archived_uuid): Path<Uuid>,
+) -> Result<Negotiated<ArchivedPerson>, ApiError> {
+    let person = archive.find(archived_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved archived person '{}'",
+        user.username, archived_uuid
+    );
+
+    Ok(Negotiated(format, person))
+}
+
+/// Archive a person
+///
+/// Moves the person, along with their employment, contact details, emergency contacts, consent
+/// and photo, out of the people returned by every other endpoint until they're restored.
+/// People scheduled to be deleted can't be archived.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    post,
+    tag = "archive",
+    path = "/person/{person_uuid}/archive",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 201, description = "Person archived successfully", body = ArchivedPerson,
+            headers(("location" = String, description = "The URL of the archived person"))),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+        (status = 409, description = "The person is scheduled to be deleted", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn archive_person(
+    user: WriteUser,
+    archive: ArchiveService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<
+    (
+        StatusCode,
+        [(HeaderName, String); 1],
+        Negotiated<ArchivedPerson>,
+    ),
+    ApiError,
+> {
+    let person = archive.archive(&user.username, person_uuid).await?;
+
+    let location = format!("{}/person/archive/{}", v1::PREFIX, person.id);
+
+    Ok((
+        StatusCode::CREATED,
+        [(LOCATION, location)],
+        Negotiated(format, person),
+    ))
+}
+
+/// Restore an archived person
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    post,
+    tag = "archive",
+    path = "/person/archive/{archived_uuid}/restore",
+    params(
+        ("archived_uuid" = Uuid, Path, description = "The UUID of the archived person")
+    ),
+    responses(
+        (status = 200, description = "Person restored successfully", body = Person,
+            headers(("location" = String, description = "The URL of the restored person"))),
+        (status = 404, description = "Archived person not found", body = ErrorResponse),
+        (status = 409, description = "The person's user name or external ID has since been taken", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn restore_person(
+    user: WriteUser,
+    archive: ArchiveService,
+    format: Format,
+    Path(archived_uuid): Path<Uuid>,
+) -> Result<(StatusCode, [(HeaderName, String); 1], Negotiated<Person>), ApiError> {
+    let person = archive.restore(&user.username, archived_uuid).await?;
+
+    let location = format!("{}/person/{}", v1::PREFIX, person.id);
+
+    Ok((
+        StatusCode::OK,
+        [(LOCATION, location)],
+        Negotiated(format, person),
+    ))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/person/archive", get(list_archived_people))
+        .route("/person/archive/:archived_uuid", get(get_archived_person))
+        .route(
+            "/person/archive/:archived_uuid/restore",
+            post(restore_person),
+        )
+        .route("/person/:person_uuid/archive", post(archive_person))
+}
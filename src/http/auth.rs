@@ -1,9 +1,9 @@
 use axum::{
     async_trait,
     extract::FromRequestParts,
-    http::request::Parts,
+    http::{header::RETRY_AFTER, request::Parts},
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use axum_extra::{
     headers::{authorization::Bearer, Authorization},
@@ -15,49 +15,245 @@ use jsonwebtoken::{
     jwk::{AlgorithmParameters, JwkSet},
     Algorithm, DecodingKey, Validation,
 };
+use reqwest::Client;
 use serde::Deserialize;
 use serde_json::json;
-use std::{env, str::FromStr};
+use sqlx::PgPool;
+use std::{
+    env,
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+use super::{api_client, error::Report, usage::Usage};
 
+#[derive(thiserror::Error, Debug)]
 pub enum AuthError {
+    #[error("Missing token")]
     MissingToken,
+    #[error("Invalid token")]
     InvalidToken,
+    #[error("Token expired")]
     ExpiredToken,
+    #[error("Unable to verify JWT token")]
     Unavailable,
+    #[error("Client requires the scope: {0}")]
     MissingScope(String),
+    #[error("Invalid API key")]
+    InvalidApiKey,
+    #[error("Client has used its quota of {quota} requests for today")]
+    QuotaExceeded { quota: i64, retry_after: u64 },
 }
 
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AuthError::MissingToken => (StatusCode::UNAUTHORIZED, "Missing token".to_owned()),
-            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token".to_owned()),
-            AuthError::ExpiredToken => (StatusCode::UNAUTHORIZED, "Token expired".to_owned()),
-            AuthError::Unavailable => (
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Unable to verify JWT token".to_owned(),
-            ),
-            AuthError::MissingScope(scope) => (
-                StatusCode::FORBIDDEN,
-                format!("Client requires the scope: {}", scope),
-            ),
+        let status = match self {
+            AuthError::MissingToken
+            | AuthError::InvalidToken
+            | AuthError::ExpiredToken
+            | AuthError::InvalidApiKey => StatusCode::UNAUTHORIZED,
+            AuthError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            AuthError::MissingScope(_) => StatusCode::FORBIDDEN,
+            AuthError::QuotaExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
         };
         let body = Json(json!({
-            "message": error_message,
+            "message": self.to_string(),
         }));
-        (status, body).into_response()
+
+        match self {
+            AuthError::QuotaExceeded { retry_after, .. } => {
+                (status, [(RETRY_AFTER, retry_after.to_string())], body).into_response()
+            }
+            _ => (status, body).into_response(),
+        }
     }
 }
 
-async fn get_jwks() -> Result<JwkSet, AuthError> {
+struct CachedJwks {
+    jwks: Arc<JwkSet>,
+    fetched: Instant,
+}
+
+static JWKS: RwLock<Option<CachedJwks>> = RwLock::new(None);
+
+/// How long fetched keys are trusted for, from `JWKS_CACHE_SECONDS` (default 600)
+fn jwks_ttl() -> Duration {
+    env::var("JWKS_CACHE_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(600))
+}
+
+/// How long the last fetched keys may still be used for when they can't be fetched again, from
+/// `JWKS_MAX_STALE_SECONDS` (default 86400)
+fn jwks_max_stale() -> Duration {
+    env::var("JWKS_MAX_STALE_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(24 * 60 * 60))
+}
+
+/// How many fetches in a row may fail before the auth server is left alone, from
+/// `JWKS_BREAKER_FAILURES` (default 3)
+fn breaker_failures() -> u32 {
+    env::var("JWKS_BREAKER_FAILURES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(3)
+}
+
+/// How long to leave the auth server alone before trying it again, from
+/// `JWKS_BREAKER_PROBE_SECONDS` (default 30)
+fn breaker_probe_interval() -> Duration {
+    env::var("JWKS_BREAKER_PROBE_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(30))
+}
+
+/// Stops fetching keys from an auth server which keeps failing, so requests aren't each held
+/// up waiting on it. Once open, a single fetch is let through every so often to find out
+/// whether it has recovered.
+struct Breaker {
+    failures: u32,
+    opened: Option<Instant>,
+}
+
+static BREAKER: Mutex<Breaker> = Mutex::new(Breaker {
+    failures: 0,
+    opened: None,
+});
+
+impl Breaker {
+    /// Whether a fetch may be tried, restarting the wait for the next probe if it's one
+    fn allow(&mut self) -> bool {
+        match self.opened {
+            None => true,
+            Some(opened) if opened.elapsed() >= breaker_probe_interval() => {
+                self.opened = Some(Instant::now());
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    fn succeeded(&mut self) {
+        if self.opened.take().is_some() {
+            info!("The auth server is reachable again, fetching signing keys from it");
+        }
+        self.failures = 0;
+    }
+
+    fn failed(&mut self) {
+        self.failures += 1;
+
+        if self.failures >= breaker_failures() {
+            if self.opened.is_none() {
+                warn!(
+                    "Failed to fetch signing keys {} time(s) in a row, only trying again every {:?}",
+                    self.failures,
+                    breaker_probe_interval()
+                );
+            }
+            self.opened = Some(Instant::now());
+        }
+    }
+}
+
+/// Fetches the signing keys from the auth server, replacing any cached ones
+pub async fn refresh_jwks(client: &Client) -> Result<Arc<JwkSet>, AuthError> {
+    let result = fetch_jwks(client).await;
+
+    let mut breaker = BREAKER.lock().unwrap();
+    match &result {
+        Ok(_) => breaker.succeeded(),
+        Err(_) => breaker.failed(),
+    }
+
+    result
+}
+
+async fn fetch_jwks(client: &Client) -> Result<Arc<JwkSet>, AuthError> {
     let auth_url = env::var("AUTH_URL").map_err(|_| AuthError::Unavailable)?;
 
-    reqwest::get(format!("{auth_url}/.well-known/jwks.json"))
+    let jwks = client
+        .get(format!("{auth_url}/.well-known/jwks.json"))
+        .send()
         .await
         .map_err(|_| AuthError::Unavailable)?
         .json::<JwkSet>()
         .await
-        .map_err(|_| AuthError::Unavailable)
+        .map_err(|_| AuthError::Unavailable)?;
+
+    let jwks = Arc::new(jwks);
+
+    *JWKS.write().unwrap() = Some(CachedJwks {
+        jwks: jwks.clone(),
+        fetched: Instant::now(),
+    });
+
+    Ok(jwks)
+}
+
+/// The cached keys and how long ago they were fetched
+fn cached_jwks() -> Option<(Arc<JwkSet>, Duration)> {
+    JWKS.read()
+        .unwrap()
+        .as_ref()
+        .map(|cached| (cached.jwks.clone(), cached.fetched.elapsed()))
+}
+
+/// Fetches the keys again unless the breaker is open, falling back on the last known keys
+/// for up to `JWKS_MAX_STALE_SECONDS` when they can't be
+async fn refresh_or_stale(
+    client: &Client,
+    cached: Option<(Arc<JwkSet>, Duration)>,
+) -> Result<Arc<JwkSet>, AuthError> {
+    let allowed = BREAKER.lock().unwrap().allow();
+
+    if allowed {
+        if let Ok(jwks) = refresh_jwks(client).await {
+            return Ok(jwks);
+        }
+    }
+
+    cached
+        .filter(|(_, age)| *age < jwks_max_stale())
+        .map(|(jwks, _)| jwks)
+        .ok_or(AuthError::Unavailable)
+}
+
+/// Whether tokens can be verified, as the cached signing keys are still within
+/// `JWKS_CACHE_SECONDS`, can be fetched again now, or are within `JWKS_MAX_STALE_SECONDS`
+/// while the auth server can't be reached
+pub async fn jwks_available(client: &Client) -> bool {
+    let cached = cached_jwks();
+
+    if cached.as_ref().is_some_and(|(_, age)| *age < jwks_ttl()) {
+        return true;
+    }
+
+    refresh_or_stale(client, cached).await.is_ok()
+}
+
+/// The cached signing keys, fetched again once stale or when none match `kid`, as the auth
+/// server may have rotated its keys. Unknown key IDs only trigger a fetch every so often, so
+/// tokens with made up IDs can't be used to hammer the auth server.
+async fn get_jwks(client: &Client, kid: &str) -> Result<Arc<JwkSet>, AuthError> {
+    const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+    let cached = cached_jwks();
+
+    match &cached {
+        Some((jwks, age)) if *age < jwks_ttl() && jwks.find(kid).is_some() => Ok(jwks.clone()),
+        Some((jwks, age)) if *age < MIN_REFRESH_INTERVAL => Ok(jwks.clone()),
+        _ => refresh_or_stale(client, cached).await,
+    }
 }
 
 impl From<jsonwebtoken::errors::Error> for AuthError {
@@ -77,16 +273,51 @@ pub struct Claims {
     exp: usize,
     scope: Vec<String>,
     authorities: Vec<String>,
+    /// How many requests the client may make a day, if limited
+    #[serde(skip)]
+    daily_quota: Option<i64>,
 }
 
-#[async_trait]
-impl<S> FromRequestParts<S> for Claims
-where
-    S: Send + Sync,
-{
-    type Rejection = AuthError;
+/// Marks a request as already counted towards its client's usage
+#[derive(Clone, Copy)]
+struct Counted;
 
-    async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+impl Claims {
+    /// The claims of an API client authenticated by its key, rather than a token
+    fn for_api_client(client: api_client::KeyHolder) -> Self {
+        Claims {
+            iss: "api_client".to_owned(),
+            sub: client.name,
+            exp: 0,
+            scope: client.scopes,
+            authorities: Vec::new(),
+            daily_quota: client.daily_quota,
+        }
+    }
+
+    async fn from_api_key<S: Send + Sync>(
+        req: &mut Parts,
+        state: &S,
+        key: &str,
+    ) -> Result<Self, AuthError> {
+        let Extension(db) = Extension::<PgPool>::from_request_parts(req, state)
+            .await
+            .map_err(|_| AuthError::Unavailable)?;
+
+        match api_client::authenticate(&db, key).await {
+            Ok(Some(client)) => Ok(Claims::for_api_client(client)),
+            Ok(None) => Err(AuthError::InvalidApiKey),
+            Err(e) => {
+                error!("Failed to look up an API key: {}", Report(&e));
+                Err(AuthError::Unavailable)
+            }
+        }
+    }
+
+    async fn from_bearer_token<S: Send + Sync>(
+        req: &mut Parts,
+        state: &S,
+    ) -> Result<Self, AuthError> {
         let TypedHeader(Authorization(bearer_token)) =
             TypedHeader::<Authorization<Bearer>>::from_request_parts(req, state)
                 .await
@@ -99,7 +330,11 @@ where
             None => return Err(AuthError::InvalidToken),
         };
 
-        let jwks = get_jwks().await?;
+        let Extension(client) = Extension::<Client>::from_request_parts(req, state)
+            .await
+            .map_err(|_| AuthError::Unavailable)?;
+
+        let jwks = get_jwks(&client, &kid).await?;
 
         let decoded_token = match jwks.find(&kid) {
             Some(j) => match j.algorithm {
@@ -121,6 +356,45 @@ where
 
         Ok(decoded_token.claims)
     }
+
+    pub fn require_scope(&self, scope: &str) -> Result<(), AuthError> {
+        if self.scope.iter().any(|s| s == scope) {
+            Ok(())
+        } else {
+            Err(AuthError::MissingScope(scope.to_owned()))
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Claims
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let claims = match req.headers.get(api_client::API_KEY) {
+            Some(key) => {
+                let key = key
+                    .to_str()
+                    .map_err(|_| AuthError::InvalidApiKey)?
+                    .to_owned();
+                Claims::from_api_key(req, state, &key).await?
+            }
+            None => Claims::from_bearer_token(req, state).await?,
+        };
+
+        // handlers may check the claims more than once, but the request only counts once
+        if req.extensions.get::<Counted>().is_none() {
+            if let Ok(Extension(usage)) = Extension::<Usage>::from_request_parts(req, state).await {
+                usage.record(&claims.sub, claims.daily_quota).await?;
+            }
+            req.extensions.insert(Counted);
+        }
+
+        Ok(claims)
+    }
 }
 
 #[derive(Debug)]
@@ -145,13 +419,9 @@ where
 
     async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let claims = Claims::from_request_parts(req, state).await?;
-        let scope = String::from("read");
+        claims.require_scope("read")?;
 
-        if claims.scope.contains(&scope) {
-            Ok(ReadUser::from(claims))
-        } else {
-            Err(AuthError::MissingScope(scope))
-        }
+        Ok(ReadUser::from(claims))
     }
 }
 
@@ -177,12 +447,84 @@ where
 
     async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let claims = Claims::from_request_parts(req, state).await?;
-        let scope = String::from("write");
+        claims.require_scope("write")?;
 
-        if claims.scope.contains(&scope) {
-            Ok(WriteUser::from(claims))
-        } else {
-            Err(AuthError::MissingScope(scope))
+        Ok(WriteUser::from(claims))
+    }
+}
+
+#[derive(Debug)]
+pub struct AdminUser {
+    pub username: String,
+}
+
+impl From<Claims> for AdminUser {
+    fn from(claims: Claims) -> Self {
+        AdminUser {
+            username: claims.sub,
         }
     }
 }
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AdminUser
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request_parts(req, state).await?;
+        claims.require_scope("admin")?;
+
+        Ok(AdminUser::from(claims))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        time::{Duration, Instant},
+    };
+
+    use reqwest::Client;
+
+    use super::{jwks_available, Breaker};
+
+    #[test]
+    fn the_breaker_opens_after_repeated_failures() {
+        let mut breaker = Breaker {
+            failures: 0,
+            opened: None,
+        };
+
+        breaker.failed();
+        breaker.failed();
+        assert!(breaker.allow());
+
+        breaker.failed();
+        assert!(!breaker.allow());
+    }
+
+    #[test]
+    fn open_breakers_let_a_single_probe_through() {
+        let mut breaker = Breaker {
+            failures: 3,
+            opened: Instant::now().checked_sub(Duration::from_secs(31)),
+        };
+
+        assert!(breaker.allow(), "Should probe once the interval has passed");
+        assert!(!breaker.allow(), "Should wait for the probe");
+
+        breaker.succeeded();
+        assert!(breaker.allow());
+    }
+
+    #[tokio::test]
+    async fn keys_are_unavailable_without_an_auth_server() {
+        env::remove_var("AUTH_URL");
+
+        assert!(!jwks_available(&Client::new()).await);
+    }
+}
@@ -0,0 +1,192 @@
+//! An in-process cache of recently read people, for read-heavy deployments that can tolerate
+//! a little staleness.
+//!
+//! Disabled unless `PERSON_CACHE_SECONDS` is set. Entries are dropped once they are older than
+//! that, or sooner when the person is changed through this instance; other instances may keep
+//! serving their copy until it expires. The least recently used person is evicted once
+//! `PERSON_CACHE_CAPACITY` (default 1000) people are held.
+
+use std::{
+    collections::HashMap,
+    env,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use uuid::Uuid;
+
+use super::person::Person;
+
+struct Entry {
+    person: Person,
+    cached: Instant,
+    last_used: u64,
+}
+
+pub(crate) struct PersonCache {
+    ttl: Duration,
+    capacity: usize,
+    entries: HashMap<Uuid, Entry>,
+    uses: u64,
+}
+
+impl PersonCache {
+    fn new(ttl: Duration, capacity: usize) -> Self {
+        PersonCache {
+            ttl,
+            capacity,
+            entries: HashMap::new(),
+            uses: 0,
+        }
+    }
+
+    fn get(&mut self, id: Uuid) -> Option<Person> {
+        self.uses += 1;
+
+        match self.entries.get_mut(&id) {
+            Some(entry) if entry.cached.elapsed() < self.ttl => {
+                entry.last_used = self.uses;
+                Some(entry.person.clone())
+            }
+            Some(_) => {
+                self.entries.remove(&id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&mut self, person: Person) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&person.id) {
+            let least_recent = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(id, _)| *id);
+
+            if let Some(id) = least_recent {
+                self.entries.remove(&id);
+            }
+        }
+
+        self.uses += 1;
+        self.entries.insert(
+            person.id,
+            Entry {
+                person,
+                cached: Instant::now(),
+                last_used: self.uses,
+            },
+        );
+    }
+
+    fn invalidate(&mut self, id: Uuid) {
+        self.entries.remove(&id);
+    }
+}
+
+static PEOPLE: OnceLock<Option<Mutex<PersonCache>>> = OnceLock::new();
+
+fn people() -> Option<&'static Mutex<PersonCache>> {
+    PEOPLE
+        .get_or_init(|| {
+            let ttl = env::var("PERSON_CACHE_SECONDS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_secs)?;
+            let capacity = env::var("PERSON_CACHE_CAPACITY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1000);
+
+            Some(Mutex::new(PersonCache::new(ttl, capacity)))
+        })
+        .as_ref()
+}
+
+/// The cached copy of a person, if there's one fresh enough
+pub(crate) fn get(id: Uuid) -> Option<Person> {
+    people()?.lock().unwrap().get(id)
+}
+
+/// Remembers a person just read from the database
+pub(crate) fn insert(person: &Person) {
+    if let Some(cache) = people() {
+        cache.lock().unwrap().insert(person.clone());
+    }
+}
+
+/// Forgets a person, to be called once a change to them has been committed
+pub(crate) fn invalidate(id: Uuid) {
+    if let Some(cache) = people() {
+        cache.lock().unwrap().invalidate(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use time::{macros::date, OffsetDateTime};
+    use uuid::Uuid;
+
+    use super::PersonCache;
+    use crate::http::person::Person;
+
+    fn person() -> Person {
+        Person {
+            id: Uuid::new_v4(),
+            first_name: "John".to_owned(),
+            family_name: "Doe".to_owned(),
+            date_of_birth: date!(1990 - 1 - 1),
+            created: OffsetDateTime::now_utc(),
+            last_edited: OffsetDateTime::now_utc(),
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn cached_people_are_returned_until_invalidated() {
+        let mut cache = PersonCache::new(Duration::from_secs(60), 10);
+        let person = person();
+
+        cache.insert(person.clone());
+        assert_eq!(cache.get(person.id).map(|p| p.id), Some(person.id));
+
+        cache.invalidate(person.id);
+        assert!(cache.get(person.id).is_none(), "Should have been forgotten");
+    }
+
+    #[test]
+    fn cached_people_expire() {
+        let mut cache = PersonCache::new(Duration::from_millis(10), 10);
+        let person = person();
+
+        cache.insert(person.clone());
+        thread::sleep(Duration::from_millis(20));
+
+        assert!(cache.get(person.id).is_none(), "Should have expired");
+    }
+
+    #[test]
+    fn least_recently_used_person_is_evicted() {
+        let mut cache = PersonCache::new(Duration::from_secs(60), 2);
+        let (first, second, third) = (person(), person(), person());
+
+        cache.insert(first.clone());
+        cache.insert(second.clone());
+        cache.get(first.id);
+        cache.insert(third.clone());
+
+        assert!(cache.get(first.id).is_some(), "Recently read, so kept");
+        assert!(
+            cache.get(second.id).is_none(),
+            "Least recently used, so evicted"
+        );
+        assert!(cache.get(third.id).is_some(), "Just added, so kept");
+    }
+}
@@ -0,0 +1,85 @@
+//! `Cache-Control` and `Vary` headers, telling clients and intermediary caches how long each
+//! response may be reused for.
+//!
+//! Each router picks the [`CachePolicy`] for its reads and layers [`apply`] over its routes.
+//! Anything other than a successful or not modified `GET` or `HEAD` is sent with `no-store`, so
+//! writes and errors are never replayed from a cache. Handlers setting their own `Cache-Control` are left
+//! alone.
+
+use std::env;
+
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{CACHE_CONTROL, VARY},
+        HeaderValue, Method, StatusCode,
+    },
+    middleware::Next,
+    response::Response,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CachePolicy {
+    /// Never stored
+    NoStore,
+    /// Only stored by the client itself, as the response depends on who is asking
+    Private { max_age: u64 },
+    /// Stored by shared caches too, as the response is the same for everyone
+    Public { max_age: u64 },
+}
+
+impl CachePolicy {
+    /// Reads of the caller's own resources, kept for `CACHE_MAX_AGE_SECONDS` (default 0)
+    pub fn private_from_env() -> Self {
+        let max_age = env::var("CACHE_MAX_AGE_SECONDS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+
+        CachePolicy::Private { max_age }
+    }
+
+    fn header_value(&self) -> HeaderValue {
+        match self {
+            CachePolicy::NoStore => HeaderValue::from_static("no-store"),
+            CachePolicy::Private { max_age } => {
+                HeaderValue::from_str(&format!("private, max-age={max_age}")).unwrap()
+            }
+            CachePolicy::Public { max_age } => {
+                HeaderValue::from_str(&format!("public, max-age={max_age}")).unwrap()
+            }
+        }
+    }
+}
+
+/// Sets the caching headers for the routes it is layered over, using `reads` for successful
+/// `GET` and `HEAD` requests
+pub async fn apply(State(reads): State<CachePolicy>, request: Request, next: Next) -> Response {
+    let is_read = matches!(*request.method(), Method::GET | Method::HEAD);
+
+    let mut response = next.run(request).await;
+
+    // a 304 refreshes the cached copy, so it's kept for as long again
+    let fresh = response.status().is_success() || response.status() == StatusCode::NOT_MODIFIED;
+    let policy = if is_read && fresh {
+        reads
+    } else {
+        CachePolicy::NoStore
+    };
+
+    let headers = response.headers_mut();
+
+    if headers.contains_key(CACHE_CONTROL) {
+        return response;
+    }
+
+    headers.insert(CACHE_CONTROL, policy.header_value());
+
+    // a cached copy must not be handed to a client with a different token or key
+    if let CachePolicy::Private { .. } = policy {
+        headers.append(VARY, HeaderValue::from_static("authorization"));
+        headers.append(VARY, HeaderValue::from_static("x-api-key"));
+    }
+
+    response
+}
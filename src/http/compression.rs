@@ -0,0 +1,116 @@
+//! Decompression of request bodies sent with `Content-Encoding: gzip`, so clients uploading
+//! large amounts of JSON or CSV can compress them on the wire.
+//!
+//! Bodies are decompressed in full before reaching the handler, and rejected with
+//! `413 Payload Too Large` once they would grow past `MAX_DECOMPRESSED_BODY_BYTES` (default
+//! 32 MiB), so a small compressed body can't be used to exhaust memory. The usual body limits
+//! of the extractors still apply to the decompressed body.
+
+use std::{env, io::Read};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::header::{CONTENT_ENCODING, CONTENT_LENGTH},
+    middleware::Next,
+    response::Response,
+};
+use flate2::read::GzDecoder;
+
+use super::error::ApiError;
+
+fn max_decompressed_bytes() -> usize {
+    env::var("MAX_DECOMPRESSED_BODY_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(32 * 1024 * 1024)
+}
+
+fn gunzip(compressed: &[u8], limit: usize) -> Result<Vec<u8>, ApiError> {
+    let mut decompressed = vec![];
+
+    // reading one byte past the limit tells a body of exactly the limit from a larger one
+    GzDecoder::new(compressed)
+        .take(limit as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| ApiError::InvalidEncoding(e.to_string()))?;
+
+    if decompressed.len() > limit {
+        return Err(ApiError::PayloadTooLarge(limit));
+    }
+
+    Ok(decompressed)
+}
+
+/// Replaces a gzipped request body with its decompressed contents
+pub async fn decompress_requests(request: Request, next: Next) -> Result<Response, ApiError> {
+    let encoding = match request.headers().get(CONTENT_ENCODING) {
+        Some(encoding) => encoding
+            .to_str()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase(),
+        None => return Ok(next.run(request).await),
+    };
+
+    match encoding.as_str() {
+        "identity" => return Ok(next.run(request).await),
+        "gzip" | "x-gzip" => {}
+        _ => return Err(ApiError::UnsupportedEncoding(encoding)),
+    }
+
+    let limit = max_decompressed_bytes();
+    let (mut parts, body) = request.into_parts();
+
+    let compressed = to_bytes(body, limit)
+        .await
+        .map_err(|_| ApiError::PayloadTooLarge(limit))?;
+
+    let decompressed = tokio::task::spawn_blocking(move || gunzip(&compressed, limit))
+        .await
+        .map_err(|e| ApiError::InvalidEncoding(e.to_string()))??;
+
+    parts.headers.remove(CONTENT_ENCODING);
+    parts.headers.remove(CONTENT_LENGTH);
+
+    Ok(next
+        .run(Request::from_parts(parts, Body::from(decompressed)))
+        .await)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::gunzip;
+    use crate::http::error::ApiError;
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn bodies_within_the_limit_are_decompressed() {
+        assert_eq!(gunzip(&gzip(b"hello"), 5).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn bodies_over_the_limit_are_rejected() {
+        assert!(matches!(
+            gunzip(&gzip(&[0; 1024]), 1023),
+            Err(ApiError::PayloadTooLarge(1023))
+        ));
+    }
+
+    #[test]
+    fn invalid_gzip_is_rejected() {
+        assert!(matches!(
+            gunzip(b"not gzip", 1024),
+            Err(ApiError::InvalidEncoding(_))
+        ));
+    }
+}
@@ -0,0 +1,256 @@
+use axum::{extract::Path, routing::get, Router};
+use hyper::{
+    header::{HeaderName, LOCATION},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::{
+    auth::{ReadUser, WriteUser},
+    content::{Format, Link, Negotiated, Resource, ValidatedPayload},
+    error::ApiError,
+    query::ValidatedQuery,
+    timestamp, v1,
+};
+use crate::service::consent::ConsentService;
+
+/// What a person can consent to their details being used for
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum ConsentPurpose {
+    /// Contacting them by email
+    Email,
+    /// Contacting them by text message
+    Sms,
+    /// Contacting them by phone
+    Phone,
+    /// Contacting them by post
+    Post,
+    /// Sending them marketing
+    Marketing,
+    /// Including their details in research
+    Research,
+}
+
+impl ConsentPurpose {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConsentPurpose::Email => "email",
+            ConsentPurpose::Sms => "sms",
+            ConsentPurpose::Phone => "phone",
+            ConsentPurpose::Post => "post",
+            ConsentPurpose::Marketing => "marketing",
+            ConsentPurpose::Research => "research",
+        }
+    }
+}
+
+/// How a person gave or withdrew their consent
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum ConsentChannel {
+    Web,
+    Email,
+    Phone,
+    Paper,
+    Verbal,
+}
+
+#[derive(Debug, Validate, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NewConsent {
+    pub purpose: ConsentPurpose,
+    /// Whether the person gave their consent, or withdrew it
+    pub granted: bool,
+    pub channel: ConsentChannel,
+}
+
+/// A person giving or withdrawing their consent for one purpose. Records are never changed,
+/// the latest for a purpose is the person's current consent.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Consent {
+    pub id: Uuid,
+    pub person_id: Uuid,
+    pub purpose: ConsentPurpose,
+    pub granted: bool,
+    pub channel: ConsentChannel,
+    /// When the consent was recorded, as an RFC 3339 timestamp in UTC, or milliseconds since
+    /// the Unix epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub recorded: OffsetDateTime,
+}
+
+impl Consent {
+    /// Where every consent recorded for the same person and purpose can be found
+    pub fn history_url(&self) -> String {
+        format!(
+            "{}/person/{}/consents/history?purpose={}",
+            v1::PREFIX,
+            self.person_id,
+            self.purpose.as_str()
+        )
+    }
+}
+
+impl Resource for Consent {
+    const ELEMENT: &'static str = "consent";
+    const COLLECTION: &'static str = "consents";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        vec![("history", Link::to(self.history_url()))]
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        let person = format!("{}/person/{}", v1::PREFIX, self.person_id);
+
+        vec![("person", Link::to(person))]
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryQuery {
+    /// Only return the consent recorded for this purpose
+    #[param(inline)]
+    pub purpose: Option<ConsentPurpose>,
+}
+
+/// List a person's current consent
+///
+/// Returns the latest consent recorded for each purpose, so purposes the person has never been
+/// asked about are missing.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "consent",
+    path = "/person/{person_uuid}/consents",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "The person's current consent for each purpose", body = [Consent]),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn list_consents(
+    user: ReadUser,
+    consents: ConsentService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Negotiated<Vec<Consent>>, ApiError> {
+    let consents = consents.current(person_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved the consent of person '{}'",
+        user.username, person_uuid
+    );
+
+    Ok(Negotiated(format, consents))
+}
+
+/// List every consent recorded for a person
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "consent",
+    path = "/person/{person_uuid}/consents/history",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        HistoryQuery
+    ),
+    responses(
+        (status = 200, description = "The consent recorded for the person, newest first", body = [Consent]),
+        (status = 400, description = "Invalid query", body = ErrorResponse),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn list_consent_history(
+    user: ReadUser,
+    consents: ConsentService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+    ValidatedQuery(query): ValidatedQuery<HistoryQuery>,
+) -> Result<Negotiated<Vec<Consent>>, ApiError> {
+    let consents = consents.history(person_uuid, query.purpose).await?;
+
+    info!(
+        "Client '{}' retrieved {} consent record(s) of person '{}'",
+        user.username,
+        consents.len(),
+        person_uuid
+    );
+
+    Ok(Negotiated(format, consents))
+}
+
+/// Record a person giving or withdrawing their consent
+///
+/// The new record replaces the person's current consent for its purpose, while the earlier ones
+/// are kept in their history.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    post,
+    tag = "consent",
+    path = "/person/{person_uuid}/consents",
+    request_body = NewConsent,
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 201, description = "Consent recorded successfully", body = Consent,
+            headers(("location" = String, description = "The URL of the person's consent history for the purpose"))),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn record_consent(
+    user: WriteUser,
+    consents: ConsentService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+    ValidatedPayload(request): ValidatedPayload<NewConsent>,
+) -> Result<(StatusCode, [(HeaderName, String); 1], Negotiated<Consent>), ApiError> {
+    let consent = consents
+        .record(&user.username, person_uuid, &request)
+        .await?;
+
+    let location = consent.history_url();
+
+    Ok((
+        StatusCode::CREATED,
+        [(LOCATION, location)],
+        Negotiated(format, consent),
+    ))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/person/:person_uuid/consents",
+            get(list_consents).post(record_consent),
+        )
+        .route(
+            "/person/:person_uuid/consents/history",
+            get(list_consent_history),
+        )
+}
@@ -0,0 +1,271 @@
+use axum::{extract::Path, routing::get, Router};
+use hyper::{
+    header::{HeaderName, LOCATION},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::{Validate, ValidationErrors};
+
+use super::{
+    auth::{ReadUser, WriteUser},
+    content::{Format, Link, Negotiated, Resource, ValidatedPayload},
+    error::ApiError,
+    fields::{self, EmailAddress, InternationalPhoneNumber},
+    response::Deleted,
+    timestamp, v1,
+};
+use crate::service::contact_detail::ContactDetailService;
+
+/// How a person can be contacted
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum ContactType {
+    /// By email
+    Email,
+    /// By phone
+    Phone,
+}
+
+#[derive(Debug, Validate, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NewContactDetail {
+    #[serde(rename = "type")]
+    pub contact_type: ContactType,
+    /// An email address, or a phone number in E.164 form such as `+44 20 7946 0000`, which may
+    /// be broken up with spaces, dots, dashes and parentheses
+    #[validate(length(min = 1, max = 254))]
+    #[schema(min_length = 1, max_length = 254, example = "ada@example.com")]
+    pub value: String,
+    /// What the contact detail is for, such as `Work` or `Home`
+    #[validate(length(min = 1, max = 64))]
+    #[schema(min_length = 1, max_length = 64)]
+    #[serde(default, deserialize_with = "fields::normalized")]
+    pub label: Option<String>,
+}
+
+impl NewContactDetail {
+    /// The value as it's stored, once checked against the type, with phone numbers in E.164
+    /// form
+    pub fn stored_value(&self) -> Result<String, ValidationErrors> {
+        let value = match self.contact_type {
+            ContactType::Email => EmailAddress::try_from(self.value.clone()).map(String::from),
+            ContactType::Phone => {
+                InternationalPhoneNumber::try_from(self.value.clone()).map(String::from)
+            }
+        };
+
+        value.map_err(|error| {
+            let mut errors = ValidationErrors::new();
+            errors.add("value", error);
+            errors
+        })
+    }
+}
+
+/// An email address or phone number a person can be contacted on
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactDetail {
+    pub id: Uuid,
+    pub person_id: Uuid,
+    #[serde(rename = "type")]
+    pub contact_type: ContactType,
+    /// The email address, or the phone number in E.164 form
+    pub value: String,
+    pub label: Option<String>,
+    /// When the contact detail was added, as an RFC 3339 timestamp in UTC, or milliseconds
+    /// since the Unix epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub created: OffsetDateTime,
+}
+
+impl Resource for ContactDetail {
+    const ELEMENT: &'static str = "contactDetail";
+    const COLLECTION: &'static str = "contactDetails";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        let detail = format!(
+            "{}/person/{}/contacts/{}",
+            v1::PREFIX,
+            self.person_id,
+            self.id
+        );
+
+        vec![("self", Link::to(&detail)), ("delete", Link::to(&detail))]
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        let person = format!("{}/person/{}", v1::PREFIX, self.person_id);
+
+        vec![("person", Link::to(person))]
+    }
+}
+
+/// List a person's email addresses and phone numbers
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "contact detail",
+    path = "/person/{person_uuid}/contacts",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "The person's contact details, first added first", body = [ContactDetail]),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn list_contact_details(
+    user: ReadUser,
+    details: ContactDetailService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Negotiated<Vec<ContactDetail>>, ApiError> {
+    let details = details.list(person_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved {} contact detail(s) of person '{}'",
+        user.username,
+        details.len(),
+        person_uuid
+    );
+
+    Ok(Negotiated(format, details))
+}
+
+/// Add an email address or phone number for a person
+///
+/// Email addresses must be valid, and phone numbers in E.164 form. A person can't have the same
+/// email address, whatever its case, or phone number twice.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    post,
+    tag = "contact detail",
+    path = "/person/{person_uuid}/contacts",
+    request_body = NewContactDetail,
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 201, description = "Contact detail added successfully", body = ContactDetail,
+            headers(("location" = String, description = "The URL of the added contact detail"))),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+        (status = 409, description = "The person already has this contact detail", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn add_contact_detail(
+    user: WriteUser,
+    details: ContactDetailService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+    ValidatedPayload(request): ValidatedPayload<NewContactDetail>,
+) -> Result<
+    (
+        StatusCode,
+        [(HeaderName, String); 1],
+        Negotiated<ContactDetail>,
+    ),
+    ApiError,
+> {
+    let detail = details.add(&user.username, person_uuid, &request).await?;
+
+    let location = format!("{}/person/{person_uuid}/contacts/{}", v1::PREFIX, detail.id);
+
+    Ok((
+        StatusCode::CREATED,
+        [(LOCATION, location)],
+        Negotiated(format, detail),
+    ))
+}
+
+/// Get one of a person's email addresses or phone numbers
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "contact detail",
+    path = "/person/{person_uuid}/contacts/{detail_uuid}",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        ("detail_uuid" = Uuid, Path, description = "The UUID of the contact detail")
+    ),
+    responses(
+        (status = 200, description = "The contact detail matching the given UUID", body = ContactDetail),
+        (status = 404, description = "Contact detail not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn get_contact_detail(
+    user: ReadUser,
+    details: ContactDetailService,
+    format: Format,
+    Path((person_uuid, detail_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Negotiated<ContactDetail>, ApiError> {
+    let detail = details.find(person_uuid, detail_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved contact detail '{}'",
+        user.username, detail_uuid
+    );
+
+    Ok(Negotiated(format, detail))
+}
+
+/// Remove one of a person's email addresses or phone numbers
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    delete,
+    tag = "contact detail",
+    path = "/person/{person_uuid}/contacts/{detail_uuid}",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        ("detail_uuid" = Uuid, Path, description = "The UUID of the contact detail")
+    ),
+    responses(
+        (status = 204, description = "Contact detail deleted successfully"),
+        (status = 404, description = "Contact detail not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn remove_contact_detail(
+    user: WriteUser,
+    details: ContactDetailService,
+    Path((person_uuid, detail_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Deleted, ApiError> {
+    details
+        .remove(&user.username, person_uuid, detail_uuid)
+        .await?;
+
+    Ok(Deleted)
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/person/:person_uuid/contacts",
+            get(list_contact_details).post(add_contact_detail),
+        )
+        .route(
+            "/person/:person_uuid/contacts/:detail_uuid",
+            get(get_contact_detail).delete(remove_contact_detail),
+        )
+}
@@ -0,0 +1,684 @@
+//! Content negotiation for resource representations.
+//!
+//! Requests and responses are JSON by default. Clients may instead send bodies as MessagePack
+//! by setting `Content-Type: application/msgpack`, and ask for MessagePack back with
+//! `Accept: application/msgpack`, which is smaller and cheaper to parse for high-throughput
+//! internal clients. Responses may also be requested as XML with `Accept: application/xml`, for
+//! consumers unable to read JSON, though XML request bodies are not accepted. Errors are always
+//! returned as JSON.
+//!
+//! Lists can be asked for as newline delimited JSON with `Accept: application/x-ndjson`, one
+//! resource to a line, which the person list streams from the database as the rows arrive so
+//! exports of any size can be taken without the service holding them all.
+//!
+//! Clients wanting to navigate the API by following links, rather than building URLs
+//! themselves, can ask for [HAL](https://datatracker.ietf.org/doc/html/draft-kelly-json-hal)
+//! with `Accept: application/hal+json` or the `?hateoas=true` flag. Resources then carry a
+//! `_links` section, and lists are embedded under `_embedded`.
+//!
+//! Frontends built on [JSON:API](https://jsonapi.org) tooling can use
+//! `application/vnd.api+json` for both requests and responses, in which case errors are also
+//! written as JSON:API error objects by [`json_api_errors`].
+
+use std::convert::Infallible;
+
+use axum::{
+    async_trait,
+    body::Bytes,
+    body::{to_bytes, Body},
+    extract::{FromRequest, FromRequestParts, OriginalUri, Query, Request},
+    http::{
+        header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, VARY},
+        request::Parts,
+        HeaderMap, HeaderValue,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+    BoxError, Json,
+};
+use futures::stream;
+use hyper::StatusCode;
+use serde::{de::DeserializeOwned, ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use serde_json::{json, Map, Value};
+use tokio::sync::mpsc;
+use tracing::error;
+use validator::Validate;
+
+use super::{error::ApiError, strict};
+
+pub const JSON: &str = "application/json";
+pub const MSGPACK: &str = "application/msgpack";
+pub const XML: &str = "application/xml";
+pub const HAL: &str = "application/hal+json";
+pub const JSON_API: &str = "application/vnd.api+json";
+pub const NDJSON: &str = "application/x-ndjson";
+
+/// A representation the API can read and write resources in
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Format {
+    #[default]
+    Json,
+    MessagePack,
+    Xml,
+    /// JSON with links, in response to the request for `location`
+    Hal {
+        location: String,
+    },
+    JsonApi,
+    /// JSON with each resource of a list on its own line
+    NdJson,
+}
+
+impl Format {
+    fn from_media_type(media_type: &str) -> Option<Format> {
+        let essence = media_type.split(';').next().unwrap_or_default().trim();
+
+        match essence.to_ascii_lowercase().as_str() {
+            JSON | "application/*" | "*/*" => Some(Format::Json),
+            MSGPACK | "application/x-msgpack" => Some(Format::MessagePack),
+            XML | "text/xml" => Some(Format::Xml),
+            HAL => Some(Format::Hal {
+                location: String::new(),
+            }),
+            JSON_API => Some(Format::JsonApi),
+            NDJSON => Some(Format::NdJson),
+            _ => None,
+        }
+    }
+
+    /// The supported format the client most prefers, falling back to JSON
+    pub fn preferred(headers: &HeaderMap) -> Format {
+        let mut accepted: Vec<(f32, Format)> = headers
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|media_range| {
+                let quality = media_range
+                    .split(';')
+                    .skip(1)
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.parse().ok())
+                    .unwrap_or(1.0);
+
+                Some((quality, Format::from_media_type(media_range)?))
+            })
+            .filter(|(quality, _)| *quality > 0.0)
+            .collect();
+
+        // stable, so equally preferred formats keep the client's order
+        accepted.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        accepted
+            .into_iter()
+            .next()
+            .map(|(_, format)| format)
+            .unwrap_or_default()
+    }
+
+    fn of_body(headers: &HeaderMap) -> Format {
+        headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Format::from_media_type)
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Format
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        #[derive(Deserialize)]
+        struct Flags {
+            #[serde(default)]
+            hateoas: bool,
+        }
+
+        let mut format = Format::preferred(&parts.headers);
+
+        let hateoas = Query::<Flags>::try_from_uri(&parts.uri).is_ok_and(|flags| flags.hateoas);
+        if hateoas && format == Format::Json {
+            format = Format::Hal {
+                location: String::new(),
+            };
+        }
+
+        if let Format::Hal { location } = &mut format {
+            // nested routers only see the rest of the path
+            let uri = match parts.extensions.get::<OriginalUri>() {
+                Some(OriginalUri(uri)) => uri,
+                None => &parts.uri,
+            };
+            *location = uri
+                .path_and_query()
+                .map(|p| p.to_string())
+                .unwrap_or_default();
+        }
+
+        Ok(format)
+    }
+}
+
+/// A request body in whichever format its `Content-Type` names, JSON when unspecified
+pub struct Payload<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for Payload<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Format::of_body(req.headers()) {
+            // XML and NDJSON are only written, so the JSON extractor rejects them as a missing
+            // JSON content type
+            Format::Json | Format::Xml | Format::NdJson | Format::Hal { .. }
+                if !strict::enabled() =>
+            {
+                let Json(value) = Json::from_request(req, state).await?;
+                Ok(Payload(value))
+            }
+            Format::Json | Format::Xml | Format::NdJson | Format::Hal { .. } => {
+                // kept aside to look for unknown fields in
+                let (parts, body) = req.into_parts();
+                let bytes = Bytes::from_request(Request::from_parts(parts.clone(), body), state)
+                    .await
+                    .map_err(|e| ApiError::InvalidBody(e.into()))?;
+
+                let request = Request::from_parts(parts, Body::from(bytes.clone()));
+                let result = Json::from_request(request, state).await;
+
+                // reported first, as a misspelt field usually also shows up as a missing one
+                strict::check::<T>(&serde_json::from_slice(&bytes).unwrap_or_default())?;
+                let Json(value) = result?;
+
+                Ok(Payload(value))
+            }
+            Format::MessagePack => {
+                let bytes = Bytes::from_request(req, state)
+                    .await
+                    .map_err(|e| ApiError::InvalidBody(e.into()))?;
+
+                // human readable, so dates and UUIDs are strings as they are in JSON
+                let mut deserializer =
+                    rmp_serde::Deserializer::from_read_ref(&bytes).with_human_readable();
+
+                strict::check::<T>(&rmp_serde::from_slice(&bytes).unwrap_or_default())?;
+
+                Ok(Payload(T::deserialize(&mut deserializer)?))
+            }
+            Format::JsonApi => {
+                #[derive(Deserialize)]
+                struct Document {
+                    data: ResourceObject,
+                }
+
+                #[derive(Deserialize)]
+                struct ResourceObject {
+                    attributes: Value,
+                }
+
+                let Json(document) = Json::<Document>::from_request(req, state).await?;
+
+                strict::check::<T>(&document.data.attributes)?;
+
+                Ok(Payload(serde_json::from_value(document.data.attributes)?))
+            }
+        }
+    }
+}
+
+/// A [`Payload`] which has passed validation, so handlers taking one can't forget to validate
+/// it. Invalid bodies are rejected with the usual `400` listing the failing fields.
+pub struct ValidatedPayload<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidatedPayload<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Payload(value) = Payload::<T>::from_request(req, state).await?;
+        value.validate()?;
+
+        Ok(ValidatedPayload(value))
+    }
+}
+
+/// A link to a related resource, or an action on this one
+#[derive(Debug, Serialize)]
+pub struct Link {
+    pub href: String,
+}
+
+impl Link {
+    pub fn to(href: impl Into<String>) -> Self {
+        Link { href: href.into() }
+    }
+}
+
+/// A resource that can be written in any of the supported formats
+pub trait Resource: Serialize {
+    /// The XML element holding a single resource
+    const ELEMENT: &'static str;
+    /// The name for many of these resources: the XML element wrapping a list of them, the HAL
+    /// relation they're embedded as, and their JSON:API type
+    const COLLECTION: &'static str;
+
+    /// Links to the resource and the actions on it, by relation, starting with `self`
+    fn links(&self) -> Vec<(&'static str, Link)>;
+
+    /// Links to the resources related to this one
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        vec![]
+    }
+}
+
+/// A response body that can be written in each of the formats beyond plain JSON
+pub trait Representation: Serialize {
+    fn to_xml(&self) -> Result<String, quick_xml::SeError>;
+    fn to_hal(&self, location: &str) -> Result<Value, serde_json::Error>;
+    fn to_json_api(&self) -> Result<Value, serde_json::Error>;
+    fn to_ndjson(&self) -> Result<Vec<u8>, serde_json::Error>;
+}
+
+fn with_links<T: Resource>(resource: &T) -> Result<Value, serde_json::Error> {
+    let mut value = serde_json::to_value(resource)?;
+    let links: Map<String, Value> = resource
+        .links()
+        .into_iter()
+        .chain(resource.relationships())
+        .map(|(relation, link)| Ok((relation.to_owned(), serde_json::to_value(link)?)))
+        .collect::<Result<_, serde_json::Error>>()?;
+
+    if let Value::Object(fields) = &mut value {
+        fields.insert("_links".to_owned(), Value::Object(links));
+    }
+
+    Ok(value)
+}
+
+/// Splits the resource's fields into its JSON:API identity and attributes
+fn resource_object<T: Resource>(resource: &T) -> Result<Value, serde_json::Error> {
+    let mut attributes = serde_json::to_value(resource)?;
+    let id = attributes
+        .as_object_mut()
+        .and_then(|fields| fields.remove("id"))
+        .unwrap_or_default();
+
+    let links = resource.links();
+    let self_link = links.iter().find(|(relation, _)| *relation == "self");
+
+    let relationships: Map<String, Value> = resource
+        .relationships()
+        .into_iter()
+        .map(|(relation, link)| {
+            let related = json!({ "links": { "related": link.href } });
+            (relation.to_owned(), related)
+        })
+        .collect();
+
+    Ok(json!({
+        "type": T::COLLECTION,
+        "id": id,
+        "attributes": attributes,
+        "relationships": relationships,
+        "links": { "self": self_link.map(|(_, link)| &link.href) },
+    }))
+}
+
+impl<T: Resource> Representation for T {
+    fn to_xml(&self) -> Result<String, quick_xml::SeError> {
+        quick_xml::se::to_string_with_root(T::ELEMENT, self)
+    }
+
+    fn to_hal(&self, _location: &str) -> Result<Value, serde_json::Error> {
+        with_links(self)
+    }
+
+    fn to_json_api(&self) -> Result<Value, serde_json::Error> {
+        Ok(json!({ "data": resource_object(self)? }))
+    }
+
+    fn to_ndjson(&self) -> Result<Vec<u8>, serde_json::Error> {
+        ndjson_line(self)
+    }
+}
+
+/// A value as a line of newline delimited JSON
+fn ndjson_line<T: Serialize>(value: &T) -> Result<Vec<u8>, serde_json::Error> {
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+
+    Ok(line)
+}
+
+/// Wraps the elements of a list, as XML has no bare sequences
+struct XmlList<'a, T>(&'a [T]);
+
+impl<T: Resource> Serialize for XmlList<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut list = serializer.serialize_struct(T::COLLECTION, 1)?;
+        list.serialize_field(T::ELEMENT, self.0)?;
+        list.end()
+    }
+}
+
+impl<T: Resource> Representation for Vec<T> {
+    fn to_xml(&self) -> Result<String, quick_xml::SeError> {
+        quick_xml::se::to_string_with_root(T::COLLECTION, &XmlList(self))
+    }
+
+    fn to_hal(&self, location: &str) -> Result<Value, serde_json::Error> {
+        let embedded = self.iter().map(with_links).collect::<Result<Vec<_>, _>>()?;
+
+        Ok(json!({
+            "_links": { "self": Link::to(location) },
+            "_embedded": { T::COLLECTION: embedded },
+        }))
+    }
+
+    fn to_json_api(&self) -> Result<Value, serde_json::Error> {
+        let data = self
+            .iter()
+            .map(resource_object)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(json!({ "data": data }))
+    }
+
+    fn to_ndjson(&self) -> Result<Vec<u8>, serde_json::Error> {
+        let lines = self
+            .iter()
+            .map(ndjson_line)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(lines.concat())
+    }
+}
+
+/// A response body written in the format the client asked for
+pub struct Negotiated<T>(pub Format, pub T);
+
+impl<T> IntoResponse for Negotiated<T>
+where
+    T: Representation,
+{
+    fn into_response(self) -> Response {
+        let Negotiated(format, value) = self;
+
+        let mut response = match format {
+            Format::Json => Json(value).into_response(),
+            Format::MessagePack => {
+                let mut body = vec![];
+                let mut serializer = rmp_serde::Serializer::new(&mut body)
+                    .with_struct_map()
+                    .with_human_readable();
+
+                match value.serialize(&mut serializer) {
+                    Ok(()) => ([(CONTENT_TYPE, MSGPACK)], body).into_response(),
+                    Err(e) => {
+                        error!("Failed to serialize response as MessagePack: {e}");
+                        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                    }
+                }
+            }
+            Format::Xml => match value.to_xml() {
+                Ok(body) => ([(CONTENT_TYPE, XML)], body).into_response(),
+                Err(e) => {
+                    error!("Failed to serialize response as XML: {e}");
+                    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                }
+            },
+            Format::Hal { location } => match value.to_hal(&location) {
+                Ok(body) => ([(CONTENT_TYPE, HAL)], Json(body)).into_response(),
+                Err(e) => {
+                    error!("Failed to serialize response as HAL: {e}");
+                    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                }
+            },
+            Format::JsonApi => match value.to_json_api() {
+                Ok(body) => ([(CONTENT_TYPE, JSON_API)], Json(body)).into_response(),
+                Err(e) => {
+                    error!("Failed to serialize response as JSON:API: {e}");
+                    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                }
+            },
+            Format::NdJson => match value.to_ndjson() {
+                Ok(body) => ([(CONTENT_TYPE, NDJSON)], body).into_response(),
+                Err(e) => {
+                    error!("Failed to serialize response as NDJSON: {e}");
+                    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                }
+            },
+        };
+
+        response
+            .headers_mut()
+            .append(VARY, HeaderValue::from_static("accept"));
+
+        response
+    }
+}
+
+/// A JSON array written an element at a time as they're received.
+///
+/// Waits for the first element, so an error before anything is sent can still be reported
+/// with an error status. Later errors abort the response, leaving the array unterminated.
+pub async fn json_array<T>(
+    mut elements: mpsc::Receiver<Result<T, sqlx::Error>>,
+) -> Result<Response, ApiError>
+where
+    T: Serialize + Send + 'static,
+{
+    let first = elements.recv().await.transpose()?;
+
+    let chunks = stream::unfold(
+        (elements, first, true),
+        |(mut elements, next, opening)| async move {
+            let element = match next {
+                Some(element) => element,
+                None if opening => {
+                    return Some((Ok(Bytes::from_static(b"[]")), (elements, None, false)))
+                }
+                None => return None,
+            };
+
+            let mut chunk = if opening {
+                b"[".to_vec()
+            } else {
+                b",".to_vec()
+            };
+            if let Err(e) = serde_json::to_writer(&mut chunk, &element) {
+                return Some((Err(BoxError::from(e)), (elements, None, false)));
+            }
+
+            match elements.recv().await {
+                Some(Ok(following)) => Some((Ok(chunk.into()), (elements, Some(following), false))),
+                Some(Err(e)) => {
+                    error!("Failed whilst streaming a JSON array: {e}");
+                    Some((Err(BoxError::from(e)), (elements, None, false)))
+                }
+                None => {
+                    chunk.push(b']');
+                    Some((Ok(chunk.into()), (elements, None, false)))
+                }
+            }
+        },
+    );
+
+    let headers = [(CONTENT_TYPE, JSON), (VARY, "accept")];
+
+    Ok((headers, Body::from_stream(chunks)).into_response())
+}
+
+/// Newline delimited JSON written a line at a time as the elements are received.
+///
+/// As with [`json_array`], waits for the first element so an early error can still be reported
+/// with an error status. Later errors abort the response, so it can't be mistaken for all of them.
+pub async fn ndjson<T>(
+    mut elements: mpsc::Receiver<Result<T, sqlx::Error>>,
+) -> Result<Response, ApiError>
+where
+    T: Serialize + Send + 'static,
+{
+    let first = elements.recv().await.transpose()?;
+
+    let lines = stream::unfold((elements, first), |(mut elements, next)| async move {
+        let line = match ndjson_line(&next?) {
+            Ok(line) => line,
+            Err(e) => return Some((Err(BoxError::from(e)), (elements, None))),
+        };
+
+        match elements.recv().await {
+            Some(Ok(following)) => Some((Ok(Bytes::from(line)), (elements, Some(following)))),
+            Some(Err(e)) => {
+                error!("Failed whilst streaming newline delimited JSON: {e}");
+                Some((Err(BoxError::from(e)), (elements, None)))
+            }
+            None => Some((Ok(Bytes::from(line)), (elements, None))),
+        }
+    });
+
+    let headers = [(CONTENT_TYPE, NDJSON), (VARY, "accept")];
+
+    Ok((headers, Body::from_stream(lines)).into_response())
+}
+
+/// JSON:API error objects for an [`ErrorResponse`](super::error::ErrorResponse) or similar
+/// error body, with one per invalid field for validation errors
+fn error_objects(status: StatusCode, error: &Value) -> Value {
+    let status_code = status.as_str();
+    let title = status.canonical_reason();
+
+    let mut errors: Vec<Value> = error["errors"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .flat_map(|(field, failures)| {
+            failures
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(move |failure| {
+                    let detail = failure["message"]
+                        .as_str()
+                        .map(str::to_owned)
+                        .unwrap_or_else(|| {
+                            format!("`{field}` failed the `{}` check", failure["code"])
+                        });
+
+                    json!({
+                        "status": status_code,
+                        "code": failure["code"],
+                        "title": title,
+                        "detail": detail,
+                        "source": { "pointer": format!("/data/attributes/{field}") },
+                    })
+                })
+        })
+        .collect();
+
+    if errors.is_empty() {
+        errors.push(json!({
+            "status": status_code,
+            "title": title,
+            "detail": error["message"],
+        }));
+    }
+
+    json!({ "errors": errors })
+}
+
+/// Rewrites JSON error responses as JSON:API error documents, for clients accepting JSON:API
+pub async fn json_api_errors(request: Request, next: Next) -> Response {
+    let wants_json_api = Format::preferred(request.headers()) == Format::JsonApi;
+
+    let response = next.run(request).await;
+
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes() == JSON.as_bytes());
+
+    if !wants_json_api || !is_json || response.status().is_success() {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+
+    let Ok(error) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    parts
+        .headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static(JSON_API));
+    parts.headers.remove(CONTENT_LENGTH);
+
+    let document = error_objects(parts.status, &error);
+
+    Response::from_parts(parts, Body::from(document.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header::ACCEPT, HeaderMap, HeaderValue};
+
+    use super::Format;
+
+    fn accepting(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn json_is_preferred_unless_asked_otherwise() {
+        assert_eq!(Format::preferred(&HeaderMap::new()), Format::Json);
+        assert_eq!(Format::preferred(&accepting("*/*")), Format::Json);
+        assert_eq!(Format::preferred(&accepting("text/html")), Format::Json);
+    }
+
+    #[test]
+    fn the_most_preferred_supported_format_is_chosen() {
+        assert_eq!(
+            Format::preferred(&accepting("application/msgpack")),
+            Format::MessagePack
+        );
+        assert_eq!(
+            Format::preferred(&accepting("application/json;q=0.5, application/msgpack")),
+            Format::MessagePack
+        );
+        assert_eq!(
+            Format::preferred(&accepting("application/msgpack;q=0, */*")),
+            Format::Json
+        );
+        assert_eq!(
+            Format::preferred(&accepting("text/xml, */*;q=0.1")),
+            Format::Xml
+        );
+        assert!(matches!(
+            Format::preferred(&accepting("application/hal+json")),
+            Format::Hal { .. }
+        ));
+        assert_eq!(
+            Format::preferred(&accepting("application/x-ndjson")),
+            Format::NdJson
+        );
+    }
+}
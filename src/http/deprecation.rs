@@ -0,0 +1,255 @@
+//! Announcing routes on their way out, so clients have warning before they are removed.
+//!
+//! A route is deprecated by listing it in [`v1::DEPRECATIONS`](super::v1::DEPRECATIONS).
+//! Responses from it then carry a `Deprecation` header ([RFC 9745]) with when it was
+//! deprecated, a `Sunset` header ([RFC 8594]) with when it will stop working, if decided, and a
+//! `Link` to what to use instead. Its operation is flagged as deprecated in the OpenAPI document.
+//!
+//! [RFC 9745]: https://www.rfc-editor.org/rfc/rfc9745
+//! [RFC 8594]: https://www.rfc-editor.org/rfc/rfc8594
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{
+        header::{HeaderName, LINK},
+        HeaderValue, Method,
+    },
+    middleware::Next,
+    response::Response,
+};
+use time::{format_description::FormatItem, macros::format_description, OffsetDateTime};
+use utoipa::{
+    openapi::{path::PathItemType, Deprecated},
+    Modify,
+};
+
+const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+const SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+/// The HTTP date format, such as `Wed, 31 Dec 2025 23:59:59 GMT`
+pub(super) const HTTP_DATE: &[FormatItem<'_>] = format_description!(
+    "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT"
+);
+
+/// A deprecated route, and when it's going away
+#[derive(Clone, Debug)]
+pub struct Deprecation {
+    pub method: Method,
+    /// The path as routed within the version, such as `/person/:person_uuid`
+    pub path: &'static str,
+    /// When the route was deprecated
+    pub since: OffsetDateTime,
+    /// When the route will stop working, if decided
+    pub sunset: Option<OffsetDateTime>,
+    /// Where to find out what to use instead
+    pub link: Option<&'static str>,
+}
+
+impl Deprecation {
+    /// The path as documented in the OpenAPI document, such as `/person/{person_uuid}`
+    fn documented_path(&self) -> String {
+        self.path
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(parameter) => format!("{{{parameter}}}"),
+                None => segment.to_owned(),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    fn operation(&self) -> Option<PathItemType> {
+        match self.method {
+            Method::GET => Some(PathItemType::Get),
+            Method::POST => Some(PathItemType::Post),
+            Method::PUT => Some(PathItemType::Put),
+            Method::PATCH => Some(PathItemType::Patch),
+            Method::DELETE => Some(PathItemType::Delete),
+            _ => None,
+        }
+    }
+
+    fn description(&self) -> String {
+        let mut description = format!("Deprecated since {}", self.since.date());
+
+        if let Some(sunset) = self.sunset {
+            description.push_str(&format!(", and will be removed on {}", sunset.date()));
+        }
+        if let Some(link) = self.link {
+            description.push_str(&format!(". See {link}"));
+        }
+
+        description
+    }
+}
+
+/// The deprecated routes of a version of the API, nested under `prefix`
+#[derive(Clone, Copy, Debug)]
+pub struct Deprecations {
+    pub prefix: &'static str,
+    pub routes: &'static [Deprecation],
+}
+
+impl Deprecations {
+    fn find(&self, method: &Method, matched: &str) -> Option<&'static Deprecation> {
+        let path = matched.strip_prefix(self.prefix)?;
+
+        self.routes
+            .iter()
+            .find(|route| route.method == method && route.path == path)
+    }
+}
+
+/// Adds the deprecation headers to responses from deprecated routes
+pub async fn announce(
+    State(deprecations): State<Deprecations>,
+    matched: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let deprecation =
+        matched.and_then(|matched| deprecations.find(request.method(), matched.as_str()));
+
+    let mut response = next.run(request).await;
+
+    if let Some(deprecation) = deprecation {
+        let headers = response.headers_mut();
+
+        headers.insert(
+            DEPRECATION,
+            HeaderValue::from_str(&format!("@{}", deprecation.since.unix_timestamp())).unwrap(),
+        );
+
+        if let Some(sunset) = deprecation.sunset.and_then(|s| s.format(HTTP_DATE).ok()) {
+            headers.insert(SUNSET, HeaderValue::from_str(&sunset).unwrap());
+        }
+
+        if let Some(link) = deprecation.link {
+            if let Ok(value) = HeaderValue::from_str(&format!("<{link}>; rel=\"deprecation\"")) {
+                headers.append(LINK, value);
+            }
+        }
+    }
+
+    response
+}
+
+/// Flags the deprecated operations in the OpenAPI document, describing when they go away
+impl Modify for Deprecations {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        for deprecation in self.routes {
+            let operation = openapi
+                .paths
+                .paths
+                .get_mut(&deprecation.documented_path())
+                .zip(deprecation.operation())
+                .and_then(|(item, operation)| item.operations.get_mut(&operation));
+
+            if let Some(operation) = operation {
+                operation.deprecated = Some(Deprecated::True);
+
+                let description = deprecation.description();
+                operation.description = Some(match operation.description.take() {
+                    Some(existing) => format!("{existing}\n\n{description}"),
+                    None => description,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Method, Request},
+        middleware,
+        routing::get,
+        Router,
+    };
+    use time::macros::datetime;
+    use tower::ServiceExt;
+    use utoipa::{
+        openapi::{
+            path::{OperationBuilder, PathItemBuilder, PathItemType},
+            Deprecated, OpenApiBuilder, PathsBuilder,
+        },
+        Modify,
+    };
+
+    use super::{announce, Deprecation, Deprecations};
+
+    const DEPRECATIONS: Deprecations = Deprecations {
+        prefix: "/api/v1",
+        routes: &[Deprecation {
+            method: Method::GET,
+            path: "/old/:id",
+            since: datetime!(2025-01-01 0:00 UTC),
+            sunset: Some(datetime!(2025-12-31 23:59:59 UTC)),
+            link: Some("https://example.com/migrating"),
+        }],
+    };
+
+    /// Layered in the same way as version 1 of the API
+    fn app() -> Router {
+        let routes = Router::new()
+            .route("/old/:id", get(|| async {}))
+            .route("/new/:id", get(|| async {}))
+            .layer(middleware::from_fn_with_state(DEPRECATIONS, announce));
+
+        Router::new().nest("/api/v1", routes)
+    }
+
+    #[tokio::test]
+    async fn deprecated_routes_announce_their_sunset() {
+        let request = Request::get("/api/v1/old/1").body(Body::empty()).unwrap();
+        let response = app().oneshot(request).await.unwrap();
+
+        let headers = response.headers();
+        assert_eq!(headers["deprecation"], "@1735689600");
+        assert_eq!(headers["sunset"], "Wed, 31 Dec 2025 23:59:59 GMT");
+        assert_eq!(
+            headers["link"],
+            r#"<https://example.com/migrating>; rel="deprecation""#
+        );
+    }
+
+    #[tokio::test]
+    async fn other_routes_are_left_alone() {
+        let request = Request::get("/api/v1/new/1").body(Body::empty()).unwrap();
+        let response = app().oneshot(request).await.unwrap();
+
+        assert!(response.headers().get("deprecation").is_none());
+    }
+
+    #[test]
+    fn deprecated_operations_are_flagged() {
+        let item = || {
+            PathItemBuilder::new()
+                .operation(PathItemType::Get, OperationBuilder::new().build())
+                .build()
+        };
+        let mut openapi = OpenApiBuilder::new()
+            .paths(
+                PathsBuilder::new()
+                    .path("/old/{id}", item())
+                    .path("/new/{id}", item()),
+            )
+            .build();
+
+        DEPRECATIONS.modify(&mut openapi);
+
+        let old = &openapi.paths.paths["/old/{id}"].operations[&PathItemType::Get];
+        assert!(old.deprecated == Some(Deprecated::True));
+        assert_eq!(
+            old.description.as_deref(),
+            Some(
+                "Deprecated since 2025-01-01, and will be removed on 2025-12-31. \
+                 See https://example.com/migrating"
+            )
+        );
+
+        let new = &openapi.paths.paths["/new/{id}"].operations[&PathItemType::Get];
+        assert!(new.deprecated.is_none());
+    }
+}
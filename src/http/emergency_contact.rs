@@ -0,0 +1,291 @@
+use axum::{extract::Path, routing::get, Router};
+use hyper::{
+    header::{HeaderName, LOCATION},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::{
+    auth::{ReadUser, WriteUser},
+    content::{Format, Link, Negotiated, Resource, ValidatedPayload},
+    error::ApiError,
+    fields::{self, PhoneNumber},
+    response::Deleted,
+    timestamp, v1,
+};
+use crate::service::emergency_contact::EmergencyContactService;
+
+/// The most emergency contacts one person can have
+pub const MAX_EMERGENCY_CONTACTS: i64 = 5;
+
+#[derive(Debug, Validate, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NewEmergencyContact {
+    #[validate(length(min = 1, max = 128))]
+    #[schema(min_length = 1, max_length = 128)]
+    #[serde(deserialize_with = "fields::normalized")]
+    pub name: String,
+    /// How the contact is related to the person, such as `Sister` or `Neighbour`
+    #[validate(length(min = 1, max = 64))]
+    #[schema(min_length = 1, max_length = 64)]
+    #[serde(deserialize_with = "fields::normalized")]
+    pub relationship: String,
+    /// 7 to 15 digits, optionally starting with `+`, which may be broken up with spaces, dots,
+    /// dashes and parentheses
+    #[schema(value_type = String, example = "+44 20 7946 0000")]
+    pub phone: PhoneNumber,
+}
+
+/// Someone to contact about a person in an emergency
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EmergencyContact {
+    pub id: Uuid,
+    pub person_id: Uuid,
+    pub name: String,
+    pub relationship: String,
+    /// The phone number, without any spaces or punctuation besides a leading `+`
+    pub phone: String,
+    /// When the contact was added, as an RFC 3339 timestamp in UTC, or milliseconds since the
+    /// Unix epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub created: OffsetDateTime,
+    /// When the contact was last edited, in the same format as `created`
+    #[serde(with = "timestamp")]
+    pub last_edited: OffsetDateTime,
+}
+
+impl Resource for EmergencyContact {
+    const ELEMENT: &'static str = "emergencyContact";
+    const COLLECTION: &'static str = "emergencyContacts";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        let contact = format!(
+            "{}/person/{}/emergency-contacts/{}",
+            v1::PREFIX,
+            self.person_id,
+            self.id
+        );
+
+        vec![
+            ("self", Link::to(&contact)),
+            ("update", Link::to(&contact)),
+            ("delete", Link::to(&contact)),
+        ]
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        let person = format!("{}/person/{}", v1::PREFIX, self.person_id);
+
+        vec![("person", Link::to(person))]
+    }
+}
+
+/// List a person's emergency contacts
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "emergency contact",
+    path = "/person/{person_uuid}/emergency-contacts",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "The person's emergency contacts, first added first", body = [EmergencyContact]),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn list_emergency_contacts(
+    user: ReadUser,
+    contacts: EmergencyContactService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Negotiated<Vec<EmergencyContact>>, ApiError> {
+    let contacts = contacts.list(person_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved {} emergency contact(s) of person '{}'",
+        user.username,
+        contacts.len(),
+        person_uuid
+    );
+
+    Ok(Negotiated(format, contacts))
+}
+
+/// Add an emergency contact for a person
+///
+/// A person can have at most 5 emergency contacts.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    post,
+    tag = "emergency contact",
+    path = "/person/{person_uuid}/emergency-contacts",
+    request_body = NewEmergencyContact,
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 201, description = "Emergency contact added successfully", body = EmergencyContact,
+            headers(("location" = String, description = "The URL of the added emergency contact"))),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+        (status = 409, description = "The person already has as many emergency contacts as they can", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn add_emergency_contact(
+    user: WriteUser,
+    contacts: EmergencyContactService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+    ValidatedPayload(request): ValidatedPayload<NewEmergencyContact>,
+) -> Result<
+    (
+        StatusCode,
+        [(HeaderName, String); 1],
+        Negotiated<EmergencyContact>,
+    ),
+    ApiError,
+> {
+    let contact = contacts.add(&user.username, person_uuid, &request).await?;
+
+    let location = format!(
+        "{}/person/{person_uuid}/emergency-contacts/{}",
+        v1::PREFIX,
+        contact.id
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        [(LOCATION, location)],
+        Negotiated(format, contact),
+    ))
+}
+
+/// Get one of a person's emergency contacts
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "emergency contact",
+    path = "/person/{person_uuid}/emergency-contacts/{contact_uuid}",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        ("contact_uuid" = Uuid, Path, description = "The UUID of the emergency contact")
+    ),
+    responses(
+        (status = 200, description = "The emergency contact matching the given UUID", body = EmergencyContact),
+        (status = 404, description = "Emergency contact not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn get_emergency_contact(
+    user: ReadUser,
+    contacts: EmergencyContactService,
+    format: Format,
+    Path((person_uuid, contact_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Negotiated<EmergencyContact>, ApiError> {
+    let contact = contacts.find(person_uuid, contact_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved emergency contact '{}'",
+        user.username, contact_uuid
+    );
+
+    Ok(Negotiated(format, contact))
+}
+
+/// Replace one of a person's emergency contacts
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    put,
+    tag = "emergency contact",
+    path = "/person/{person_uuid}/emergency-contacts/{contact_uuid}",
+    request_body = NewEmergencyContact,
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        ("contact_uuid" = Uuid, Path, description = "The UUID of the emergency contact")
+    ),
+    responses(
+        (status = 200, description = "Emergency contact updated successfully", body = EmergencyContact),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Emergency contact not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn update_emergency_contact(
+    user: WriteUser,
+    contacts: EmergencyContactService,
+    format: Format,
+    Path((person_uuid, contact_uuid)): Path<(Uuid, Uuid)>,
+    ValidatedPayload(request): ValidatedPayload<NewEmergencyContact>,
+) -> Result<Negotiated<EmergencyContact>, ApiError> {
+    let contact = contacts
+        .update(&user.username, person_uuid, contact_uuid, &request)
+        .await?;
+
+    Ok(Negotiated(format, contact))
+}
+
+/// Remove one of a person's emergency contacts
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    delete,
+    tag = "emergency contact",
+    path = "/person/{person_uuid}/emergency-contacts/{contact_uuid}",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        ("contact_uuid" = Uuid, Path, description = "The UUID of the emergency contact")
+    ),
+    responses(
+        (status = 204, description = "Emergency contact deleted successfully"),
+        (status = 404, description = "Emergency contact not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn remove_emergency_contact(
+    user: WriteUser,
+    contacts: EmergencyContactService,
+    Path((person_uuid, contact_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Deleted, ApiError> {
+    contacts
+        .remove(&user.username, person_uuid, contact_uuid)
+        .await?;
+
+    Ok(Deleted)
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/person/:person_uuid/emergency-contacts",
+            get(list_emergency_contacts).post(add_emergency_contact),
+        )
+        .route(
+            "/person/:person_uuid/emergency-contacts/:contact_uuid",
+            get(get_emergency_contact)
+                .put(update_emergency_contact)
+                .delete(remove_emergency_contact),
+        )
+}
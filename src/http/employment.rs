@@ -0,0 +1,307 @@
+use axum::{extract::Path, routing::get, Router};
+use hyper::{
+    header::{HeaderName, LOCATION},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime};
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use super::{
+    auth::{ReadUser, WriteUser},
+    content::{Format, Link, Negotiated, Resource, ValidatedPayload},
+    error::ApiError,
+    fields,
+    response::Deleted,
+    timestamp, v1,
+};
+use crate::service::employment::EmploymentService;
+
+#[derive(Debug, Validate, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "ends_after_it_starts"))]
+pub struct NewEmployment {
+    #[validate(length(min = 1, max = 128))]
+    #[schema(min_length = 1, max_length = 128)]
+    #[serde(deserialize_with = "fields::normalized")]
+    pub employer: String,
+    #[validate(length(min = 1, max = 128))]
+    #[schema(min_length = 1, max_length = 128)]
+    #[serde(deserialize_with = "fields::normalized")]
+    pub role: String,
+    /// The first day of the employment
+    pub start_date: Date,
+    /// The last day of the employment, or nothing while it's ongoing
+    #[serde(default)]
+    pub end_date: Option<Date>,
+}
+
+fn ends_after_it_starts(employment: &NewEmployment) -> Result<(), ValidationError> {
+    match employment.end_date {
+        Some(end_date) if end_date < employment.start_date => {
+            Err(ValidationError::new("ends_after_it_starts")
+                .with_message("endDate must not be before startDate".into()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A period a person worked somewhere
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Employment {
+    pub id: Uuid,
+    pub person_id: Uuid,
+    pub employer: String,
+    pub role: String,
+    /// The first day of the employment
+    pub start_date: Date,
+    /// The last day of the employment, or nothing while it's ongoing
+    pub end_date: Option<Date>,
+    /// When the employment was recorded, as an RFC 3339 timestamp in UTC, or milliseconds since
+    /// the Unix epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub created: OffsetDateTime,
+    /// When the employment was last edited, in the same format as `created`
+    #[serde(with = "timestamp")]
+    pub last_edited: OffsetDateTime,
+}
+
+impl Resource for Employment {
+    const ELEMENT: &'static str = "employment";
+    const COLLECTION: &'static str = "employments";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        let employment = format!(
+            "{}/person/{}/employments/{}",
+            v1::PREFIX,
+            self.person_id,
+            self.id
+        );
+
+        vec![
+            ("self", Link::to(&employment)),
+            ("update", Link::to(&employment)),
+            ("delete", Link::to(&employment)),
+        ]
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        let person = format!("{}/person/{}", v1::PREFIX, self.person_id);
+
+        vec![("person", Link::to(person))]
+    }
+}
+
+/// List a person's employment history
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "employment",
+    path = "/person/{person_uuid}/employments",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "The person's employment, earliest first", body = [Employment]),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn list_employments(
+    user: ReadUser,
+    employments: EmploymentService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Negotiated<Vec<Employment>>, ApiError> {
+    let employments = employments.list(person_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved {} employment(s) of person '{}'",
+        user.username,
+        employments.len(),
+        person_uuid
+    );
+
+    Ok(Negotiated(format, employments))
+}
+
+/// Add to a person's employment history
+///
+/// The employment may not overlap any of the person's other employment, counting both the
+/// start and end dates, and employment without an end date as ongoing.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    post,
+    tag = "employment",
+    path = "/person/{person_uuid}/employments",
+    request_body = NewEmployment,
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 201, description = "Employment added successfully", body = Employment,
+            headers(("location" = String, description = "The URL of the added employment"))),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+        (status = 409, description = "The employment overlaps another of the person's", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn add_employment(
+    user: WriteUser,
+    employments: EmploymentService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+    ValidatedPayload(request): ValidatedPayload<NewEmployment>,
+) -> Result<
+    (
+        StatusCode,
+        [(HeaderName, String); 1],
+        Negotiated<Employment>,
+    ),
+    ApiError,
+> {
+    let employment = employments
+        .add(&user.username, person_uuid, &request)
+        .await?;
+
+    let location = format!(
+        "{}/person/{person_uuid}/employments/{}",
+        v1::PREFIX,
+        employment.id
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        [(LOCATION, location)],
+        Negotiated(format, employment),
+    ))
+}
+
+/// Get one of a person's employments
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "employment",
+    path = "/person/{person_uuid}/employments/{employment_uuid}",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        ("employment_uuid" = Uuid, Path, description = "The UUID of the employment")
+    ),
+    responses(
+        (status = 200, description = "The employment matching the given UUID", body = Employment),
+        (status = 404, description = "Employment not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn get_employment(
+    user: ReadUser,
+    employments: EmploymentService,
+    format: Format,
+    Path((person_uuid, employment_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Negotiated<Employment>, ApiError> {
+    let employment = employments.find(person_uuid, employment_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved employment '{}'",
+        user.username, employment_uuid
+    );
+
+    Ok(Negotiated(format, employment))
+}
+
+/// Replace one of a person's employments
+///
+/// The employment may not overlap any of the person's other employment, as when adding it.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    put,
+    tag = "employment",
+    path = "/person/{person_uuid}/employments/{employment_uuid}",
+    request_body = NewEmployment,
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        ("employment_uuid" = Uuid, Path, description = "The UUID of the employment")
+    ),
+    responses(
+        (status = 200, description = "Employment updated successfully", body = Employment),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Person or employment not found", body = ErrorResponse),
+        (status = 409, description = "The employment overlaps another of the person's", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn update_employment(
+    user: WriteUser,
+    employments: EmploymentService,
+    format: Format,
+    Path((person_uuid, employment_uuid)): Path<(Uuid, Uuid)>,
+    ValidatedPayload(request): ValidatedPayload<NewEmployment>,
+) -> Result<Negotiated<Employment>, ApiError> {
+    let employment = employments
+        .update(&user.username, person_uuid, employment_uuid, &request)
+        .await?;
+
+    Ok(Negotiated(format, employment))
+}
+
+/// Remove one of a person's employments
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    delete,
+    tag = "employment",
+    path = "/person/{person_uuid}/employments/{employment_uuid}",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        ("employment_uuid" = Uuid, Path, description = "The UUID of the employment")
+    ),
+    responses(
+        (status = 204, description = "Employment deleted successfully"),
+        (status = 404, description = "Employment not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn remove_employment(
+    user: WriteUser,
+    employments: EmploymentService,
+    Path((person_uuid, employment_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Deleted, ApiError> {
+    employments
+        .remove(&user.username, person_uuid, employment_uuid)
+        .await?;
+
+    Ok(Deleted)
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/person/:person_uuid/employments",
+            get(list_employments).post(add_employment),
+        )
+        .route(
+            "/person/:person_uuid/employments/:employment_uuid",
+            get(get_employment)
+                .put(update_employment)
+                .delete(remove_employment),
+        )
+}
@@ -1,22 +1,193 @@
-use axum::{response::IntoResponse, Json};
-use hyper::StatusCode;
+//! The errors handlers respond with.
+//!
+//! Internal failures are given [context](Context) saying what was being done when they happened.
+//! Clients only see the generic message of the failure, while the [`Report`] of it, with the
+//! context and every underlying cause, is logged when the response is made.
+
+use std::{borrow::Cow, error::Error, fmt};
+
+use axum::{
+    extract::rejection::{JsonRejection, QueryRejection},
+    http::HeaderValue,
+    response::IntoResponse,
+    Json,
+};
+use hyper::{
+    header::{CONTENT_RANGE, RETRY_AFTER},
+    StatusCode,
+};
 use serde::Serialize;
 use serde_with::DisplayFromStr;
+use tracing::{error, warn};
 use utoipa::ToSchema;
 use validator::ValidationErrors;
 
+use crate::failover;
+
 #[derive(thiserror::Error, Debug)]
 pub enum ApiError {
     #[error("{0}")]
     NotFound(String),
     #[error("{0}")]
     Conflict(String),
+    #[error("{0}")]
+    Locked(String),
+    #[error("{0}")]
+    Gone(String),
+    #[error("{0}")]
+    PreconditionRequired(String),
+    #[error("{0}")]
+    PreconditionFailed(String),
     #[error("An error occurred whilst querying the database")]
-    DatabaseError(#[from] sqlx::Error),
+    DatabaseError(#[source] sqlx::Error),
+    #[error("The service is busy, try again shortly")]
+    DatabaseBusy(#[source] sqlx::Error),
+    #[error("The database can't be reached, try again shortly")]
+    DatabaseUnreachable(#[source] sqlx::Error),
     #[error("Invalid request")]
     ValidationError(#[from] ValidationErrors),
+    #[error("{}", .0.body_text())]
+    InvalidBody(#[from] JsonRejection),
+    #[error("Failed to parse the request body as MessagePack: {0}")]
+    InvalidMessagePack(#[from] rmp_serde::decode::Error),
+    #[error("Invalid JSON:API resource attributes: {0}")]
+    InvalidJsonApi(#[from] serde_json::Error),
+    #[error("Invalid merge patch: {0}")]
+    InvalidPatch(String),
+    #[error("{}", .0.body_text())]
+    InvalidQuery(#[from] QueryRejection),
+    #[error("{0}")]
+    Unavailable(String),
+    #[error("Failed to generate the export")]
+    ExportError(#[source] Box<dyn Error + Send + Sync>),
+    #[error("Unsupported content encoding: {0}")]
+    UnsupportedEncoding(String),
+    #[error("Unsupported content type: {0}")]
+    UnsupportedMediaType(String),
+    #[error("Failed to read the request body: {0}")]
+    UnreadableBody(String),
+    #[error("Failed to decompress the request body: {0}")]
+    InvalidEncoding(String),
+    #[error("The request body must be at most {0} bytes once decompressed")]
+    PayloadTooLarge(usize),
+    #[error("The photo must be at most {0} bytes")]
+    PhotoTooLarge(usize),
+    #[error(
+        "At most {0} people can be exported at once, so export them with \
+         POST /api/v1/person/export-jobs instead"
+    )]
+    ExportTooLarge(i64),
+    #[error("The range starts beyond the {0} item(s) in the collection")]
+    RangeNotSatisfiable(i64),
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
+    #[error("Invalid order: {0}")]
+    InvalidOrder(String),
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(String),
+    #[error("Unknown field(s): {}", .0.join(", "))]
+    UnknownFields(Vec<String>),
+    /// Another error, with what was being done when it happened. Only the message of the error
+    /// is shown to clients.
+    #[error("{source}")]
+    Context {
+        context: Cow<'static, str>,
+        source: Box<ApiError>,
+    },
 }
 
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            // every connection is in use, so the request is turned away rather than left waiting
+            sqlx::Error::PoolTimedOut => ApiError::DatabaseBusy(e),
+            e if unreachable(&e) => ApiError::DatabaseUnreachable(e),
+            e => ApiError::DatabaseError(e),
+        }
+    }
+}
+
+/// Whether the database failed to answer at all, such as while the primary is failing over,
+/// rather than refusing the query. Writes sent to a primary since demoted to a standby are
+/// refused as read only.
+fn unreachable(e: &sqlx::Error) -> bool {
+    match e {
+        sqlx::Error::Io(_)
+        | sqlx::Error::Tls(_)
+        | sqlx::Error::PoolClosed
+        | sqlx::Error::WorkerCrashed => true,
+        sqlx::Error::Database(e) => e.code().is_some_and(|code| {
+            // connection exceptions, shutting down, starting up and read only transactions
+            code.starts_with("08") || matches!(&*code, "57P01" | "57P02" | "57P03" | "25006")
+        }),
+        _ => false,
+    }
+}
+
+/// Adds context to the error of a result, turning it into an [`ApiError`]
+pub trait Context<T> {
+    fn context(self, context: impl Into<Cow<'static, str>>) -> Result<T, ApiError>;
+
+    /// Adds context built only when there's an error
+    fn with_context<C, F>(self, context: F) -> Result<T, ApiError>
+    where
+        C: Into<Cow<'static, str>>,
+        F: FnOnce() -> C;
+}
+
+impl<T, E> Context<T> for Result<T, E>
+where
+    E: Into<ApiError>,
+{
+    fn context(self, context: impl Into<Cow<'static, str>>) -> Result<T, ApiError> {
+        self.map_err(|e| e.into().context(context))
+    }
+
+    fn with_context<C, F>(self, context: F) -> Result<T, ApiError>
+    where
+        C: Into<Cow<'static, str>>,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|e| e.into().context(context()))
+    }
+}
+
+/// Formats an error with each of its causes in turn, separated by colons, for the logs.
+///
+/// Context is shown in place of the message it wraps, and a cause is left out when the error
+/// before it already ends with its message, as errors often repeat their source.
+pub struct Report<'a>(pub &'a (dyn Error + 'static));
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut previous = String::new();
+        let mut next = Some(self.0);
+
+        while let Some(error) = next {
+            let message = match error.downcast_ref::<ApiError>() {
+                Some(ApiError::Context { context, .. }) => context.to_string(),
+                _ => error.to_string(),
+            };
+
+            if !previous.ends_with(&message) {
+                if !previous.is_empty() {
+                    f.write_str(": ")?;
+                }
+                f.write_str(&message)?;
+                previous = message;
+            }
+
+            next = error.source();
+        }
+
+        Ok(())
+    }
+}
+
+/// How long clients are asked to wait before retrying when the database is busy or unreachable,
+/// in seconds
+const RETRY_AFTER_SECONDS: u32 = 1;
+
 #[serde_with::serde_as]
 #[serde_with::skip_serializing_none]
 #[derive(Serialize, ToSchema)]
@@ -24,35 +195,177 @@ pub struct ErrorResponse<'a> {
     #[serde_as(as = "DisplayFromStr")]
     #[schema(value_type=String)]
     message: &'a ApiError,
-    #[schema(value_type=Option<Any>)]
+    #[schema(value_type=Option<Object>)]
     errors: Option<&'a ValidationErrors>,
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
-        let validation_errors = match &self {
+        self.log();
+
+        let validation_errors = match self.root() {
             ApiError::ValidationError(e) => Some(e),
             _ => None,
         };
 
-        (
+        let mut response = (
             self.status_code(),
             Json(ErrorResponse {
                 message: &self,
                 errors: validation_errors,
             }),
         )
-            .into_response()
+            .into_response();
+
+        match self.root() {
+            ApiError::DatabaseBusy(_) => {
+                response
+                    .headers_mut()
+                    .insert(RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECONDS));
+            }
+            // reads are retried while the database fails over
+            ApiError::DatabaseUnreachable(_) => {
+                response
+                    .headers_mut()
+                    .insert(RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECONDS));
+                response.extensions_mut().insert(failover::Unreachable);
+            }
+            // tells the client how many items there are to ask for instead
+            ApiError::RangeNotSatisfiable(total) => {
+                let content_range = HeaderValue::from_str(&format!("items */{total}")).unwrap();
+                response.headers_mut().insert(CONTENT_RANGE, content_range);
+            }
+            _ => {}
+        }
+
+        response
     }
 }
 
 impl ApiError {
-    fn status_code(&self) -> StatusCode {
+    /// Wraps the error with what was being done when it happened
+    pub fn context(self, context: impl Into<Cow<'static, str>>) -> ApiError {
+        ApiError::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The error without any context
+    pub(crate) fn root(&self) -> &ApiError {
+        match self {
+            ApiError::Context { source, .. } => source.root(),
+            e => e,
+        }
+    }
+
+    /// Logs the full cause of an internal failure, as the client only sees its message, and
+    /// warns when the database is too busy to serve requests or can't be reached
+    pub(crate) fn log(&self) {
+        if let ApiError::DatabaseBusy(_) | ApiError::DatabaseUnreachable(_) = self.root() {
+            warn!("{}", Report(self));
+        } else if self.status_code() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("{}", Report(self));
+        }
+    }
+
+    pub(crate) fn status_code(&self) -> StatusCode {
         match self {
             ApiError::NotFound(_) => StatusCode::NOT_FOUND,
             ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Locked(_) => StatusCode::LOCKED,
+            ApiError::Gone(_) => StatusCode::GONE,
+            ApiError::PreconditionRequired(_) => StatusCode::PRECONDITION_REQUIRED,
+            ApiError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
             ApiError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::DatabaseBusy(_) | ApiError::DatabaseUnreachable(_) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
             ApiError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            ApiError::InvalidBody(_) => StatusCode::BAD_REQUEST,
+            ApiError::InvalidMessagePack(_) => StatusCode::BAD_REQUEST,
+            ApiError::InvalidJsonApi(_) => StatusCode::BAD_REQUEST,
+            ApiError::InvalidPatch(_) => StatusCode::BAD_REQUEST,
+            ApiError::InvalidQuery(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::ExportError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::UnsupportedEncoding(_) | ApiError::UnsupportedMediaType(_) => {
+                StatusCode::UNSUPPORTED_MEDIA_TYPE
+            }
+            ApiError::UnreadableBody(_) => StatusCode::BAD_REQUEST,
+            ApiError::InvalidEncoding(_) => StatusCode::BAD_REQUEST,
+            ApiError::PayloadTooLarge(_)
+            | ApiError::PhotoTooLarge(_)
+            | ApiError::ExportTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::RangeNotSatisfiable(_) => StatusCode::RANGE_NOT_SATISFIABLE,
+            ApiError::InvalidFilter(_) | ApiError::InvalidOrder(_) => StatusCode::BAD_REQUEST,
+            ApiError::InvalidCursor(_) => StatusCode::BAD_REQUEST,
+            ApiError::UnknownFields(_) => StatusCode::BAD_REQUEST,
+            ApiError::Context { source, .. } => source.status_code(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::to_bytes, response::IntoResponse};
+    use hyper::StatusCode;
+
+    use super::{ApiError, Context, Report};
+
+    fn failed_query() -> Result<(), ApiError> {
+        Err(sqlx::Error::Protocol("unexpected message".to_owned()))
+            .context("Failed to find person '1'")
+    }
+
+    #[test]
+    fn reports_include_the_context_and_every_cause() {
+        let error = failed_query().unwrap_err();
+
+        assert_eq!(
+            Report(&error).to_string(),
+            "Failed to find person '1': An error occurred whilst querying the database: \
+             encountered unexpected or invalid data: unexpected message"
+        );
+    }
+
+    #[tokio::test]
+    async fn pool_timeouts_are_retriable() {
+        let response = Err::<(), _>(sqlx::Error::PoolTimedOut)
+            .context("Failed to list people")
+            .unwrap_err()
+            .into_response();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["retry-after"], "1");
+    }
+
+    #[tokio::test]
+    async fn unreachable_databases_are_marked_for_retrying() {
+        let io = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        let response = Err::<(), _>(sqlx::Error::Io(io))
+            .context("Failed to find person '1'")
+            .unwrap_err()
+            .into_response();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["retry-after"], "1");
+        assert!(response
+            .extensions()
+            .get::<crate::failover::Unreachable>()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn responses_only_include_the_message_of_the_error() {
+        let response = failed_query().unwrap_err().into_response();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(
+            body,
+            r#"{"message":"An error occurred whilst querying the database"}"#
+        );
+    }
+}
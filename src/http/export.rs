@@ -0,0 +1,369 @@
+//! Exports of people for use outside of the service, as printable records or spreadsheets.
+
+use std::borrow::Cow;
+
+use axum::{
+    extract::Path,
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use printpdf::{
+    BuiltinFont, IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference,
+};
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook, XlsxError};
+use time::{format_description::well_known::Rfc3339, macros::format_description, OffsetDateTime};
+use tracing::info;
+use uuid::Uuid;
+
+use super::{
+    address::Address,
+    auth::ReadUser,
+    error::{ApiError, Context},
+    limit::{Limit, MAX_LIMIT},
+    person::Person,
+    person_history::HistoryEntry,
+};
+use crate::service::{
+    address::AddressService, person::PersonService, person_history::PersonHistoryService,
+};
+
+pub(crate) const XLSX: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+// A4, in millimetres
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+
+fn describe(change_type: &str) -> &str {
+    match change_type {
+        "person.created" => "Record created",
+        "person.updated" => "Details updated",
+        "person.deleted" => "Record deleted",
+        "person.archived" => "Record archived",
+        "person.restored" => "Record restored",
+        other => other,
+    }
+}
+
+/// Lays out lines of text down A4 pages, starting a new page when one fills up
+struct PdfWriter {
+    document: PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    y: f32,
+}
+
+impl PdfWriter {
+    fn new(title: &str) -> Result<Self, printpdf::Error> {
+        let (document, page, layer) =
+            PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+
+        Ok(PdfWriter {
+            layer: document.get_page(page).get_layer(layer),
+            regular: document.add_builtin_font(BuiltinFont::Helvetica)?,
+            bold: document.add_builtin_font(BuiltinFont::HelveticaBold)?,
+            document,
+            y: PAGE_HEIGHT - MARGIN,
+        })
+    }
+
+    fn write(&mut self, text: &str, size: f32, bold: bool) {
+        // points to millimetres, with some leading
+        let line_height = size * 0.3528 * 1.5;
+
+        if self.y - line_height < MARGIN {
+            let (page, layer) = self
+                .document
+                .add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+            self.layer = self.document.get_page(page).get_layer(layer);
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+
+        self.y -= line_height;
+
+        let font = if bold { &self.bold } else { &self.regular };
+        self.layer
+            .use_text(text, size, Mm(MARGIN), Mm(self.y), font);
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.y -= 4.0;
+        self.write(text, 14.0, true);
+    }
+
+    fn field(&mut self, label: &str, value: &str) {
+        self.write(&format!("{label}: {value}"), 11.0, false);
+    }
+
+    fn finish(self) -> Result<Vec<u8>, printpdf::Error> {
+        self.document.save_to_bytes()
+    }
+}
+
+fn render(
+    person: &Person,
+    address: Option<&Address>,
+    history: &[HistoryEntry],
+) -> Result<Vec<u8>, printpdf::Error> {
+    let date = format_description!("[day] [month repr:short] [year]");
+    let timestamp = format_description!("[year]-[month]-[day] [hour]:[minute] UTC");
+    let format_timestamp = |t: OffsetDateTime| {
+        t.to_offset(time::UtcOffset::UTC)
+            .format(timestamp)
+            .unwrap_or_default()
+    };
+
+    let name = format!("{} {}", person.first_name, person.family_name);
+    let mut pdf = PdfWriter::new(&format!("Person record: {name}"))?;
+
+    pdf.write(&name, 20.0, true);
+
+    pdf.heading("Details");
+    pdf.field("ID", &person.id.to_string());
+    pdf.field("First name", &person.first_name);
+    pdf.field("Family name", &person.family_name);
+    pdf.field(
+        "Date of birth",
+        &person.date_of_birth.format(date).unwrap_or_default(),
+    );
+    pdf.field("Created", &format_timestamp(person.created));
+    pdf.field("Last edited", &format_timestamp(person.last_edited));
+
+    pdf.heading("Address");
+    match address {
+        Some(address) => {
+            let lines = [
+                Some(&address.building),
+                address.street.as_ref(),
+                address.town_or_city.as_ref(),
+                Some(&address.postcode),
+            ];
+            for line in lines.into_iter().flatten() {
+                pdf.write(line, 11.0, false);
+            }
+        }
+        None => pdf.write("No current address", 11.0, false),
+    }
+
+    pdf.heading("History");
+    if history.is_empty() {
+        pdf.write("No recent changes", 11.0, false);
+    }
+    for entry in history {
+        pdf.field(
+            &format_timestamp(entry.changed),
+            &format!("{} by {}", describe(&entry.change_type), entry.changed_by),
+        );
+    }
+
+    pdf.write("", 11.0, false);
+    pdf.write(
+        &format!("Generated {}", format_timestamp(OffsetDateTime::now_utc())),
+        9.0,
+        false,
+    );
+
+    pdf.finish()
+}
+
+/// Export a person as a PDF
+///
+/// A printable summary of the person's details, current address and the most recent changes
+/// in their history, with who made them.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/{person_uuid}/export.pdf",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "The person's record as a PDF", content_type = "application/pdf", body = Vec<u8>),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+async fn export_person_pdf(
+    user: ReadUser,
+    people: PersonService,
+    addresses: AddressService,
+    history: PersonHistoryService,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let person = people.find(person_uuid).await?;
+    let address = addresses.find_for_person(person_uuid).await?;
+    let history = history.recent(person_uuid, Limit::default()).await?;
+
+    let pdf = render(&person, address.as_ref(), &history)
+        .map_err(|e| ApiError::ExportError(e.into()))
+        .with_context(|| format!("Failed to render person '{person_uuid}' as a PDF"))?;
+
+    info!(
+        "Client '{}' exported person '{}' as a PDF",
+        user.username, person_uuid
+    );
+
+    Ok((
+        [
+            (CONTENT_TYPE, "application/pdf".to_owned()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"person-{person_uuid}.pdf\""),
+            ),
+        ],
+        pdf,
+    ))
+}
+
+const SPREADSHEET_COLUMNS: [(&str, f64); 6] = [
+    ("ID", 38.0),
+    ("First name", 20.0),
+    ("Family name", 20.0),
+    ("Date of birth", 14.0),
+    ("Created", 20.0),
+    ("Last edited", 20.0),
+];
+
+/// Writes one row per person beneath a header row, with dates and timestamps as Excel values
+pub(crate) fn spreadsheet(people: &[Person]) -> Result<Vec<u8>, XlsxError> {
+    let header = Format::new().set_bold();
+    let date = Format::new().set_num_format("yyyy-mm-dd");
+    let timestamp = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+
+    let mut workbook = Workbook::new();
+    // rows are flushed to a temporary file as they're written, rather than held in memory
+    let worksheet = workbook.add_worksheet_with_constant_memory();
+    worksheet.set_name("People")?;
+
+    for (column, (title, width)) in SPREADSHEET_COLUMNS.into_iter().enumerate() {
+        let column = column as u16;
+        worksheet.write_string_with_format(0, column, title, &header)?;
+        worksheet.set_column_width(column, width)?;
+    }
+
+    for (index, person) in people.iter().enumerate() {
+        let row = index as u32 + 1;
+        let dob = person.date_of_birth;
+        let date_of_birth =
+            ExcelDateTime::from_ymd(dob.year() as u16, dob.month() as u8, dob.day())?;
+
+        worksheet.write_string(row, 0, person.id.to_string())?;
+        worksheet.write_string(row, 1, &person.first_name)?;
+        worksheet.write_string(row, 2, &person.family_name)?;
+        worksheet.write_datetime_with_format(row, 3, &date_of_birth, &date)?;
+        worksheet.write_datetime_with_format(
+            row,
+            4,
+            &ExcelDateTime::from_timestamp(person.created.unix_timestamp())?,
+            &timestamp,
+        )?;
+        worksheet.write_datetime_with_format(
+            row,
+            5,
+            &ExcelDateTime::from_timestamp(person.last_edited.unix_timestamp())?,
+            &timestamp,
+        )?;
+    }
+
+    worksheet.set_freeze_panes(1, 0)?;
+    worksheet.autofilter(
+        0,
+        0,
+        people.len() as u32,
+        SPREADSHEET_COLUMNS.len() as u16 - 1,
+    )?;
+
+    workbook.save_to_buffer()
+}
+
+/// Quotes a CSV field when it holds a comma, quote or line break, doubling any quotes
+fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Writes one line per person beneath a header line, with dates and timestamps as RFC 3339
+pub(crate) fn csv(people: &[Person]) -> Vec<u8> {
+    let mut csv = String::from("id,firstName,familyName,dateOfBirth,created,lastEdited\r\n");
+
+    for person in people {
+        let fields = [
+            person.id.to_string(),
+            csv_field(&person.first_name).into_owned(),
+            csv_field(&person.family_name).into_owned(),
+            person.date_of_birth.to_string(),
+            person.created.format(&Rfc3339).unwrap_or_default(),
+            person.last_edited.format(&Rfc3339).unwrap_or_default(),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+
+    csv.into_bytes()
+}
+
+/// Export people as a spreadsheet
+///
+/// An Excel workbook with a row for each person, oldest first, with typed date columns. At most
+/// 1000 people can be exported this way, more being refused rather than cut short, so larger
+/// exports are made with `POST /person/export-jobs`.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/export.xlsx",
+    responses(
+        (status = 200, description = "People as an Excel workbook", content_type = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", body = Vec<u8>),
+        (status = 413, description = "There are more people than can be exported at once", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+async fn export_people_xlsx(
+    user: ReadUser,
+    people: PersonService,
+) -> Result<impl IntoResponse, ApiError> {
+    if people.count(None).await? > MAX_LIMIT {
+        return Err(ApiError::ExportTooLarge(MAX_LIMIT));
+    }
+
+    let people = people.list(Limit::new(Some(MAX_LIMIT))).await?;
+    let count = people.len();
+
+    let workbook = tokio::task::spawn_blocking(move || spreadsheet(&people))
+        .await
+        .map_err(|e| ApiError::ExportError(e.into()))
+        .and_then(|result| result.map_err(|e| ApiError::ExportError(e.into())))
+        .context("Failed to write people to a spreadsheet")?;
+
+    info!(
+        "Client '{}' exported {} person(s) as a spreadsheet",
+        user.username, count
+    );
+
+    Ok((
+        [
+            (CONTENT_TYPE, XLSX),
+            (CONTENT_DISPOSITION, "attachment; filename=\"people.xlsx\""),
+        ],
+        workbook,
+    ))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/person/export.xlsx", get(export_people_xlsx))
+        .route("/person/:person_uuid/export.pdf", get(export_person_pdf))
+}
@@ -0,0 +1,254 @@
+//! Exports of people generated in the background, for lists too large to be written while the
+//! client waits.
+//!
+//! Posting an export job queues a `person.export` job and answers straight away. Once the job
+//! has run, the export gives a link to download the file from until it expires, after which the
+//! file is discarded.
+
+use axum::{
+    extract::Path,
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::IntoResponse,
+    routing::{get, post},
+    Router,
+};
+use hyper::{
+    header::{HeaderName, LOCATION},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::{
+    auth::ReadUser,
+    content::{Format, Link, Negotiated, Resource, ValidatedPayload},
+    error::ApiError,
+    export::XLSX,
+    timestamp, v1,
+};
+use crate::service::person_export::PersonExportService;
+
+/// The kinds of file people can be exported as
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Xlsx => XLSX,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum ExportStatus {
+    Pending,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Validate, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NewExportJob {
+    pub format: ExportFormat,
+    /// Only export people matching this RSQL expression, as when listing people
+    #[schema(max_length = 2000, example = "familyName==Smith")]
+    #[validate(length(max = 2000))]
+    pub filter: Option<String>,
+}
+
+/// An export of people and how far it has got
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportJob {
+    pub id: Uuid,
+    pub format: ExportFormat,
+    pub filter: Option<String>,
+    pub status: ExportStatus,
+    /// How many people were exported, once completed
+    pub row_count: Option<i64>,
+    /// Why the export failed
+    pub error: Option<String>,
+    /// Where to download the file from, while it's available
+    pub download_url: Option<String>,
+    /// Until when the file can be downloaded, in the same format as `created`
+    #[serde(default, with = "timestamp::option")]
+    pub expires: Option<OffsetDateTime>,
+    /// When the export was asked for, as an RFC 3339 timestamp in UTC, or milliseconds since
+    /// the Unix epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub created: OffsetDateTime,
+    /// When the export was last edited, in the same format as `created`
+    #[serde(with = "timestamp")]
+    pub last_edited: OffsetDateTime,
+}
+
+impl Resource for ExportJob {
+    const ELEMENT: &'static str = "exportJob";
+    const COLLECTION: &'static str = "exportJobs";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        let mut links = vec![(
+            "self",
+            Link::to(format!("{}/person/export-jobs/{}", v1::PREFIX, self.id)),
+        )];
+
+        if let Some(download_url) = &self.download_url {
+            links.push(("download", Link::to(download_url)));
+        }
+
+        links
+    }
+}
+
+/// Export people in the background
+///
+/// Queues an export of every person, or those matching the `filter`, as a CSV, JSON or XLSX
+/// file. The export can be followed until it's completed, when it gives a `downloadUrl` to
+/// fetch the file from until it `expires`.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    post,
+    tag = "person",
+    path = "/person/export-jobs",
+    request_body = NewExportJob,
+    responses(
+        (status = 202, description = "Export queued", body = ExportJob,
+            headers(("location" = String, description = "The URL to follow the export at"))),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn create_export_job(
+    user: ReadUser,
+    exports: PersonExportService,
+    format: Format,
+    ValidatedPayload(request): ValidatedPayload<NewExportJob>,
+) -> Result<(StatusCode, [(HeaderName, String); 1], Negotiated<ExportJob>), ApiError> {
+    let export = exports.create(&user.username, &request).await?;
+
+    let location = format!("{}/person/export-jobs/{}", v1::PREFIX, export.id);
+
+    Ok((
+        StatusCode::ACCEPTED,
+        [(LOCATION, location)],
+        Negotiated(format, export),
+    ))
+}
+
+/// Get an export of people
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/export-jobs/{export_uuid}",
+    params(
+        ("export_uuid" = Uuid, Path, description = "The UUID of the export")
+    ),
+    responses(
+        (status = 200, description = "The export and how far it has got", body = ExportJob),
+        (status = 404, description = "Export not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn get_export_job(
+    user: ReadUser,
+    exports: PersonExportService,
+    format: Format,
+    Path(export_uuid): Path<Uuid>,
+) -> Result<Negotiated<ExportJob>, ApiError> {
+    let export = exports.find(export_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved export '{}'",
+        user.username, export_uuid
+    );
+
+    Ok(Negotiated(format, export))
+}
+
+/// Download an export of people
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/export-jobs/{export_uuid}/download",
+    params(
+        ("export_uuid" = Uuid, Path, description = "The UUID of the export")
+    ),
+    responses(
+        (status = 200, description = "The exported people as CSV, JSON or an Excel workbook", content_type = "application/octet-stream", body = Vec<u8>),
+        (status = 404, description = "Export not found", body = ErrorResponse),
+        (status = 409, description = "The export hasn't completed", body = ErrorResponse),
+        (status = 410, description = "The export has expired", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn download_export(
+    user: ReadUser,
+    exports: PersonExportService,
+    Path(export_uuid): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (format, content) = exports.download(export_uuid).await?;
+
+    info!(
+        "Client '{}' downloaded export '{}'",
+        user.username, export_uuid
+    );
+
+    Ok((
+        [
+            (CONTENT_TYPE, format.content_type().to_owned()),
+            (
+                CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"people-{export_uuid}.{}\"",
+                    format.extension()
+                ),
+            ),
+        ],
+        content,
+    ))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/person/export-jobs", post(create_export_job))
+        .route("/person/export-jobs/:export_uuid", get(get_export_job))
+        .route(
+            "/person/export-jobs/:export_uuid/download",
+            get(download_export),
+        )
+}
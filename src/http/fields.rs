@@ -0,0 +1,474 @@
+//! Newtypes for the fields of people and addresses which have rules beyond their type, checked
+//! whenever one is made so an invalid name, postcode, phone number, email address, tag or date
+//! of birth can't get any further.
+//!
+//! Each deserializes through the same checks, so a request carrying an invalid field is
+//! rejected as it is read. They appear as plain strings and dates in the API schemas.
+//!
+//! Names and addresses are [normalized](normalize) before they are checked, as are the address
+//! fields kept as plain strings, so `"  John "` and `"John"` are stored as the same name.
+
+use std::{borrow::Cow, fmt};
+
+use async_graphql::{registry::Registry, InputType, InputValueError, InputValueResult, Value};
+use serde::{Deserialize, Deserializer, Serialize};
+use time::{Date, OffsetDateTime};
+use unicode_normalization::UnicodeNormalization;
+use validator::{ValidateEmail, ValidationError};
+
+use crate::clock;
+
+/// The longest first or family name, in characters
+pub const MAX_NAME_LENGTH: usize = 64;
+
+/// The longest postcode, in characters
+pub const MAX_POSTCODE_LENGTH: usize = 8;
+
+/// The fewest and most digits in a phone number, the most being the limit of E.164
+const PHONE_DIGITS: std::ops::RangeInclusive<usize> = 7..=15;
+
+/// The longest email address, in characters
+pub const MAX_EMAIL_LENGTH: usize = 254;
+
+/// The longest tag, in characters
+pub const MAX_TAG_LENGTH: usize = 64;
+
+/// Trims surrounding whitespace, collapses any run of whitespace within to a single space and
+/// puts the text in Unicode normalization form C
+pub fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .nfc()
+        .collect()
+}
+
+/// Free text fields which are [normalized](normalize) as they are read
+pub trait Normalize {
+    fn normalize(&mut self);
+}
+
+impl Normalize for String {
+    fn normalize(&mut self) {
+        *self = normalize(self);
+    }
+}
+
+impl<T: Normalize> Normalize for Option<T> {
+    fn normalize(&mut self) {
+        if let Some(value) = self {
+            value.normalize();
+        }
+    }
+}
+
+/// Normalizes a GraphQL input field once parsed, for use with `#[graphql(process_with)]`
+pub(crate) fn normalize_input<T: Normalize>(value: &mut T) {
+    value.normalize();
+}
+
+/// Deserializes a field and normalizes it, for use with `#[serde(deserialize_with)]`
+pub(crate) fn normalized<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Normalize,
+{
+    let mut value = T::deserialize(deserializer)?;
+    value.normalize();
+
+    Ok(value)
+}
+
+/// Checks a time is still to come, for use with `#[validate(custom)]`
+pub(crate) fn in_the_future(time: &OffsetDateTime) -> Result<(), ValidationError> {
+    if *time > clock::now() {
+        Ok(())
+    } else {
+        Err(ValidationError::new("in_the_future").with_message("must be in the future".into()))
+    }
+}
+
+/// Checks text has more than whitespace in it, for use with `#[validate(custom)]`
+pub(crate) fn not_blank(text: &str) -> Result<(), ValidationError> {
+    if text.trim().is_empty() {
+        return Err(ValidationError::new("blank").with_message("must not be blank".into()));
+    }
+
+    Ok(())
+}
+
+fn check_length(value: &str, max: usize) -> Result<(), ValidationError> {
+    let length = value.chars().count();
+
+    if length == 0 || length > max {
+        let mut error = ValidationError::new("length")
+            .with_message(format!("must be between 1 and {max} characters").into());
+        error.add_param("min".into(), &1);
+        error.add_param("max".into(), &max);
+        error.add_param("value".into(), &value);
+
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// A person's first or family name, of 1 to 64 characters
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PersonName(String);
+
+impl PersonName {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for PersonName {
+    type Error = ValidationError;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        let name = normalize(&name);
+        check_length(&name, MAX_NAME_LENGTH)?;
+
+        Ok(PersonName(name))
+    }
+}
+
+/// A postcode, of 1 to 8 characters
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Postcode(String);
+
+impl Postcode {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for Postcode {
+    type Error = ValidationError;
+
+    fn try_from(postcode: String) -> Result<Self, Self::Error> {
+        let postcode = normalize(&postcode);
+        check_length(&postcode, MAX_POSTCODE_LENGTH)?;
+
+        Ok(Postcode(postcode))
+    }
+}
+
+/// A phone number of 7 to 15 digits, optionally starting with `+`. Spaces, dots, dashes and
+/// parentheses are allowed for readability but removed, so `+44 (0)20 7946-0000` is stored as
+/// `+4402079460000`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PhoneNumber(String);
+
+impl PhoneNumber {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for PhoneNumber {
+    type Error = ValidationError;
+
+    fn try_from(phone: String) -> Result<Self, Self::Error> {
+        let compact: String = phone
+            .chars()
+            .filter(|c| !c.is_whitespace() && !matches!(c, '.' | '-' | '(' | ')'))
+            .collect();
+        let digits = compact.strip_prefix('+').unwrap_or(&compact);
+
+        if !digits.chars().all(|c| c.is_ascii_digit()) || !PHONE_DIGITS.contains(&digits.len()) {
+            let mut error = ValidationError::new("phone").with_message(
+                "must be a phone number of 7 to 15 digits, optionally starting with +".into(),
+            );
+            error.add_param("value".into(), &phone);
+
+            return Err(error);
+        }
+
+        Ok(PhoneNumber(compact))
+    }
+}
+
+/// A phone number in E.164 form, `+` and a country code followed by up to 15 digits in all.
+/// Spaces, dots, dashes and parentheses are removed as for [`PhoneNumber`], so
+/// `+44 20 7946 0000` is stored as `+442079460000`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct InternationalPhoneNumber(String);
+
+impl InternationalPhoneNumber {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for InternationalPhoneNumber {
+    type Error = ValidationError;
+
+    fn try_from(phone: String) -> Result<Self, Self::Error> {
+        let compact: String = phone
+            .chars()
+            .filter(|c| !c.is_whitespace() && !matches!(c, '.' | '-' | '(' | ')'))
+            .collect();
+        let digits = compact.strip_prefix('+').unwrap_or_default();
+
+        if !digits.starts_with(|c: char| ('1'..='9').contains(&c))
+            || !digits.chars().all(|c| c.is_ascii_digit())
+            || !PHONE_DIGITS.contains(&digits.len())
+        {
+            let mut error = ValidationError::new("e164")
+                .with_message("must be an E.164 phone number, such as +442079460000".into());
+            error.add_param("value".into(), &phone);
+
+            return Err(error);
+        }
+
+        Ok(InternationalPhoneNumber(compact))
+    }
+}
+
+/// An email address of up to 254 characters, trimmed of surrounding whitespace
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct EmailAddress(String);
+
+impl EmailAddress {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for EmailAddress {
+    type Error = ValidationError;
+
+    fn try_from(email: String) -> Result<Self, Self::Error> {
+        let email = email.trim().to_owned();
+
+        if email.chars().count() > MAX_EMAIL_LENGTH || !email.validate_email() {
+            let mut error =
+                ValidationError::new("email").with_message("must be an email address".into());
+            error.add_param("value".into(), &email);
+
+            return Err(error);
+        }
+
+        Ok(EmailAddress(email))
+    }
+}
+
+/// A tag grouping people, of 1 to 64 characters. Tags are lowercased as well as normalized, so
+/// `At  Risk` and `at risk` are the same tag.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Tag(String);
+
+impl Tag {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for Tag {
+    type Error = ValidationError;
+
+    fn try_from(tag: String) -> Result<Self, Self::Error> {
+        let tag = normalize(&tag).to_lowercase();
+        check_length(&tag, MAX_TAG_LENGTH)?;
+
+        Ok(Tag(tag))
+    }
+}
+
+/// A date of birth, which can't be in the future
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Date", into = "Date")]
+pub struct DateOfBirth(Date);
+
+impl DateOfBirth {
+    pub fn date(self) -> Date {
+        self.0
+    }
+}
+
+impl TryFrom<Date> for DateOfBirth {
+    type Error = ValidationError;
+
+    fn try_from(date: Date) -> Result<Self, Self::Error> {
+        if date > clock::today() {
+            return Err(ValidationError::new("date_not_in_future")
+                .with_message("must not be in the future".into()));
+        }
+
+        Ok(DateOfBirth(date))
+    }
+}
+
+/// Implements the conversions back to the wrapped value, and has the newtype take the GraphQL
+/// type of that value while parsing through the same checks
+macro_rules! wraps {
+    ($newtype:ident, $inner:ty) => {
+        impl From<$newtype> for $inner {
+            fn from(value: $newtype) -> Self {
+                value.0
+            }
+        }
+
+        impl fmt::Display for $newtype {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl InputType for $newtype {
+            type RawValueType = $inner;
+
+            fn type_name() -> Cow<'static, str> {
+                <$inner as InputType>::type_name()
+            }
+
+            fn create_type_info(registry: &mut Registry) -> String {
+                <$inner as InputType>::create_type_info(registry)
+            }
+
+            fn parse(value: Option<Value>) -> InputValueResult<Self> {
+                let inner =
+                    <$inner as InputType>::parse(value).map_err(InputValueError::propagate)?;
+
+                $newtype::try_from(inner).map_err(InputValueError::custom)
+            }
+
+            fn to_value(&self) -> Value {
+                InputType::to_value(&self.0)
+            }
+
+            fn as_raw_value(&self) -> Option<&Self::RawValueType> {
+                Some(&self.0)
+            }
+        }
+    };
+}
+
+wraps!(PersonName, String);
+wraps!(Postcode, String);
+wraps!(PhoneNumber, String);
+wraps!(InternationalPhoneNumber, String);
+wraps!(EmailAddress, String);
+wraps!(Tag, String);
+wraps!(DateOfBirth, Date);
+
+#[cfg(test)]
+mod tests {
+    use time::{macros::date, Duration, OffsetDateTime};
+
+    use super::{
+        normalize, DateOfBirth, EmailAddress, InternationalPhoneNumber, PersonName, PhoneNumber,
+        Postcode, Tag,
+    };
+
+    #[test]
+    fn text_is_normalized() {
+        assert_eq!(normalize("  John "), "John");
+        assert_eq!(normalize("Mary \t Jane"), "Mary Jane");
+        // "e" followed by a combining acute accent is composed into "é"
+        assert_eq!(normalize("Rene\u{301}e"), "Ren\u{e9}e");
+    }
+
+    #[test]
+    fn names_must_be_1_to_64_characters() {
+        assert!(PersonName::try_from("Ada".to_owned()).is_ok());
+        assert!(PersonName::try_from("é".repeat(64)).is_ok());
+
+        let error = PersonName::try_from(String::new()).unwrap_err();
+        assert_eq!(error.code, "length");
+        assert!(PersonName::try_from("a".repeat(65)).is_err());
+        assert!(PersonName::try_from("   ".to_owned()).is_err());
+        assert_eq!(
+            PersonName::try_from(" Ada  King ".to_owned()).unwrap(),
+            PersonName::try_from("Ada King".to_owned()).unwrap()
+        );
+    }
+
+    #[test]
+    fn postcodes_must_be_1_to_8_characters() {
+        assert!(Postcode::try_from("SW1A 1AA".to_owned()).is_ok());
+        assert!(Postcode::try_from(String::new()).is_err());
+        assert!(Postcode::try_from("SW1A 1AAA".to_owned()).is_err());
+    }
+
+    #[test]
+    fn phone_numbers_are_stored_without_punctuation() {
+        assert_eq!(
+            PhoneNumber::try_from("+44 (0)20 7946-0000".to_owned())
+                .unwrap()
+                .as_str(),
+            "+4402079460000"
+        );
+        assert!(PhoneNumber::try_from("020.7946.0000".to_owned()).is_ok());
+
+        let error = PhoneNumber::try_from("call me".to_owned()).unwrap_err();
+        assert_eq!(error.code, "phone");
+        assert!(PhoneNumber::try_from("12345".to_owned()).is_err());
+        assert!(PhoneNumber::try_from("+1234567890123456".to_owned()).is_err());
+        assert!(PhoneNumber::try_from("44+20794600".to_owned()).is_err());
+    }
+
+    #[test]
+    fn international_phone_numbers_must_be_e164() {
+        assert_eq!(
+            InternationalPhoneNumber::try_from("+44 20 7946-0000".to_owned())
+                .unwrap()
+                .as_str(),
+            "+442079460000"
+        );
+
+        let error = InternationalPhoneNumber::try_from("020 7946 0000".to_owned()).unwrap_err();
+        assert_eq!(error.code, "e164");
+        assert!(InternationalPhoneNumber::try_from("+0442079460000".to_owned()).is_err());
+        assert!(InternationalPhoneNumber::try_from("+1234567890123456".to_owned()).is_err());
+        assert!(InternationalPhoneNumber::try_from("+44 call me".to_owned()).is_err());
+    }
+
+    #[test]
+    fn email_addresses_must_be_valid() {
+        assert_eq!(
+            EmailAddress::try_from(" ada@example.com ".to_owned())
+                .unwrap()
+                .as_str(),
+            "ada@example.com"
+        );
+
+        let error = EmailAddress::try_from("ada.example.com".to_owned()).unwrap_err();
+        assert_eq!(error.code, "email");
+        assert!(EmailAddress::try_from(format!("{}@example.com", "a".repeat(250))).is_err());
+    }
+
+    #[test]
+    fn tags_are_lowercased() {
+        assert_eq!(
+            Tag::try_from(" At  Risk ".to_owned()).unwrap().as_str(),
+            "at risk"
+        );
+        assert!(Tag::try_from("   ".to_owned()).is_err());
+        assert!(Tag::try_from("a".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn dates_of_birth_must_not_be_in_the_future() {
+        assert!(DateOfBirth::try_from(date!(1815 - 12 - 10)).is_ok());
+
+        let tomorrow = OffsetDateTime::now_utc().date() + Duration::days(1);
+        let error = DateOfBirth::try_from(tomorrow).unwrap_err();
+        assert_eq!(error.code, "date_not_in_future");
+    }
+
+    #[test]
+    fn invalid_fields_are_not_deserialized() {
+        assert!(serde_json::from_str::<PersonName>(r#""Ada""#).is_ok());
+        assert!(serde_json::from_str::<PersonName>(r#""""#).is_err());
+        assert!(serde_json::from_str::<DateOfBirth>(r#""2999-01-01""#).is_err());
+    }
+}
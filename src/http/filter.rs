@@ -0,0 +1,657 @@
+//! Filtering lists of people with [RSQL], a URL friendly query language, such as
+//! `?filter=familyName==Smith;dateOfBirth=ge=1980-01-01`.
+//!
+//! Comparisons are joined with `;` (and) or `,` (or), with `and` binding tighter, and grouped
+//! with parentheses. The operators are `==`, `!=`, `=lt=`, `=le=`, `=gt=`, `=ge=`, and `=in=`
+//! and `=out=` taking a list such as `(Smith,Jones)`. Values containing spaces or reserved
+//! characters are quoted with `'` or `"`, and `*` in a text value matches anything, ignoring
+//! case. Only the fields in [`Field`] can be filtered on, and values are always bound as
+//! parameters, never written into the SQL.
+//!
+//! Lists can also be sorted on the same fields with a [`SortQuery`], such as
+//! `?sort=family_name,-created`.
+//!
+//! [RSQL]: https://github.com/jirutka/rsql-parser
+
+use serde::Deserialize;
+use sqlx::{Postgres, QueryBuilder};
+use time::{
+    format_description::well_known::{Iso8601, Rfc3339},
+    Date, OffsetDateTime,
+};
+use utoipa::IntoParams;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::error::ApiError;
+
+/// The longest filter accepted
+const MAX_LENGTH: u64 = 2000;
+
+/// How deeply parentheses may be nested
+const MAX_DEPTH: usize = 8;
+
+/// How many comparisons one filter may make
+const MAX_COMPARISONS: usize = 32;
+
+#[derive(Debug, Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct FilterQuery {
+    /// Only return people matching this RSQL expression, such as
+    /// `familyName==Smith;dateOfBirth=ge=1980-01-01`
+    #[param(max_length = 2000)]
+    #[validate(length(max = MAX_LENGTH))]
+    pub filter: Option<String>,
+}
+
+impl FilterQuery {
+    pub fn parse(&self) -> Result<Option<Filter>, ApiError> {
+        self.filter.as_deref().map(Filter::parse).transpose()
+    }
+}
+
+/// The longest sort accepted
+const MAX_SORT_LENGTH: u64 = 200;
+
+#[derive(Debug, Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct SortQuery {
+    /// The fields to sort by, separated by commas and each descending when prefixed with `-`,
+    /// such as `family_name,-created`. Any of `id`, `first_name`, `family_name`,
+    /// `date_of_birth`, `created` or `last_edited`.
+    #[param(max_length = 200)]
+    #[validate(length(max = MAX_SORT_LENGTH))]
+    pub sort: Option<String>,
+}
+
+impl SortQuery {
+    pub fn parse(&self) -> Result<Vec<Sort>, ApiError> {
+        let Some(sort) = self.sort.as_deref() else {
+            return Ok(Vec::new());
+        };
+
+        sort.split(',')
+            .map(|item| {
+                let (name, descending) = match item.trim().strip_prefix('-') {
+                    Some(name) => (name, true),
+                    None => (item.trim(), false),
+                };
+                let field = Field::from_param(name).ok_or_else(|| {
+                    ApiError::InvalidOrder(format!("Sorting by '{name}' is not supported"))
+                })?;
+
+                Ok(Sort { field, descending })
+            })
+            .collect()
+    }
+}
+
+/// The fields of a person which can be filtered and sorted on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Id,
+    FirstName,
+    FamilyName,
+    DateOfBirth,
+    Created,
+    LastEdited,
+}
+
+impl Field {
+    /// The field by its name in responses
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "id" => Some(Field::Id),
+            "firstName" => Some(Field::FirstName),
+            "familyName" => Some(Field::FamilyName),
+            "dateOfBirth" => Some(Field::DateOfBirth),
+            "created" => Some(Field::Created),
+            "lastEdited" => Some(Field::LastEdited),
+            _ => None,
+        }
+    }
+
+    /// The field by its name in query parameters
+    pub fn from_param(name: &str) -> Option<Self> {
+        match name {
+            "id" => Some(Field::Id),
+            "first_name" => Some(Field::FirstName),
+            "family_name" => Some(Field::FamilyName),
+            "date_of_birth" => Some(Field::DateOfBirth),
+            "created" => Some(Field::Created),
+            "last_edited" => Some(Field::LastEdited),
+            _ => None,
+        }
+    }
+
+    /// The column of the `person` table, aliased as `p`, holding the field
+    pub fn column(self) -> &'static str {
+        match self {
+            Field::Id => "p.uuid",
+            Field::FirstName => "p.first_name",
+            Field::FamilyName => "p.family_name",
+            Field::DateOfBirth => "p.date_of_birth",
+            Field::Created => "p.created",
+            Field::LastEdited => "p.last_edited",
+        }
+    }
+
+    /// Reads a value to compare the field with
+    pub fn value(self, value: &str) -> Result<Value, String> {
+        match self {
+            Field::Id => Uuid::parse_str(value)
+                .map(Value::Uuid)
+                .map_err(|_| format!("'{value}' is not a UUID")),
+            Field::FirstName | Field::FamilyName => Ok(Value::Text(value.to_owned())),
+            Field::DateOfBirth => Date::parse(value, &Iso8601::DATE)
+                .map(Value::Date)
+                .map_err(|_| format!("'{value}' is not a date such as 1980-01-31")),
+            Field::Created | Field::LastEdited => OffsetDateTime::parse(value, &Rfc3339)
+                .map(Value::Timestamp)
+                .map_err(|_| format!("'{value}' is not an RFC 3339 timestamp")),
+        }
+    }
+}
+
+/// A value compared with a field, of the field's type
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Text(String),
+    /// An `ILIKE` pattern matching text, ignoring case
+    Pattern(String),
+    Date(Date),
+    Timestamp(OffsetDateTime),
+    Uuid(Uuid),
+}
+
+impl Value {
+    fn push_bind(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        match self {
+            Value::Text(text) | Value::Pattern(text) => builder.push_bind(text.clone()),
+            Value::Date(date) => builder.push_bind(*date),
+            Value::Timestamp(timestamp) => builder.push_bind(*timestamp),
+            Value::Uuid(uuid) => builder.push_bind(*uuid),
+        };
+    }
+
+    /// A pattern for text with `*` wildcards, or the text itself when it has none
+    fn wildcard(text: String) -> Self {
+        if text.contains('*') {
+            Value::Pattern(escape_like(&text).replace('*', "%"))
+        } else {
+            Value::Text(text)
+        }
+    }
+}
+
+/// Escapes the characters with a meaning of their own in `ILIKE` patterns
+pub fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// A field to sort people by
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sort {
+    pub field: Field,
+    pub descending: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    LessThan,
+    LessOrEqual,
+    GreaterThan,
+    GreaterOrEqual,
+    In,
+    NotIn,
+}
+
+impl Operator {
+    fn from_rsql(operator: &str) -> Option<Self> {
+        match operator {
+            "==" => Some(Operator::Equal),
+            "!=" => Some(Operator::NotEqual),
+            "=lt=" => Some(Operator::LessThan),
+            "=le=" => Some(Operator::LessOrEqual),
+            "=gt=" => Some(Operator::GreaterThan),
+            "=ge=" => Some(Operator::GreaterOrEqual),
+            "=in=" => Some(Operator::In),
+            "=out=" => Some(Operator::NotIn),
+            _ => None,
+        }
+    }
+
+    fn sql(self) -> &'static str {
+        match self {
+            Operator::Equal => " = ",
+            Operator::NotEqual => " <> ",
+            Operator::LessThan => " < ",
+            Operator::LessOrEqual => " <= ",
+            Operator::GreaterThan => " > ",
+            Operator::GreaterOrEqual => " >= ",
+            Operator::In => " IN ",
+            Operator::NotIn => " NOT IN ",
+        }
+    }
+}
+
+/// Which people to return, as parsed from a query
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+    Compare {
+        field: Field,
+        operator: Operator,
+        values: Vec<Value>,
+    },
+    /// People with the tag
+    Tagged(String),
+}
+
+impl Filter {
+    /// Parses an RSQL expression
+    pub fn parse(filter: &str) -> Result<Self, ApiError> {
+        let mut parser = Parser {
+            chars: filter.chars().collect(),
+            position: 0,
+            depth: 0,
+            comparisons: 0,
+        };
+
+        let parsed = parser.or().map_err(|e| parser.error(e))?;
+
+        match parser.peek() {
+            None => Ok(parsed),
+            Some(c) => Err(parser.error(format!("Unexpected '{c}'"))),
+        }
+    }
+
+    /// Every one of the filters, or none when there are none
+    pub fn all(mut filters: Vec<Filter>) -> Option<Self> {
+        match filters.len() {
+            0 | 1 => filters.pop(),
+            _ => Some(Filter::And(filters)),
+        }
+    }
+
+    /// Writes the filter as a SQL condition on the `person` table, aliased as `p`
+    pub fn push_sql(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        match self {
+            Filter::And(filters) | Filter::Or(filters) => {
+                let joiner = match self {
+                    Filter::And(_) => " AND ",
+                    _ => " OR ",
+                };
+
+                builder.push("(");
+                for (i, filter) in filters.iter().enumerate() {
+                    if i > 0 {
+                        builder.push(joiner);
+                    }
+                    filter.push_sql(builder);
+                }
+                builder.push(")");
+            }
+            Filter::Not(filter) => {
+                builder.push("(NOT ");
+                filter.push_sql(builder);
+                builder.push(")");
+            }
+            Filter::Compare {
+                field,
+                operator,
+                values,
+            } => {
+                builder.push("(").push(field.column());
+
+                match (operator, values.as_slice()) {
+                    (Operator::Equal | Operator::NotEqual, [Value::Pattern(pattern)]) => {
+                        if *operator == Operator::NotEqual {
+                            builder.push(" NOT");
+                        }
+                        builder.push(" ILIKE ").push_bind(pattern.clone());
+                    }
+                    (Operator::In | Operator::NotIn, values) => {
+                        builder.push(operator.sql()).push("(");
+                        for (i, value) in values.iter().enumerate() {
+                            if i > 0 {
+                                builder.push(", ");
+                            }
+                            value.push_bind(builder);
+                        }
+                        builder.push(")");
+                    }
+                    (_, [value, ..]) => {
+                        builder.push(operator.sql());
+                        value.push_bind(builder);
+                    }
+                    (_, []) => unreachable!("Comparisons always have a value"),
+                }
+
+                builder.push(")");
+            }
+            Filter::Tagged(tag) => {
+                builder
+                    .push(
+                        "(EXISTS (SELECT 1 FROM person_tag pt JOIN tag t ON t.id = pt.tag \
+                         WHERE pt.person = p.uuid AND t.name = ",
+                    )
+                    .push_bind(tag.clone())
+                    .push("))");
+            }
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    depth: usize,
+    comparisons: usize,
+}
+
+/// Characters with a meaning of their own, which must be quoted to appear in values
+const RESERVED: &[char] = &['"', '\'', '(', ')', ';', ',', '=', '!', '<', '>', ' '];
+
+impl Parser {
+    fn error(&self, message: impl Into<String>) -> ApiError {
+        ApiError::InvalidFilter(format!(
+            "{} at position {}",
+            message.into(),
+            self.position + 1
+        ))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Comparisons joined by `,`
+    fn or(&mut self) -> Result<Filter, String> {
+        let mut filters = vec![self.and()?];
+        while self.eat(',') {
+            filters.push(self.and()?);
+        }
+
+        Ok(match filters.len() {
+            1 => filters.remove(0),
+            _ => Filter::Or(filters),
+        })
+    }
+
+    /// Comparisons joined by `;`
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut filters = vec![self.constraint()?];
+        while self.eat(';') {
+            filters.push(self.constraint()?);
+        }
+
+        Ok(match filters.len() {
+            1 => filters.remove(0),
+            _ => Filter::And(filters),
+        })
+    }
+
+    fn constraint(&mut self) -> Result<Filter, String> {
+        if self.eat('(') {
+            self.depth += 1;
+            if self.depth > MAX_DEPTH {
+                return Err(format!(
+                    "Parentheses can be nested at most {MAX_DEPTH} deep"
+                ));
+            }
+
+            let filter = self.or()?;
+            if !self.eat(')') {
+                return Err("Expected ')'".to_owned());
+            }
+            self.depth -= 1;
+
+            return Ok(filter);
+        }
+
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Filter, String> {
+        self.comparisons += 1;
+        if self.comparisons > MAX_COMPARISONS {
+            return Err(format!(
+                "A filter can make at most {MAX_COMPARISONS} comparisons"
+            ));
+        }
+
+        let selector = self.take_while(|c| c.is_ascii_alphanumeric() || c == '.');
+        if selector.is_empty() {
+            return Err("Expected a field such as familyName".to_owned());
+        }
+        let field = Field::from_name(&selector)
+            .ok_or_else(|| format!("Filtering on '{selector}' is not supported"))?;
+
+        let operator = self.operator()?;
+
+        let arguments = if self.eat('(') {
+            let mut arguments = vec![self.argument()?];
+            while self.eat(',') {
+                arguments.push(self.argument()?);
+            }
+            if !self.eat(')') {
+                return Err("Expected ')' to close the list of values".to_owned());
+            }
+            arguments
+        } else {
+            vec![self.argument()?]
+        };
+
+        if !matches!(operator, Operator::In | Operator::NotIn) && arguments.len() > 1 {
+            return Err(format!("Only =in= and =out= take a list, not '{selector}'"));
+        }
+
+        let values = arguments
+            .iter()
+            .map(|argument| field.value(argument))
+            .map(|value| match (operator, value) {
+                (Operator::Equal | Operator::NotEqual, Ok(Value::Text(text))) => {
+                    Ok(Value::wildcard(text))
+                }
+                (_, value) => value,
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Filter::Compare {
+            field,
+            operator,
+            values,
+        })
+    }
+
+    fn operator(&mut self) -> Result<Operator, String> {
+        let start = self.position;
+
+        let operator = match self.peek() {
+            Some('!') => {
+                self.position += 1;
+                format!("!{}", self.take_while(|c| c == '='))
+            }
+            Some('=') => {
+                self.position += 1;
+                let name = self.take_while(|c| c.is_ascii_lowercase());
+                let closing = if self.eat('=') { "=" } else { "" };
+                format!("={name}{closing}")
+            }
+            _ => String::new(),
+        };
+
+        Operator::from_rsql(&operator).ok_or_else(|| {
+            self.position = start;
+            "Expected an operator such as ==, != or =ge=".to_owned()
+        })
+    }
+
+    fn argument(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.position += 1;
+                let mut value = String::new();
+
+                loop {
+                    match self.peek() {
+                        Some('\\') => {
+                            self.position += 1;
+                            value.extend(self.peek());
+                            self.position += 1;
+                        }
+                        Some(c) if c == quote => {
+                            self.position += 1;
+                            return Ok(value);
+                        }
+                        Some(c) => {
+                            value.push(c);
+                            self.position += 1;
+                        }
+                        None => return Err("Unterminated quoted value".to_owned()),
+                    }
+                }
+            }
+            _ => {
+                let value = self.take_while(|c| !RESERVED.contains(&c));
+                if value.is_empty() {
+                    return Err("Expected a value".to_owned());
+                }
+                Ok(value)
+            }
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let start = self.position;
+        while self.peek().is_some_and(&predicate) {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::QueryBuilder;
+    use time::macros::date;
+
+    use super::{Field, Filter, Operator, Sort, SortQuery, Value};
+
+    fn sql(filter: &str) -> String {
+        let mut builder = QueryBuilder::new("");
+        Filter::parse(filter).unwrap().push_sql(&mut builder);
+        builder.into_sql()
+    }
+
+    #[test]
+    fn comparisons_are_joined_with_and_binding_tighter() {
+        assert_eq!(
+            sql("familyName==Smith;dateOfBirth=ge=1980-01-01,firstName=in=(Ada,'Grace Brewster')"),
+            "(((p.family_name = $1) AND (p.date_of_birth >= $2)) OR (p.first_name IN ($3, $4)))"
+        );
+        assert_eq!(
+            sql("familyName==Smith;(firstName==Ada,firstName==Grace)"),
+            "((p.family_name = $1) AND ((p.first_name = $2) OR (p.first_name = $3)))"
+        );
+    }
+
+    #[test]
+    fn values_are_typed_by_their_field() {
+        assert_eq!(
+            Filter::parse("dateOfBirth=lt=1980-01-01").unwrap(),
+            Filter::Compare {
+                field: Field::DateOfBirth,
+                operator: Operator::LessThan,
+                values: vec![Value::Date(date!(1980 - 01 - 01))],
+            }
+        );
+        assert!(Filter::parse("dateOfBirth=lt=yesterday").is_err());
+        assert!(Filter::parse("id==not-a-uuid").is_err());
+    }
+
+    #[test]
+    fn wildcards_match_ignoring_case() {
+        assert_eq!(sql("familyName==Sm*"), "(p.family_name ILIKE $1)");
+        assert_eq!(sql("familyName!=*son"), "(p.family_name NOT ILIKE $1)");
+        assert_eq!(
+            Value::wildcard("100%_*".to_owned()),
+            Value::Pattern("100\\%\\_%".to_owned())
+        );
+        assert_eq!(sql("familyName=lt=Sm*"), "(p.family_name < $1)");
+    }
+
+    #[test]
+    fn invalid_filters_are_rejected() {
+        for invalid in [
+            "",
+            "password==secret",
+            "familyName=like=Smith",
+            "familyName==",
+            "familyName==Smith;",
+            "(familyName==Smith",
+            "familyName=='Smith",
+            "familyName==(Smith,Jones)",
+            "familyName==Smith) OR 1=1",
+        ] {
+            assert!(Filter::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn filters_are_kept_small() {
+        let many = vec!["familyName==Smith"; 33].join(";");
+        assert!(Filter::parse(&many).is_err());
+
+        let deep = format!("{}familyName==Smith{}", "(".repeat(9), ")".repeat(9));
+        assert!(Filter::parse(&deep).is_err());
+    }
+
+    #[test]
+    fn sorts_are_descending_when_prefixed() {
+        let query = SortQuery {
+            sort: Some("family_name,-created".to_owned()),
+        };
+
+        assert_eq!(
+            query.parse().unwrap(),
+            [
+                Sort {
+                    field: Field::FamilyName,
+                    descending: false
+                },
+                Sort {
+                    field: Field::Created,
+                    descending: true
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn sorts_on_other_fields_are_rejected() {
+        for sort in ["address", "familyName", "", "--created"] {
+            let query = SortQuery {
+                sort: Some(sort.to_owned()),
+            };
+
+            assert!(query.parse().is_err(), "{sort}");
+        }
+    }
+}
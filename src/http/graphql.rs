@@ -0,0 +1,198 @@
+use async_graphql::{
+    http::GraphiQLSource, ComplexObject, Context, EmptySubscription, Object, Schema,
+};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::{
+    middleware,
+    response::{Html, IntoResponse},
+    routing::get,
+    Extension, Router,
+};
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+
+use super::{
+    address::{Address, NewAddress},
+    auth::{Claims, ReadUser, WriteUser},
+    cache_control::{self, CachePolicy},
+    limit::Limit,
+    person::{NewPerson, Person, UpdatePerson},
+    precondition::IfMatch,
+};
+use crate::service::{address::AddressService, person::PersonService};
+
+pub type ApiSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+fn read_user(ctx: &Context<'_>) -> async_graphql::Result<ReadUser> {
+    let claims = ctx.data::<Claims>()?;
+    claims.require_scope("read")?;
+    Ok(ReadUser::from(claims.clone()))
+}
+
+fn write_user(ctx: &Context<'_>) -> async_graphql::Result<WriteUser> {
+    let claims = ctx.data::<Claims>()?;
+    claims.require_scope("write")?;
+    Ok(WriteUser::from(claims.clone()))
+}
+
+fn people(ctx: &Context<'_>) -> async_graphql::Result<PersonService> {
+    Ok(PersonService::new(ctx.data::<PgPool>()?.clone()))
+}
+
+fn addresses(ctx: &Context<'_>) -> async_graphql::Result<AddressService> {
+    Ok(AddressService::new(ctx.data::<PgPool>()?.clone()))
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// List people, oldest first
+    async fn people(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The maximum number of people to return, defaults to 100")] limit: Option<
+            i64,
+        >,
+    ) -> async_graphql::Result<Vec<Person>> {
+        let user = read_user(ctx)?;
+        let people = people(ctx)?.list(Limit::new(limit)).await?;
+
+        info!(
+            "Client '{}' retrieved {} person(s) via GraphQL",
+            user.username,
+            people.len()
+        );
+
+        Ok(people)
+    }
+
+    /// Get a person by their UUID
+    async fn person(&self, ctx: &Context<'_>, id: Uuid) -> async_graphql::Result<Person> {
+        let user = read_user(ctx)?;
+        let person = people(ctx)?.find(id).await?;
+
+        info!(
+            "Client '{}' retrieved person '{}' via GraphQL",
+            user.username, person.id
+        );
+
+        Ok(person)
+    }
+}
+
+#[ComplexObject]
+impl Person {
+    /// The person's current address
+    async fn address(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Address>> {
+        read_user(ctx)?;
+        Ok(addresses(ctx)?.find_for_person(self.id).await?)
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    async fn create_person(
+        &self,
+        ctx: &Context<'_>,
+        person: NewPerson,
+    ) -> async_graphql::Result<Person> {
+        let user = write_user(ctx)?;
+
+        Ok(people(ctx)?.create(&user.username, &person).await?)
+    }
+
+    /// Update a person, if they're still at the version given
+    async fn update_person(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        #[graphql(desc = "The version of the person being changed, as last read")] version: i32,
+        changes: UpdatePerson,
+    ) -> async_graphql::Result<Person> {
+        let user = write_user(ctx)?;
+        let if_match = IfMatch::Versions(vec![version]);
+
+        Ok(people(ctx)?
+            .update(&user.username, id, changes, &if_match)
+            .await?)
+    }
+
+    /// Delete a person if they're still at the version given, returning the UUID of the
+    /// deleted person
+    async fn delete_person(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        #[graphql(desc = "The version of the person being deleted, as last read")] version: i32,
+    ) -> async_graphql::Result<Uuid> {
+        let user = write_user(ctx)?;
+        let if_match = IfMatch::Versions(vec![version]);
+        people(ctx)?.delete(&user.username, id, &if_match).await?;
+
+        Ok(id)
+    }
+
+    /// Create an address for a person, returning the person
+    async fn add_address(
+        &self,
+        ctx: &Context<'_>,
+        person_id: Uuid,
+        address: NewAddress,
+    ) -> async_graphql::Result<Person> {
+        let user = write_user(ctx)?;
+        addresses(ctx)?
+            .add(&user.username, person_id, &address)
+            .await?;
+
+        Ok(people(ctx)?.find(person_id).await?)
+    }
+
+    /// Remove an address, returning the UUID of the removed address
+    async fn remove_address(&self, ctx: &Context<'_>, id: Uuid) -> async_graphql::Result<Uuid> {
+        let user = write_user(ctx)?;
+        addresses(ctx)?.remove(&user.username, id).await?;
+
+        Ok(id)
+    }
+}
+
+pub fn schema() -> ApiSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription).finish()
+}
+
+/// Executes a GraphQL request, with the caller's claims made available to the resolvers so
+/// each field can check the scope it requires
+async fn graphql(
+    claims: Claims,
+    db: Extension<PgPool>,
+    schema: Extension<ApiSchema>,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    let request = request.into_inner().data(claims).data(db.0);
+    schema.execute(request).await.into()
+}
+
+async fn graphiql() -> impl IntoResponse {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+pub fn router() -> Router {
+    let route = if cfg!(debug_assertions) {
+        get(graphiql).post(graphql)
+    } else {
+        get(graphql).post(graphql)
+    };
+
+    Router::new()
+        .route("/graphql", route)
+        .layer(Extension(schema()))
+        // results depend on the caller, even for queries sent with GET
+        .layer(middleware::from_fn_with_state(
+            CachePolicy::NoStore,
+            cache_control::apply,
+        ))
+}
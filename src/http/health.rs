@@ -0,0 +1,73 @@
+//! Health checks for the orchestrator, outside of the versioned API.
+//!
+//! `/health/live` answers as long as the server is running, while `/health/ready` only does
+//! once the instance can serve requests, so it's taken out of rotation while it can't.
+
+use axum::{http::StatusCode, response::IntoResponse, routing::get, Extension, Json, Router};
+use reqwest::Client;
+use serde::Serialize;
+use sqlx::PgPool;
+
+use super::auth;
+use crate::failover;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DatabaseHealth {
+    reachable: bool,
+    #[serde(flatten)]
+    failover: failover::Status,
+}
+
+#[derive(Serialize)]
+struct AuthHealth {
+    /// Whether the signing keys to verify tokens with are at hand
+    available: bool,
+}
+
+#[derive(Serialize)]
+struct Readiness {
+    ready: bool,
+    database: DatabaseHealth,
+    auth: AuthHealth,
+}
+
+async fn live() -> StatusCode {
+    StatusCode::OK
+}
+
+/// Ready while the database answers and isn't failing over, and tokens can be verified, as
+/// an instance unable to verify them would turn every request away
+async fn ready(
+    Extension(pool): Extension<PgPool>,
+    Extension(client): Extension<Client>,
+) -> impl IntoResponse {
+    let reachable = sqlx::query("SELECT 1").execute(&pool).await.is_ok();
+    let failover = failover::status();
+    let available = auth::jwks_available(&client).await;
+    let ready = reachable && !failover.failing_over && available;
+
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(Readiness {
+            ready,
+            database: DatabaseHealth {
+                reachable,
+                failover,
+            },
+            auth: AuthHealth { available },
+        }),
+    )
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/health/live", get(live))
+        .route("/health/ready", get(ready))
+}
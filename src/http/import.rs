@@ -0,0 +1,552 @@
+//! Importing people from newline delimited JSON, one `NewPerson` per line, for migrations too
+//! large to send as a single request body, or from a CSV or JSON file uploaded as a form.
+//!
+//! The newline delimited body is read as it arrives, holding no more than one line and one
+//! batch of people at a time, so its size is unbounded. Each batch is inserted in its own
+//! transaction, and a line that can't be imported is reported without stopping the rest.
+//!
+//! An uploaded file is read whole, and everyone in it who can be imported is inserted in a
+//! single transaction, with the rows that can't be reported in the same way.
+
+use axum::{
+    body::Body,
+    extract::{FromRequest, Multipart, Request},
+    http::{header::CONTENT_TYPE, HeaderMap},
+    routing::post,
+    Router,
+};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::{
+    openapi::{path::PathItemType, Content, OpenApi, Ref},
+    Modify, ToSchema,
+};
+
+use super::{
+    auth::WriteUser,
+    content::{Format, Link, Negotiated, Resource, NDJSON},
+    error::ApiError,
+    person::NewPerson,
+    v1,
+};
+use crate::service::person::PersonService;
+
+const MULTIPART: &str = "multipart/form-data";
+
+/// The form field holding an uploaded file
+const FILE_FIELD: &str = "file";
+
+/// The longest line accepted, well beyond any valid person
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// How many people are inserted in each transaction
+const IMPORT_BATCH: usize = 500;
+
+/// How many failed lines are reported, the rest only being counted
+const MAX_REPORTED_ERRORS: usize = 100;
+
+/// Why a line of an import failed
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportError {
+    /// The line of the body or uploaded CSV file, or the position in an uploaded JSON array,
+    /// counting from 1
+    pub line: u64,
+    pub message: String,
+}
+
+/// What came of an import
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    /// How many people were created
+    pub imported: u64,
+    /// How many lines couldn't be imported, blank lines aside
+    pub failed: u64,
+    /// Why the first 100 failed lines couldn't be imported
+    pub errors: Vec<ImportError>,
+}
+
+impl ImportSummary {
+    fn fail(&mut self, line: u64, message: String) {
+        self.failed += 1;
+
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(ImportError { line, message });
+        }
+    }
+}
+
+impl Resource for ImportSummary {
+    const ELEMENT: &'static str = "importSummary";
+    const COLLECTION: &'static str = "importSummaries";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        vec![("people", Link::to(format!("{}/person", v1::PREFIX)))]
+    }
+}
+
+/// A file of people to import, uploaded as a form
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct ImportUpload {
+    /// A CSV file with a header row naming the fields of a person, or a JSON array of people,
+    /// told apart by its content type or file extension
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+/// Documents uploading a file as the alternative to a newline delimited body, which the path
+/// attributes have no way of saying
+pub struct FileUploads;
+
+impl Modify for FileUploads {
+    fn modify(&self, openapi: &mut OpenApi) {
+        let body = openapi
+            .paths
+            .paths
+            .get_mut("/person/import")
+            .and_then(|item| item.operations.get_mut(&PathItemType::Post))
+            .and_then(|operation| operation.request_body.as_mut());
+
+        if let Some(body) = body {
+            body.content.insert(
+                MULTIPART.to_owned(),
+                Content::new(Ref::from_schema_name("ImportUpload")),
+            );
+        }
+    }
+}
+
+/// A row of an uploaded file by its line or position, with the person in it or why there isn't
+/// a valid one
+type Row = (u64, Result<NewPerson, String>);
+
+/// The kinds of file which can be uploaded
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum FileFormat {
+    Csv,
+    Json,
+}
+
+impl FileFormat {
+    fn of(content_type: Option<&str>, file_name: Option<&str>) -> Option<Self> {
+        let extension = file_name
+            .and_then(|name| name.rsplit_once('.'))
+            .map(|(_, extension)| extension);
+
+        match (content_type, extension) {
+            (Some(t), _) if t.eq_ignore_ascii_case("text/csv") => Some(FileFormat::Csv),
+            (Some(t), _) if t.eq_ignore_ascii_case("application/json") => Some(FileFormat::Json),
+            (_, Some(e)) if e.eq_ignore_ascii_case("csv") => Some(FileFormat::Csv),
+            (_, Some(e)) if e.eq_ignore_ascii_case("json") => Some(FileFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Splits a body into lines as its chunks arrive
+#[derive(Default)]
+struct Lines {
+    partial: Vec<u8>,
+    overlong: bool,
+}
+
+impl Lines {
+    /// The lines completed by the chunk, being `None` for any longer than allowed
+    fn push(&mut self, mut chunk: &[u8]) -> Vec<Option<Vec<u8>>> {
+        let mut lines = vec![];
+
+        while let Some(end) = chunk.iter().position(|b| *b == b'\n') {
+            self.extend(&chunk[..end]);
+            lines.push(self.take());
+            chunk = &chunk[end + 1..];
+        }
+        self.extend(chunk);
+
+        lines
+    }
+
+    /// The last line, when the body doesn't end with a newline
+    fn finish(mut self) -> Option<Option<Vec<u8>>> {
+        (self.overlong || !self.partial.is_empty()).then(|| self.take())
+    }
+
+    fn extend(&mut self, bytes: &[u8]) {
+        // the rest of an overlong line is dropped rather than held
+        if self.partial.len() + bytes.len() > MAX_LINE_BYTES {
+            self.overlong = true;
+            self.partial.clear();
+        } else if !self.overlong {
+            self.partial.extend_from_slice(bytes);
+        }
+    }
+
+    fn take(&mut self) -> Option<Vec<u8>> {
+        let line = std::mem::take(&mut self.partial);
+        (!std::mem::replace(&mut self.overlong, false)).then_some(line)
+    }
+}
+
+/// The people waiting to be inserted, with the lines they came from
+struct Batch<'a> {
+    people: &'a PersonService,
+    actor: &'a str,
+    lines: Vec<u64>,
+    requests: Vec<NewPerson>,
+}
+
+impl Batch<'_> {
+    async fn add(
+        &mut self,
+        line: u64,
+        request: NewPerson,
+        summary: &mut ImportSummary,
+    ) -> Result<(), ApiError> {
+        self.lines.push(line);
+        self.requests.push(request);
+
+        if self.requests.len() == IMPORT_BATCH {
+            self.flush(summary).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn flush(&mut self, summary: &mut ImportSummary) -> Result<(), ApiError> {
+        let results = self.people.create_each(self.actor, &self.requests).await?;
+
+        for (line, result) in self.lines.drain(..).zip(results) {
+            match result {
+                Ok(_) => summary.imported += 1,
+                Err(e) => summary.fail(line, e.to_string()),
+            }
+        }
+        self.requests.clear();
+
+        Ok(())
+    }
+}
+
+/// The media type of the body, without any parameters
+fn media_type(headers: &HeaderMap) -> Option<&str> {
+    let content_type = headers.get(CONTENT_TYPE)?.to_str().unwrap_or_default();
+
+    Some(content_type.split(';').next().unwrap_or_default().trim())
+}
+
+/// Import people
+///
+/// Creates a person from each line of a newline delimited JSON body, in the same form as when
+/// creating one person, skipping blank lines. The body is processed as it arrives, so can be
+/// as large as needed, and people are created in batches of 500 as they're read. Lines that
+/// can't be imported are reported by number in the summary, without stopping the rest. Should
+/// the import fail part way, the batches already created are kept.
+///
+/// Alternatively, a CSV or JSON file can be uploaded as the `file` field of a form. A CSV file
+/// has a header row naming the fields, such as `firstName,familyName,dateOfBirth`, while a JSON
+/// file holds an array of people. Everyone who can be is created in a single transaction, with
+/// the rows which can't be reported by their line in a CSV file or position in a JSON array.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    post,
+    tag = "person",
+    path = "/person/import",
+    request_body(
+        content = String,
+        content_type = "application/x-ndjson",
+        description = "One person per line, as in the body creating a person, or a file of them"
+    ),
+    responses(
+        (status = 200, description = "The outcome of the import", body = ImportSummary),
+        (status = 400, description = "The body or uploaded file couldn't be read", body = ErrorResponse),
+        (status = 415, description = "The body isn't newline delimited JSON or a form, or the file isn't CSV or JSON", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn import_people(
+    user: WriteUser,
+    people: PersonService,
+    format: Format,
+    request: Request,
+) -> Result<Negotiated<ImportSummary>, ApiError> {
+    let summary = match media_type(request.headers()) {
+        None => import_lines(&people, &user.username, request.into_body()).await?,
+        Some(media_type) if media_type.eq_ignore_ascii_case(NDJSON) => {
+            import_lines(&people, &user.username, request.into_body()).await?
+        }
+        Some(media_type) if media_type.eq_ignore_ascii_case(MULTIPART) => {
+            let form = Multipart::from_request(request, &())
+                .await
+                .map_err(|e| ApiError::UnreadableBody(e.body_text()))?;
+            import_file(&people, &user.username, form).await?
+        }
+        Some(media_type) => return Err(ApiError::UnsupportedMediaType(media_type.to_owned())),
+    };
+
+    info!(
+        "Client '{}' imported {} person(s), with {} failing",
+        user.username, summary.imported, summary.failed
+    );
+
+    Ok(Negotiated(format, summary))
+}
+
+/// Imports each line of a newline delimited JSON body as it arrives
+async fn import_lines(
+    people: &PersonService,
+    actor: &str,
+    body: Body,
+) -> Result<ImportSummary, ApiError> {
+    let mut summary = ImportSummary::default();
+    let mut batch = Batch {
+        people,
+        actor,
+        lines: Vec::with_capacity(IMPORT_BATCH),
+        requests: Vec::with_capacity(IMPORT_BATCH),
+    };
+    let mut lines = Lines::default();
+    let mut number = 0;
+    let mut stream = body.into_data_stream();
+
+    loop {
+        let completed = match stream.next().await {
+            Some(chunk) => {
+                let chunk = chunk.map_err(|e| ApiError::UnreadableBody(e.to_string()))?;
+                lines.push(&chunk)
+            }
+            None => break,
+        };
+
+        for line in completed {
+            number += 1;
+            import_line(number, line, &mut batch, &mut summary).await?;
+        }
+    }
+
+    if let Some(line) = lines.finish() {
+        number += 1;
+        import_line(number, line, &mut batch, &mut summary).await?;
+    }
+    batch.flush(&mut summary).await?;
+
+    Ok(summary)
+}
+
+/// Imports everyone in the file uploaded as the `file` field of the form, in one transaction
+async fn import_file(
+    people: &PersonService,
+    actor: &str,
+    mut form: Multipart,
+) -> Result<ImportSummary, ApiError> {
+    let unreadable =
+        |e: axum::extract::multipart::MultipartError| ApiError::UnreadableBody(e.body_text());
+
+    let (file_format, file) = loop {
+        let Some(field) = form.next_field().await.map_err(unreadable)? else {
+            return Err(ApiError::UnreadableBody(format!(
+                "No file was uploaded as the `{FILE_FIELD}` field"
+            )));
+        };
+
+        if field.name() == Some(FILE_FIELD) {
+            let file_format =
+                FileFormat::of(field.content_type(), field.file_name()).ok_or_else(|| {
+                    ApiError::UnsupportedMediaType(
+                        field.content_type().unwrap_or_default().to_owned(),
+                    )
+                })?;
+            break (file_format, field.bytes().await.map_err(unreadable)?);
+        }
+    };
+
+    let rows = match file_format {
+        FileFormat::Csv => csv_rows(&file)?,
+        FileFormat::Json => json_rows(&file)?,
+    };
+
+    let mut summary = ImportSummary::default();
+    let mut lines = Vec::with_capacity(rows.len());
+    let mut requests = Vec::with_capacity(rows.len());
+
+    for (line, row) in rows {
+        match row {
+            Ok(request) => {
+                lines.push(line);
+                requests.push(request);
+            }
+            Err(message) => summary.fail(line, message),
+        }
+    }
+
+    let results = people.create_each(actor, &requests).await?;
+
+    for (line, result) in lines.into_iter().zip(results) {
+        match result {
+            Ok(_) => summary.imported += 1,
+            Err(e) => summary.fail(line, e.to_string()),
+        }
+    }
+
+    // rows are reported in the order they appear, whether rejected as read or when inserted
+    summary.errors.sort_by_key(|error| error.line);
+
+    Ok(summary)
+}
+
+/// The people in each row of a CSV file, by the line the row starts on, with its header row
+/// naming the fields
+fn csv_rows(file: &[u8]) -> Result<Vec<Row>, ApiError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(file);
+    let headers = reader
+        .headers()
+        .map_err(|e| ApiError::UnreadableBody(format!("The CSV header row is invalid: {e}")))?
+        .clone();
+
+    let rows = reader
+        .records()
+        .map(|record| {
+            let position = match &record {
+                Ok(record) => record.position(),
+                Err(e) => e.position(),
+            };
+            let line = position.map_or(0, |position| line_at(file, position.byte()));
+
+            let person = record.map_err(|e| e.to_string()).and_then(|record| {
+                let fields = headers
+                    .iter()
+                    .zip(&record)
+                    .map(|(name, value)| (name.to_owned(), value.into()))
+                    .collect();
+                serde_json::from_value(serde_json::Value::Object(fields)).map_err(|e| e.to_string())
+            });
+
+            (line, person)
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+/// The line of the file a record starts on, counting from 1.
+///
+/// The reader counts the lines of files ending them with `\r\n` from the `\n`, putting the
+/// records one line early, so the lines are counted here instead. A record's position is then
+/// the `\n` ending the line before, which is counted along with those before it.
+fn line_at(file: &[u8], byte: u64) -> u64 {
+    let end = (byte as usize + 1).min(file.len());
+
+    1 + file[..end].iter().filter(|b| **b == b'\n').count() as u64
+}
+
+/// The people in a JSON array, by their position in it
+fn json_rows(file: &[u8]) -> Result<Vec<Row>, ApiError> {
+    let values: Vec<serde_json::Value> = serde_json::from_slice(file).map_err(|e| {
+        ApiError::UnreadableBody(format!("The file isn't a JSON array of people: {e}"))
+    })?;
+
+    let rows = (1..)
+        .zip(values)
+        .map(|(position, value)| {
+            let person = serde_json::from_value(value).map_err(|e| e.to_string());
+            (position, person)
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+async fn import_line(
+    number: u64,
+    line: Option<Vec<u8>>,
+    batch: &mut Batch<'_>,
+    summary: &mut ImportSummary,
+) -> Result<(), ApiError> {
+    let Some(line) = line else {
+        summary.fail(
+            number,
+            format!("The line is longer than {MAX_LINE_BYTES} bytes"),
+        );
+        return Ok(());
+    };
+
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return Ok(());
+    }
+
+    match serde_json::from_slice::<NewPerson>(&line) {
+        Ok(request) => batch.add(number, request, summary).await,
+        Err(e) => {
+            summary.fail(number, e.to_string());
+            Ok(())
+        }
+    }
+}
+
+pub fn router() -> Router {
+    Router::new().route("/person/import", post(import_people))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{csv_rows, FileFormat, Lines, MAX_LINE_BYTES};
+
+    #[test]
+    fn lines_are_split_across_chunks() {
+        let mut lines = Lines::default();
+
+        assert_eq!(lines.push(b"{\"a\""), Vec::<Option<Vec<u8>>>::new());
+        assert_eq!(
+            lines.push(b":1}\n{\"b\":2}\n{\"c\""),
+            [Some(b"{\"a\":1}".to_vec()), Some(b"{\"b\":2}".to_vec())]
+        );
+        assert_eq!(lines.finish(), Some(Some(b"{\"c\"".to_vec())));
+    }
+
+    #[test]
+    fn overlong_lines_are_dropped_without_losing_the_next() {
+        let mut lines = Lines::default();
+        let long = vec![b'x'; MAX_LINE_BYTES];
+
+        assert!(lines.push(&long).is_empty());
+        assert_eq!(lines.push(b"xx\n{}\n"), [None, Some(b"{}".to_vec())]);
+        assert_eq!(lines.finish(), None);
+    }
+
+    #[test]
+    fn files_are_told_apart_by_content_type_then_extension() {
+        assert_eq!(
+            FileFormat::of(Some("text/csv"), Some("people.json")),
+            Some(FileFormat::Csv)
+        );
+        assert_eq!(
+            FileFormat::of(Some("application/octet-stream"), Some("people.JSON")),
+            Some(FileFormat::Json)
+        );
+        assert_eq!(FileFormat::of(None, Some("people.xlsx")), None);
+    }
+
+    #[test]
+    fn csv_rows_are_numbered_by_line() {
+        let file = "first_name,familyName,dateOfBirth\nAda,Lovelace,1815-12-10\n\"Grace\nBrewster\",Hopper,1906-12-09\nAlan,,1912-06-23\n";
+
+        for file in [file.to_owned(), file.replace('\n', "\r\n")] {
+            let rows = csv_rows(file.as_bytes()).unwrap();
+
+            let lines: Vec<_> = rows.iter().map(|(line, _)| *line).collect();
+            assert_eq!(lines, [2, 3, 5]);
+            assert_eq!(rows[0].1.as_ref().unwrap().first_name.as_str(), "Ada");
+            assert_eq!(
+                rows[1].1.as_ref().unwrap().first_name.as_str(),
+                "Grace Brewster"
+            );
+            assert!(rows[2].1.is_err(), "An empty family name is invalid");
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use axum::{extract::Path, routing::get, Json, Router};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::{
+    auth::AdminUser, content::ValidatedPayload, error::ApiError, fields, response::Deleted,
+};
+use crate::service::legal_hold::LegalHoldService;
+
+#[derive(Debug, Validate, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NewLegalHold {
+    /// Why the person's details must be kept, such as the case or matter it's for
+    #[validate(length(min = 1, max = 500))]
+    #[schema(min_length = 1, max_length = 500)]
+    #[serde(deserialize_with = "fields::normalized")]
+    pub reason: String,
+}
+
+/// Whether a person is under legal hold, which stops them being deleted
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LegalHold {
+    pub person_id: Uuid,
+    pub held: bool,
+}
+
+/// Get whether a person is under legal hold
+///
+/// Requires the scope `admin`
+#[utoipa::path(
+    get,
+    tag = "admin",
+    path = "/admin/person/{person_uuid}/legal-hold",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "Whether the person is under legal hold", body = LegalHold),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn get_legal_hold(
+    user: AdminUser,
+    holds: LegalHoldService,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Json<LegalHold>, ApiError> {
+    let hold = holds.find(person_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved the legal hold of person '{}'",
+        user.username, person_uuid
+    );
+
+    Ok(Json(hold))
+}
+
+/// Place a person under legal hold
+///
+/// Until the hold is lifted, deleting the person is refused with `423 Locked`. Placing a hold
+/// on someone already under one changes nothing.
+///
+/// Requires the scope `admin`
+#[utoipa::path(
+    put,
+    tag = "admin",
+    path = "/admin/person/{person_uuid}/legal-hold",
+    request_body = NewLegalHold,
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "The person is under legal hold", body = LegalHold),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn place_legal_hold(
+    user: AdminUser,
+    holds: LegalHoldService,
+    Path(person_uuid): Path<Uuid>,
+    ValidatedPayload(request): ValidatedPayload<NewLegalHold>,
+) -> Result<Json<LegalHold>, ApiError> {
+    let hold = holds.place(&user.username, person_uuid, &request).await?;
+
+    Ok(Json(hold))
+}
+
+/// Lift a person's legal hold
+///
+/// Requires the scope `admin`
+#[utoipa::path(
+    delete,
+    tag = "admin",
+    path = "/admin/person/{person_uuid}/legal-hold",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 204, description = "The person is no longer under legal hold"),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn lift_legal_hold(
+    user: AdminUser,
+    holds: LegalHoldService,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Deleted, ApiError> {
+    holds.lift(&user.username, person_uuid).await?;
+
+    Ok(Deleted)
+}
+
+pub fn router() -> Router {
+    Router::new().route(
+        "/admin/person/:person_uuid/legal-hold",
+        get(get_legal_hold)
+            .put(place_legal_hold)
+            .delete(lift_legal_hold),
+    )
+}
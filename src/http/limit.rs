@@ -0,0 +1,84 @@
+//! Bounds on how many items collection endpoints return, so no single request can load an
+//! unbounded number of rows into memory.
+//!
+//! Queries listing a collection take a [`Limit`] rather than a bare number, which can only be
+//! made within the bounds, and clients may ask for fewer or more with [`LimitQuery`], paging
+//! through the rest with [`OffsetQuery`].
+
+use serde::Deserialize;
+use utoipa::IntoParams;
+use validator::Validate;
+
+/// How many items are returned when the client doesn't say
+pub const DEFAULT_LIMIT: i64 = 100;
+
+/// The most items any one request can return
+pub const MAX_LIMIT: i64 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limit(i64);
+
+impl Limit {
+    /// The requested number of items, brought within `1..=MAX_LIMIT`
+    pub fn new(requested: Option<i64>) -> Self {
+        Limit(requested.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT))
+    }
+
+    pub fn get(self) -> i64 {
+        self.0
+    }
+}
+
+impl Default for Limit {
+    fn default() -> Self {
+        Limit(DEFAULT_LIMIT)
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct LimitQuery {
+    /// The maximum number of items to return, defaults to 100
+    #[param(minimum = 1, maximum = 1000)]
+    #[validate(range(min = 1, max = MAX_LIMIT))]
+    limit: Option<i64>,
+}
+
+impl LimitQuery {
+    pub fn limit(&self) -> Limit {
+        Limit::new(self.limit)
+    }
+
+    /// The limit, only when the client gave one
+    pub fn requested(&self) -> Option<Limit> {
+        self.limit.map(|limit| Limit::new(Some(limit)))
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct OffsetQuery {
+    /// How many items to skip before those returned, defaults to 0
+    #[param(minimum = 0)]
+    #[validate(range(min = 0))]
+    offset: Option<i64>,
+}
+
+impl OffsetQuery {
+    pub fn offset(&self) -> Option<i64> {
+        self.offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Limit, DEFAULT_LIMIT, MAX_LIMIT};
+
+    #[test]
+    fn limits_are_kept_within_bounds() {
+        assert_eq!(Limit::new(None).get(), DEFAULT_LIMIT);
+        assert_eq!(Limit::new(Some(20)).get(), 20);
+        assert_eq!(Limit::new(Some(0)).get(), 1);
+        assert_eq!(Limit::new(Some(1_000_000)).get(), MAX_LIMIT);
+    }
+}
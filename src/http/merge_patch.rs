@@ -0,0 +1,122 @@
+//! Partial updates as [JSON Merge Patch](https://www.rfc-editor.org/rfc/rfc7396) documents.
+//!
+//! A patch gives the fields to change, in the same form as the resource. Fields left out are
+//! kept as they are, fields set to `null` are removed, and objects are patched field by field,
+//! while any other value replaces what was there. The patched resource is then read as a whole,
+//! so it's held to the same rules as one sent in full.
+
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::header::CONTENT_TYPE,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use super::{content::JSON, error::ApiError, strict};
+
+pub const MERGE_PATCH: &str = "application/merge-patch+json";
+
+/// A merge patch request body, sent as `application/merge-patch+json` or plain JSON, which is
+/// assumed when unspecified
+pub struct MergePatch(pub Value);
+
+impl MergePatch {
+    /// The document with the patch applied, read as a `T`
+    pub fn apply<T: DeserializeOwned>(&self, mut document: Value) -> Result<T, ApiError> {
+        merge(&mut document, &self.0);
+
+        strict::check::<T>(&document)?;
+
+        serde_json::from_value(document).map_err(|e| ApiError::InvalidPatch(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl<S> FromRequest<S> for MergePatch
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let content_type = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or(JSON);
+        let media_type = content_type.split(';').next().unwrap_or_default().trim();
+
+        if !media_type.eq_ignore_ascii_case(MERGE_PATCH) && !media_type.eq_ignore_ascii_case(JSON) {
+            return Err(ApiError::UnsupportedMediaType(content_type.to_owned()));
+        }
+
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(|e| ApiError::UnreadableBody(e.body_text()))?;
+        let patch =
+            serde_json::from_slice(&bytes).map_err(|e| ApiError::InvalidPatch(e.to_string()))?;
+
+        Ok(MergePatch(patch))
+    }
+}
+
+/// Applies the patch to the target, as laid out by RFC 7396
+pub fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(changes) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(fields) = target else {
+        unreachable!("The target was made an object");
+    };
+
+    for (name, change) in changes {
+        if change.is_null() {
+            fields.remove(name);
+        } else {
+            merge(fields.entry(name).or_insert(Value::Null), change);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::merge;
+
+    fn merged(mut target: Value, patch: Value) -> Value {
+        merge(&mut target, &patch);
+        target
+    }
+
+    #[test]
+    fn fields_are_changed_removed_and_kept() {
+        assert_eq!(
+            merged(
+                json!({ "a": "b", "c": { "d": "e", "f": "g" } }),
+                json!({ "a": "z", "c": { "f": null } })
+            ),
+            json!({ "a": "z", "c": { "d": "e" } })
+        );
+    }
+
+    #[test]
+    fn anything_but_an_object_replaces_the_target() {
+        assert_eq!(
+            merged(json!({ "a": ["b"] }), json!({ "a": ["c"] })),
+            json!({ "a": ["c"] })
+        );
+        assert_eq!(merged(json!({ "a": "b" }), json!(["c"])), json!(["c"]));
+        assert_eq!(
+            merged(json!("a"), json!({ "b": { "c": null } })),
+            json!({ "b": {} })
+        );
+    }
+}
@@ -1,5 +1,50 @@
 pub mod address;
+pub mod admin;
+pub mod admin_ui;
+pub mod api_client;
+pub mod archive;
 pub mod auth;
+pub mod cache;
+pub mod cache_control;
+pub mod compression;
+pub mod consent;
+pub mod contact_detail;
+pub mod content;
+pub mod cursor;
+pub mod deprecation;
+pub mod emergency_contact;
+pub mod employment;
 pub mod error;
+pub mod export;
+pub mod export_job;
+pub mod fields;
+pub mod filter;
+pub mod graphql;
+pub mod health;
+pub mod import;
+pub mod legal_hold;
+pub mod limit;
+pub mod merge_patch;
+pub mod odata;
 pub mod openapi;
+pub mod path;
 pub mod person;
+pub mod person_event;
+pub mod person_history;
+pub mod person_merge;
+pub mod person_note;
+pub mod person_photo;
+pub mod person_stats;
+pub mod person_tag;
+pub mod precondition;
+pub mod query;
+pub mod range;
+pub mod relationship;
+pub mod response;
+pub mod scheduled_deletion;
+pub mod scim;
+pub mod sparse;
+pub mod strict;
+pub mod timestamp;
+pub mod usage;
+pub mod v1;
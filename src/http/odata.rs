@@ -0,0 +1,476 @@
+//! [OData] query options for listing people, for integration tools which only speak OData,
+//! such as `?$filter=familyName eq 'Smith' and dateOfBirth ge 1980-01-01&$orderby=firstName`.
+//!
+//! `$filter` is parsed into the same [`Filter`] as RSQL filters, so it's limited to the same
+//! fields and values are bound in the same way. It supports `eq`, `ne`, `lt`, `le`, `gt`, `ge`
+//! and `in`, joined with `and`, `or` and `not` and grouped with parentheses, and the `contains`,
+//! `startswith` and `endswith` functions on names, which ignore case. `$orderby` sorts by
+//! fields, each optionally followed by `asc` or `desc`, while `$top` and `$skip` page through
+//! the results and `$count=true` includes how many people match as `@odata.count`.
+//!
+//! [OData]: https://docs.oasis-open.org/odata/odata/v4.01/odata-v4.01-part2-url-conventions.html
+
+use serde::Deserialize;
+use utoipa::IntoParams;
+use validator::Validate;
+
+use super::{
+    error::ApiError,
+    filter::{self, Field, Filter, Operator, Sort, Value},
+    limit::{Limit, MAX_LIMIT},
+};
+
+/// The longest `$filter` or `$orderby` accepted
+const MAX_LENGTH: u64 = 2000;
+
+/// How deeply parentheses may be nested
+const MAX_DEPTH: usize = 8;
+
+/// How many comparisons one filter may make
+const MAX_COMPARISONS: usize = 32;
+
+#[derive(Debug, Default, Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct ODataQuery {
+    /// Only return people matching this OData expression, such as `familyName eq 'Smith'`
+    #[serde(rename = "$filter")]
+    #[param(max_length = 2000)]
+    #[validate(length(max = MAX_LENGTH))]
+    filter: Option<String>,
+    /// The fields to sort by, such as `familyName desc,firstName`, oldest first otherwise
+    #[serde(rename = "$orderby")]
+    #[param(max_length = 2000)]
+    #[validate(length(max = MAX_LENGTH))]
+    order_by: Option<String>,
+    /// The maximum number of people to return, in place of `limit`
+    #[serde(rename = "$top")]
+    #[param(minimum = 1, maximum = 1000)]
+    #[validate(range(min = 1, max = MAX_LIMIT))]
+    top: Option<i64>,
+    /// How many people to skip before those returned
+    #[serde(rename = "$skip")]
+    #[param(minimum = 0)]
+    #[validate(range(min = 0))]
+    skip: Option<i64>,
+    /// Return `{"@odata.count": ..., "value": [...]}` including how many people match
+    #[serde(rename = "$count")]
+    count: Option<bool>,
+}
+
+impl ODataQuery {
+    pub fn filter(&self) -> Result<Option<Filter>, ApiError> {
+        self.filter.as_deref().map(parse_filter).transpose()
+    }
+
+    pub fn order(&self) -> Result<Vec<Sort>, ApiError> {
+        self.order_by.as_deref().map_or(Ok(Vec::new()), parse_order)
+    }
+
+    /// The limit given by `$top`, if any
+    pub fn top(&self) -> Option<Limit> {
+        self.top.map(|top| Limit::new(Some(top)))
+    }
+
+    pub fn skip(&self) -> Option<i64> {
+        self.skip
+    }
+
+    pub fn count(&self) -> bool {
+        self.count.unwrap_or(false)
+    }
+}
+
+/// Parses an `$orderby` such as `familyName desc,firstName`
+fn parse_order(order_by: &str) -> Result<Vec<Sort>, ApiError> {
+    order_by
+        .split(',')
+        .map(|item| {
+            let mut words = item.split_whitespace();
+            let name = words.next().unwrap_or_default();
+            let field = Field::from_name(name).ok_or_else(|| {
+                ApiError::InvalidOrder(format!("Sorting by '{name}' is not supported"))
+            })?;
+
+            let descending = match words.next() {
+                None | Some("asc") => false,
+                Some("desc") => true,
+                Some(other) => {
+                    return Err(ApiError::InvalidOrder(format!(
+                        "Expected asc or desc after '{name}', not '{other}'"
+                    )))
+                }
+            };
+
+            match words.next() {
+                None => Ok(Sort { field, descending }),
+                Some(other) => Err(ApiError::InvalidOrder(format!("Unexpected '{other}'"))),
+            }
+        })
+        .collect()
+}
+
+/// Parses a `$filter` expression
+fn parse_filter(filter: &str) -> Result<Filter, ApiError> {
+    let mut parser = Parser {
+        tokens: tokenize(filter).map_err(ApiError::InvalidFilter)?,
+        position: 0,
+        depth: 0,
+        comparisons: 0,
+    };
+
+    let parsed = parser.or().map_err(ApiError::InvalidFilter)?;
+
+    match parser.next() {
+        None => Ok(parsed),
+        Some(token) => Err(ApiError::InvalidFilter(format!("Unexpected {token}"))),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// A name, keyword or unquoted value such as a date
+    Word(String),
+    /// A quoted string, with `''` unescaped
+    Quoted(String),
+    Open,
+    Close,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "'{word}'"),
+            Token::Quoted(text) => write!(f, "'{}'", text.replace('\'', "''")),
+            Token::Open => f.write_str("'('"),
+            Token::Close => f.write_str("')'"),
+            Token::Comma => f.write_str("','"),
+        }
+    }
+}
+
+fn tokenize(filter: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = filter.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            '\'' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\'') if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            text.push('\'');
+                        }
+                        Some('\'') => break,
+                        Some(c) => text.push(c),
+                        None => return Err("Unterminated string".to_owned()),
+                    }
+                }
+                tokens.push(Token::Quoted(text));
+            }
+            c => {
+                let mut word = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | ',' | '\'') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+    comparisons: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("Expected {expected}, not {token}")),
+            None => Err(format!("Expected {expected}")),
+        }
+    }
+
+    /// Whether the next token is the keyword, taking it if so
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) if word == keyword => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<Filter, String> {
+        let mut filters = vec![self.and()?];
+        while self.keyword("or") {
+            filters.push(self.and()?);
+        }
+
+        Ok(match filters.len() {
+            1 => filters.remove(0),
+            _ => Filter::Or(filters),
+        })
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut filters = vec![self.unary()?];
+        while self.keyword("and") {
+            filters.push(self.unary()?);
+        }
+
+        Ok(match filters.len() {
+            1 => filters.remove(0),
+            _ => Filter::And(filters),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Filter, String> {
+        if self.keyword("not") {
+            return Ok(Filter::Not(Box::new(self.unary()?)));
+        }
+
+        if self.peek() == Some(&Token::Open) {
+            self.position += 1;
+            self.depth += 1;
+            if self.depth > MAX_DEPTH {
+                return Err(format!(
+                    "Parentheses can be nested at most {MAX_DEPTH} deep"
+                ));
+            }
+
+            let filter = self.or()?;
+            self.expect(Token::Close)?;
+            self.depth -= 1;
+
+            return Ok(filter);
+        }
+
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Filter, String> {
+        self.comparisons += 1;
+        if self.comparisons > MAX_COMPARISONS {
+            return Err(format!(
+                "A filter can make at most {MAX_COMPARISONS} comparisons"
+            ));
+        }
+
+        let name = match self.next() {
+            Some(Token::Word(name)) => name,
+            Some(token) => return Err(format!("Expected a field such as familyName, not {token}")),
+            None => return Err("Expected a field such as familyName".to_owned()),
+        };
+
+        if let Some(matching) = Matching::from_name(&name) {
+            return self.function(matching);
+        }
+
+        let field = field(&name)?;
+        let operator = match self.next() {
+            Some(Token::Word(operator)) => match operator.as_str() {
+                "eq" => Operator::Equal,
+                "ne" => Operator::NotEqual,
+                "lt" => Operator::LessThan,
+                "le" => Operator::LessOrEqual,
+                "gt" => Operator::GreaterThan,
+                "ge" => Operator::GreaterOrEqual,
+                "in" => Operator::In,
+                _ => return Err(format!("'{operator}' is not a supported operator")),
+            },
+            _ => return Err("Expected an operator such as eq, ne or ge".to_owned()),
+        };
+
+        let values = if operator == Operator::In {
+            self.expect(Token::Open)?;
+            let mut values = vec![self.value(field)?];
+            while self.peek() == Some(&Token::Comma) {
+                self.position += 1;
+                values.push(self.value(field)?);
+            }
+            self.expect(Token::Close)?;
+            values
+        } else {
+            vec![self.value(field)?]
+        };
+
+        Ok(Filter::Compare {
+            field,
+            operator,
+            values,
+        })
+    }
+
+    /// A call such as `contains(familyName,'mit')`
+    fn function(&mut self, matching: Matching) -> Result<Filter, String> {
+        self.expect(Token::Open)?;
+        let field = match self.next() {
+            Some(Token::Word(name)) => field(&name)?,
+            _ => return Err("Expected a field such as familyName".to_owned()),
+        };
+        if !matches!(field, Field::FirstName | Field::FamilyName) {
+            return Err(
+                "Only names can be matched with contains, startswith and endswith".to_owned(),
+            );
+        }
+        self.expect(Token::Comma)?;
+        let text = match self.next() {
+            Some(Token::Quoted(text)) => text,
+            _ => return Err("Expected a quoted string such as 'Smith'".to_owned()),
+        };
+        self.expect(Token::Close)?;
+
+        let text = filter::escape_like(&text);
+        let pattern = match matching {
+            Matching::Contains => format!("%{text}%"),
+            Matching::StartsWith => format!("{text}%"),
+            Matching::EndsWith => format!("%{text}"),
+        };
+
+        Ok(Filter::Compare {
+            field,
+            operator: Operator::Equal,
+            values: vec![Value::Pattern(pattern)],
+        })
+    }
+
+    fn value(&mut self, field: Field) -> Result<Value, String> {
+        match self.next() {
+            Some(Token::Quoted(value) | Token::Word(value)) => field.value(&value),
+            Some(token) => Err(format!("Expected a value, not {token}")),
+            None => Err("Expected a value".to_owned()),
+        }
+    }
+}
+
+fn field(name: &str) -> Result<Field, String> {
+    Field::from_name(name).ok_or_else(|| format!("Filtering on '{name}' is not supported"))
+}
+
+/// The functions matching part of a name
+#[derive(Clone, Copy)]
+enum Matching {
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+impl Matching {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "contains" => Some(Matching::Contains),
+            "startswith" => Some(Matching::StartsWith),
+            "endswith" => Some(Matching::EndsWith),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::QueryBuilder;
+
+    use super::{parse_filter, parse_order};
+    use crate::http::filter::{Field, Sort};
+
+    fn sql(filter: &str) -> String {
+        let mut builder = QueryBuilder::new("");
+        parse_filter(filter).unwrap().push_sql(&mut builder);
+        builder.into_sql()
+    }
+
+    #[test]
+    fn filters_map_onto_the_same_conditions() {
+        assert_eq!(
+            sql("familyName eq 'Smith' and dateOfBirth ge 1980-01-01 or firstName in ('Ada', 'Grace')"),
+            "(((p.family_name = $1) AND (p.date_of_birth >= $2)) OR (p.first_name IN ($3, $4)))"
+        );
+        assert_eq!(
+            sql("not (familyName eq 'O''Brien' or firstName ne 'Sm*')"),
+            "(NOT ((p.family_name = $1) OR (p.first_name <> $2)))"
+        );
+    }
+
+    #[test]
+    fn names_can_be_matched_in_part() {
+        assert_eq!(
+            sql("contains(familyName,'mit') and startswith(firstName, 'A')"),
+            "((p.family_name ILIKE $1) AND (p.first_name ILIKE $2))"
+        );
+        assert!(parse_filter("endswith(dateOfBirth,'01')").is_err());
+    }
+
+    #[test]
+    fn invalid_filters_are_rejected() {
+        for invalid in [
+            "",
+            "password eq 'secret'",
+            "familyName like 'Smith'",
+            "familyName eq",
+            "familyName eq 'Smith' and",
+            "(familyName eq 'Smith'",
+            "familyName eq 'Smith",
+            "dateOfBirth eq 'yesterday'",
+            "familyName eq 'Smith') or 1 eq 1",
+        ] {
+            assert!(parse_filter(invalid).is_err(), "{invalid}");
+        }
+
+        let many = vec!["familyName eq 'Smith'"; 33].join(" and ");
+        assert!(parse_filter(&many).is_err());
+    }
+
+    #[test]
+    fn order_is_parsed() {
+        assert_eq!(
+            parse_order("familyName desc, firstName").unwrap(),
+            [
+                Sort {
+                    field: Field::FamilyName,
+                    descending: true
+                },
+                Sort {
+                    field: Field::FirstName,
+                    descending: false
+                },
+            ]
+        );
+
+        for invalid in [
+            "password",
+            "familyName sideways",
+            "familyName desc firstName",
+            "",
+        ] {
+            assert!(parse_order(invalid).is_err(), "{invalid}");
+        }
+    }
+}
@@ -1,33 +1,23 @@
-use axum::Router;
+use axum::{middleware, Router};
 use utoipa::{
-    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    openapi::{
+        security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+        RefOr,
+    },
     Modify, OpenApi,
 };
 use utoipa_swagger_ui::SwaggerUi;
 
-#[derive(OpenApi)]
-#[openapi(
-    paths(
-        super::address::add_address,
-        super::address::remove_address,
-        super::person::create_person,
-        super::person::list_people,
-        super::person::get_person,
-        super::person::delete_person,
-        super::person::update_person,
-    ),
-    components(schemas(
-        super::address::NewAddress,
-        super::person::NewPerson,
-        super::person::UpdatePerson,
-        super::person::Person,
-        super::error::ErrorResponse
-    )),
-    modifiers(&SecurityAddon),
-    tags()
-)]
-struct ApiDoc;
-struct SecurityAddon;
+use super::{
+    cache_control::{self, CachePolicy},
+    content::{JSON, MSGPACK, XML},
+    v1,
+};
+
+/// How long the spec and Swagger UI assets may be cached for, as they only change on deploy
+const MAX_AGE: u64 = 24 * 60 * 60;
+
+pub struct SecurityAddon;
 
 impl Modify for SecurityAddon {
     fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
@@ -45,7 +35,50 @@ impl Modify for SecurityAddon {
     }
 }
 
+/// Documents the MessagePack and XML alternatives to each JSON body on the resource endpoints
+pub struct NegotiatedContent;
+
+impl Modify for NegotiatedContent {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let resources = openapi
+            .paths
+            .paths
+            .iter_mut()
+            .filter(|(path, _)| path.starts_with("/person") || path.starts_with("/address"));
+
+        for (_, item) in resources {
+            for operation in item.operations.values_mut() {
+                if let Some(body) = operation.request_body.as_mut() {
+                    if let Some(json) = body.content.get(JSON).cloned() {
+                        body.content.insert(MSGPACK.to_owned(), json);
+                    }
+                }
+
+                // errors are always JSON
+                let successes = operation
+                    .responses
+                    .responses
+                    .iter_mut()
+                    .filter(|(status, _)| status.starts_with('2'));
+
+                for (_, response) in successes {
+                    if let RefOr::T(response) = response {
+                        if let Some(json) = response.content.get(JSON).cloned() {
+                            response.content.insert(MSGPACK.to_owned(), json.clone());
+                            response.content.insert(XML.to_owned(), json);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
 pub fn router() -> Router {
     Router::new()
-        .merge(SwaggerUi::new("/swagger-ui").url("/api-doc/openapi.json", ApiDoc::openapi()))
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-doc/v1/openapi.json", v1::ApiDoc::openapi()))
+        .layer(middleware::from_fn_with_state(
+            CachePolicy::Public { max_age: MAX_AGE },
+            cache_control::apply,
+        ))
 }
@@ -0,0 +1,64 @@
+//! Normalizes request paths before they are routed, so `/api/v1/person/` and `/api//v1/person`
+//! reach the same handler as `/api/v1/person` rather than one of them not being found.
+//!
+//! Trailing and leading runs of slashes are trimmed by tower-http's `NormalizePathLayer`, and
+//! [`collapse_slashes`] takes care of those within the path. The Swagger UI is left out, as it
+//! redirects `/swagger-ui` to `/swagger-ui/` and would otherwise never be reached.
+
+use axum::{extract::Request, http::Uri};
+
+/// Collapses any run of slashes in the path to a single slash, keeping the query as it was
+pub fn collapse_slashes(mut request: Request) -> Request {
+    let uri = request.uri();
+
+    if !uri.path().contains("//") {
+        return request;
+    }
+
+    let mut path = uri
+        .path()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .fold(String::new(), |path, segment| path + "/" + segment);
+
+    if path.is_empty() || uri.path().ends_with('/') {
+        path.push('/');
+    }
+
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{path}?{query}"),
+        None => path,
+    };
+
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = path_and_query.parse().ok();
+
+    if let Ok(uri) = Uri::from_parts(parts) {
+        *request.uri_mut() = uri;
+    }
+
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, extract::Request};
+
+    use super::collapse_slashes;
+
+    fn collapse(uri: &str) -> String {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        collapse_slashes(request).uri().to_string()
+    }
+
+    #[test]
+    fn runs_of_slashes_are_collapsed() {
+        assert_eq!(collapse("/api//v1///person"), "/api/v1/person");
+        assert_eq!(
+            collapse("/api//v1/person/?limit=10"),
+            "/api/v1/person/?limit=10"
+        );
+        assert_eq!(collapse("/api/v1/person?q=a//b"), "/api/v1/person?q=a//b");
+        assert_eq!(collapse("//"), "/");
+    }
+}
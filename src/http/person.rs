@@ -1,53 +1,421 @@
-use axum::{extract::Path, routing::get, Extension, Json, Router};
-use hyper::StatusCode;
+use async_graphql::{InputObject, SimpleObject};
+use axum::{
+    extract::Path,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use hyper::{
+    header::{HeaderName, HeaderValue, ACCEPT_RANGES, CONTENT_RANGE, LOCATION, VARY},
+    StatusCode,
+};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
 use time::{Date, OffsetDateTime};
-use tracing::info;
-use utoipa::ToSchema;
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 use validator::{Validate, ValidationError};
 
+use super::address::Address;
 use super::auth::{ReadUser, WriteUser};
-use super::error::ApiError;
+use super::content::{self, Format, Link, Negotiated, Payload, Resource, ValidatedPayload};
+use super::cursor::{Cursor, CursorQuery};
+use super::emergency_contact::EmergencyContact;
+use super::error::{ApiError, Report};
+use super::fields::{DateOfBirth, PersonName, Tag};
+use super::filter::{self, Field, Filter, FilterQuery, Operator, SortQuery, Value};
+use super::limit::{LimitQuery, OffsetQuery};
+use super::merge_patch::MergePatch;
+use super::odata::ODataQuery;
+use super::precondition::{self, entity_tag, IfMatch, IfNoneMatch, Validators};
+use super::query::ValidatedQuery;
+use super::range::{self, RequestedRange};
+use super::response::Deleted;
+use super::sparse::{self, FieldsQuery, Sparse};
+use super::timestamp;
+use super::v1;
+use crate::{
+    search::SearchIndex,
+    service::{
+        emergency_contact::EmergencyContactService, person::PersonService,
+        person_history::PersonHistoryService,
+    },
+};
 
-#[derive(Debug, Validate, Deserialize, ToSchema)]
+// the snake_case aliases keep clients written before the fields were camelCase working
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject)]
+#[serde(rename_all = "camelCase")]
 pub struct NewPerson {
-    #[validate(length(min = 1, max = 64))]
-    first_name: String,
-    #[validate(length(min = 1, max = 64))]
-    family_name: String,
-    #[validate(custom(function = "date_not_in_future"))]
-    date_of_birth: Date,
+    #[serde(alias = "first_name")]
+    #[schema(value_type = String, min_length = 1, max_length = 64)]
+    pub first_name: PersonName,
+    #[serde(alias = "family_name")]
+    #[schema(value_type = String, min_length = 1, max_length = 64)]
+    pub family_name: PersonName,
+    #[serde(alias = "date_of_birth")]
+    #[schema(value_type = Date)]
+    pub date_of_birth: DateOfBirth,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema, InputObject)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePerson {
+    #[serde(alias = "first_name")]
+    #[schema(value_type = Option<String>, min_length = 1, max_length = 64)]
+    pub first_name: Option<PersonName>,
+    #[serde(alias = "family_name")]
+    #[schema(value_type = Option<String>, min_length = 1, max_length = 64)]
+    pub family_name: Option<PersonName>,
+    #[serde(alias = "date_of_birth")]
+    #[schema(value_type = Option<Date>)]
+    pub date_of_birth: Option<DateOfBirth>,
 }
 
-fn date_not_in_future(date: &Date) -> Result<(), ValidationError> {
-    if *date > OffsetDateTime::now_utc().date() {
-        return Err(ValidationError::new("date_not_in_future"));
+impl From<NewPerson> for UpdatePerson {
+    fn from(person: NewPerson) -> Self {
+        UpdatePerson {
+            first_name: Some(person.first_name),
+            family_name: Some(person.family_name),
+            date_of_birth: Some(person.date_of_birth),
+        }
     }
+}
+
+/// How many items match a listing, alongside the page of them returned
+pub const TOTAL_COUNT: HeaderName = HeaderName::from_static("x-total-count");
+
+/// Where the next page of people starts, when paging with cursors
+const NEXT_CURSOR: HeaderName = HeaderName::from_static("x-next-cursor");
 
-    Ok(())
+/// The most people one bulk update can change
+pub const MAX_BULK_UPDATES: u64 = 1000;
+
+/// Changes to one of the people in a bulk update
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PersonChanges {
+    pub id: Uuid,
+    /// The version of the person the changes are made to, as given in their `ETag`. Changes to
+    /// a person since changed fail only this person, with `412`.
+    pub version: i32,
+    /// The fields to change, as for updating a single person. Invalid changes fail only this
+    /// person.
+    #[schema(value_type = UpdatePerson)]
+    pub changes: serde_json::Value,
 }
 
-#[derive(Debug, Validate, Deserialize, ToSchema)]
-pub struct UpdatePerson {
-    #[validate(length(min = 1, max = 64))]
-    first_name: Option<String>,
-    #[validate(length(min = 1, max = 64))]
-    family_name: Option<String>,
-    #[validate(custom(function = "date_not_in_future"))]
-    date_of_birth: Option<Date>,
+#[derive(Debug, Deserialize, Validate)]
+#[serde(transparent)]
+pub struct BulkUpdate {
+    #[validate(length(min = 1, max = MAX_BULK_UPDATES))]
+    pub people: Vec<PersonChanges>,
 }
 
-#[derive(Debug, Serialize, ToSchema)]
+/// The most people one bulk delete can remove
+pub const MAX_BULK_DELETES: u64 = 1000;
+
+#[derive(Debug, Deserialize, Validate)]
+#[serde(transparent)]
+pub struct BulkDelete {
+    #[validate(length(min = 1, max = MAX_BULK_DELETES))]
+    pub ids: Vec<Uuid>,
+}
+
+/// The outcome of a bulk delete
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkDeleteResult {
+    /// How many people were deleted
+    pub deleted: u64,
+    /// The UUIDs given which no person was found for, in the order they were given
+    pub not_found: Vec<Uuid>,
+}
+
+/// The most people one lookup can find
+pub const MAX_LOOKUPS: u64 = 1000;
+
+#[derive(Debug, Deserialize, Validate)]
+#[serde(transparent)]
+pub struct PersonLookup {
+    #[validate(length(min = 1, max = MAX_LOOKUPS))]
+    pub ids: Vec<Uuid>,
+}
+
+/// The people found by a lookup
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
+pub struct PersonLookupResult {
+    /// The people found, in the order their UUIDs were given, each appearing once
+    pub people: Vec<Person>,
+    /// The UUIDs given which no person was found for, in the order they were given
+    pub not_found: Vec<Uuid>,
+}
+
+/// What became of one of the people in a bulk update
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PersonChangeResult {
+    pub id: Uuid,
+    /// The status a single update would have responded with
+    pub status: u16,
+    /// The person as updated
+    pub person: Option<Person>,
+    /// Why the person wasn't updated
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, SimpleObject, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+#[graphql(complex)]
 pub struct Person {
-    id: Uuid,
-    first_name: String,
-    family_name: String,
-    date_of_birth: Date,
-    created: OffsetDateTime,
-    last_edited: OffsetDateTime,
+    pub id: Uuid,
+    pub first_name: String,
+    pub family_name: String,
+    pub date_of_birth: Date,
+    /// When the person was created, as an RFC 3339 timestamp in UTC, or milliseconds since the
+    /// Unix epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub created: OffsetDateTime,
+    /// When the person was last edited, in the same format as `created`
+    #[serde(with = "timestamp")]
+    pub last_edited: OffsetDateTime,
+    /// Counts the changes made to the person, to be sent in `If-Match` when changing them.
+    /// Events recorded before people had versions have none.
+    #[serde(default)]
+    pub version: i32,
+}
+
+impl Person {
+    /// Validators for the person as they are now, their version serving as the entity tag so it
+    /// can be sent straight back in `If-Match`
+    pub fn validators(&self) -> Validators {
+        Validators {
+            etag: entity_tag(self.version),
+            last_modified: Some(self.last_edited),
+        }
+    }
+
+    /// The fields clients can change, in the same form as when creating a person, for a patch
+    /// to be applied to
+    pub fn changeable_fields(&self) -> serde_json::Value {
+        serde_json::json!({
+            "firstName": self.first_name,
+            "familyName": self.family_name,
+            "dateOfBirth": self.date_of_birth,
+        })
+    }
+}
+
+impl Resource for Person {
+    const ELEMENT: &'static str = "person";
+    const COLLECTION: &'static str = "people";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        let person = format!("{}/person/{}", v1::PREFIX, self.id);
+
+        vec![
+            ("self", Link::to(&person)),
+            ("update", Link::to(&person)),
+            ("delete", Link::to(&person)),
+        ]
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        let address = format!("{}/person/{}/address", v1::PREFIX, self.id);
+
+        vec![("address", Link::to(address))]
+    }
+}
+
+/// A related resource that can be included alongside a person
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum Expansion {
+    Address,
+    EmergencyContacts,
+}
+
+#[derive(Debug, Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct ExpandQuery {
+    /// Include the person's current address or emergency contacts, also accepted as `include`
+    #[serde(alias = "include")]
+    #[param(inline)]
+    expand: Option<Expansion>,
+}
+
+#[derive(Debug, Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct NameQuery {
+    /// Only return people whose first name starts with this, ignoring case
+    #[param(value_type = Option<String>, min_length = 1, max_length = 64)]
+    first_name: Option<PersonName>,
+    /// Only return people whose family name starts with this, ignoring case
+    #[param(value_type = Option<String>, min_length = 1, max_length = 64)]
+    family_name: Option<PersonName>,
+}
+
+impl NameQuery {
+    /// The names given, as a filter
+    fn filter(&self) -> Option<Filter> {
+        let names = [
+            (Field::FirstName, &self.first_name),
+            (Field::FamilyName, &self.family_name),
+        ]
+        .into_iter()
+        .filter_map(|(field, name)| {
+            let pattern = format!("{}%", filter::escape_like(name.as_ref()?.as_str()));
+            Some(Filter::Compare {
+                field,
+                operator: Operator::Equal,
+                values: vec![Value::Pattern(pattern)],
+            })
+        })
+        .collect();
+
+        Filter::all(names)
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct TagQuery {
+    /// Only return people with this tag, ignoring case
+    #[param(value_type = Option<String>, min_length = 1, max_length = 64)]
+    tag: Option<Tag>,
+}
+
+impl TagQuery {
+    /// The tag given, as a filter
+    fn filter(&self) -> Option<Filter> {
+        let tag = self.tag.as_ref()?;
+
+        Some(Filter::Tagged(tag.as_str().to_owned()))
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+#[validate(schema(function = "not_expanded_as_of"))]
+pub struct GetPersonQuery {
+    /// Include the person's current address or emergency contacts, also accepted as `include`
+    #[serde(alias = "include")]
+    #[param(inline)]
+    expand: Option<Expansion>,
+    /// Get the person as they were at this time, from their history, rather than as they are
+    #[serde(default, with = "timestamp::option")]
+    as_of: Option<OffsetDateTime>,
+}
+
+fn not_expanded_as_of(query: &GetPersonQuery) -> Result<(), ValidationError> {
+    match (query.expand, query.as_of) {
+        (Some(_), Some(_)) => Err(ValidationError::new("not_expanded_as_of")
+            .with_message("expand can't be combined with as_of".into())),
+        _ => Ok(()),
+    }
+}
+
+/// The fields of listed people that can be asked for with `?fields`
+const LISTED_FIELDS: &[&str] = &[
+    "id",
+    "firstName",
+    "familyName",
+    "dateOfBirth",
+    "created",
+    "lastEdited",
+    "version",
+    "address",
+    "emergencyContacts",
+];
+
+/// A person, along with any related resources asked for with `?expand`
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpandedPerson {
+    #[serde(flatten)]
+    pub person: Person,
+    /// The person's current address, when expanded and they have one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<Address>,
+    /// The person's emergency contacts, when expanded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emergency_contacts: Option<Vec<EmergencyContact>>,
+}
+
+impl Resource for ExpandedPerson {
+    const ELEMENT: &'static str = Person::ELEMENT;
+    const COLLECTION: &'static str = Person::COLLECTION;
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        self.person.links()
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        self.person.relationships()
+    }
+}
+
+/// Why an existing person might be the one about to be created
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicateReason {
+    /// The same first name, family name and date of birth, ignoring case
+    SameNameAndDateOfBirth,
+    /// A similar name, allowing for typos, and the same date of birth
+    SimilarNameSameDateOfBirth,
+    /// A similar name, allowing for typos
+    SimilarName,
+}
+
+/// An existing person who may be the same as one about to be created
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PossibleDuplicate {
+    #[serde(flatten)]
+    pub person: Person,
+    pub reason: DuplicateReason,
+    /// How similar the names are, from 0 to 1
+    pub similarity: f32,
+}
+
+impl Resource for PossibleDuplicate {
+    const ELEMENT: &'static str = Person::ELEMENT;
+    const COLLECTION: &'static str = Person::COLLECTION;
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        self.person.links()
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        self.person.relationships()
+    }
+}
+
+/// How many people match a query
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PersonCount {
+    pub count: i64,
+}
+
+/// People along with how many match, when asked for with `$count=true`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CountedPeople {
+    #[serde(rename = "@odata.count")]
+    pub count: i64,
+    #[schema(value_type = Vec<ExpandedPerson>)]
+    pub value: Vec<Sparse<ExpandedPerson>>,
+}
+
+#[derive(Debug, Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Text to match against names, allowing for typos
+    #[validate(length(min = 1))]
+    q: String,
+    /// The maximum number of people to return, defaults to 20
+    #[param(minimum = 1, maximum = 100)]
+    #[validate(range(min = 1, max = 100))]
+    limit: Option<i64>,
 }
 
 /// Create a new person
@@ -59,7 +427,9 @@ pub struct Person {
     path = "/person",
     request_body = NewPerson,
     responses(
-        (status = 201, description = "Person created successfully", body = Person),
+        (status = 201, description = "Person created successfully", body = Person,
+            headers(("location" = String, description = "The URL of the created person"))),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
         (status = 409, description = "Person already exists", body = ErrorResponse),
     ),
     security(
@@ -68,128 +438,601 @@ pub struct Person {
 )]
 async fn create_person(
     user: WriteUser,
-    db: Extension<PgPool>,
-    Json(request): Json<NewPerson>,
-) -> Result<(StatusCode, Json<Person>), ApiError> {
-    request.validate()?;
-
-    let person = sqlx::query_as!(
-        Person,
-        r#"
-            INSERT INTO person (first_name, family_name, date_of_birth)
-            VALUES ($1, $2, $3)
-            RETURNING uuid AS id, created, last_edited, first_name, family_name, date_of_birth;
-        "#,
-        request.first_name,
-        request.family_name,
-        request.date_of_birth
-    )
-    .fetch_one(&*db)
-    .await
-    .map_err(|e| match e {
-        sqlx::Error::Database(dbe) if dbe.constraint().is_some() => ApiError::Conflict(format!(
-            "Unable to create person due to constraint: {}",
-            dbe.constraint().unwrap()
-        )),
-        _ => ApiError::DatabaseError(e),
-    })?;
+    people: PersonService,
+    format: Format,
+    Payload(request): Payload<NewPerson>,
+) -> Result<(StatusCode, [(HeaderName, String); 1], Negotiated<Person>), ApiError> {
+    let person = people.create(&user.username, &request).await?;
 
-    info!("Client '{}' created person '{}'", user.username, person.id);
+    let location = format!("{}/person/{}", v1::PREFIX, person.id);
 
-    Ok((StatusCode::CREATED, Json(person)))
+    Ok((
+        StatusCode::CREATED,
+        [(LOCATION, location)],
+        Negotiated(format, person),
+    ))
 }
 
-/// List all people
+/// List people
+///
+/// Returns the people created first, or as sorted by `sort` such as `family_name,-created`, up
+/// to the `limit` (default 100, at most 1000), skipping the first `offset` of them to page
+/// through the rest. People sorted the same are kept in the order they were created, then by
+/// their `id`, so pages don't overlap. Unless expanding, how many people there are in all is in
+/// the `X-Total-Count` header, for rendering pagers.
+///
+/// Asked for as newline delimited JSON with `Accept: application/x-ndjson`, everyone matching
+/// is streamed a line at a time as they're read from the database, with no limit unless one is
+/// given, for exports too large to fetch a page at a time. Expanding, `$count` and a `Range`
+/// aren't streamed, answering with the page of people as usual.
+///
+/// To walk every person efficiently, however many there are, page with a `cursor` instead,
+/// starting with an empty one. Each page's `X-Next-Cursor` header holds the cursor of the page
+/// after it, and is left out on the last page. Cursors can be used with filters, but not
+/// sorting, an `offset`, expansion, `$count` or a `Range`.
+///
+/// Alternatively, a `Range` of them such as `items=0-49` can be asked for, which is answered
+/// with `206 Partial Content` and a `Content-Range` saying which people were returned out of how
+/// many. Ranges aren't supported when expanding addresses or emergency contacts, so the whole
+/// list is returned instead.
+///
+/// `fields` such as `firstName,familyName` returns only those fields of each person, along with
+/// their `id`, in whichever format and however they're paged.
+///
+/// People can be found by the start of their names, ignoring case, with `first_name` and
+/// `family_name`, such as `family_name=Smith&first_name=Jo`. For anything more, they can be
+/// filtered with an [RSQL](https://github.com/jirutka/rsql-parser) expression such as
+/// `familyName==Smith;dateOfBirth=ge=1980-01-01`, comparing `id`, `firstName`, `familyName`,
+/// `dateOfBirth`, `created` or `lastEdited`. Both apply when given together, as does `tag`,
+/// returning only the people with that tag.
+///
+/// The OData query options `$filter`, `$orderby`, `$top`, `$skip` and `$count` are supported
+/// too, for tools which only speak OData. A `$filter` applies alongside any `filter`, while
+/// `$orderby`, `$top` and `$skip` take the place of `sort`, `limit` and `offset`, and a `Range`
+/// takes the place of all of them.
 ///
 /// Requires the scope `read`
 #[utoipa::path(
     get,
     tag = "person",
     path = "/person",
+    params(
+        ExpandQuery,
+        NameQuery,
+        TagQuery,
+        FilterQuery,
+        SortQuery,
+        ODataQuery,
+        LimitQuery,
+        OffsetQuery,
+        CursorQuery,
+        FieldsQuery,
+        ("Range" = Option<String>, Header, description = "The people to return, such as `items=0-49`, counted from zero"),
+        ("If-None-Match" = Option<String>, Header, description = "The `ETag` of the list already held, to be told if it's still current"),
+        ("If-Modified-Since" = Option<String>, Header, description = "The `Last-Modified` of the list already held, to be told if it's still current"),
+    ),
     responses(
-        (status = 200, description = "List all people", body = [Person]),
+        (status = 200, description = "List people, oldest first unless sorted with `sort` or `$orderby`, along with how many match when `$count=true`", body = [ExpandedPerson],
+            headers(
+                ("X-Total-Count" = i64, description = "How many people match, however many were returned, unless expanding or paging with a cursor"),
+                ("X-Next-Cursor" = String, description = "The cursor of the next page, when paging with a cursor and there are more people"),
+                ("ETag" = String, description = "A weak entity tag, changing whenever anyone is changed, unless expanding"),
+                ("Last-Modified" = String, description = "When anyone was last changed, unless expanding"),
+            )),
+        (status = 206, description = "The requested range of people, oldest first", body = [Person],
+            headers(("Content-Range" = String, description = "The people returned and how many there are, such as `items 0-49/1234`"))),
+        (status = 304, description = "Nobody has been changed since the list held was returned"),
+        (status = 400, description = "Invalid query", body = ErrorResponse),
+        (status = 416, description = "The range starts beyond the last person", body = ErrorResponse),
     ),
     security(
         ("bearer" = [])
     )
 )]
-async fn list_people(user: ReadUser, db: Extension<PgPool>) -> Result<Json<Vec<Person>>, ApiError> {
-    let people = sqlx::query_as!(
-        Person,
-        r#"
-            SELECT uuid AS id, created, last_edited, first_name, family_name, date_of_birth FROM person;
-        "#
+#[allow(clippy::too_many_arguments)]
+async fn list_people(
+    user: ReadUser,
+    people: PersonService,
+    format: Format,
+    contacts: EmergencyContactService,
+    conditions: IfNoneMatch,
+    RequestedRange(range): RequestedRange,
+    ValidatedQuery(query): ValidatedQuery<ExpandQuery>,
+    ValidatedQuery(names): ValidatedQuery<NameQuery>,
+    ValidatedQuery(tag): ValidatedQuery<TagQuery>,
+    ValidatedQuery(filter): ValidatedQuery<FilterQuery>,
+    ValidatedQuery(sort): ValidatedQuery<SortQuery>,
+    ValidatedQuery(odata): ValidatedQuery<ODataQuery>,
+    ValidatedQuery(page): ValidatedQuery<LimitQuery>,
+    ValidatedQuery(offset): ValidatedQuery<OffsetQuery>,
+    ValidatedQuery(cursor): ValidatedQuery<CursorQuery>,
+    ValidatedQuery(fields): ValidatedQuery<FieldsQuery>,
+) -> Result<Response, ApiError> {
+    let fields = fields.parse(LISTED_FIELDS)?;
+    let limit = odata.top().unwrap_or(page.limit());
+    let filter = Filter::all(
+        [
+            names.filter(),
+            tag.filter(),
+            filter.parse()?,
+            odata.filter()?,
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+    );
+    let filter = filter.as_ref();
+    let order = match (sort.parse()?, odata.order()?) {
+        (sort, order) if order.is_empty() => sort,
+        (_, order) => order,
+    };
+    let skip = odata.skip().or(offset.offset()).unwrap_or(0);
+
+    // expanded people change with their addresses and contacts, which the validators don't cover
+    let validators = match query.expand {
+        Some(_) => None,
+        None => Some(people.list_validators().await?),
+    };
+    if let Some(not_modified) = validators.as_ref().and_then(|v| conditions.not_modified(v)) {
+        info!("Client '{}' already has the current people", user.username);
+        return Ok(not_modified);
+    }
+
+    if let Some(after) = cursor.after()? {
+        if query.expand.is_some()
+            || !order.is_empty()
+            || skip > 0
+            || odata.count()
+            || range.is_some()
+        {
+            return Err(ApiError::InvalidCursor(
+                "Cursors can't be used with expand, sort, offset, $count or a Range".to_owned(),
+            ));
+        }
+
+        let mut found = people.list_after(filter, after, limit).await?;
+        let more = found.len() as i64 > limit.get();
+        found.truncate(limit.get() as usize);
+
+        let next = found.last().filter(|_| more).map(|last| Cursor {
+            created: last.created,
+            id: last.id,
+        });
+
+        info!(
+            "Client '{}' retrieved {} person(s) after a cursor",
+            user.username,
+            found.len(),
+        );
+
+        let mut response = Negotiated(format, Sparse::each(found, &fields)).into_response();
+        if let Some(next) = next {
+            let next = HeaderValue::try_from(next.encode()).expect("Cursors are base64");
+            response.headers_mut().insert(NEXT_CURSOR, next);
+        }
+        return Ok(match validators {
+            Some(validators) => precondition::with_validators(validators, response),
+            None => response,
+        });
+    }
+
+    let expanded = match query.expand {
+        Some(Expansion::Address) => {
+            let expanded = people.list_expanded(filter, &order, skip, limit).await?;
+
+            info!(
+                "Client '{}' retrieved {} person(s) with their addresses",
+                user.username,
+                expanded.len(),
+            );
+
+            Some(expanded)
+        }
+        Some(Expansion::EmergencyContacts) => {
+            let found = people.list_from(filter, &order, skip, limit).await?;
+            let expanded = contacts.expand(found).await?;
+
+            info!(
+                "Client '{}' retrieved {} person(s) with their emergency contacts",
+                user.username,
+                expanded.len(),
+            );
+
+            Some(expanded)
+        }
+        None => None,
+    };
+
+    let mut response = if let Some(expanded) = expanded {
+        if odata.count() {
+            let count = people.count(filter).await?;
+            Json(CountedPeople {
+                count,
+                value: Sparse::each(expanded, &fields),
+            })
+            .into_response()
+        } else {
+            Negotiated(format, Sparse::each(expanded, &fields)).into_response()
+        }
+    } else if let Some(range) = range {
+        let total = people.count(filter).await?;
+        let (first, limit) = range.within(total)?;
+        let people = people.list_from(filter, &order, first, limit).await?;
+
+        // everyone in the range has been deleted since they were counted
+        if people.is_empty() {
+            return Err(ApiError::RangeNotSatisfiable(total));
+        }
+
+        info!(
+            "Client '{}' retrieved {} person(s) from {}",
+            user.username,
+            people.len(),
+            first
+        );
+
+        let content_range = range::content_range(first, people.len(), total);
+        let mut response = Negotiated(format, Sparse::each(people, &fields)).into_response();
+        *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+        response.headers_mut().insert(CONTENT_RANGE, content_range);
+        response
+    } else if odata.count() {
+        let count = people.count(filter).await?;
+        let people = people.list_from(filter, &order, skip, limit).await?;
+
+        info!(
+            "Client '{}' retrieved {} of {} person(s)",
+            user.username,
+            people.len(),
+            count
+        );
+
+        let value = people
+            .into_iter()
+            .map(|person| {
+                let person = ExpandedPerson {
+                    person,
+                    address: None,
+                    emergency_contacts: None,
+                };
+                Sparse::new(person, &fields)
+            })
+            .collect();
+        Json(CountedPeople { count, value }).into_response()
+    } else if format == Format::NdJson {
+        let limit = odata.top().or(page.requested());
+
+        info!(
+            "Client '{}' is streaming people as newline delimited JSON",
+            user.username
+        );
+
+        let people = people.stream_from(filter.cloned(), order.clone(), skip, limit);
+        content::ndjson(sparse::stream(people, fields))
+            .await?
+            .into_response()
+    } else if filter.is_some() || !order.is_empty() || skip > 0 {
+        let people = people.list_from(filter, &order, skip, limit).await?;
+
+        info!(
+            "Client '{}' retrieved {} person(s) matching a query",
+            user.username,
+            people.len(),
+        );
+
+        Negotiated(format, Sparse::each(people, &fields)).into_response()
+    } else if format == Format::Json {
+        // JSON arrays can be written as the rows arrive, keeping memory flat however many there are
+        info!(
+            "Client '{}' is streaming up to {} person(s)",
+            user.username,
+            limit.get()
+        );
+
+        content::json_array(sparse::stream(people.stream(limit), fields))
+            .await?
+            .into_response()
+    } else {
+        let people = people.list(limit).await?;
+
+        info!(
+            "Client '{}' retrieved {} person(s)",
+            user.username,
+            people.len(),
+        );
+
+        Negotiated(format, Sparse::each(people, &fields)).into_response()
+    };
+
+    if query.expand.is_none() && response.status() == StatusCode::OK {
+        let total = people.count(filter).await?;
+        response.headers_mut().insert(TOTAL_COUNT, total.into());
+    }
+
+    let headers = response.headers_mut();
+    headers.insert(ACCEPT_RANGES, range::ITEMS);
+    headers.append(VARY, HeaderValue::from_static("range"));
+
+    Ok(match validators {
+        Some(validators) => precondition::with_validators(validators, response),
+        None => response,
+    })
+}
+
+/// Count people
+///
+/// Counts the people `GET /person` would list with the same `first_name`, `family_name`, `tag`
+/// and `filter`, without fetching any of them.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/count",
+    params(NameQuery, TagQuery, FilterQuery),
+    responses(
+        (status = 200, description = "How many people match", body = PersonCount),
+        (status = 400, description = "Invalid query", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
     )
-    .fetch_all(&*db)
-    .await?;
+)]
+async fn count_people(
+    user: ReadUser,
+    people: PersonService,
+    ValidatedQuery(names): ValidatedQuery<NameQuery>,
+    ValidatedQuery(tag): ValidatedQuery<TagQuery>,
+    ValidatedQuery(filter): ValidatedQuery<FilterQuery>,
+) -> Result<Json<PersonCount>, ApiError> {
+    let filter = Filter::all(
+        [names.filter(), tag.filter(), filter.parse()?]
+            .into_iter()
+            .flatten()
+            .collect(),
+    );
+    let count = people.count(filter.as_ref()).await?;
+
+    info!("Client '{}' counted {} person(s)", user.username, count);
+
+    Ok(Json(PersonCount { count }))
+}
+
+/// Search for people
+///
+/// Matches people by name, tolerating typos, with the most relevant first. Served from the
+/// search index when there is one, so recent changes may take a moment to appear, and otherwise
+/// by comparing the trigrams of names in the database.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/search",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "People matching the query, most relevant first", body = [Person]),
+        (status = 400, description = "Invalid query", body = ErrorResponse),
+        (status = 503, description = "The search index is unavailable", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+async fn search_people(
+    user: ReadUser,
+    search: Extension<SearchIndex>,
+    people: PersonService,
+    format: Format,
+    ValidatedQuery(query): ValidatedQuery<SearchQuery>,
+) -> Result<Negotiated<Vec<Person>>, ApiError> {
+    let limit = query.limit.unwrap_or(20);
+
+    let people = match search.search(&query.q, limit).await {
+        Some(found) => found.map_err(|e| {
+            error!("Failed to search people: {}", Report(&e));
+            ApiError::Unavailable("Search is currently unavailable".to_owned())
+        })?,
+        None => people.search(&query.q, limit).await?,
+    };
 
     info!(
-        "Client '{}' retrieved {} person(s)",
+        "Client '{}' found {} person(s) searching for '{}'",
         user.username,
         people.len(),
+        query.q
     );
 
-    Ok(Json(people))
+    Ok(Negotiated(format, people))
+}
+
+/// Find people who may already be the person about to be created
+///
+/// Takes the person as they would be created, and returns up to 20 existing people who could
+/// be them: those with the same name and date of birth first, then those with similar names and
+/// the same date of birth, then those with similar names, most similar first. Nothing is
+/// created.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    post,
+    tag = "person",
+    path = "/person/check-duplicates",
+    request_body = NewPerson,
+    responses(
+        (status = 200, description = "People who may be the same as the one given, most likely first", body = [PossibleDuplicate]),
+        (status = 400, description = "Invalid person", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+async fn check_duplicates(
+    user: ReadUser,
+    people: PersonService,
+    format: Format,
+    Payload(request): Payload<NewPerson>,
+) -> Result<Negotiated<Vec<PossibleDuplicate>>, ApiError> {
+    let duplicates = people.find_duplicates(&request).await?;
+
+    info!(
+        "Client '{}' found {} possible duplicate(s) of a new person",
+        user.username,
+        duplicates.len()
+    );
+
+    Ok(Negotiated(format, duplicates))
+}
+
+/// Look up people by UUID
+///
+/// Finds everyone with the given UUIDs, up to 1000 of them, in one request rather than one
+/// each, saying which of them weren't found. People are returned in the order their UUIDs were
+/// given, and only once however many times they were given.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    post,
+    tag = "person",
+    path = "/person/lookup",
+    request_body = [Uuid],
+    responses(
+        (status = 200, description = "The people found, and the UUIDs not found", body = PersonLookupResult),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+async fn lookup_people(
+    user: ReadUser,
+    people: PersonService,
+    ValidatedPayload(request): ValidatedPayload<PersonLookup>,
+) -> Result<Json<PersonLookupResult>, ApiError> {
+    let result = people.find_each(&request.ids).await?;
+
+    info!(
+        "Client '{}' looked up {} person(s), finding {}",
+        user.username,
+        request.ids.len(),
+        result.people.len()
+    );
+
+    Ok(Json(result))
 }
 
 /// Get a person
 ///
+/// With `as_of`, the person is given as they were left by the last change in their history
+/// recorded by then.
+///
+/// The person's `ETag` is their `version` and their `Last-Modified` when they were last edited,
+/// so sending either back in `If-None-Match` or `If-Modified-Since` gets `304 Not Modified`
+/// while they're unchanged. Expanded and `as_of` responses have neither.
+///
 /// Requires the scope `read`
 #[utoipa::path(
     get,
     tag = "person",
     path = "/person/{person_uuid}",
     params(
-        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        GetPersonQuery,
+        ("If-None-Match" = Option<String>, Header, description = "The `ETag` of the person already held, to be told if they're unchanged"),
+        ("If-Modified-Since" = Option<String>, Header, description = "The `Last-Modified` of the person already held, to be told if they're unchanged"),
     ),
     responses(
-        (status = 200, description = "The person matching the given UUID", body = Person),
-        (status = 404, description = "Person not found", body = ErrorResponse),
+        (status = 200, description = "The person matching the given UUID", body = ExpandedPerson,
+            headers(
+                ("ETag" = String, description = "The person's version, such as `\"3\"`, unless expanded or as of a time"),
+                ("Last-Modified" = String, description = "When the person was last edited, unless expanded or as of a time"),
+            )),
+        (status = 304, description = "The person is unchanged since the copy held"),
+        (status = 400, description = "Invalid query", body = ErrorResponse),
+        (status = 404, description = "Person not found, or not recorded as existing at the given time", body = ErrorResponse),
     ),
     security(
         ("bearer" = [])
     )
 )]
+#[allow(clippy::too_many_arguments)]
 async fn get_person(
     user: ReadUser,
-    db: Extension<PgPool>,
+    people: PersonService,
+    contacts: EmergencyContactService,
+    history: PersonHistoryService,
+    format: Format,
+    conditions: IfNoneMatch,
     Path(person_uuid): Path<Uuid>,
-) -> Result<Json<Person>, ApiError> {
-    let person = sqlx::query_as!(
-        Person,
-        r#"
-            SELECT uuid AS id, created, last_edited, first_name, family_name, date_of_birth FROM person WHERE uuid = $1;
-        "#,
-        person_uuid
-    )
-    .fetch_one(&*db)
-    .await
-    .map_err(|e| match e {
-        sqlx::Error::RowNotFound => ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}")),
-        _ => ApiError::DatabaseError(e),
-    })?;
+    ValidatedQuery(query): ValidatedQuery<GetPersonQuery>,
+) -> Result<Response, ApiError> {
+    if let Some(as_of) = query.as_of {
+        let person = history.as_of(person_uuid, as_of).await?;
+
+        info!(
+            "Client '{}' retrieved person '{}' as of {}",
+            user.username, person_uuid, as_of
+        );
+
+        return Ok(Negotiated(format, person).into_response());
+    }
+
+    match query.expand {
+        Some(Expansion::Address) => {
+            let person = people.find_expanded(person_uuid).await?;
+
+            info!(
+                "Client '{}' retrieved person '{}' with their address",
+                user.username, person_uuid
+            );
+
+            return Ok(Negotiated(format, person).into_response());
+        }
+        Some(Expansion::EmergencyContacts) => {
+            let person = people.find(person_uuid).await?;
+            let mut expanded = contacts.expand(vec![person]).await?;
+
+            info!(
+                "Client '{}' retrieved person '{}' with their emergency contacts",
+                user.username, person_uuid
+            );
+
+            return Ok(Negotiated(format, expanded.remove(0)).into_response());
+        }
+        None => {}
+    }
+
+    let person = people.find(person_uuid).await?;
 
     info!(
         "Client '{}' retrieved person '{}'",
         person.id, user.username
     );
 
-    Ok(Json(person))
+    Ok(conditions.respond(person.validators(), Negotiated(format, person)))
 }
 
 /// Delete a person
 ///
+/// The person is kept, marked as deleted, so they can be brought back with
+/// `POST /person/{person_uuid}/restore`. Until then they're left out of every other endpoint.
+/// The person's `version` must be given in `If-Match`, as when changing them.
+///
 /// Requires the scope `write`
 #[utoipa::path(
     delete,
     tag = "person",
     path = "/person/{person_uuid}",
     params(
-        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        ("If-Match" = String, Header, description = "The version of the person being changed, such as `\"3\"`, or `*` for any")
     ),
     responses(
-        (status = 200, description = "Person deleted successfully"),
+        (status = 204, description = "Person deleted successfully"),
         (status = 404, description = "Person not found", body = ErrorResponse),
+        (status = 412, description = "The person has been changed since the version given", body = ErrorResponse),
+        (status = 423, description = "The person is under legal hold", body = ErrorResponse),
+        (status = 428, description = "No version was given in If-Match", body = ErrorResponse),
     ),
     security(
         ("bearer" = [])
@@ -197,34 +1040,58 @@ async fn get_person(
 )]
 async fn delete_person(
     user: WriteUser,
-    db: Extension<PgPool>,
+    people: PersonService,
+    if_match: IfMatch,
     Path(person_uuid): Path<Uuid>,
-) -> Result<(), ApiError> {
-    sqlx::query!(
-        r#"
-            DELETE FROM person WHERE uuid = $1
-            RETURNING uuid as id;
-        "#,
-        person_uuid
+) -> Result<Deleted, ApiError> {
+    people
+        .delete(&user.username, person_uuid, &if_match)
+        .await?;
+
+    Ok(Deleted)
+}
+
+/// Restore a deleted person
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    post,
+    tag = "person",
+    path = "/person/{deleted_uuid}/restore",
+    params(
+        ("deleted_uuid" = Uuid, Path, description = "The UUID of the deleted person")
+    ),
+    responses(
+        (status = 200, description = "Person restored successfully", body = Person,
+            headers(("location" = String, description = "The URL of the restored person"))),
+        (status = 404, description = "Deleted person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
     )
-    .fetch_one(&*db)
-    .await
-    .map_err(|e| match e {
-        sqlx::Error::RowNotFound => {
-            ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}"))
-        }
-        _ => ApiError::DatabaseError(e),
-    })?;
+)]
+async fn restore_person(
+    user: WriteUser,
+    people: PersonService,
+    format: Format,
+    Path(deleted_uuid): Path<Uuid>,
+) -> Result<(StatusCode, [(HeaderName, String); 1], Negotiated<Person>), ApiError> {
+    let person = people.restore(&user.username, deleted_uuid).await?;
 
-    info!(
-        "Client '{}' deleted person '{}'",
-        user.username, person_uuid
-    );
+    let location = format!("{}/person/{}", v1::PREFIX, person.id);
 
-    Ok(())
+    Ok((
+        StatusCode::OK,
+        [(LOCATION, location)],
+        Negotiated(format, person),
+    ))
 }
 
-/// Update a person
+/// Replace a person
+///
+/// Every field is given, replacing the person's details in full. To change only some of them,
+/// patch the person instead. The `version` of the person being replaced must be given in
+/// `If-Match`, so changes made since it was read aren't overwritten unknowingly.
 ///
 /// Requires the scope `write`
 #[utoipa::path(
@@ -232,12 +1099,16 @@ async fn delete_person(
     tag = "person",
     path = "/person/{person_uuid}",
     params(
-        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        ("If-Match" = String, Header, description = "The version of the person being changed, such as `\"3\"`, or `*` for any")
     ),
-    request_body = UpdatePerson,
+    request_body = NewPerson,
     responses(
-        (status = 200, description = "Person updated successfully"),
+        (status = 200, description = "Person replaced successfully", body = Person),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
         (status = 404, description = "Person not found", body = ErrorResponse),
+        (status = 412, description = "The person has been changed since the version given", body = ErrorResponse),
+        (status = 428, description = "No version was given in If-Match", body = ErrorResponse),
     ),
     security(
         ("bearer" = [])
@@ -245,85 +1116,225 @@ async fn delete_person(
 )]
 async fn update_person(
     user: WriteUser,
-    db: Extension<PgPool>,
+    people: PersonService,
+    format: Format,
+    if_match: IfMatch,
     Path(person_uuid): Path<Uuid>,
-    Json(request): Json<UpdatePerson>,
-) -> Result<Json<Person>, ApiError> {
-    let existing = sqlx::query_as!(
-        Person,
-        r#"
-            SELECT uuid AS id, created, last_edited, first_name, family_name, date_of_birth FROM person WHERE uuid = $1;
-        "#,
-        person_uuid
+    Payload(request): Payload<NewPerson>,
+) -> Result<Negotiated<Person>, ApiError> {
+    let updated_person = people
+        .update(&user.username, person_uuid, request.into(), &if_match)
+        .await?;
+
+    Ok(Negotiated(format, updated_person))
+}
+
+/// Patch a person
+///
+/// Changes only the fields given, as a [JSON Merge Patch](https://www.rfc-editor.org/rfc/rfc7396)
+/// sent as `application/merge-patch+json`. Fields left out are kept as they are, and a field
+/// set to `null` is cleared, which is refused for the fields every person must have. The
+/// patched person must be valid as a whole, as when creating one. The `version` of the person
+/// being patched must be given in `If-Match`, as when replacing them.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    patch,
+    tag = "person",
+    path = "/person/{person_uuid}",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        ("If-Match" = String, Header, description = "The version of the person being changed, such as `\"3\"`, or `*` for any")
+    ),
+    request_body(content = UpdatePerson, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "Person patched successfully", body = Person),
+        (status = 400, description = "Invalid patch, or the patched person is invalid", body = ErrorResponse),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+        (status = 412, description = "The person has been changed since the version given", body = ErrorResponse),
+        (status = 415, description = "The body isn't a merge patch", body = ErrorResponse),
+        (status = 428, description = "No version was given in If-Match", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
     )
-    .fetch_one(&*db)
-    .await
-    .map_err(|e| match e {
-        sqlx::Error::RowNotFound => ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}")),
-        _ => ApiError::DatabaseError(e),
-    })?;
-
-    let updated_person = sqlx::query_as!(
-        Person,
-        r#"
-            UPDATE person SET first_name = $1, family_name = $2, date_of_birth = $3, last_edited = now()
-            WHERE uuid = $4
-            RETURNING uuid AS id, created, last_edited, first_name, family_name, date_of_birth;
-        "#,
-        request.first_name.unwrap_or(existing.first_name),
-        request.family_name.unwrap_or(existing.family_name),
-        request.date_of_birth.unwrap_or(existing.date_of_birth),
-        person_uuid
+)]
+async fn patch_person(
+    user: WriteUser,
+    people: PersonService,
+    format: Format,
+    if_match: IfMatch,
+    Path(person_uuid): Path<Uuid>,
+    patch: MergePatch,
+) -> Result<Negotiated<Person>, ApiError> {
+    let patched_person = people
+        .patch(&user.username, person_uuid, &patch, &if_match)
+        .await?;
+
+    Ok(Negotiated(format, patched_person))
+}
+
+/// Update people in bulk
+///
+/// Applies changes to many people in one go, each leaving the fields not provided as they are,
+/// for sync jobs with many changes to make. Everyone is changed in one transaction, and the
+/// result for each is given in the same order as the changes. People who can't be changed,
+/// such as those who don't exist, have changed since the `version` given or whose changes are
+/// invalid, don't stop the rest.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    patch,
+    tag = "person",
+    path = "/person",
+    request_body = [PersonChanges],
+    responses(
+        (status = 200, description = "The result of each change, in order", body = [PersonChangeResult]),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
     )
-    .fetch_one(&*db)
-    .await?;
+)]
+async fn update_people(
+    user: WriteUser,
+    people: PersonService,
+    ValidatedPayload(request): ValidatedPayload<BulkUpdate>,
+) -> Result<Json<Vec<PersonChangeResult>>, ApiError> {
+    let mut results = Vec::with_capacity(request.people.len());
+    let mut valid = Vec::new();
 
-    info!(
-        "Client '{}' updated person '{}'",
-        user.username, updated_person.id
-    );
+    for PersonChanges {
+        id,
+        version,
+        changes,
+    } in request.people
+    {
+        match serde_json::from_value::<UpdatePerson>(changes) {
+            Ok(changes) => {
+                valid.push((id, version, changes));
+                results.push(None);
+            }
+            Err(e) => results.push(Some(PersonChangeResult {
+                id,
+                status: StatusCode::BAD_REQUEST.as_u16(),
+                person: None,
+                error: Some(format!("Invalid changes: {e}")),
+            })),
+        }
+    }
+
+    let ids: Vec<_> = valid.iter().map(|(id, ..)| *id).collect();
+    let mut updated = people
+        .update_each(&user.username, valid)
+        .await?
+        .into_iter()
+        .zip(ids);
+
+    let results = results
+        .into_iter()
+        .map(|result| {
+            result.unwrap_or_else(|| {
+                let (outcome, id) = updated.next().expect("A result for every valid change");
+                match outcome {
+                    Ok(person) => PersonChangeResult {
+                        id,
+                        status: StatusCode::OK.as_u16(),
+                        person: Some(person),
+                        error: None,
+                    },
+                    Err(e) => PersonChangeResult {
+                        id,
+                        status: e.status_code().as_u16(),
+                        person: None,
+                        error: Some(e.to_string()),
+                    },
+                }
+            })
+        })
+        .collect();
+
+    Ok(Json(results))
+}
 
-    Ok(Json(updated_person))
+/// Delete people in bulk
+///
+/// Deletes everyone with the given UUIDs in one transaction, such as to clear away test data,
+/// saying which of them weren't found. If any of them is under legal hold, nobody is deleted.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    delete,
+    tag = "person",
+    path = "/person",
+    request_body = [Uuid],
+    responses(
+        (status = 200, description = "How many people were deleted, and the UUIDs not found", body = BulkDeleteResult),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 423, description = "One of the people is under legal hold", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+async fn delete_people(
+    user: WriteUser,
+    people: PersonService,
+    ValidatedPayload(request): ValidatedPayload<BulkDelete>,
+) -> Result<Json<BulkDeleteResult>, ApiError> {
+    let result = people.delete_each(&user.username, &request.ids).await?;
+
+    Ok(Json(result))
 }
 
 pub fn router() -> Router {
     Router::new()
-        .route("/person", get(list_people).post(create_person))
+        .route(
+            "/person",
+            get(list_people)
+                .post(create_person)
+                .patch(update_people)
+                .delete(delete_people),
+        )
+        .route("/person/count", get(count_people))
+        .route("/person/search", get(search_people))
+        .route("/person/check-duplicates", post(check_duplicates))
+        .route("/person/lookup", post(lookup_people))
         .route(
             "/person/:person_uuid",
-            get(get_person).put(update_person).delete(delete_person),
+            get(get_person)
+                .put(update_person)
+                .patch(patch_person)
+                .delete(delete_person),
         )
+        .route("/person/:person_uuid/restore", post(restore_person))
 }
 
 #[cfg(test)]
 mod tests {
-    use time::macros::date;
-    use validator::Validate;
+    use serde_json::json;
 
     use super::NewPerson;
 
     #[test]
-    fn new_person_is_valid_when_dob_is_in_the_future() {
-        let new_person = NewPerson {
-            first_name: "John".to_owned(),
-            family_name: "Doe".to_owned(),
-            date_of_birth: date!(1900 - 1 - 1),
-        };
-
-        assert!(new_person.validate().is_ok(), "Should be a valid person");
+    fn new_person_is_valid_when_dob_is_in_the_past() {
+        let new_person = serde_json::from_value::<NewPerson>(json!({
+            "first_name": "John",
+            "family_name": "Doe",
+            "date_of_birth": "1900-01-01",
+        }));
+
+        assert!(new_person.is_ok(), "Should be a valid person");
     }
 
     #[test]
     fn new_person_is_invalid_when_dob_is_in_the_future() {
-        let new_person = NewPerson {
-            first_name: "John".to_owned(),
-            family_name: "Doe".to_owned(),
-            date_of_birth: date!(2050 - 1 - 1),
-        };
-
-        assert!(
-            new_person.validate().is_err(),
-            "Should return a validation error"
-        );
+        let new_person = serde_json::from_value::<NewPerson>(json!({
+            "first_name": "John",
+            "family_name": "Doe",
+            "date_of_birth": "2050-01-01",
+        }));
+
+        assert!(new_person.is_err(), "Should be rejected");
     }
 }
@@ -0,0 +1,140 @@
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{
+    auth::{AdminUser, ReadUser},
+    content::{Format, Link, Negotiated, Resource},
+    error::ApiError,
+    person::Person,
+    timestamp, v1,
+};
+use crate::service::person_event::PersonEventService;
+
+/// A change to a person recorded in their event stream
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonEvent {
+    pub person_id: Uuid,
+    /// The position of the event in the person's stream, starting from 1
+    pub version: i32,
+    /// The type of the event, such as `person.created` or `person.updated`
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// The person as the event left them, absent once they were deleted or archived
+    pub person: Option<Person>,
+    /// The client responsible for the change
+    pub actor: String,
+    /// When the event was recorded, as an RFC 3339 timestamp in UTC, or milliseconds since the
+    /// Unix epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub recorded: OffsetDateTime,
+}
+
+impl Resource for PersonEvent {
+    const ELEMENT: &'static str = "personEvent";
+    const COLLECTION: &'static str = "personEvents";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        let events = format!("{}/person/{}/events", v1::PREFIX, self.person_id);
+
+        vec![("events", Link::to(events))]
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        let person = format!("{}/person/{}", v1::PREFIX, self.person_id);
+
+        vec![("person", Link::to(person))]
+    }
+}
+
+/// List a person's events
+///
+/// Events are only recorded while event sourcing is enabled with `PERSON_EVENT_SOURCING`, and
+/// are kept after the person is deleted.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/{person_uuid}/events",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "The person's events, oldest first", body = [PersonEvent]),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn list_person_events(
+    user: ReadUser,
+    events: PersonEventService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Negotiated<Vec<PersonEvent>>, ApiError> {
+    let events = events.list(person_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved {} event(s) of person '{}'",
+        user.username,
+        events.len(),
+        person_uuid
+    );
+
+    Ok(Negotiated(format, events))
+}
+
+/// Rebuild a person from their events
+///
+/// Replaces the person's details with those left by the last event in their stream, or
+/// deletes them if it deleted or archived them. Their address and anything else not held in
+/// the events is kept.
+///
+/// Requires the scope `admin`
+#[utoipa::path(
+    post,
+    tag = "admin",
+    path = "/admin/person/{person_uuid}/replay",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "The person as rebuilt", body = Person),
+        (status = 204, description = "The events leave the person deleted or archived"),
+        (status = 404, description = "No events recorded for the person", body = ErrorResponse),
+        (status = 409, description = "The person's details conflict with another's", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn replay_person(
+    user: AdminUser,
+    events: PersonEventService,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Response, ApiError> {
+    let response = match events.replay(&user.username, person_uuid).await? {
+        Some(person) => Json(person).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    };
+
+    Ok(response)
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/person/:person_uuid/events", get(list_person_events))
+        .route("/admin/person/:person_uuid/replay", post(replay_person))
+}
@@ -0,0 +1,211 @@
+use axum::{extract::Path, routing::get, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::OffsetDateTime;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{
+    auth::ReadUser,
+    content::{Format, Link, Negotiated, Resource},
+    error::ApiError,
+    timestamp, v1,
+};
+use crate::service::person_history::PersonHistoryService;
+
+/// A field of a person changed by a change in their history
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangedField {
+    /// The field, as named in the person
+    pub field: String,
+    /// The value before the change, null if the person didn't exist or it isn't known
+    #[schema(value_type = Option<String>)]
+    pub old_value: Value,
+    /// The value after the change, null if it deleted or archived the person
+    #[schema(value_type = Option<String>)]
+    pub new_value: Value,
+}
+
+/// A change made to a person, as recorded in their history
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub person_id: Uuid,
+    /// The position of the change in the person's history, starting from 1
+    pub revision: i32,
+    /// The type of the change, such as `person.created` or `person.updated`
+    #[serde(rename = "type")]
+    pub change_type: String,
+    /// The fields the change made different, in the order they appear in the person
+    pub changes: Vec<ChangedField>,
+    /// The client responsible for the change
+    pub changed_by: String,
+    /// When the change was made, as an RFC 3339 timestamp in UTC, or milliseconds since the
+    /// Unix epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub changed: OffsetDateTime,
+}
+
+impl Resource for HistoryEntry {
+    const ELEMENT: &'static str = "historyEntry";
+    const COLLECTION: &'static str = "history";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        let history = format!("{}/person/{}/history", v1::PREFIX, self.person_id);
+
+        vec![("history", Link::to(history))]
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        let person = format!("{}/person/{}", v1::PREFIX, self.person_id);
+
+        vec![("person", Link::to(person))]
+    }
+}
+
+/// How a field of a person differs between two revisions
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    /// The field, as named in the person
+    pub field: String,
+    /// The value at the first revision, null if the person didn't exist then
+    #[schema(value_type = Option<String>)]
+    pub old_value: Value,
+    /// The value at the second revision, null if the person didn't exist then
+    #[schema(value_type = Option<String>)]
+    pub new_value: Value,
+    /// The client who last changed the field between the revisions
+    pub changed_by: String,
+    /// When they changed it, in the same format as `changed` on history entries
+    #[serde(with = "timestamp")]
+    pub changed: OffsetDateTime,
+}
+
+/// The fields of a person differing between two revisions in their history
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonDiff {
+    pub person_id: Uuid,
+    /// The first revision
+    pub from: i32,
+    /// The second revision
+    pub to: i32,
+    /// The differing fields, in the order they appear in the person
+    pub changes: Vec<FieldChange>,
+}
+
+impl Resource for PersonDiff {
+    const ELEMENT: &'static str = "personDiff";
+    const COLLECTION: &'static str = "personDiffs";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        let diff = format!(
+            "{}/person/{}/history/{}/diff/{}",
+            v1::PREFIX,
+            self.person_id,
+            self.from,
+            self.to
+        );
+
+        vec![("self", Link::to(diff))]
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        let person = format!("{}/person/{}", v1::PREFIX, self.person_id);
+
+        vec![("person", Link::to(person))]
+    }
+}
+
+/// List the changes made to a person
+///
+/// Every change is recorded, however it was made, with who made it, when, and the old and new
+/// value of each field it changed. Changes are numbered by their `revision`, by which they can
+/// be compared. The history is kept after the person is deleted or archived.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/{person_uuid}/history",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "The person's history, oldest first", body = [HistoryEntry]),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn get_person_history(
+    user: ReadUser,
+    history: PersonHistoryService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Negotiated<Vec<HistoryEntry>>, ApiError> {
+    let history = history.list(person_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved {} change(s) to person '{}'",
+        user.username,
+        history.len(),
+        person_uuid
+    );
+
+    Ok(Negotiated(format, history))
+}
+
+/// Compare two revisions of a person
+///
+/// Revisions are the changes in the person's history, numbered from 1. Each field differing
+/// between them is given with its old and new value and who last changed it in between. The
+/// earlier revision normally comes first, but they can be given either way round.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/{person_uuid}/history/{rev_a}/diff/{rev_b}",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        ("rev_a" = i32, Path, description = "The first revision"),
+        ("rev_b" = i32, Path, description = "The second revision")
+    ),
+    responses(
+        (status = 200, description = "The fields differing between the revisions", body = PersonDiff),
+        (status = 400, description = "Invalid revision", body = ErrorResponse),
+        (status = 404, description = "Revision not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn diff_person_revisions(
+    user: ReadUser,
+    history: PersonHistoryService,
+    format: Format,
+    Path((person_uuid, rev_a, rev_b)): Path<(Uuid, i32, i32)>,
+) -> Result<Negotiated<PersonDiff>, ApiError> {
+    let diff = history.diff(person_uuid, rev_a, rev_b).await?;
+
+    info!(
+        "Client '{}' compared revisions {} and {} of person '{}'",
+        user.username, rev_a, rev_b, person_uuid
+    );
+
+    Ok(Negotiated(format, diff))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/person/:person_uuid/history", get(get_person_history))
+        .route(
+            "/person/:person_uuid/history/:rev_a/diff/:rev_b",
+            get(diff_person_revisions),
+        )
+}
@@ -0,0 +1,86 @@
+use axum::{extract::Path, routing::post, Json, Router};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{auth::WriteUser, error::ApiError, timestamp};
+use crate::service::person_merge::PersonMergeService;
+
+/// A duplicate person merged into another, and what was moved across
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonMerge {
+    pub id: Uuid,
+    /// The person kept
+    pub target_id: Uuid,
+    /// The person merged into the target, and then deleted
+    pub source_id: Uuid,
+    /// Whether the source's address was moved, which happens when the target has none
+    pub address_moved: bool,
+    /// Whether the source's photo was moved, which happens when the target has none
+    pub photo_moved: bool,
+    pub employments_moved: i64,
+    pub emergency_contacts_moved: i64,
+    /// The email addresses and phone numbers moved, leaving any the target already had
+    pub contact_details_moved: i64,
+    pub consents_moved: i64,
+    /// The tags moved, leaving any the target already had
+    pub tags_moved: i64,
+    pub notes_moved: i64,
+    /// The relationships moved, leaving any the target already had and those between the two
+    pub relationships_moved: i64,
+    /// The client who merged them
+    pub merged_by: String,
+    /// When they were merged, as an RFC 3339 timestamp in UTC, or milliseconds since the Unix
+    /// epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub merged: OffsetDateTime,
+}
+
+/// Merge a duplicate person into another
+///
+/// Moves the source person's employment, emergency contacts, consent and notes to the target,
+/// along with any contact details, tags and relationships the target doesn't already have and
+/// their address and photo when the target has none, then deletes the source, all at once.
+/// Nothing is merged if the target would end up with more than 5 emergency contacts, or with
+/// employment overlapping. The deleted source can still be restored, but what was moved stays
+/// with the target. The merge is recorded, and published as `person.merged`.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    post,
+    tag = "person",
+    path = "/person/{target_uuid}/merge/{source_uuid}",
+    params(
+        ("target_uuid" = Uuid, Path, description = "The UUID of the person to keep"),
+        ("source_uuid" = Uuid, Path, description = "The UUID of the duplicate person to merge into them")
+    ),
+    responses(
+        (status = 200, description = "People merged successfully", body = PersonMerge),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+        (status = 409, description = "A person can't be merged into themselves, or the merge would leave the target with too many emergency contacts, overlapping employment or relationships forming a cycle", body = ErrorResponse),
+        (status = 423, description = "The source person is under legal hold", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn merge_people(
+    user: WriteUser,
+    merges: PersonMergeService,
+    Path((target_uuid, source_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Json<PersonMerge>, ApiError> {
+    let merge = merges
+        .merge(&user.username, target_uuid, source_uuid)
+        .await?;
+
+    Ok(Json(merge))
+}
+
+pub fn router() -> Router {
+    Router::new().route(
+        "/person/:person_uuid/merge/:source_uuid",
+        post(merge_people),
+    )
+}
@@ -0,0 +1,200 @@
+use axum::{extract::Path, response::IntoResponse, routing::get, Router};
+use hyper::{
+    header::{HeaderName, LOCATION},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::{
+    auth::{ReadUser, WriteUser},
+    content::{Format, Link, Negotiated, Resource, ValidatedPayload},
+    error::ApiError,
+    fields,
+    limit::{LimitQuery, OffsetQuery},
+    person::TOTAL_COUNT,
+    query::ValidatedQuery,
+    timestamp, v1,
+};
+use crate::service::person_note::PersonNoteService;
+
+/// The longest note, in characters
+pub const MAX_NOTE_LENGTH: u64 = 10_000;
+
+#[derive(Debug, Validate, Serialize, Deserialize, ToSchema)]
+pub struct NewNote {
+    /// The text of the note, kept as written, line breaks and all
+    #[validate(
+        length(min = 1, max = MAX_NOTE_LENGTH),
+        custom(function = "fields::not_blank")
+    )]
+    #[schema(
+        min_length = 1,
+        max_length = 10000,
+        example = "Called to arrange a home visit"
+    )]
+    pub body: String,
+}
+
+/// A case note about a person
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Note {
+    pub id: Uuid,
+    pub person_id: Uuid,
+    pub body: String,
+    /// The client who wrote the note
+    pub author: String,
+    /// When the note was written, as an RFC 3339 timestamp in UTC, or milliseconds since the
+    /// Unix epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub created: OffsetDateTime,
+}
+
+impl Resource for Note {
+    const ELEMENT: &'static str = "note";
+    const COLLECTION: &'static str = "notes";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        let note = format!("{}/person/{}/notes/{}", v1::PREFIX, self.person_id, self.id);
+
+        vec![("self", Link::to(note))]
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        let person = format!("{}/person/{}", v1::PREFIX, self.person_id);
+
+        vec![("person", Link::to(person))]
+    }
+}
+
+/// List the notes about a person
+///
+/// Notes are listed newest first, a page at a time, with how many there are in all in the
+/// `X-Total-Count` header.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/{person_uuid}/notes",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        LimitQuery,
+        OffsetQuery,
+    ),
+    responses(
+        (status = 200, description = "The notes about the person, newest first", body = [Note],
+            headers(("X-Total-Count" = i64, description = "How many notes there are about the person"))),
+        (status = 400, description = "Invalid query", body = ErrorResponse),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn list_notes(
+    user: ReadUser,
+    notes: PersonNoteService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+    ValidatedQuery(page): ValidatedQuery<LimitQuery>,
+    ValidatedQuery(offset): ValidatedQuery<OffsetQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (total, notes) = notes
+        .list(person_uuid, offset.offset().unwrap_or(0), page.limit())
+        .await?;
+
+    info!(
+        "Client '{}' retrieved {} note(s) about person '{}'",
+        user.username,
+        notes.len(),
+        person_uuid
+    );
+
+    Ok(([(TOTAL_COUNT, total)], Negotiated(format, notes)))
+}
+
+/// Write a note about a person
+///
+/// The note's author is the client writing it. Notes are up to 10,000 characters, and can't be
+/// changed once written.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    post,
+    tag = "person",
+    path = "/person/{person_uuid}/notes",
+    request_body = NewNote,
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 201, description = "Note written successfully", body = Note,
+            headers(("location" = String, description = "The URL of the note"))),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn add_note(
+    user: WriteUser,
+    notes: PersonNoteService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+    ValidatedPayload(request): ValidatedPayload<NewNote>,
+) -> Result<(StatusCode, [(HeaderName, String); 1], Negotiated<Note>), ApiError> {
+    let note = notes.add(&user.username, person_uuid, &request).await?;
+
+    let location = format!("{}/person/{person_uuid}/notes/{}", v1::PREFIX, note.id);
+
+    Ok((
+        StatusCode::CREATED,
+        [(LOCATION, location)],
+        Negotiated(format, note),
+    ))
+}
+
+/// Get a note about a person
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/{person_uuid}/notes/{note_uuid}",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        ("note_uuid" = Uuid, Path, description = "The UUID of the note")
+    ),
+    responses(
+        (status = 200, description = "The note matching the given UUID", body = Note),
+        (status = 404, description = "Note not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn get_note(
+    user: ReadUser,
+    notes: PersonNoteService,
+    format: Format,
+    Path((person_uuid, note_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Negotiated<Note>, ApiError> {
+    let note = notes.find(person_uuid, note_uuid).await?;
+
+    info!("Client '{}' retrieved note '{}'", user.username, note_uuid);
+
+    Ok(Negotiated(format, note))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/person/:person_uuid/notes", get(list_notes).post(add_note))
+        .route("/person/:person_uuid/notes/:note_uuid", get(get_note))
+}
@@ -0,0 +1,267 @@
+//! Profile photos of people, uploaded as the `photo` field of a form and served back as the
+//! image itself.
+//!
+//! Photos are JPEG, PNG or WebP images of up to `MAX_PHOTO_BYTES` bytes (default 5 MiB), held
+//! in the database alongside the person. The upload is read a chunk at a time and turned away
+//! with `413 Payload Too Large` as soon as it passes the limit, and an image whose content
+//! doesn't match the type it was sent as is refused.
+
+use std::env;
+
+use axum::{
+    body::Body,
+    extract::{multipart::MultipartError, DefaultBodyLimit, FromRequest, Multipart, Path, Request},
+    http::header::{CONTENT_LENGTH, CONTENT_TYPE},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{
+    auth::{ReadUser, WriteUser},
+    content::{Format, Link, Negotiated, Resource},
+    error::ApiError,
+    timestamp, v1,
+};
+use crate::service::person_photo::PersonPhotoService;
+
+/// The form field holding an uploaded photo
+const PHOTO_FIELD: &str = "photo";
+
+/// The largest photo accepted, from `MAX_PHOTO_BYTES`
+fn max_photo_bytes() -> usize {
+    env::var("MAX_PHOTO_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(5 * 1024 * 1024)
+}
+
+/// The kinds of image a photo can be
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PhotoType {
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl PhotoType {
+    fn of(content_type: &str) -> Option<Self> {
+        match content_type.to_ascii_lowercase().as_str() {
+            "image/jpeg" => Some(PhotoType::Jpeg),
+            "image/png" => Some(PhotoType::Png),
+            "image/webp" => Some(PhotoType::Webp),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            PhotoType::Jpeg => "image/jpeg",
+            PhotoType::Png => "image/png",
+            PhotoType::Webp => "image/webp",
+        }
+    }
+
+    /// Whether the image starts with the signature of its type
+    fn matches(self, image: &[u8]) -> bool {
+        match self {
+            PhotoType::Jpeg => image.starts_with(&[0xFF, 0xD8, 0xFF]),
+            PhotoType::Png => image.starts_with(b"\x89PNG\r\n\x1a\n"),
+            PhotoType::Webp => {
+                image.len() >= 12 && image.starts_with(b"RIFF") && &image[8..12] == b"WEBP"
+            }
+        }
+    }
+}
+
+/// A person's photo, as described rather than served
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonPhoto {
+    pub person_id: Uuid,
+    /// The type of image, being `image/jpeg`, `image/png` or `image/webp`
+    pub content_type: String,
+    /// The size of the image in bytes
+    pub size: i64,
+    /// When the photo was uploaded, in the same format as `created` on a person
+    #[serde(with = "timestamp")]
+    pub uploaded: OffsetDateTime,
+}
+
+impl Resource for PersonPhoto {
+    const ELEMENT: &'static str = "photo";
+    const COLLECTION: &'static str = "photos";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        vec![(
+            "self",
+            Link::to(format!("{}/person/{}/photo", v1::PREFIX, self.person_id)),
+        )]
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        let person = format!("{}/person/{}", v1::PREFIX, self.person_id);
+
+        vec![("person", Link::to(person))]
+    }
+}
+
+/// A photo uploaded as a form
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct PhotoUpload {
+    /// A JPEG, PNG or WebP image, sent with its content type
+    #[schema(value_type = String, format = Binary)]
+    photo: Vec<u8>,
+}
+
+/// Get a person's photo
+///
+/// Responds with the image itself, as the type it was uploaded as.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/{person_uuid}/photo",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "The person's photo", content_type = "image/*", body = Vec<u8>),
+        (status = 404, description = "Person not found, or they have no photo", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn get_photo(
+    user: ReadUser,
+    photos: PersonPhotoService,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (content_type, content) = photos.download(person_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved the photo of person '{}'",
+        user.username, person_uuid
+    );
+
+    Ok((
+        [
+            (CONTENT_TYPE, content_type),
+            (CONTENT_LENGTH, content.len().to_string()),
+        ],
+        Body::from(content),
+    ))
+}
+
+/// Upload a photo of a person
+///
+/// The photo is sent as the `photo` field of a form, with a content type of `image/jpeg`,
+/// `image/png` or `image/webp` matching the image. Photos of up to 5 MiB are accepted unless
+/// configured otherwise. Any photo the person already has is replaced, and
+/// `person.photo_updated` is published.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    put,
+    tag = "person",
+    path = "/person/{person_uuid}/photo",
+    request_body(content = PhotoUpload, content_type = "multipart/form-data"),
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "Photo uploaded successfully", body = PersonPhoto),
+        (status = 400, description = "The form couldn't be read, or the photo isn't the type it was sent as", body = ErrorResponse),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+        (status = 413, description = "The photo is too large", body = ErrorResponse),
+        (status = 415, description = "The photo isn't a JPEG, PNG or WebP image", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn upload_photo(
+    user: WriteUser,
+    photos: PersonPhotoService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+    request: Request,
+) -> Result<Negotiated<PersonPhoto>, ApiError> {
+    let unreadable = |e: MultipartError| ApiError::UnreadableBody(e.body_text());
+    let limit = max_photo_bytes();
+
+    let mut form = Multipart::from_request(request, &())
+        .await
+        .map_err(|e| ApiError::UnreadableBody(e.body_text()))?;
+
+    let mut field = loop {
+        let Some(field) = form.next_field().await.map_err(unreadable)? else {
+            return Err(ApiError::UnreadableBody(format!(
+                "No photo was uploaded as the `{PHOTO_FIELD}` field"
+            )));
+        };
+
+        if field.name() == Some(PHOTO_FIELD) {
+            break field;
+        }
+    };
+
+    let content_type = field.content_type().unwrap_or_default().to_owned();
+    let photo_type = PhotoType::of(&content_type)
+        .ok_or_else(|| ApiError::UnsupportedMediaType(content_type.clone()))?;
+
+    let mut content = vec![];
+    while let Some(chunk) = field.chunk().await.map_err(unreadable)? {
+        if content.len() + chunk.len() > limit {
+            return Err(ApiError::PhotoTooLarge(limit));
+        }
+        content.extend_from_slice(&chunk);
+    }
+
+    if !photo_type.matches(&content) {
+        return Err(ApiError::UnreadableBody(format!(
+            "The photo isn't a valid {content_type} image"
+        )));
+    }
+
+    let photo = photos
+        .replace(&user.username, person_uuid, photo_type, content)
+        .await?;
+
+    Ok(Negotiated(format, photo))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/person/:person_uuid/photo",
+            get(get_photo).put(upload_photo),
+        )
+        // uploads are limited to the size of a photo as they're read instead
+        .layer(DefaultBodyLimit::disable())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PhotoType;
+
+    #[test]
+    fn photos_must_match_their_type() {
+        assert_eq!(PhotoType::of("Image/PNG"), Some(PhotoType::Png));
+        assert_eq!(PhotoType::of("image/gif"), None);
+
+        assert!(PhotoType::Jpeg.matches(&[0xFF, 0xD8, 0xFF, 0xE0]));
+        assert!(PhotoType::Png.matches(b"\x89PNG\r\n\x1a\n...."));
+        assert!(PhotoType::Webp.matches(b"RIFF\0\0\0\0WEBPVP8 "));
+        assert!(!PhotoType::Png.matches(&[0xFF, 0xD8, 0xFF, 0xE0]));
+        assert!(!PhotoType::Webp.matches(b"RIFF\0\0\0\0WAVE"));
+    }
+}
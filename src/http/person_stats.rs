@@ -0,0 +1,77 @@
+use axum::{routing::get, Json, Router};
+use serde::{Deserialize, Serialize};
+use time::Date;
+use tracing::info;
+use utoipa::ToSchema;
+
+use super::{auth::ReadUser, error::ApiError};
+use crate::service::person_stats::PersonStatsService;
+
+/// How many people there are of ages within a band
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AgeBand {
+    /// The youngest age in the band
+    pub min_age: i32,
+    /// The oldest age in the band, being `null` for the oldest band
+    pub max_age: Option<i32>,
+    pub count: i64,
+}
+
+/// How many people were created on a day
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DailyCount {
+    /// The day, in UTC
+    pub date: Date,
+    pub count: i64,
+}
+
+/// Figures summarising everyone who hasn't been deleted
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonStats {
+    pub total: i64,
+    /// How many people are of each age, youngest first, with every band given even when it's
+    /// empty
+    pub age_bands: Vec<AgeBand>,
+    /// How many of the people were created on each of the last 30 days, today last
+    pub created_per_day: Vec<DailyCount>,
+    pub with_address: i64,
+    pub without_address: i64,
+}
+
+/// Get statistics about people
+///
+/// Summarises everyone who hasn't been deleted: how many there are in each age band, how many
+/// were created on each of the last 30 days, and how many have an address. Everything is
+/// counted by the database, so no people are fetched.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/stats",
+    responses(
+        (status = 200, description = "Statistics about people", body = PersonStats),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn get_person_stats(
+    user: ReadUser,
+    stats: PersonStatsService,
+) -> Result<Json<PersonStats>, ApiError> {
+    let stats = stats.summarise().await?;
+
+    info!(
+        "Client '{}' retrieved statistics about people",
+        user.username
+    );
+
+    Ok(Json(stats))
+}
+
+pub fn router() -> Router {
+    Router::new().route("/person/stats", get(get_person_stats))
+}
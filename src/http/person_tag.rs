@@ -0,0 +1,136 @@
+use axum::{
+    extract::Path,
+    routing::{delete, get},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{
+    auth::{ReadUser, WriteUser},
+    content::Payload,
+    error::ApiError,
+    fields::Tag,
+    response::Deleted,
+};
+use crate::service::person_tag::PersonTagService;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewTag {
+    /// The tag, such as `at risk`, which is lowercased and ignores surrounding whitespace
+    #[schema(value_type = String, min_length = 1, max_length = 64, example = "at risk")]
+    pub tag: Tag,
+}
+
+/// List a person's tags
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/{person_uuid}/tags",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "The person's tags, alphabetically", body = [String]),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn list_tags(
+    user: ReadUser,
+    tags: PersonTagService,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Json<Vec<String>>, ApiError> {
+    let tags = tags.list(person_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved {} tag(s) of person '{}'",
+        user.username,
+        tags.len(),
+        person_uuid
+    );
+
+    Ok(Json(tags))
+}
+
+/// Tag a person
+///
+/// Tags group people into ad-hoc cohorts, which the person list can be filtered by with `tag`.
+/// Tagging a person with a tag they already have changes nothing, while a new tag publishes
+/// `person.tagged`.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    post,
+    tag = "person",
+    path = "/person/{person_uuid}/tags",
+    request_body = NewTag,
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "The person's tags once tagged, alphabetically", body = [String]),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn add_tag(
+    user: WriteUser,
+    tags: PersonTagService,
+    Path(person_uuid): Path<Uuid>,
+    Payload(request): Payload<NewTag>,
+) -> Result<Json<Vec<String>>, ApiError> {
+    let tags = tags.add(&user.username, person_uuid, &request.tag).await?;
+
+    Ok(Json(tags))
+}
+
+/// Remove a tag from a person
+///
+/// The tag is matched ignoring case, and its removal publishes `person.untagged`.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    delete,
+    tag = "person",
+    path = "/person/{person_uuid}/tags/{tag}",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person"),
+        ("tag" = String, Path, description = "The tag to remove")
+    ),
+    responses(
+        (status = 204, description = "Tag removed successfully"),
+        (status = 404, description = "Person not found, or they don't have the tag", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn remove_tag(
+    user: WriteUser,
+    tags: PersonTagService,
+    Path((person_uuid, tag)): Path<(Uuid, String)>,
+) -> Result<Deleted, ApiError> {
+    // a tag which couldn't have been added can't be on anyone
+    let tag = Tag::try_from(tag.clone())
+        .map_err(|_| ApiError::NotFound(format!("Person '{person_uuid}' isn't tagged '{tag}'")))?;
+
+    tags.remove(&user.username, person_uuid, &tag).await?;
+
+    Ok(Deleted)
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/person/:person_uuid/tags", get(list_tags).post(add_tag))
+        .route("/person/:person_uuid/tags/:tag", delete(remove_tag))
+}
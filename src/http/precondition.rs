@@ -0,0 +1,264 @@
+//! Conditional changes, so clients can't unknowingly overwrite each other's changes.
+//!
+//! Resources with a version give it as an entity tag, such as `"3"`, which clients send back in
+//! `If-Match` when changing them. The change is refused with `412 Precondition Failed` if the
+//! resource has been changed since, or `428 Precondition Required` if no version was given.
+//! `If-Match: *` makes the change whatever the version is.
+//!
+//! Reads work the other way round: responses carry an `ETag` and `Last-Modified`, and a client
+//! sending them back in `If-None-Match` or `If-Modified-Since` gets an empty
+//! `304 Not Modified` while its copy is still current.
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use hyper::{
+    header::{HeaderValue, ETAG, IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, VARY},
+    StatusCode,
+};
+use time::{OffsetDateTime, PrimitiveDateTime};
+
+use super::{deprecation::HTTP_DATE, error::ApiError};
+
+/// The versions of a resource a change may be made to, from `If-Match`
+#[derive(Clone, Debug, PartialEq)]
+pub enum IfMatch {
+    Any,
+    Versions(Vec<i32>),
+}
+
+impl IfMatch {
+    /// Parses the entity tags of the header's values. Weak tags never match under the strong
+    /// comparison `If-Match` uses, so they're left out along with anything not a version.
+    fn parse<'a>(values: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut versions = vec![];
+
+        for tag in values.into_iter().flat_map(|value| value.split(',')) {
+            let tag = tag.trim();
+            if tag == "*" {
+                return IfMatch::Any;
+            }
+
+            let version = tag
+                .strip_prefix('"')
+                .and_then(|tag| tag.strip_suffix('"'))
+                .and_then(|version| version.parse::<i32>().ok());
+            versions.extend(version);
+        }
+
+        IfMatch::Versions(versions)
+    }
+
+    /// Checks the resource's current version is one the change may be made to
+    pub fn check(&self, version: i32) -> Result<(), ApiError> {
+        match self {
+            IfMatch::Versions(versions) if !versions.contains(&version) => {
+                Err(ApiError::PreconditionFailed(format!(
+                    "The resource has been changed, and is now at version {version}"
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The entity tag of a version of a resource
+pub fn entity_tag(version: i32) -> String {
+    format!("\"{version}\"")
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IfMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let values: Vec<_> = parts
+            .headers
+            .get_all(IF_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+
+        if values.is_empty() {
+            return Err(ApiError::PreconditionRequired(
+                "The version being changed must be given in If-Match".to_owned(),
+            ));
+        }
+
+        Ok(IfMatch::parse(values))
+    }
+}
+
+/// What a client's copy of a representation can be checked against, to tell if it's current
+#[derive(Clone, Debug, PartialEq)]
+pub struct Validators {
+    /// The entity tag, quoted and possibly weak, such as `"3"` or `W/"12-34"`
+    pub etag: String,
+    /// When the representation last changed, if anything it's made from has
+    pub last_modified: Option<OffsetDateTime>,
+}
+
+/// The conditions a read was made on, from `If-None-Match` and `If-Modified-Since`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IfNoneMatch {
+    /// The entity tags of the client's copies, if `If-None-Match` was sent
+    tags: Option<Vec<String>>,
+    modified_since: Option<OffsetDateTime>,
+}
+
+impl IfNoneMatch {
+    /// Parses the headers' values. A date that isn't an HTTP date is ignored, as the RFC says.
+    fn parse<'a>(tags: impl IntoIterator<Item = &'a str>, modified_since: Option<&str>) -> Self {
+        let tags: Vec<_> = tags
+            .into_iter()
+            .flat_map(|value| value.split(','))
+            .map(|tag| tag.trim().to_owned())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+
+        let modified_since = modified_since
+            .and_then(|date| PrimitiveDateTime::parse(date.trim(), HTTP_DATE).ok())
+            .map(PrimitiveDateTime::assume_utc);
+
+        IfNoneMatch {
+            tags: (!tags.is_empty()).then_some(tags),
+            modified_since,
+        }
+    }
+
+    /// Whether the client already has the current representation. `If-None-Match` uses the
+    /// weak comparison, and when sent `If-Modified-Since` is ignored.
+    fn is_current(&self, validators: &Validators) -> bool {
+        if let Some(tags) = &self.tags {
+            let opaque = |tag: &str| tag.strip_prefix("W/").unwrap_or(tag).to_owned();
+            let current = opaque(&validators.etag);
+
+            return tags.iter().any(|tag| tag == "*" || opaque(tag) == current);
+        }
+
+        // HTTP dates are only to the second
+        match (self.modified_since, validators.last_modified) {
+            (Some(since), Some(modified)) => modified.unix_timestamp() <= since.unix_timestamp(),
+            _ => false,
+        }
+    }
+
+    /// An empty `304 Not Modified` to send in place of the representation, if the client's copy
+    /// is current
+    pub fn not_modified(&self, validators: &Validators) -> Option<Response> {
+        self.is_current(validators).then(|| {
+            // the representation depends on Accept, as the full response would have said
+            let response = (StatusCode::NOT_MODIFIED, [(VARY, "accept")]).into_response();
+            with_validators(validators.clone(), response)
+        })
+    }
+
+    /// The response with the validators set, or `304 Not Modified` in its place if the client's
+    /// copy is current
+    pub fn respond(&self, validators: Validators, response: impl IntoResponse) -> Response {
+        match self.not_modified(&validators) {
+            Some(not_modified) => not_modified,
+            None => with_validators(validators, response.into_response()),
+        }
+    }
+}
+
+/// Sets the `ETag` and `Last-Modified` of the response
+pub fn with_validators(validators: Validators, mut response: Response) -> Response {
+    let headers = response.headers_mut();
+
+    if let Ok(etag) = HeaderValue::try_from(validators.etag) {
+        headers.insert(ETAG, etag);
+    }
+    let last_modified = validators
+        .last_modified
+        .and_then(|modified| modified.format(HTTP_DATE).ok())
+        .and_then(|modified| HeaderValue::try_from(modified).ok());
+    if let Some(last_modified) = last_modified {
+        headers.insert(LAST_MODIFIED, last_modified);
+    }
+
+    response
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IfNoneMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let tags = parts
+            .headers
+            .get_all(IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok());
+        let modified_since = parts
+            .headers
+            .get(IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok());
+
+        Ok(IfNoneMatch::parse(tags, modified_since))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::{entity_tag, IfMatch, IfNoneMatch, Validators};
+
+    #[test]
+    fn entity_tags_are_parsed_as_versions() {
+        assert_eq!(IfMatch::parse([r#""3""#]), IfMatch::Versions(vec![3]));
+        assert_eq!(
+            IfMatch::parse([r#""3", "4""#, r#""5""#]),
+            IfMatch::Versions(vec![3, 4, 5])
+        );
+        assert_eq!(IfMatch::parse([r#""3", *"#]), IfMatch::Any);
+        assert_eq!(
+            IfMatch::parse([r#"W/"3""#, "3", r#""three""#]),
+            IfMatch::Versions(vec![]),
+            "Weak and malformed tags match nothing"
+        );
+    }
+
+    #[test]
+    fn only_matching_versions_pass() {
+        let if_match = IfMatch::parse([entity_tag(3).as_str()]);
+
+        assert!(if_match.check(3).is_ok());
+        assert!(if_match.check(4).is_err());
+        assert!(IfMatch::Any.check(4).is_ok());
+    }
+
+    #[test]
+    fn reads_are_not_modified_while_the_copy_is_current() {
+        let validators = Validators {
+            etag: entity_tag(3),
+            last_modified: Some(datetime!(2024-05-01 09:30:00.5 UTC)),
+        };
+        let since = Some("Wed, 01 May 2024 09:30:00 GMT");
+
+        assert!(IfNoneMatch::parse([r#""2", "3""#], None).is_current(&validators));
+        assert!(IfNoneMatch::parse([r#"W/"3""#], None).is_current(&validators));
+        assert!(IfNoneMatch::parse(["*"], None).is_current(&validators));
+        assert!(IfNoneMatch::parse([], since).is_current(&validators));
+        assert!(
+            !IfNoneMatch::parse([r#""2""#], since).is_current(&validators),
+            "If-Modified-Since is ignored alongside If-None-Match"
+        );
+        assert!(
+            !IfNoneMatch::parse([], Some("Wed, 01 May 2024 09:29:59 GMT")).is_current(&validators)
+        );
+        assert!(!IfNoneMatch::parse([], Some("yesterday")).is_current(&validators));
+        assert!(!IfNoneMatch::default().is_current(&validators));
+    }
+}
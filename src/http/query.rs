@@ -0,0 +1,29 @@
+//! Query string parameters, checked before they reach the handler.
+
+use axum::{async_trait, extract::FromRequestParts, extract::Query, http::request::Parts};
+use serde::de::DeserializeOwned;
+use validator::Validate;
+
+use super::error::ApiError;
+
+/// Query parameters which have been parsed and passed validation.
+///
+/// Unparseable parameters are rejected with a JSON error naming the problem, and invalid ones
+/// with the usual `400` listing the failing fields, rather than axum's plain text rejection.
+pub struct ValidatedQuery<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ValidatedQuery<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Query(value) = Query::<T>::try_from_uri(&parts.uri)?;
+        value.validate()?;
+
+        Ok(ValidatedQuery(value))
+    }
+}
@@ -0,0 +1,140 @@
+//! Paging through collections with `Range` headers ([RFC 9110]), for clients built around them
+//! rather than query parameters.
+//!
+//! A request for `Range: items=0-49` is answered with `206 Partial Content` and
+//! `Content-Range: items 0-49/1234`, giving the items returned and how many there are in all.
+//! The last item may be left off, as in `items=50-`, for the rest of the collection. Either way
+//! no more than [`MAX_LIMIT`] items are returned, so clients should follow `Content-Range`
+//! rather than assume they got what they asked for. A range starting beyond the end of the
+//! collection is refused with `416 Range Not Satisfiable`, and other range headers are ignored.
+//!
+//! [RFC 9110]: https://www.rfc-editor.org/rfc/rfc9110#section-14
+
+use std::convert::Infallible;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::RANGE, request::Parts, HeaderValue},
+};
+
+use super::{
+    error::ApiError,
+    limit::{Limit, MAX_LIMIT},
+};
+
+/// The unit collections are ranged in, for `Accept-Ranges`
+pub const ITEMS: HeaderValue = HeaderValue::from_static("items");
+
+/// A range of items in a collection, counted from zero
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ItemRange {
+    first: i64,
+    last: Option<i64>,
+}
+
+impl ItemRange {
+    /// Parses a header such as `items=0-49` or `items=50-`
+    fn parse(header: &str) -> Option<Self> {
+        let (first, last) = header.strip_prefix("items=")?.split_once('-')?;
+
+        let first = first.trim().parse().ok().filter(|first| *first >= 0)?;
+        let last = match last.trim() {
+            "" => None,
+            last => Some(last.parse().ok().filter(|last| *last >= first)?),
+        };
+
+        Some(ItemRange { first, last })
+    }
+
+    /// Where the range starts and how many items it can cover, out of `total`
+    pub fn within(self, total: i64) -> Result<(i64, Limit), ApiError> {
+        if self.first >= total {
+            return Err(ApiError::RangeNotSatisfiable(total));
+        }
+
+        let last = self.last.unwrap_or(i64::MAX).min(total - 1);
+        let count = (last - self.first + 1).min(MAX_LIMIT);
+
+        Ok((self.first, Limit::new(Some(count))))
+    }
+}
+
+/// The `Content-Range` of `returned` items from `first`, out of `total`
+pub fn content_range(first: i64, returned: usize, total: i64) -> HeaderValue {
+    let last = first + returned as i64 - 1;
+    HeaderValue::from_str(&format!("items {first}-{last}/{total}")).unwrap()
+}
+
+/// The range of items requested, if any was understood
+pub struct RequestedRange(pub Option<ItemRange>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for RequestedRange
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let range = parts
+            .headers
+            .get(RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(ItemRange::parse);
+
+        Ok(RequestedRange(range))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{content_range, ItemRange};
+    use crate::http::limit::MAX_LIMIT;
+
+    #[test]
+    fn ranges_are_parsed() {
+        assert_eq!(
+            ItemRange::parse("items=0-49"),
+            Some(ItemRange {
+                first: 0,
+                last: Some(49)
+            })
+        );
+        assert_eq!(
+            ItemRange::parse("items=50-"),
+            Some(ItemRange {
+                first: 50,
+                last: None
+            })
+        );
+
+        for ignored in [
+            "bytes=0-49",
+            "items=-10",
+            "items=10-5",
+            "items=0-4,10-14",
+            "items",
+        ] {
+            assert_eq!(ItemRange::parse(ignored), None, "{ignored}");
+        }
+    }
+
+    #[test]
+    fn ranges_are_kept_within_the_collection() {
+        let range = ItemRange::parse("items=10-").unwrap();
+        let (first, limit) = range.within(25).unwrap();
+        assert_eq!((first, limit.get()), (10, 15));
+
+        let range = ItemRange::parse("items=0-1000000").unwrap();
+        let (_, limit) = range.within(1_000_000).unwrap();
+        assert_eq!(limit.get(), MAX_LIMIT);
+
+        assert!(ItemRange::parse("items=25-30").unwrap().within(25).is_err());
+    }
+
+    #[test]
+    fn content_ranges_give_the_items_returned() {
+        assert_eq!(content_range(10, 15, 25), "items 10-24/25");
+    }
+}
@@ -0,0 +1,275 @@
+use axum::{extract::Path, routing::get, Router};
+use hyper::{
+    header::{HeaderName, LOCATION},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{
+    auth::{ReadUser, WriteUser},
+    content::{Format, Link, Negotiated, Payload, Resource},
+    error::ApiError,
+    response::Deleted,
+    timestamp, v1,
+};
+use crate::service::relationship::RelationshipService;
+
+/// What the related person is to the person
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum RelationshipType {
+    /// The related person is the person's parent
+    Parent,
+    /// The related person is the person's legal guardian
+    Guardian,
+    /// They're married or civil partners, which goes both ways
+    Spouse,
+    /// They're brothers or sisters, which goes both ways
+    Sibling,
+    /// The related person is the person's next of kin
+    NextOfKin,
+}
+
+impl RelationshipType {
+    /// Whether the relationship goes one way up a line of people, which mustn't lead back to
+    /// where it started
+    pub fn is_lineal(self) -> bool {
+        matches!(self, RelationshipType::Parent | RelationshipType::Guardian)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NewRelationship {
+    /// The UUID of the related person, who must be someone other than the person
+    pub related_id: Uuid,
+    #[serde(rename = "type")]
+    pub relationship_type: RelationshipType,
+}
+
+/// A link between two people, read as the related person being the person's parent, guardian,
+/// spouse, sibling or next of kin
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Relationship {
+    pub id: Uuid,
+    pub person_id: Uuid,
+    pub related_id: Uuid,
+    #[serde(rename = "type")]
+    pub relationship_type: RelationshipType,
+    /// The client who linked them
+    pub created_by: String,
+    /// When they were linked, as an RFC 3339 timestamp in UTC, or milliseconds since the Unix
+    /// epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub created: OffsetDateTime,
+}
+
+impl Resource for Relationship {
+    const ELEMENT: &'static str = "relationship";
+    const COLLECTION: &'static str = "relationships";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        let relationship = format!(
+            "{}/person/{}/relationships/{}",
+            v1::PREFIX,
+            self.person_id,
+            self.id
+        );
+
+        vec![
+            ("self", Link::to(&relationship)),
+            ("delete", Link::to(&relationship)),
+        ]
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        let person = format!("{}/person/{}", v1::PREFIX, self.person_id);
+        let related = format!("{}/person/{}", v1::PREFIX, self.related_id);
+
+        vec![("person", Link::to(person)), ("related", Link::to(related))]
+    }
+}
+
+/// List a person's relationships
+///
+/// Gives the links made from either side, so a person's relationships include those where
+/// they're the `relatedId`, such as to the children naming them as their parent. Links to
+/// people who have been deleted are left out.
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "relationship",
+    path = "/person/{person_uuid}/relationships",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "The person's relationships, first made first", body = [Relationship]),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn list_relationships(
+    user: ReadUser,
+    relationships: RelationshipService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Negotiated<Vec<Relationship>>, ApiError> {
+    let relationships = relationships.list(person_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved {} relationship(s) of person '{}'",
+        user.username,
+        relationships.len(),
+        person_uuid
+    );
+
+    Ok(Negotiated(format, relationships))
+}
+
+/// Link a person to someone related to them
+///
+/// The related person is the person's `parent`, `guardian`, `spouse`, `sibling` or
+/// `next_of_kin`, spouses and siblings being linked both ways at once. A person can't be
+/// related to themselves, and parents and guardians can't lead back round to the person, such
+/// as a person's child being named as their parent.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    post,
+    tag = "relationship",
+    path = "/person/{person_uuid}/relationships",
+    request_body = NewRelationship,
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 201, description = "Relationship added successfully", body = Relationship,
+            headers(("location" = String, description = "The URL of the added relationship"))),
+        (status = 400, description = "Invalid request, or the person was related to themselves", body = ErrorResponse),
+        (status = 404, description = "Person or related person not found", body = ErrorResponse),
+        (status = 409, description = "They're already related this way, or the relationship would form a cycle", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn add_relationship(
+    user: WriteUser,
+    relationships: RelationshipService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+    Payload(request): Payload<NewRelationship>,
+) -> Result<
+    (
+        StatusCode,
+        [(HeaderName, String); 1],
+        Negotiated<Relationship>,
+    ),
+    ApiError,
+> {
+    let relationship = relationships
+        .add(&user.username, person_uuid, &request)
+        .await?;
+
+    let location = format!(
+        "{}/person/{person_uuid}/relationships/{}",
+        v1::PREFIX,
+        relationship.id
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        [(LOCATION, location)],
+        Negotiated(format, relationship),
+    ))
+}
+
+/// Get one of a person's relationships
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "relationship",
+    path = "/person/{person_uuid}/relationships/{relationship_uuid}",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of either person"),
+        ("relationship_uuid" = Uuid, Path, description = "The UUID of the relationship")
+    ),
+    responses(
+        (status = 200, description = "The relationship matching the given UUID", body = Relationship),
+        (status = 404, description = "Relationship not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn get_relationship(
+    user: ReadUser,
+    relationships: RelationshipService,
+    format: Format,
+    Path((person_uuid, relationship_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Negotiated<Relationship>, ApiError> {
+    let relationship = relationships.find(person_uuid, relationship_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved relationship '{}'",
+        user.username, relationship_uuid
+    );
+
+    Ok(Negotiated(format, relationship))
+}
+
+/// Remove one of a person's relationships
+///
+/// Either of the people can be given, the relationship being removed for both.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    delete,
+    tag = "relationship",
+    path = "/person/{person_uuid}/relationships/{relationship_uuid}",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of either person"),
+        ("relationship_uuid" = Uuid, Path, description = "The UUID of the relationship")
+    ),
+    responses(
+        (status = 204, description = "Relationship removed successfully"),
+        (status = 404, description = "Relationship not found", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn remove_relationship(
+    user: WriteUser,
+    relationships: RelationshipService,
+    Path((person_uuid, relationship_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Deleted, ApiError> {
+    relationships
+        .remove(&user.username, person_uuid, relationship_uuid)
+        .await?;
+
+    Ok(Deleted)
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/person/:person_uuid/relationships",
+            get(list_relationships).post(add_relationship),
+        )
+        .route(
+            "/person/:person_uuid/relationships/:relationship_uuid",
+            get(get_relationship).delete(remove_relationship),
+        )
+}
@@ -0,0 +1,16 @@
+//! Responses shared by handlers across the resources.
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+/// The resource was deleted, answered with an empty `204 No Content`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Deleted;
+
+impl IntoResponse for Deleted {
+    fn into_response(self) -> Response {
+        StatusCode::NO_CONTENT.into_response()
+    }
+}
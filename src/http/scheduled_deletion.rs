@@ -0,0 +1,167 @@
+use axum::{extract::Path, routing::post, Router};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::{
+    auth::{ReadUser, WriteUser},
+    content::{Format, Link, Negotiated, Resource, ValidatedPayload},
+    error::ApiError,
+    fields,
+    response::Deleted,
+    timestamp, v1,
+};
+use crate::service::scheduled_deletion::ScheduledDeletionService;
+
+#[derive(Debug, Validate, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleDeletion {
+    /// When the person should be deleted, which must be in the future
+    #[serde(with = "timestamp")]
+    #[validate(custom(function = "fields::in_the_future"))]
+    pub delete_at: OffsetDateTime,
+}
+
+/// When a person is due to be deleted
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledDeletion {
+    pub person_id: Uuid,
+    /// When the person will be deleted, as an RFC 3339 timestamp in UTC, or milliseconds since
+    /// the Unix epoch when the service is configured to use them
+    #[serde(with = "timestamp")]
+    pub delete_at: OffsetDateTime,
+}
+
+impl Resource for ScheduledDeletion {
+    const ELEMENT: &'static str = "scheduledDeletion";
+    const COLLECTION: &'static str = "scheduledDeletions";
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        let deletion = format!("{}/person/{}/schedule-deletion", v1::PREFIX, self.person_id);
+
+        vec![
+            ("self", Link::to(&deletion)),
+            ("cancel", Link::to(&deletion)),
+        ]
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        let person = format!("{}/person/{}", v1::PREFIX, self.person_id);
+
+        vec![("person", Link::to(person))]
+    }
+}
+
+/// Get when a person is due to be deleted
+///
+/// Requires the scope `read`
+#[utoipa::path(
+    get,
+    tag = "person",
+    path = "/person/{person_uuid}/schedule-deletion",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "When the person will be deleted", body = ScheduledDeletion),
+        (status = 404, description = "Person not found, or not scheduled to be deleted", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn get_scheduled_deletion(
+    user: ReadUser,
+    deletions: ScheduledDeletionService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Negotiated<ScheduledDeletion>, ApiError> {
+    let deletion = deletions.find(person_uuid).await?;
+
+    info!(
+        "Client '{}' retrieved the scheduled deletion of person '{}'",
+        user.username, person_uuid
+    );
+
+    Ok(Negotiated(format, deletion))
+}
+
+/// Schedule a person to be deleted
+///
+/// The person is deleted shortly after the given time, unless the deletion is cancelled first.
+/// Scheduling them again replaces the time. People under legal hold can't be scheduled, and
+/// anyone placed under one afterwards is only deleted once it has been lifted.
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    post,
+    tag = "person",
+    path = "/person/{person_uuid}/schedule-deletion",
+    request_body = ScheduleDeletion,
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 200, description = "Deletion scheduled successfully", body = ScheduledDeletion),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Person not found", body = ErrorResponse),
+        (status = 423, description = "The person is under legal hold", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn schedule_deletion(
+    user: WriteUser,
+    deletions: ScheduledDeletionService,
+    format: Format,
+    Path(person_uuid): Path<Uuid>,
+    ValidatedPayload(request): ValidatedPayload<ScheduleDeletion>,
+) -> Result<Negotiated<ScheduledDeletion>, ApiError> {
+    let deletion = deletions
+        .schedule(&user.username, person_uuid, &request)
+        .await?;
+
+    Ok(Negotiated(format, deletion))
+}
+
+/// Cancel a person's scheduled deletion
+///
+/// Requires the scope `write`
+#[utoipa::path(
+    delete,
+    tag = "person",
+    path = "/person/{person_uuid}/schedule-deletion",
+    params(
+        ("person_uuid" = Uuid, Path, description = "The UUID of the person")
+    ),
+    responses(
+        (status = 204, description = "Deletion cancelled successfully"),
+        (status = 404, description = "Person not found, or not scheduled to be deleted", body = ErrorResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn cancel_deletion(
+    user: WriteUser,
+    deletions: ScheduledDeletionService,
+    Path(person_uuid): Path<Uuid>,
+) -> Result<Deleted, ApiError> {
+    deletions.cancel(&user.username, person_uuid).await?;
+
+    Ok(Deleted)
+}
+
+pub fn router() -> Router {
+    Router::new().route(
+        "/person/:person_uuid/schedule-deletion",
+        post(schedule_deletion)
+            .get(get_scheduled_deletion)
+            .delete(cancel_deletion),
+    )
+}
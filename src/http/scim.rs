@@ -0,0 +1,788 @@
+//! SCIM 2.0 provisioning of people as `User` resources (RFC 7643 and RFC 7644).
+//!
+//! Identity providers can create, read, filter, patch and delete people under
+//! `/scim/v2/Users`. The core `userName`, `externalId` and `name` attributes map onto the
+//! person, while the date of birth, which SCIM has no attribute for, is carried in this
+//! service's own extension schema. Errors are reported in the SCIM error format.
+//!
+//! Creating a user with `If-None-Match: *` only creates it if no user has the same `userName`
+//! or `externalId`, answering `412 Precondition Failed` otherwise, so a provisioning request
+//! that timed out can be retried without mistaking the first attempt's user for a clash.
+use std::mem;
+
+use axum::{
+    extract::{rejection::JsonRejection, Path, Query},
+    http::{
+        header::{CONTENT_TYPE, IF_NONE_MATCH, LOCATION},
+        HeaderMap,
+    },
+    middleware,
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Json, Router,
+};
+use axum_extra::extract::WithRejection;
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use time::{format_description::well_known::Iso8601, Date, OffsetDateTime};
+use tracing::info;
+use uuid::Uuid;
+
+use super::{
+    auth::{ReadUser, WriteUser},
+    cache,
+    cache_control::{self, CachePolicy},
+    error::{ApiError, Context},
+    fields::{DateOfBirth, PersonName},
+    person::Person,
+    precondition::IfMatch,
+};
+use crate::{
+    clock,
+    events::Event,
+    outbox,
+    service::{person::PersonService, person_event, person_history},
+};
+
+const PREFIX: &str = "/scim/v2";
+const CONTENT_TYPE_SCIM: &str = "application/scim+json";
+const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+const EXTENSION_SCHEMA: &str = "urn:ietf:params:scim:schemas:extension:rust-web-app:2.0:User";
+const LIST_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+const MAX_COUNT: i64 = 200;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ScimError {
+    #[error("{detail}")]
+    BadRequest {
+        scim_type: &'static str,
+        detail: String,
+    },
+    #[error("{0}")]
+    Uniqueness(String),
+    #[error("{0}")]
+    PreconditionFailed(String),
+    #[error("{0}")]
+    Api(#[from] ApiError),
+}
+
+impl ScimError {
+    fn invalid_value(detail: impl Into<String>) -> Self {
+        ScimError::BadRequest {
+            scim_type: "invalidValue",
+            detail: detail.into(),
+        }
+    }
+
+    fn invalid_filter(detail: impl Into<String>) -> Self {
+        ScimError::BadRequest {
+            scim_type: "invalidFilter",
+            detail: detail.into(),
+        }
+    }
+
+    fn invalid_path(path: &str) -> Self {
+        ScimError::BadRequest {
+            scim_type: "invalidPath",
+            detail: format!("Unsupported attribute path: {path}"),
+        }
+    }
+}
+
+impl From<JsonRejection> for ScimError {
+    fn from(rejection: JsonRejection) -> Self {
+        ScimError::Api(ApiError::from(rejection))
+    }
+}
+
+/// Maps a failed write to a `uniqueness` error when it clashes with another person
+fn from_write_error(e: sqlx::Error) -> ScimError {
+    match e {
+        sqlx::Error::Database(dbe) if dbe.is_unique_violation() => ScimError::Uniqueness(
+            "A user with the same userName or externalId already exists".to_owned(),
+        ),
+        _ => ScimError::Api(ApiError::from(e).context("Failed to write a user")),
+    }
+}
+
+impl IntoResponse for ScimError {
+    fn into_response(self) -> Response {
+        let (status, scim_type) = match &self {
+            ScimError::BadRequest { scim_type, .. } => (StatusCode::BAD_REQUEST, Some(*scim_type)),
+            ScimError::Uniqueness(_) => (StatusCode::CONFLICT, Some("uniqueness")),
+            ScimError::PreconditionFailed(_) => (StatusCode::PRECONDITION_FAILED, None),
+            ScimError::Api(ApiError::ValidationError(_)) => {
+                (StatusCode::BAD_REQUEST, Some("invalidValue"))
+            }
+            ScimError::Api(e) => {
+                e.log();
+                (e.status_code(), None)
+            }
+        };
+
+        scim_json(
+            status,
+            json!({
+                "schemas": [ERROR_SCHEMA],
+                "status": status.as_u16().to_string(),
+                "scimType": scim_type,
+                "detail": self.to_string(),
+            }),
+        )
+    }
+}
+
+fn scim_json(status: StatusCode, body: impl Serialize) -> Response {
+    (status, [(CONTENT_TYPE, CONTENT_TYPE_SCIM)], Json(body)).into_response()
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct UserRow {
+    id: Uuid,
+    user_name: Option<String>,
+    external_id: Option<String>,
+    first_name: String,
+    family_name: String,
+    date_of_birth: Date,
+    created: OffsetDateTime,
+    last_edited: OffsetDateTime,
+    version: i32,
+}
+
+impl UserRow {
+    fn person(&self) -> Person {
+        Person {
+            id: self.id,
+            first_name: self.first_name.clone(),
+            family_name: self.family_name.clone(),
+            date_of_birth: self.date_of_birth,
+            created: self.created,
+            last_edited: self.last_edited,
+            version: self.version,
+        }
+    }
+
+    /// Checks the user's details, normalizing their names as the API does
+    fn validate(&mut self) -> Result<(), ScimError> {
+        if self.user_name.as_deref().is_some_and(str::is_empty) {
+            return Err(ScimError::invalid_value("userName must not be empty"));
+        }
+
+        self.first_name = PersonName::try_from(mem::take(&mut self.first_name))
+            .map_err(|e| ScimError::invalid_value(format!("name.givenName {e}")))?
+            .into();
+        self.family_name = PersonName::try_from(mem::take(&mut self.family_name))
+            .map_err(|e| ScimError::invalid_value(format!("name.familyName {e}")))?
+            .into();
+        DateOfBirth::try_from(self.date_of_birth)
+            .map_err(|e| ScimError::invalid_value(format!("dateOfBirth {e}")))?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Name {
+    pub given_name: String,
+    pub family_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonExtension {
+    pub date_of_birth: Date,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Meta {
+    pub resource_type: &'static str,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_modified: OffsetDateTime,
+    pub location: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimUser {
+    pub schemas: [&'static str; 2],
+    pub id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    pub user_name: String,
+    pub name: Name,
+    #[serde(rename = "urn:ietf:params:scim:schemas:extension:rust-web-app:2.0:User")]
+    pub extension: PersonExtension,
+    pub meta: Meta,
+}
+
+impl From<UserRow> for ScimUser {
+    fn from(row: UserRow) -> Self {
+        ScimUser {
+            schemas: [USER_SCHEMA, EXTENSION_SCHEMA],
+            id: row.id,
+            external_id: row.external_id,
+            // people created outside of SCIM have no userName, so are known by their UUID
+            user_name: row.user_name.unwrap_or_else(|| row.id.to_string()),
+            name: Name {
+                given_name: row.first_name,
+                family_name: row.family_name,
+            },
+            extension: PersonExtension {
+                date_of_birth: row.date_of_birth,
+            },
+            meta: Meta {
+                resource_type: "User",
+                created: row.created,
+                last_modified: row.last_edited,
+                location: format!("{PREFIX}/Users/{}", row.id),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewUser {
+    pub user_name: String,
+    pub external_id: Option<String>,
+    pub name: Name,
+    #[serde(rename = "urn:ietf:params:scim:schemas:extension:rust-web-app:2.0:User")]
+    pub extension: PersonExtension,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatchRequest {
+    #[serde(rename = "Operations")]
+    pub operations: Vec<PatchOperation>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatchOperation {
+    pub op: String,
+    pub path: Option<String>,
+    pub value: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListQuery {
+    filter: Option<String>,
+    start_index: Option<i64>,
+    count: Option<i64>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Operator {
+    Equal,
+    Contains,
+    StartsWith,
+}
+
+#[derive(Debug, PartialEq)]
+struct Comparison {
+    column: &'static str,
+    case_exact: bool,
+    operator: Operator,
+    value: String,
+}
+
+/// Splits a filter into words, with quoted strings kept whole and marked by a leading `"`
+fn tokenize(filter: &str) -> Result<Vec<String>, ScimError> {
+    let mut tokens = vec![];
+    let mut chars = filter.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut token = String::from('"');
+            loop {
+                match chars.next() {
+                    Some('\\') => token.extend(chars.next()),
+                    Some('"') => break,
+                    Some(c) => token.push(c),
+                    None => return Err(ScimError::invalid_filter("Unterminated string")),
+                }
+            }
+            tokens.push(token);
+        } else {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek().filter(|c| !c.is_whitespace()) {
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parses the supported subset of SCIM filters: `eq`, `co` and `sw` comparisons against
+/// `id`, `userName`, `externalId`, `name.givenName` and `name.familyName`, joined by `and`
+fn parse_filter(filter: &str) -> Result<Vec<Comparison>, ScimError> {
+    let tokens = tokenize(filter)?;
+    let mut comparisons = vec![];
+
+    for (i, clause) in tokens.split(|t| t.eq_ignore_ascii_case("and")).enumerate() {
+        let [attribute, operator, value] = clause else {
+            return Err(ScimError::invalid_filter(format!(
+                "Expected a comparison such as userName eq \"jdoe\" in clause {}",
+                i + 1
+            )));
+        };
+
+        let (column, case_exact) = match attribute.to_lowercase().as_str() {
+            "id" => ("uuid::TEXT", true),
+            "username" => ("user_name", false),
+            "externalid" => ("external_id", true),
+            "name.givenname" => ("first_name", false),
+            "name.familyname" => ("family_name", false),
+            _ => {
+                return Err(ScimError::invalid_filter(format!(
+                    "Filtering on {attribute} is not supported"
+                )))
+            }
+        };
+
+        let operator = match operator.to_lowercase().as_str() {
+            "eq" => Operator::Equal,
+            "co" => Operator::Contains,
+            "sw" => Operator::StartsWith,
+            _ => {
+                return Err(ScimError::invalid_filter(format!(
+                    "Unsupported operator: {operator}"
+                )))
+            }
+        };
+
+        let Some(value) = value.strip_prefix('"') else {
+            return Err(ScimError::invalid_filter(format!(
+                "Expected a quoted string to compare {attribute} with"
+            )));
+        };
+
+        comparisons.push(Comparison {
+            column,
+            case_exact,
+            operator,
+            value: value.to_owned(),
+        });
+    }
+
+    Ok(comparisons)
+}
+
+fn push_filter(builder: &mut QueryBuilder<'_, Postgres>, comparisons: &[Comparison]) {
+    builder.push(" WHERE deleted_at IS NULL");
+
+    for comparison in comparisons {
+        let column = if comparison.case_exact {
+            comparison.column.to_owned()
+        } else {
+            format!("lower({})", comparison.column)
+        };
+        let value = comparison.value.clone();
+
+        builder.push(" AND ");
+        match comparison.operator {
+            Operator::Equal => builder.push(format!("{column} = ")),
+            Operator::Contains => builder.push(format!("strpos({column}, ")),
+            Operator::StartsWith => builder.push(format!("starts_with({column}, ")),
+        };
+
+        if comparison.case_exact {
+            builder.push_bind(value);
+        } else {
+            builder.push("lower(").push_bind(value).push(")");
+        }
+
+        match comparison.operator {
+            Operator::Equal => {}
+            Operator::Contains => {
+                builder.push(") > 0");
+            }
+            Operator::StartsWith => {
+                builder.push(")");
+            }
+        }
+    }
+}
+
+fn string_value(path: &str, value: &Value) -> Result<String, ScimError> {
+    value
+        .as_str()
+        .map(str::to_owned)
+        .ok_or_else(|| ScimError::invalid_value(format!("{path} must be a string")))
+}
+
+/// Sets the attribute at `path`, or each attribute of `value` when it names a complex attribute
+fn set_attribute(user: &mut UserRow, path: &str, value: &Value) -> Result<(), ScimError> {
+    let extension = EXTENSION_SCHEMA.to_lowercase();
+
+    match path.to_lowercase().as_str() {
+        "username" => user.user_name = Some(string_value(path, value)?),
+        "externalid" => user.external_id = Some(string_value(path, value)?),
+        "name.givenname" => user.first_name = string_value(path, value)?,
+        "name.familyname" => user.family_name = string_value(path, value)?,
+        p if p == format!("{extension}:dateofbirth") => {
+            user.date_of_birth = Date::parse(&string_value(path, value)?, &Iso8601::DATE)
+                .map_err(|_| ScimError::invalid_value("dateOfBirth must be a date"))?
+        }
+        p if p == "name" || p == extension => {
+            let Value::Object(attributes) = value else {
+                return Err(ScimError::invalid_value(format!(
+                    "{path} must be an object"
+                )));
+            };
+            for (name, value) in attributes {
+                let separator = if p == "name" { '.' } else { ':' };
+                set_attribute(user, &format!("{path}{separator}{name}"), value)?;
+            }
+        }
+        _ => return Err(ScimError::invalid_path(path)),
+    }
+
+    Ok(())
+}
+
+fn apply(user: &mut UserRow, operation: &PatchOperation) -> Result<(), ScimError> {
+    match operation.op.to_lowercase().as_str() {
+        "add" | "replace" => {
+            let value = operation
+                .value
+                .as_ref()
+                .ok_or_else(|| ScimError::invalid_value("A value is required"))?;
+
+            match &operation.path {
+                Some(path) => set_attribute(user, path, value),
+                None => {
+                    let Value::Object(attributes) = value else {
+                        return Err(ScimError::invalid_value(
+                            "A value without a path must be an object",
+                        ));
+                    };
+                    attributes
+                        .iter()
+                        .try_for_each(|(name, value)| set_attribute(user, name, value))
+                }
+            }
+        }
+        "remove" => match operation.path.as_deref() {
+            Some(path) if path.eq_ignore_ascii_case("externalId") => {
+                user.external_id = None;
+                Ok(())
+            }
+            Some(path) => Err(ScimError::BadRequest {
+                scim_type: "mutability",
+                detail: format!("{path} is required so cannot be removed"),
+            }),
+            None => Err(ScimError::BadRequest {
+                scim_type: "noTarget",
+                detail: "A path is required to remove an attribute".to_owned(),
+            }),
+        },
+        op => Err(ScimError::invalid_value(format!(
+            "Unsupported operation: {op}"
+        ))),
+    }
+}
+
+async fn find_user(db: &PgPool, user_id: Uuid) -> Result<UserRow, ScimError> {
+    sqlx::query_as!(
+        UserRow,
+        r#"
+            SELECT uuid AS id, user_name, external_id, first_name, family_name, date_of_birth, created, last_edited, version
+            FROM person WHERE uuid = $1 AND deleted_at IS NULL;
+        "#,
+        user_id
+    )
+    .fetch_optional(db)
+    .await
+    .with_context(|| format!("Failed to find user '{user_id}'"))?
+    .ok_or_else(|| ApiError::NotFound(format!("User not found for the id: {user_id}")).into())
+}
+
+/// Whether the client asked for the user to be created only if it doesn't exist yet
+fn create_only(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .any(|value| value.as_bytes().trim_ascii() == b"*")
+}
+
+async fn create_user(
+    user: WriteUser,
+    db: Extension<PgPool>,
+    headers: HeaderMap,
+    WithRejection(Json(request), _): WithRejection<Json<NewUser>, ScimError>,
+) -> Result<Response, ScimError> {
+    let mut tx = db.begin().await.map_err(ApiError::from)?;
+    let now = clock::now();
+
+    let mut created = UserRow {
+        id: Uuid::nil(),
+        user_name: Some(request.user_name),
+        external_id: request.external_id,
+        first_name: request.name.given_name,
+        family_name: request.name.family_name,
+        date_of_birth: request.extension.date_of_birth,
+        created: now,
+        last_edited: now,
+        version: 1,
+    };
+    created.validate()?;
+
+    let created = sqlx::query_as!(
+        UserRow,
+        r#"
+            INSERT INTO person (user_name, external_id, first_name, family_name, date_of_birth, created, last_edited)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING uuid AS id, user_name, external_id, first_name, family_name, date_of_birth, created, last_edited, version;
+        "#,
+        created.user_name,
+        created.external_id,
+        created.first_name,
+        created.family_name,
+        created.date_of_birth,
+        created.created,
+        created.last_edited
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| match from_write_error(e) {
+        ScimError::Uniqueness(_) if create_only(&headers) => ScimError::PreconditionFailed(
+            "A user with the same userName or externalId already exists".to_owned(),
+        ),
+        e => e,
+    })?;
+
+    let event = Event::PersonCreated {
+        person: created.person(),
+    };
+    person_event::append(&mut tx, &user.username, &event).await?;
+    person_history::record(&mut tx, &user.username, &event).await?;
+    outbox::enqueue(&mut tx, &event)
+        .await
+        .context("Failed to queue the person created event")?;
+
+    tx.commit().await.map_err(ApiError::from)?;
+
+    info!(
+        "Client '{}' provisioned person '{}' via SCIM",
+        user.username, created.id
+    );
+
+    let location = format!("{PREFIX}/Users/{}", created.id);
+    let mut response = scim_json(StatusCode::CREATED, ScimUser::from(created));
+    response
+        .headers_mut()
+        .insert(LOCATION, location.parse().unwrap());
+
+    Ok(response)
+}
+
+async fn get_user(
+    user: ReadUser,
+    db: Extension<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Response, ScimError> {
+    let found = find_user(&db, user_id).await?;
+
+    info!(
+        "Client '{}' retrieved person '{}' via SCIM",
+        user.username, user_id
+    );
+
+    Ok(scim_json(StatusCode::OK, ScimUser::from(found)))
+}
+
+async fn list_users(
+    user: ReadUser,
+    db: Extension<PgPool>,
+    Query(query): Query<ListQuery>,
+) -> Result<Response, ScimError> {
+    let comparisons = match &query.filter {
+        Some(filter) => parse_filter(filter)?,
+        None => vec![],
+    };
+    let start_index = query.start_index.unwrap_or(1).max(1);
+    let count = query.count.unwrap_or(100).clamp(0, MAX_COUNT);
+
+    let mut total = QueryBuilder::new("SELECT count(*) FROM person");
+    push_filter(&mut total, &comparisons);
+    let total: i64 = total
+        .build_query_scalar()
+        .fetch_one(&*db)
+        .await
+        .context("Failed to count users")?;
+
+    let mut page = QueryBuilder::new(
+        "SELECT uuid AS id, user_name, external_id, first_name, family_name, date_of_birth, \
+         created, last_edited, version FROM person",
+    );
+    push_filter(&mut page, &comparisons);
+    page.push(" ORDER BY created, id LIMIT ")
+        .push_bind(count)
+        .push(" OFFSET ")
+        .push_bind(start_index - 1);
+
+    let users: Vec<ScimUser> = page
+        .build_query_as::<UserRow>()
+        .fetch_all(&*db)
+        .await
+        .context("Failed to list users")?
+        .into_iter()
+        .map(ScimUser::from)
+        .collect();
+
+    info!(
+        "Client '{}' retrieved {} of {} person(s) via SCIM",
+        user.username,
+        users.len(),
+        total
+    );
+
+    Ok(scim_json(
+        StatusCode::OK,
+        json!({
+            "schemas": [LIST_SCHEMA],
+            "totalResults": total,
+            "startIndex": start_index,
+            "itemsPerPage": users.len(),
+            "Resources": users,
+        }),
+    ))
+}
+
+async fn patch_user(
+    user: WriteUser,
+    db: Extension<PgPool>,
+    Path(user_id): Path<Uuid>,
+    WithRejection(Json(request), _): WithRejection<Json<PatchRequest>, ScimError>,
+) -> Result<Response, ScimError> {
+    let mut patched = find_user(&db, user_id).await?;
+
+    for operation in &request.operations {
+        apply(&mut patched, operation)?;
+    }
+    patched.validate()?;
+
+    let mut tx = db.begin().await.map_err(ApiError::from)?;
+
+    let updated = sqlx::query_as!(
+        UserRow,
+        r#"
+            UPDATE person SET user_name = $1, external_id = $2, first_name = $3, family_name = $4,
+                date_of_birth = $5, last_edited = $6, version = version + 1
+            WHERE uuid = $7 AND deleted_at IS NULL
+            RETURNING uuid AS id, user_name, external_id, first_name, family_name, date_of_birth, created, last_edited, version;
+        "#,
+        patched.user_name,
+        patched.external_id,
+        patched.first_name,
+        patched.family_name,
+        patched.date_of_birth,
+        clock::now(),
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(from_write_error)?
+    .ok_or_else(|| ApiError::NotFound(format!("User not found for the id: {user_id}")))?;
+
+    let event = Event::PersonUpdated {
+        person: updated.person(),
+    };
+    person_event::append(&mut tx, &user.username, &event).await?;
+    person_history::record(&mut tx, &user.username, &event).await?;
+    outbox::enqueue(&mut tx, &event)
+        .await
+        .context("Failed to queue the person updated event")?;
+
+    tx.commit().await.map_err(ApiError::from)?;
+    cache::invalidate(user_id);
+
+    info!(
+        "Client '{}' patched person '{}' via SCIM",
+        user.username, user_id
+    );
+
+    Ok(scim_json(StatusCode::OK, ScimUser::from(updated)))
+}
+
+async fn delete_user(
+    user: WriteUser,
+    db: Extension<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<StatusCode, ScimError> {
+    PersonService::new(db.0)
+        .delete(&user.username, user_id, &IfMatch::Any)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/scim/v2/Users", get(list_users).post(create_user))
+        .route(
+            "/scim/v2/Users/:user_id",
+            get(get_user).patch(patch_user).delete(delete_user),
+        )
+        .layer(middleware::from_fn_with_state(
+            CachePolicy::private_from_env(),
+            cache_control::apply,
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_filter, Comparison, Operator};
+
+    #[test]
+    fn filters_are_parsed_into_comparisons() {
+        let comparisons =
+            parse_filter(r#"userName eq "jdoe@example.com" and name.familyName sw "D\"o""#)
+                .unwrap();
+
+        assert_eq!(
+            comparisons,
+            [
+                Comparison {
+                    column: "user_name",
+                    case_exact: false,
+                    operator: Operator::Equal,
+                    value: "jdoe@example.com".to_owned(),
+                },
+                Comparison {
+                    column: "family_name",
+                    case_exact: false,
+                    operator: Operator::StartsWith,
+                    value: "D\"o".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported_filters_are_rejected() {
+        for filter in [
+            r#"title eq "Manager""#,
+            r#"userName gt "a""#,
+            "userName eq jdoe",
+            r#"userName eq "jdoe"#,
+            r#"userName eq "jdoe" or userName eq "jane""#,
+        ] {
+            assert!(parse_filter(filter).is_err(), "{filter} should be rejected");
+        }
+    }
+}
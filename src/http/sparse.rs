@@ -0,0 +1,179 @@
+//! Sparse fieldsets, so clients can ask for only the fields they need with `?fields=`, such as
+//! `fields=id,firstName,familyName`.
+//!
+//! The fields are taken from each resource as it's serialized, so every format gets the same
+//! fields. `id` is always kept, as links and JSON:API identify resources by it.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use utoipa::IntoParams;
+use validator::Validate;
+
+use super::{
+    content::{Link, Resource},
+    error::ApiError,
+};
+
+/// The longest list of fields accepted
+const MAX_LENGTH: u64 = 500;
+
+#[derive(Debug, Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct FieldsQuery {
+    /// Only return these fields of each person, separated by commas, such as
+    /// `id,firstName,familyName`
+    #[param(max_length = 500)]
+    #[validate(length(max = MAX_LENGTH))]
+    fields: Option<String>,
+}
+
+impl FieldsQuery {
+    /// The fields asked for, which must all be `known`
+    pub fn parse(&self, known: &[&str]) -> Result<Option<Fields>, ApiError> {
+        let Some(fields) = self.fields.as_deref() else {
+            return Ok(None);
+        };
+
+        let fields: Vec<_> = fields
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(str::to_owned)
+            .collect();
+
+        let unknown: Vec<_> = fields
+            .iter()
+            .filter(|field| !known.contains(&field.as_str()))
+            .cloned()
+            .collect();
+        if !unknown.is_empty() {
+            return Err(ApiError::UnknownFields(unknown));
+        }
+
+        Ok(Some(Fields(fields.into())))
+    }
+}
+
+/// The fields of a resource to return
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fields(Arc<[String]>);
+
+impl Fields {
+    fn keeps(&self, field: &str) -> bool {
+        field == "id" || self.0.iter().any(|kept| kept == field)
+    }
+}
+
+/// A resource with only the fields asked for, or all of them when none were
+#[derive(Debug)]
+pub struct Sparse<T> {
+    pub resource: T,
+    pub fields: Option<Fields>,
+}
+
+impl<T> Sparse<T> {
+    pub fn new(resource: T, fields: &Option<Fields>) -> Self {
+        Sparse {
+            resource,
+            fields: fields.clone(),
+        }
+    }
+
+    /// Each of the resources with the same fields
+    pub fn each(resources: Vec<T>, fields: &Option<Fields>) -> Vec<Self> {
+        resources
+            .into_iter()
+            .map(|resource| Sparse::new(resource, fields))
+            .collect()
+    }
+}
+
+impl<T: Serialize> Serialize for Sparse<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Some(fields) = &self.fields else {
+            return self.resource.serialize(serializer);
+        };
+
+        let mut value = serde_json::to_value(&self.resource).map_err(serde::ser::Error::custom)?;
+        if let Value::Object(resource) = &mut value {
+            resource.retain(|field, _| fields.keeps(field));
+        }
+
+        value.serialize(serializer)
+    }
+}
+
+impl<T: Resource> Resource for Sparse<T> {
+    const ELEMENT: &'static str = T::ELEMENT;
+    const COLLECTION: &'static str = T::COLLECTION;
+
+    fn links(&self) -> Vec<(&'static str, Link)> {
+        self.resource.links()
+    }
+
+    fn relationships(&self) -> Vec<(&'static str, Link)> {
+        self.resource.relationships()
+    }
+}
+
+/// Applies the fields to each resource as it's received, for streaming
+pub fn stream<T>(
+    mut resources: mpsc::Receiver<Result<T, sqlx::Error>>,
+    fields: Option<Fields>,
+) -> mpsc::Receiver<Result<Sparse<T>, sqlx::Error>>
+where
+    T: Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel(1);
+
+    tokio::spawn(async move {
+        while let Some(resource) = resources.recv().await {
+            let resource = resource.map(|resource| Sparse::new(resource, &fields));
+            if sender.send(resource).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{FieldsQuery, Sparse};
+
+    #[test]
+    fn only_the_fields_asked_for_are_kept() {
+        let query = FieldsQuery {
+            fields: Some("firstName, familyName".to_owned()),
+        };
+        let fields = query
+            .parse(&["firstName", "familyName", "created"])
+            .unwrap();
+        let person = json!({"id": 1, "firstName": "Ada", "familyName": "Byron", "created": 2});
+
+        assert_eq!(
+            serde_json::to_value(Sparse::new(person.clone(), &fields)).unwrap(),
+            json!({"id": 1, "firstName": "Ada", "familyName": "Byron"}),
+            "The id is always kept"
+        );
+        assert_eq!(
+            serde_json::to_value(Sparse::new(person.clone(), &None)).unwrap(),
+            person
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let query = FieldsQuery {
+            fields: Some("firstName,height".to_owned()),
+        };
+
+        assert!(query.parse(&["firstName"]).is_err());
+    }
+}
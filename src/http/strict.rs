@@ -0,0 +1,157 @@
+//! Strict request validation, rejecting bodies with fields the endpoint doesn't know.
+//!
+//! Unknown fields are ignored by default, so a typo such as `familyname` silently leaves the
+//! field unset. With `STRICT_REQUESTS=true`, bodies naming a field their type doesn't have are
+//! rejected with a `400` listing them instead, and the OpenAPI schemas of request bodies say
+//! `additionalProperties: false`. Only the top level of a body is checked.
+
+use std::{env, sync::OnceLock};
+
+use serde::{
+    de::{self, DeserializeOwned, Visitor},
+    forward_to_deserialize_any, Deserializer,
+};
+use serde_json::Value;
+use utoipa::{
+    openapi::{schema::AdditionalProperties, OpenApi, RefOr, Schema},
+    Modify,
+};
+
+use super::error::ApiError;
+
+/// Whether `STRICT_REQUESTS` is `true`
+pub fn enabled() -> bool {
+    static STRICT: OnceLock<bool> = OnceLock::new();
+
+    *STRICT.get_or_init(|| env::var("STRICT_REQUESTS").is_ok_and(|v| v == "true"))
+}
+
+/// Learns the fields of a struct from its `Deserialize` implementation, which hands them to
+/// `deserialize_struct`, aliases included
+struct FieldsProbe<'a>(&'a mut Option<&'static [&'static str]>);
+
+impl<'de> Deserializer<'de> for FieldsProbe<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = Some(fields);
+        Err(de::Error::custom("probed"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+/// The fields of the body which `T` doesn't have. Types which aren't plain structs, such as
+/// those flattening another, can't be checked so have none.
+fn unknown_fields<T: DeserializeOwned>(body: &Value) -> Vec<String> {
+    let mut fields = None;
+    let _ = T::deserialize(FieldsProbe(&mut fields));
+
+    match (fields, body) {
+        (Some(fields), Value::Object(body)) => body
+            .keys()
+            .filter(|key| !fields.contains(&key.as_str()))
+            .cloned()
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Rejects the body if it has fields `T` doesn't, when strict
+pub fn check<T: DeserializeOwned>(body: &Value) -> Result<(), ApiError> {
+    if !enabled() {
+        return Ok(());
+    }
+
+    match unknown_fields::<T>(body) {
+        unknown if unknown.is_empty() => Ok(()),
+        unknown => Err(ApiError::UnknownFields(unknown)),
+    }
+}
+
+/// Closes the schemas of request bodies to other properties, when strict
+pub struct StrictSchemas;
+
+impl Modify for StrictSchemas {
+    fn modify(&self, openapi: &mut OpenApi) {
+        if !enabled() {
+            return;
+        }
+
+        let requested: Vec<String> = openapi
+            .paths
+            .paths
+            .values()
+            .flat_map(|item| item.operations.values())
+            .filter_map(|operation| operation.request_body.as_ref())
+            .flat_map(|body| body.content.values())
+            .filter_map(|content| match &content.schema {
+                RefOr::Ref(reference) => reference.ref_location.rsplit('/').next(),
+                _ => None,
+            })
+            .map(str::to_owned)
+            .collect();
+
+        let Some(components) = openapi.components.as_mut() else {
+            return;
+        };
+
+        for name in requested {
+            if let Some(RefOr::T(Schema::Object(object))) = components.schemas.get_mut(&name) {
+                object.additional_properties =
+                    Some(Box::new(AdditionalProperties::FreeForm(false)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::unknown_fields;
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    #[allow(dead_code)]
+    struct NewThing {
+        #[serde(alias = "family_name")]
+        family_name: String,
+        nickname: Option<String>,
+    }
+
+    #[test]
+    fn fields_the_type_does_not_have_are_unknown() {
+        let body = json!({ "familyname": "Smith", "nickname": "Smithy" });
+
+        assert_eq!(unknown_fields::<NewThing>(&body), vec!["familyname"]);
+    }
+
+    #[test]
+    fn aliases_are_known() {
+        let body = json!({ "family_name": "Smith" });
+
+        assert!(unknown_fields::<NewThing>(&body).is_empty());
+    }
+
+    #[test]
+    fn bodies_which_are_not_structs_are_not_checked() {
+        let body = json!({ "anything": 1 });
+
+        assert!(unknown_fields::<Vec<NewThing>>(&body).is_empty());
+    }
+}
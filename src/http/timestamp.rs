@@ -0,0 +1,150 @@
+//! How the times people and addresses were created and last edited are written in responses,
+//! for use with `#[serde(with = "timestamp")]`.
+//!
+//! Timestamps are RFC 3339 in UTC, such as `2024-01-31T09:30:00.123456Z`, unless
+//! `TIMESTAMP_FORMAT` is `epoch_millis`, when they are whole milliseconds since the Unix epoch.
+//! Either is read back, as is the time crate's own format used before, so older events in the
+//! outbox and clients of a differently configured service still deserialize.
+
+use std::{env, fmt, sync::OnceLock};
+
+use serde::{de, Deserializer, Serializer};
+use time::{
+    format_description::{well_known::Rfc3339, FormatItem},
+    macros::format_description,
+    OffsetDateTime, UtcOffset,
+};
+
+/// How timestamps are serialized
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimestampFormat {
+    #[default]
+    Rfc3339,
+    EpochMillis,
+}
+
+impl TimestampFormat {
+    /// The format chosen by `TIMESTAMP_FORMAT`, either `rfc3339` (the default) or `epoch_millis`
+    pub fn from_env() -> Self {
+        static FORMAT: OnceLock<TimestampFormat> = OnceLock::new();
+
+        *FORMAT.get_or_init(|| match env::var("TIMESTAMP_FORMAT").as_deref() {
+            Ok("epoch_millis") => TimestampFormat::EpochMillis,
+            _ => TimestampFormat::Rfc3339,
+        })
+    }
+}
+
+/// The time crate's default human readable format, such as `2024-01-31 09:30:00.123456 +00:00:00`
+const LEGACY: &[FormatItem<'_>] = format_description!(
+    "[year]-[month]-[day] [hour]:[minute]:[second].[subsecond] [offset_hour sign:mandatory]:[offset_minute]:[offset_second]"
+);
+
+pub fn serialize<S: Serializer>(time: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error> {
+    let time = time.to_offset(UtcOffset::UTC);
+
+    match TimestampFormat::from_env() {
+        TimestampFormat::Rfc3339 => {
+            let formatted = time.format(&Rfc3339).map_err(serde::ser::Error::custom)?;
+            serializer.serialize_str(&formatted)
+        }
+        TimestampFormat::EpochMillis => {
+            let millis = time.unix_timestamp_nanos() / 1_000_000;
+            serializer.serialize_i64(millis as i64)
+        }
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<OffsetDateTime, D::Error> {
+    deserializer.deserialize_any(TimestampVisitor)
+}
+
+struct TimestampVisitor;
+
+impl de::Visitor<'_> for TimestampVisitor {
+    type Value = OffsetDateTime;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an RFC 3339 timestamp or milliseconds since the Unix epoch")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        OffsetDateTime::parse(value, &Rfc3339)
+            .or_else(|_| OffsetDateTime::parse(value, LEGACY))
+            .map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, millis: i64) -> Result<Self::Value, E> {
+        OffsetDateTime::from_unix_timestamp_nanos(i128::from(millis) * 1_000_000).map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, millis: u64) -> Result<Self::Value, E> {
+        let millis = i64::try_from(millis).map_err(E::custom)?;
+        self.visit_i64(millis)
+    }
+}
+
+/// Optional timestamps, for use with `#[serde(with = "timestamp::option")]`
+pub mod option {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use time::OffsetDateTime;
+
+    #[derive(Deserialize)]
+    struct Timestamp(#[serde(with = "super")] OffsetDateTime);
+
+    pub fn serialize<S: Serializer>(
+        time: &Option<OffsetDateTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => super::serialize(time, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<OffsetDateTime>, D::Error> {
+        let time = Option::<Timestamp>::deserialize(deserializer)?;
+        Ok(time.map(|Timestamp(time)| time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use time::{macros::datetime, OffsetDateTime};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Edited {
+        #[serde(with = "super")]
+        at: OffsetDateTime,
+    }
+
+    #[test]
+    fn timestamps_are_rfc_3339_in_utc() {
+        let edited = Edited {
+            at: datetime!(2024-01-31 10:30:00.5 +01:00),
+        };
+
+        assert_eq!(
+            serde_json::to_string(&edited).unwrap(),
+            r#"{"at":"2024-01-31T09:30:00.5Z"}"#
+        );
+    }
+
+    #[test]
+    fn every_format_is_read_back() {
+        let expected = datetime!(2024-01-31 09:30:00.5 UTC);
+
+        for json in [
+            r#"{"at":"2024-01-31T09:30:00.5Z"}"#,
+            r#"{"at":"2024-01-31T10:30:00.5+01:00"}"#,
+            r#"{"at":1706693400500}"#,
+            r#"{"at":"2024-01-31 09:30:00.5 +00:00:00"}"#,
+        ] {
+            let edited: Edited = serde_json::from_str(json).unwrap();
+            assert_eq!(edited.at, expected, "{json}");
+        }
+    }
+}
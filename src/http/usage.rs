@@ -0,0 +1,292 @@
+//! How many requests each client makes a day, for spotting heavy users and enforcing quotas.
+//!
+//! Requests are counted against the authenticated client, the token's subject or the API
+//! client's name, as they are authenticated. Counts are kept in memory and written to the
+//! database in batches every `USAGE_FLUSH_SECONDS` (default 10), so a crash loses at most that
+//! many seconds of them. API clients created with a `dailyQuota` are refused with `429` once
+//! they have made that many requests in the day (UTC) across every instance, give or take the
+//! requests other instances haven't written yet.
+
+use std::{
+    collections::HashMap,
+    env,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use axum::{routing::get, Extension, Json, Router};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::{Date, Time};
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+use super::{
+    auth::{AdminUser, AuthError},
+    error::{ApiError, Context, Report},
+    limit::LimitQuery,
+    query::ValidatedQuery,
+};
+use crate::clock;
+
+/// How often the counts are written to the database, from `USAGE_FLUSH_SECONDS`
+fn flush_interval() -> Duration {
+    static INTERVAL: OnceLock<Duration> = OnceLock::new();
+
+    *INTERVAL.get_or_init(|| {
+        let seconds = env::var("USAGE_FLUSH_SECONDS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(10);
+        Duration::from_secs(seconds)
+    })
+}
+
+#[derive(Default)]
+struct Count {
+    /// The count in the database when last read or written, if it has been
+    stored: Option<i64>,
+    /// Requests not yet written to the database
+    pending: i64,
+}
+
+struct Tally {
+    counts: HashMap<(String, Date), Count>,
+    last_flush: Instant,
+    flushing: bool,
+}
+
+/// Counts requests per client, shared by every request the app handles
+#[derive(Clone)]
+pub struct Usage {
+    db: PgPool,
+    tally: Arc<Mutex<Tally>>,
+}
+
+impl Usage {
+    pub fn new(db: PgPool) -> Self {
+        Usage {
+            db,
+            tally: Arc::new(Mutex::new(Tally {
+                counts: HashMap::new(),
+                last_flush: Instant::now(),
+                flushing: false,
+            })),
+        }
+    }
+
+    /// Counts a request by the client, unless it has already used up its daily quota
+    pub async fn record(&self, client: &str, quota: Option<i64>) -> Result<(), AuthError> {
+        let day = clock::today();
+        let key = (client.to_owned(), day);
+
+        if quota.is_some() && self.stored(&key).is_none() {
+            // quotas are enforced on what's been counted so far, which is only read once a day
+            let stored = self.load(&key).await;
+            let mut tally = self.tally.lock().unwrap();
+            tally
+                .counts
+                .entry(key.clone())
+                .or_default()
+                .stored
+                .get_or_insert(stored);
+        }
+
+        let mut tally = self.tally.lock().unwrap();
+        let count = tally.counts.entry(key).or_default();
+
+        if let Some(quota) = quota {
+            if count.stored.unwrap_or(0) + count.pending >= quota {
+                return Err(AuthError::QuotaExceeded {
+                    quota,
+                    retry_after: seconds_until_tomorrow(),
+                });
+            }
+        }
+        count.pending += 1;
+
+        let due = !tally.flushing && tally.last_flush.elapsed() >= flush_interval();
+        if due {
+            tally.flushing = true;
+            let usage = self.clone();
+            tokio::spawn(async move { usage.flush().await });
+        }
+
+        Ok(())
+    }
+
+    fn stored(&self, key: &(String, Date)) -> Option<i64> {
+        let tally = self.tally.lock().unwrap();
+        tally.counts.get(key).and_then(|count| count.stored)
+    }
+
+    /// The count in the database, or nothing if it can't be read, rather than refuse the client
+    async fn load(&self, (client, day): &(String, Date)) -> i64 {
+        let stored = sqlx::query_scalar!(
+            "SELECT requests FROM client_usage WHERE client = $1 AND day = $2;",
+            client,
+            day
+        )
+        .fetch_optional(&self.db)
+        .await;
+
+        match stored {
+            Ok(requests) => requests.unwrap_or(0),
+            Err(e) => {
+                error!("Failed to read the usage of '{client}': {}", Report(&e));
+                0
+            }
+        }
+    }
+
+    /// Writes the pending counts to the database, putting them back to try again if it fails
+    pub async fn flush(&self) {
+        let pending: Vec<_> = {
+            let mut tally = self.tally.lock().unwrap();
+            tally
+                .counts
+                .iter_mut()
+                .filter(|(_, count)| count.pending > 0)
+                .map(|(key, count)| (key.clone(), std::mem::take(&mut count.pending)))
+                .collect()
+        };
+
+        let result = if pending.is_empty() {
+            Ok(Vec::new())
+        } else {
+            let (keys, requests): (Vec<_>, Vec<_>) = pending.iter().cloned().unzip();
+            let (clients, days): (Vec<_>, Vec<_>) = keys.into_iter().unzip();
+
+            sqlx::query!(
+                r#"
+                    INSERT INTO client_usage (client, day, requests)
+                    SELECT * FROM UNNEST($1::TEXT[], $2::DATE[], $3::BIGINT[])
+                    ON CONFLICT (client, day) DO UPDATE SET requests = client_usage.requests + EXCLUDED.requests
+                    RETURNING client, day, requests;
+                "#,
+                &clients,
+                &days,
+                &requests
+            )
+            .fetch_all(&self.db)
+            .await
+        };
+
+        let today = clock::today();
+        let mut tally = self.tally.lock().unwrap();
+
+        match result {
+            Ok(rows) => {
+                for row in rows {
+                    if let Some(count) = tally.counts.get_mut(&(row.client, row.day)) {
+                        count.stored = Some(row.requests);
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Failed to record usage: {}", Report(&e));
+
+                for (key, requests) in pending {
+                    tally.counts.entry(key).or_default().pending += requests;
+                }
+            }
+        }
+
+        tally
+            .counts
+            .retain(|(_, day), count| *day >= today || count.pending > 0);
+        tally.last_flush = Instant::now();
+        tally.flushing = false;
+    }
+}
+
+/// How long until quotas are reset at midnight UTC
+fn seconds_until_tomorrow() -> u64 {
+    let now = clock::now();
+    let tomorrow = now
+        .date()
+        .next_day()
+        .map(|day| day.with_time(Time::MIDNIGHT).assume_utc());
+
+    tomorrow.map_or(0, |tomorrow| (tomorrow - now).whole_seconds().max(1) as u64)
+}
+
+/// The requests a client made in a day
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ClientUsage {
+    /// The token's subject, or the name of the API client
+    pub client: String,
+    pub day: Date,
+    pub requests: i64,
+}
+
+#[derive(Debug, Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct UsageFilter {
+    /// Only return the usage of this client
+    client: Option<String>,
+    /// Only return usage on or after this day
+    from: Option<Date>,
+    /// Only return usage on or before this day
+    to: Option<Date>,
+}
+
+/// List usage by client
+///
+/// Reports how many requests each client made a day, including any not yet written to the
+/// database by this instance.
+///
+/// Requires the scope `admin`
+#[utoipa::path(
+    get,
+    tag = "admin",
+    path = "/admin/usage",
+    params(UsageFilter, LimitQuery),
+    responses(
+        (status = 200, description = "Requests per client per day, most recent and busiest first", body = [ClientUsage]),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+async fn list_usage(
+    user: AdminUser,
+    db: Extension<PgPool>,
+    usage: Extension<Usage>,
+    ValidatedQuery(filter): ValidatedQuery<UsageFilter>,
+    ValidatedQuery(page): ValidatedQuery<LimitQuery>,
+) -> Result<Json<Vec<ClientUsage>>, ApiError> {
+    usage.flush().await;
+
+    let usage = sqlx::query_as!(
+        ClientUsage,
+        r#"
+            SELECT client, day, requests FROM client_usage
+            WHERE ($1::TEXT IS NULL OR client = $1)
+                AND ($2::DATE IS NULL OR day >= $2)
+                AND ($3::DATE IS NULL OR day <= $3)
+            ORDER BY day DESC, requests DESC, client
+            LIMIT $4;
+        "#,
+        filter.client,
+        filter.from,
+        filter.to,
+        page.limit().get()
+    )
+    .fetch_all(&*db)
+    .await
+    .context("Failed to list usage")?;
+
+    info!(
+        "Client '{}' retrieved {} day(s) of usage",
+        user.username,
+        usage.len()
+    );
+
+    Ok(Json(usage))
+}
+
+pub fn router() -> Router {
+    Router::new().route("/admin/usage", get(list_usage))
+}
@@ -0,0 +1,222 @@
+use axum::{middleware, Router};
+use utoipa::OpenApi;
+
+use super::{
+    address, admin, api_client, archive,
+    cache_control::{self, CachePolicy},
+    compression, consent, contact_detail, content,
+    deprecation::{self, Deprecations},
+    emergency_contact, employment, export, export_job,
+    import::{self, FileUploads},
+    legal_hold,
+    openapi::{NegotiatedContent, SecurityAddon},
+    person, person_event, person_history, person_merge, person_note, person_photo, person_stats,
+    person_tag, relationship, scheduled_deletion,
+    strict::StrictSchemas,
+    usage,
+};
+
+/// The path prefix every version 1 route is nested under
+pub const PREFIX: &str = "/api/v1";
+
+/// Routes on their way out, announced to clients as described in [`deprecation`]
+pub const DEPRECATIONS: Deprecations = Deprecations {
+    prefix: PREFIX,
+    routes: &[],
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        address::add_address,
+        address::remove_address,
+        address::remove_person_address,
+        admin::list_jobs,
+        admin::list_scheduled_tasks,
+        legal_hold::get_legal_hold,
+        legal_hold::place_legal_hold,
+        legal_hold::lift_legal_hold,
+        api_client::create_client,
+        api_client::list_clients,
+        api_client::disable_client,
+        usage::list_usage,
+        employment::list_employments,
+        employment::add_employment,
+        employment::get_employment,
+        employment::update_employment,
+        employment::remove_employment,
+        contact_detail::list_contact_details,
+        contact_detail::add_contact_detail,
+        contact_detail::get_contact_detail,
+        contact_detail::remove_contact_detail,
+        emergency_contact::list_emergency_contacts,
+        emergency_contact::add_emergency_contact,
+        emergency_contact::get_emergency_contact,
+        emergency_contact::update_emergency_contact,
+        emergency_contact::remove_emergency_contact,
+        consent::list_consents,
+        consent::record_consent,
+        consent::list_consent_history,
+        person_photo::get_photo,
+        person_photo::upload_photo,
+        person_stats::get_person_stats,
+        person_tag::list_tags,
+        person_tag::add_tag,
+        person_tag::remove_tag,
+        person_note::list_notes,
+        person_note::add_note,
+        person_note::get_note,
+        relationship::list_relationships,
+        relationship::add_relationship,
+        relationship::get_relationship,
+        relationship::remove_relationship,
+        export::export_person_pdf,
+        export::export_people_xlsx,
+        export_job::create_export_job,
+        export_job::get_export_job,
+        export_job::download_export,
+        person::create_person,
+        person::list_people,
+        person::count_people,
+        person::search_people,
+        person::check_duplicates,
+        person::lookup_people,
+        person::get_person,
+        person::delete_person,
+        person::restore_person,
+        person::update_person,
+        person::patch_person,
+        person::update_people,
+        person::delete_people,
+        import::import_people,
+        person_event::list_person_events,
+        person_event::replay_person,
+        person_history::get_person_history,
+        person_history::diff_person_revisions,
+        person_merge::merge_people,
+        archive::list_archived_people,
+        archive::get_archived_person,
+        archive::archive_person,
+        archive::restore_person,
+        scheduled_deletion::get_scheduled_deletion,
+        scheduled_deletion::schedule_deletion,
+        scheduled_deletion::cancel_deletion,
+    ),
+    components(schemas(
+        address::Address,
+        address::NewAddress,
+        admin::JobStatus,
+        admin::JobSummary,
+        legal_hold::LegalHold,
+        legal_hold::NewLegalHold,
+        api_client::ApiClient,
+        api_client::NewApiClient,
+        api_client::CreatedApiClient,
+        api_client::Scope,
+        usage::ClientUsage,
+        employment::Employment,
+        employment::NewEmployment,
+        contact_detail::ContactDetail,
+        contact_detail::NewContactDetail,
+        contact_detail::ContactType,
+        emergency_contact::EmergencyContact,
+        emergency_contact::NewEmergencyContact,
+        consent::Consent,
+        consent::NewConsent,
+        consent::ConsentPurpose,
+        consent::ConsentChannel,
+        person::NewPerson,
+        person::UpdatePerson,
+        person::PersonChanges,
+        person::PersonChangeResult,
+        person::BulkDeleteResult,
+        person::PersonLookupResult,
+        person::Person,
+        person::ExpandedPerson,
+        person::PersonCount,
+        person::PossibleDuplicate,
+        person::DuplicateReason,
+        import::ImportSummary,
+        import::ImportError,
+        import::ImportUpload,
+        export_job::ExportFormat,
+        export_job::ExportStatus,
+        export_job::NewExportJob,
+        export_job::ExportJob,
+        person_event::PersonEvent,
+        person_history::HistoryEntry,
+        person_history::ChangedField,
+        person_history::PersonDiff,
+        person_history::FieldChange,
+        person_merge::PersonMerge,
+        person_photo::PersonPhoto,
+        person_photo::PhotoUpload,
+        person_stats::PersonStats,
+        person_stats::AgeBand,
+        person_stats::DailyCount,
+        person_tag::NewTag,
+        person_note::NewNote,
+        person_note::Note,
+        relationship::RelationshipType,
+        relationship::NewRelationship,
+        relationship::Relationship,
+        archive::ArchivedPerson,
+        scheduled_deletion::ScheduleDeletion,
+        scheduled_deletion::ScheduledDeletion,
+        crate::scheduler::ScheduledTaskStatus,
+        super::error::ErrorResponse
+    )),
+    modifiers(
+        &SecurityAddon,
+        &NegotiatedContent,
+        &FileUploads,
+        &DEPRECATIONS,
+        &StrictSchemas
+    ),
+    servers(
+        (url = "/api/v1", description = "Version 1 of the API")
+    ),
+    tags()
+)]
+pub struct ApiDoc;
+
+/// All of the resource routes making up version 1 of the API.
+///
+/// Breaking changes should not be made here; instead add a `v2` module alongside this one
+/// and nest it under its own prefix so both versions can be served side by side.
+pub fn router() -> Router {
+    Router::new()
+        .merge(person::router())
+        .merge(import::router())
+        .merge(person_event::router())
+        .merge(person_history::router())
+        .merge(person_merge::router())
+        .merge(person_photo::router())
+        .merge(person_stats::router())
+        .merge(person_tag::router())
+        .merge(person_note::router())
+        .merge(relationship::router())
+        .merge(scheduled_deletion::router())
+        .merge(archive::router())
+        .merge(address::router())
+        .merge(employment::router())
+        .merge(contact_detail::router())
+        .merge(emergency_contact::router())
+        .merge(consent::router())
+        .merge(admin::router())
+        .merge(legal_hold::router())
+        .merge(api_client::router())
+        .merge(usage::router())
+        .merge(export::router())
+        .merge(export_job::router())
+        .layer(middleware::from_fn_with_state(
+            DEPRECATIONS,
+            deprecation::announce,
+        ))
+        .layer(middleware::from_fn(compression::decompress_requests))
+        .layer(middleware::from_fn(content::json_api_errors))
+        .layer(middleware::from_fn_with_state(
+            CachePolicy::private_from_env(),
+            cache_control::apply,
+        ))
+}
@@ -0,0 +1,244 @@
+//! Database-backed background jobs.
+//!
+//! Jobs are rows in the `job` table, claimed by workers with `FOR UPDATE SKIP LOCKED` so any
+//! number of instances can work through the queue without running a job twice. A claimed job
+//! holds a lease until `locked_until`; should its worker die the lease lapses and another
+//! worker picks it up. Failed jobs are retried with exponential backoff until they run out of
+//! attempts.
+
+use std::{env, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    http::error::{ApiError, Report},
+    ldap::{self, LdapConfig, LdapSyncError},
+    service::{
+        archive::ArchiveService, person_export::PersonExportService,
+        scheduled_deletion::ScheduledDeletionService,
+    },
+};
+
+/// How long a worker may run a job before it is assumed lost and offered to another worker
+const LEASE_SECONDS: f64 = 300.0;
+
+#[derive(thiserror::Error, Debug)]
+pub enum JobError {
+    #[error("{0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Unreadable job payload: {0}")]
+    Payload(#[from] serde_json::Error),
+    #[error("{0}")]
+    Ldap(#[from] LdapSyncError),
+    #[error("{}", Report(.0))]
+    Service(#[from] ApiError),
+}
+
+/// Work to be done in the background
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all_fields = "camelCase")]
+pub enum Job {
+    /// Deletes outbox events delivered more than the given number of days ago
+    #[serde(rename = "outbox.purge")]
+    PurgeOutbox { older_than_days: i32 },
+    /// Imports people from the LDAP directory
+    #[serde(rename = "ldap.sync")]
+    SyncLdap,
+    /// Deletes the people whose scheduled deletion is due
+    #[serde(rename = "person.delete_due")]
+    DeleteDuePeople,
+    /// Archives people who haven't been changed for the given number of days
+    #[serde(rename = "person.archive_inactive")]
+    ArchiveInactivePeople { inactive_days: i32 },
+    /// Writes the people asked for by an export to its file
+    #[serde(rename = "person.export")]
+    ExportPeople { export_id: Uuid },
+    /// Discards the files of exports whose download has expired
+    #[serde(rename = "person.export_purge")]
+    PurgeExports,
+}
+
+impl Job {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Job::PurgeOutbox { .. } => "outbox.purge",
+            Job::SyncLdap => "ldap.sync",
+            Job::DeleteDuePeople => "person.delete_due",
+            Job::ArchiveInactivePeople { .. } => "person.archive_inactive",
+            Job::ExportPeople { .. } => "person.export",
+            Job::PurgeExports => "person.export_purge",
+        }
+    }
+
+    async fn run(&self, db: &PgPool) -> Result<(), JobError> {
+        match self {
+            Job::PurgeOutbox { older_than_days } => {
+                let purged = sqlx::query!(
+                    r#"
+                        DELETE FROM outbox WHERE sent < now() - make_interval(days => $1);
+                    "#,
+                    older_than_days
+                )
+                .execute(db)
+                .await?
+                .rows_affected();
+
+                info!("Purged {purged} delivered event(s) from the outbox");
+            }
+            Job::SyncLdap => {
+                let config = LdapConfig::from_env().unwrap_or_else(|| {
+                    Err(LdapSyncError::Configuration(
+                        "LDAP_URL is not set".to_owned(),
+                    ))
+                })?;
+
+                ldap::sync(db, &config).await?;
+            }
+            Job::DeleteDuePeople => {
+                let deleted = ScheduledDeletionService::new(db.clone())
+                    .delete_due()
+                    .await?;
+
+                info!("Deleted {deleted} person(s) due to be deleted");
+            }
+            Job::ArchiveInactivePeople { inactive_days } => {
+                let archived = ArchiveService::new(db.clone())
+                    .archive_inactive(*inactive_days)
+                    .await?;
+
+                info!("Archived {archived} person(s) inactive for {inactive_days} day(s)");
+            }
+            Job::ExportPeople { export_id } => {
+                PersonExportService::new(db.clone()).run(*export_id).await?;
+            }
+            Job::PurgeExports => {
+                let purged = PersonExportService::new(db.clone()).purge_expired().await?;
+
+                info!("Discarded the files of {purged} expired export(s)");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Queues a job to be run as soon as a worker is free, returning its UUID
+pub async fn enqueue(db: impl PgExecutor<'_>, job: &Job) -> Result<Uuid, JobError> {
+    let payload = serde_json::to_value(job)?;
+
+    let uuid = sqlx::query_scalar!(
+        r#"
+            INSERT INTO job (kind, payload)
+            VALUES ($1, $2)
+            RETURNING uuid;
+        "#,
+        job.name(),
+        payload
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(uuid)
+}
+
+/// Claims and runs the next due job, returning whether there was one
+pub async fn run_next(db: &PgPool) -> Result<bool, JobError> {
+    let claimed = sqlx::query!(
+        r#"
+            UPDATE job
+            SET status = 'running', attempts = attempts + 1, last_edited = now(),
+                locked_until = now() + make_interval(secs => $1)
+            WHERE id = (
+                SELECT id FROM job
+                WHERE (status = 'pending' AND run_at <= now())
+                    OR (status = 'running' AND locked_until < now())
+                ORDER BY run_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, kind, payload, attempts, max_attempts;
+        "#,
+        LEASE_SECONDS
+    )
+    .fetch_optional(db)
+    .await?;
+
+    let Some(claimed) = claimed else {
+        return Ok(false);
+    };
+
+    let outcome = match serde_json::from_value::<Job>(claimed.payload) {
+        Ok(job) => job.run(db).await,
+        Err(e) => Err(JobError::from(e)),
+    };
+
+    match outcome {
+        Ok(()) => {
+            sqlx::query!(
+                r#"
+                    UPDATE job SET status = 'completed', locked_until = NULL, last_edited = now()
+                    WHERE id = $1;
+                "#,
+                claimed.id
+            )
+            .execute(db)
+            .await?;
+        }
+        Err(e) => {
+            let retry = claimed.attempts < claimed.max_attempts;
+
+            if retry {
+                warn!(
+                    "Job {} '{}' failed, will retry: {}",
+                    claimed.id, claimed.kind, e
+                );
+            } else {
+                error!(
+                    "Job {} '{}' failed, giving up: {}",
+                    claimed.id, claimed.kind, e
+                );
+            }
+
+            sqlx::query!(
+                r#"
+                    UPDATE job
+                    SET status = CASE WHEN $2 THEN 'pending' ELSE 'failed' END,
+                        run_at = now() + make_interval(secs => power(2, attempts)),
+                        locked_until = NULL, last_error = $3, last_edited = now()
+                    WHERE id = $1;
+                "#,
+                claimed.id,
+                retry,
+                e.to_string()
+            )
+            .execute(db)
+            .await?;
+        }
+    }
+
+    Ok(true)
+}
+
+/// Works through jobs forever, polling every `JOB_POLL_INTERVAL_MS` (default 1000) when idle
+pub async fn work(db: PgPool) {
+    let poll_interval = env::var("JOB_POLL_INTERVAL_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(Duration::from_secs(1));
+
+    info!("Polling for background jobs every {poll_interval:?}");
+
+    loop {
+        match run_next(&db).await {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(e) => error!("Failed to run background jobs: {e}"),
+        }
+
+        tokio::time::sleep(poll_interval).await;
+    }
+}
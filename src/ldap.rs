@@ -0,0 +1,383 @@
+//! Imports people from an LDAP or Active Directory subtree.
+//!
+//! Entries under `LDAP_BASE_DN` matching `LDAP_FILTER` are mapped onto people, with the
+//! attribute for each field configurable to suit the directory's schema. People are matched to
+//! entries on their `external_id`, so a sync creates those new to the directory and updates any
+//! whose details have changed there, recording the usual events for each. People are never
+//! deleted by a sync, as the directory may only hold a subset of them.
+
+use std::env;
+
+use ldap3::{
+    adapters::{Adapter, EntriesOnly, PagedResults},
+    LdapConnAsync, Scope, SearchEntry,
+};
+use sqlx::PgPool;
+use time::{format_description::well_known::Iso8601, macros::format_description, Date};
+use tracing::{info, warn};
+use validator::ValidationError;
+
+use crate::{
+    events::Event,
+    http::{
+        error::ApiError,
+        person::{NewPerson, Person},
+    },
+    outbox,
+    service::{person_event, person_history},
+};
+
+const PAGE_SIZE: i32 = 500;
+
+#[derive(thiserror::Error, Debug)]
+pub enum LdapSyncError {
+    #[error("{0}")]
+    Configuration(String),
+    #[error("{0}")]
+    Ldap(#[from] ldap3::LdapError),
+    #[error("{0}")]
+    Import(#[from] ApiError),
+}
+
+/// Which directory attribute each field is read from
+#[derive(Debug)]
+pub struct AttributeMap {
+    pub external_id: String,
+    pub first_name: String,
+    pub family_name: String,
+    pub date_of_birth: String,
+}
+
+impl Default for AttributeMap {
+    fn default() -> Self {
+        AttributeMap {
+            external_id: "entryUUID".to_owned(),
+            first_name: "givenName".to_owned(),
+            family_name: "sn".to_owned(),
+            date_of_birth: "birthDate".to_owned(),
+        }
+    }
+}
+
+impl AttributeMap {
+    fn names(&self) -> Vec<&str> {
+        vec![
+            &self.external_id,
+            &self.first_name,
+            &self.family_name,
+            &self.date_of_birth,
+        ]
+    }
+
+    /// Maps an entry onto a person, or `None` when it lacks any of the required attributes.
+    ///
+    /// Binary identifiers, such as Active Directory's `objectGUID`, are hex encoded. Dates may
+    /// be ISO 8601 dates or LDAP generalized times.
+    pub fn map(&self, entry: &SearchEntry) -> Option<DirectoryPerson> {
+        let text = |name: &str| entry.attrs.get(name).and_then(|v| v.first()).cloned();
+
+        let external_id = text(&self.external_id).or_else(|| {
+            entry
+                .bin_attrs
+                .get(&self.external_id)
+                .and_then(|v| v.first())
+                .map(|bytes| bytes.iter().map(|b| format!("{b:02x}")).collect())
+        })?;
+
+        let date_of_birth = text(&self.date_of_birth).and_then(|value| parse_date(&value))?;
+
+        Some(DirectoryPerson {
+            external_id,
+            first_name: text(&self.first_name)?,
+            family_name: text(&self.family_name)?,
+            date_of_birth,
+        })
+    }
+}
+
+fn parse_date(value: &str) -> Option<Date> {
+    Date::parse(value, &Iso8601::DATE).ok().or_else(|| {
+        let generalized = format_description!("[year][month][day]");
+        value
+            .get(..8)
+            .and_then(|v| Date::parse(v, generalized).ok())
+    })
+}
+
+#[derive(Debug)]
+pub struct LdapConfig {
+    pub url: String,
+    pub bind_dn: Option<String>,
+    pub bind_password: Option<String>,
+    pub base_dn: String,
+    pub filter: String,
+    pub attributes: AttributeMap,
+}
+
+impl LdapConfig {
+    /// Reads the directory settings, or `None` when `LDAP_URL` isn't set
+    pub fn from_env() -> Option<Result<Self, LdapSyncError>> {
+        let url = env::var("LDAP_URL").ok()?;
+
+        let Ok(base_dn) = env::var("LDAP_BASE_DN") else {
+            return Some(Err(LdapSyncError::Configuration(
+                "LDAP_BASE_DN is required when LDAP_URL is set".to_owned(),
+            )));
+        };
+
+        let defaults = AttributeMap::default();
+        let attribute = |variable: &str, default: String| env::var(variable).unwrap_or(default);
+
+        Some(Ok(LdapConfig {
+            url,
+            bind_dn: env::var("LDAP_BIND_DN").ok(),
+            bind_password: env::var("LDAP_BIND_PASSWORD").ok(),
+            base_dn,
+            filter: env::var("LDAP_FILTER").unwrap_or_else(|_| "(objectClass=person)".to_owned()),
+            attributes: AttributeMap {
+                external_id: attribute("LDAP_ATTR_EXTERNAL_ID", defaults.external_id),
+                first_name: attribute("LDAP_ATTR_FIRST_NAME", defaults.first_name),
+                family_name: attribute("LDAP_ATTR_FAMILY_NAME", defaults.family_name),
+                date_of_birth: attribute("LDAP_ATTR_DATE_OF_BIRTH", defaults.date_of_birth),
+            },
+        }))
+    }
+}
+
+/// A person as recorded in the directory
+#[derive(Debug, PartialEq)]
+pub struct DirectoryPerson {
+    pub external_id: String,
+    pub first_name: String,
+    pub family_name: String,
+    pub date_of_birth: Date,
+}
+
+impl DirectoryPerson {
+    /// The person's details, normalized and checked as those sent to the API are
+    fn details(&self) -> Result<NewPerson, ValidationError> {
+        Ok(NewPerson {
+            first_name: self.first_name.clone().try_into()?,
+            family_name: self.family_name.clone().try_into()?,
+            date_of_birth: self.date_of_birth.try_into()?,
+        })
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncSummary {
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    /// Entries missing one of the mapped attributes, or with details that aren't valid
+    pub skipped: usize,
+}
+
+/// Reads every matching entry from the directory, a page at a time
+async fn fetch(config: &LdapConfig) -> Result<Vec<SearchEntry>, LdapSyncError> {
+    let (connection, mut ldap) = LdapConnAsync::new(&config.url).await?;
+    ldap3::drive!(connection);
+
+    if let (Some(dn), Some(password)) = (&config.bind_dn, &config.bind_password) {
+        ldap.simple_bind(dn, password).await?.success()?;
+    }
+
+    let adapters: Vec<Box<dyn Adapter<_, _>>> = vec![
+        Box::new(EntriesOnly::new()),
+        Box::new(PagedResults::new(PAGE_SIZE)),
+    ];
+
+    let mut search = ldap
+        .streaming_search_with(
+            adapters,
+            &config.base_dn,
+            Scope::Subtree,
+            &config.filter,
+            config.attributes.names(),
+        )
+        .await?;
+
+    let mut entries = vec![];
+    while let Some(entry) = search.next().await? {
+        entries.push(SearchEntry::construct(entry));
+    }
+    search.finish().await.success()?;
+
+    ldap.unbind().await?;
+
+    Ok(entries)
+}
+
+/// Creates or updates the person matching each directory entry by `external_id`
+pub async fn import(db: &PgPool, people: &[DirectoryPerson]) -> Result<SyncSummary, ApiError> {
+    let mut summary = SyncSummary::default();
+
+    for person in people {
+        let details = match person.details() {
+            Ok(details) => details,
+            Err(e) => {
+                warn!(
+                    "Skipping invalid directory person '{}': {e}",
+                    person.external_id
+                );
+                summary.skipped += 1;
+                continue;
+            }
+        };
+
+        let mut tx = db.begin().await?;
+
+        // the WHERE clause leaves people already matching the directory, or deleted, untouched,
+        // in which case nothing is returned
+        let changed = sqlx::query!(
+            r#"
+                INSERT INTO person (external_id, first_name, family_name, date_of_birth)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (external_id) DO UPDATE
+                SET first_name = EXCLUDED.first_name, family_name = EXCLUDED.family_name,
+                    date_of_birth = EXCLUDED.date_of_birth, last_edited = now(),
+                    version = person.version + 1
+                WHERE person.deleted_at IS NULL
+                    AND (person.first_name, person.family_name, person.date_of_birth)
+                    IS DISTINCT FROM (EXCLUDED.first_name, EXCLUDED.family_name, EXCLUDED.date_of_birth)
+                RETURNING uuid AS id, created, last_edited, first_name, family_name, date_of_birth, version,
+                    (xmax = 0) AS "inserted!";
+            "#,
+            person.external_id,
+            details.first_name.as_str(),
+            details.family_name.as_str(),
+            details.date_of_birth.date()
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(changed) = changed else {
+            summary.unchanged += 1;
+            continue;
+        };
+
+        let record = Person {
+            id: changed.id,
+            first_name: changed.first_name,
+            family_name: changed.family_name,
+            date_of_birth: changed.date_of_birth,
+            created: changed.created,
+            last_edited: changed.last_edited,
+            version: changed.version,
+        };
+
+        let event = if changed.inserted {
+            summary.created += 1;
+            Event::PersonCreated { person: record }
+        } else {
+            summary.updated += 1;
+            Event::PersonUpdated { person: record }
+        };
+
+        person_event::append(&mut tx, "ldap.sync", &event).await?;
+
+        person_history::record(&mut tx, "ldap.sync", &event).await?;
+        outbox::enqueue(&mut tx, &event).await?;
+        tx.commit().await?;
+    }
+
+    Ok(summary)
+}
+
+/// Imports every person from the configured directory
+pub async fn sync(db: &PgPool, config: &LdapConfig) -> Result<SyncSummary, LdapSyncError> {
+    let entries = fetch(config).await?;
+
+    let people: Vec<_> = entries
+        .iter()
+        .filter_map(|entry| {
+            let person = config.attributes.map(entry);
+            if person.is_none() {
+                warn!("Skipping directory entry '{}' missing attributes", entry.dn);
+            }
+            person
+        })
+        .collect();
+
+    let mut summary = import(db, &people).await?;
+    summary.skipped += entries.len() - people.len();
+
+    info!(
+        "Directory sync created {}, updated {}, left {} unchanged and skipped {}",
+        summary.created, summary.updated, summary.unchanged, summary.skipped
+    );
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ldap3::SearchEntry;
+    use time::macros::date;
+
+    use super::{AttributeMap, DirectoryPerson};
+
+    fn entry(attrs: &[(&str, &str)], bin_attrs: &[(&str, &[u8])]) -> SearchEntry {
+        SearchEntry {
+            dn: "uid=jdoe,ou=people,dc=example,dc=com".to_owned(),
+            attrs: attrs
+                .iter()
+                .map(|(k, v)| (k.to_string(), vec![v.to_string()]))
+                .collect(),
+            bin_attrs: bin_attrs
+                .iter()
+                .map(|(k, v)| (k.to_string(), vec![v.to_vec()]))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn entries_are_mapped_by_the_configured_attributes() {
+        let entry = entry(
+            &[
+                ("entryUUID", "5e0c4a0a-2b8f-4f0e-8b1c-1f2d3c4b5a69"),
+                ("givenName", "John"),
+                ("sn", "Doe"),
+                ("birthDate", "19900101000000Z"),
+            ],
+            &[],
+        );
+
+        assert_eq!(
+            AttributeMap::default().map(&entry),
+            Some(DirectoryPerson {
+                external_id: "5e0c4a0a-2b8f-4f0e-8b1c-1f2d3c4b5a69".to_owned(),
+                first_name: "John".to_owned(),
+                family_name: "Doe".to_owned(),
+                date_of_birth: date!(1990 - 01 - 01),
+            })
+        );
+    }
+
+    #[test]
+    fn binary_identifiers_are_hex_encoded() {
+        let attributes = AttributeMap {
+            external_id: "objectGUID".to_owned(),
+            ..AttributeMap::default()
+        };
+        let entry = entry(
+            &[
+                ("givenName", "John"),
+                ("sn", "Doe"),
+                ("birthDate", "1990-01-01"),
+            ],
+            &[("objectGUID", &[0xde, 0xad, 0xbe, 0xef])],
+        );
+
+        assert_eq!(attributes.map(&entry).unwrap().external_id, "deadbeef");
+    }
+
+    #[test]
+    fn entries_missing_attributes_are_skipped() {
+        let entry = entry(&[("entryUUID", "1"), ("givenName", "John")], &[]);
+
+        assert_eq!(AttributeMap::default().map(&entry), None);
+    }
+}
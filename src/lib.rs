@@ -1,23 +1,79 @@
-use axum::{routing::get, Extension, Router};
+use axum::{middleware, routing::get, Extension, Router};
+use clock::SharedClock;
+use events::EventPublisher;
+use reqwest::Client;
+use scheduler::Scheduler;
+use search::SearchIndex;
 use sqlx::PgPool;
 use std::{env, net::SocketAddr};
+use tower::ServiceBuilder;
+use tower_http::normalize_path::NormalizePathLayer;
+use utoipa::OpenApi;
 
-mod http;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod clock;
+pub mod events;
+pub mod failover;
+pub mod http;
+pub mod jobs;
+pub mod ldap;
+pub mod outbound;
+pub mod outbox;
+pub mod scheduler;
+pub mod search;
+pub mod service;
 
 async fn hello() -> &'static str {
     "Hello, world!"
 }
 
-pub fn app(database_pool: PgPool) -> Router {
-    Router::new()
+pub fn app(
+    database_pool: PgPool,
+    client: Client,
+    clock: SharedClock,
+    scheduler: Scheduler,
+    search: SearchIndex,
+) -> Router {
+    let api = Router::new()
         .route("/", get(hello))
+        .merge(http::admin_ui::router())
+        .merge(http::graphql::router())
+        .merge(http::scim::router())
+        .nest(http::v1::PREFIX, http::v1::router())
+        .layer(middleware::from_fn(failover::retry_reads));
+
+    Router::new()
         .merge(http::openapi::router())
-        .merge(http::person::router())
-        .merge(http::address::router())
+        .merge(http::health::router())
+        // paths are normalized before the rest of the routes are matched
+        .fallback_service(
+            ServiceBuilder::new()
+                .layer(NormalizePathLayer::trim_trailing_slash())
+                .map_request(http::path::collapse_slashes)
+                .service(api),
+        )
+        .layer(Extension(http::usage::Usage::new(database_pool.clone())))
         .layer(Extension(database_pool))
+        .layer(Extension(client))
+        .layer(Extension(scheduler))
+        .layer(Extension(search))
+        .layer(middleware::from_fn_with_state(clock, clock::scope))
 }
 
-pub async fn serve(database_pool: PgPool) {
+/// The OpenAPI document describing version 1 of the API
+pub fn openapi() -> utoipa::openapi::OpenApi {
+    http::v1::ApiDoc::openapi()
+}
+
+pub async fn serve(
+    database_pool: PgPool,
+    client: Client,
+    clock: SharedClock,
+    events: EventPublisher,
+    scheduler: Scheduler,
+    search: SearchIndex,
+) {
     let server_port = env::var("SERVER_PORT")
         .ok()
         .and_then(|v: String| -> Option<u16> { v.parse().ok() })
@@ -27,8 +83,19 @@ pub async fn serve(database_pool: PgPool) {
 
     tracing::info!("Server listening on: {}", addr);
 
+    tokio::spawn(failover::watch(database_pool.clone()));
+    tokio::spawn(outbox::relay(database_pool.clone(), events));
+    tokio::spawn(jobs::work(database_pool.clone()));
+    tokio::spawn(scheduler.clone().run(database_pool.clone(), client.clone()));
+    if let SearchIndex::Elasticsearch(index) = &search {
+        tokio::spawn(search::sync(database_pool.clone(), index.clone()));
+    }
+
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app(database_pool).into_make_service())
-        .await
-        .expect("Failed to start server")
+    axum::serve(
+        listener,
+        app(database_pool, client, clock, scheduler, search).into_make_service(),
+    )
+    .await
+    .expect("Failed to start server")
 }
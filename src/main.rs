@@ -1,3 +1,9 @@
+use std::sync::Arc;
+
+use rust_web_app::{
+    clock::SystemClock, events::EventPublisher, outbound, scheduler::Scheduler, search::SearchIndex,
+};
+
 mod db;
 
 #[tokio::main]
@@ -6,8 +12,21 @@ async fn main() {
     tracing_subscriber::fmt::init();
 
     let database_pool = db::init().await.unwrap();
+    let client = outbound::from_env().unwrap();
+    let events = EventPublisher::from_env().await.unwrap();
+    let scheduler = Scheduler::from_env().unwrap();
 
-    rust_web_app::serve(database_pool).await;
+    let search = SearchIndex::from_env(client.clone());
+
+    rust_web_app::serve(
+        database_pool,
+        client,
+        Arc::new(SystemClock),
+        events,
+        scheduler,
+        search,
+    )
+    .await;
 }
 
 #[cfg(test)]
@@ -25,7 +44,13 @@ mod tests {
     async fn hello_route() {
         dotenvy::dotenv().ok();
         let database_pool = db::init().await.unwrap();
-        let app = app(database_pool);
+        let app = app(
+            database_pool,
+            reqwest::Client::new(),
+            Arc::new(SystemClock),
+            Scheduler::default(),
+            SearchIndex::Disabled,
+        );
 
         let response = app
             .oneshot(
@@ -49,7 +74,13 @@ mod tests {
     async fn not_found() {
         dotenvy::dotenv().ok();
         let database_pool = db::init().await.unwrap();
-        let app = app(database_pool);
+        let app = app(
+            database_pool,
+            reqwest::Client::new(),
+            Arc::new(SystemClock),
+            Scheduler::default(),
+            SearchIndex::Disabled,
+        );
 
         let response = app
             .oneshot(
@@ -64,4 +95,44 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
+
+    #[tokio::test]
+    async fn resource_routes_are_versioned() {
+        dotenvy::dotenv().ok();
+        let database_pool = db::init().await.unwrap();
+        let app = app(
+            database_pool,
+            reqwest::Client::new(),
+            Arc::new(SystemClock),
+            Scheduler::default(),
+            SearchIndex::Disabled,
+        );
+
+        let unversioned = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/person")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(unversioned.status(), StatusCode::NOT_FOUND);
+
+        let versioned = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/api/v1/person")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(versioned.status(), StatusCode::UNAUTHORIZED);
+    }
 }
@@ -0,0 +1,76 @@
+//! The HTTP client shared by everything calling out to other services, such as fetching the
+//! auth server's signing keys or keeping the search index up to date.
+//!
+//! One client is built at startup and handed to each user, so connections and TLS sessions
+//! are pooled and reused rather than set up again for every call. Proxies are taken from the
+//! usual `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` variables, and certificates signed by a
+//! private CA, such as those of a TLS intercepting proxy, are trusted once its certificate is in
+//! the bundle at `HTTP_CA_BUNDLE`.
+
+use std::{env, fs, io, time::Duration};
+
+use reqwest::{Certificate, Client, ClientBuilder};
+
+/// Identifies this service in the `User-Agent` of outbound requests
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+fn seconds(variable: &str, default: u64) -> Duration {
+    env::var(variable)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(default))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to read the CA bundle '{path}': {source}")]
+    CaBundle {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{0}")]
+    Client(#[from] reqwest::Error),
+}
+
+/// Trusts the PEM encoded certificates in the file at `HTTP_CA_BUNDLE`, if set, alongside the
+/// system's own
+fn with_ca_bundle(builder: ClientBuilder) -> Result<ClientBuilder, Error> {
+    let Ok(path) = env::var("HTTP_CA_BUNDLE") else {
+        return Ok(builder);
+    };
+
+    let pem = fs::read(&path).map_err(|source| Error::CaBundle { path, source })?;
+
+    Ok(Certificate::from_pem_bundle(&pem)?
+        .into_iter()
+        .fold(builder, ClientBuilder::add_root_certificate))
+}
+
+/// Builds the client, timing out connections after `HTTP_CONNECT_TIMEOUT_SECONDS` (default 5)
+/// and whole requests after `HTTP_TIMEOUT_SECONDS` (default 30)
+pub fn from_env() -> Result<Client, Error> {
+    let builder = Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(seconds("HTTP_CONNECT_TIMEOUT_SECONDS", 5))
+        .timeout(seconds("HTTP_TIMEOUT_SECONDS", 30));
+
+    Ok(with_ca_bundle(builder)?.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::{from_env, Error};
+
+    #[test]
+    fn missing_ca_bundles_are_reported() {
+        env::set_var("HTTP_CA_BUNDLE", "/nonexistent/ca.pem");
+        let result = from_env();
+        env::remove_var("HTTP_CA_BUNDLE");
+
+        assert!(matches!(result, Err(Error::CaBundle { .. })));
+    }
+}
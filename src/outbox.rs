@@ -0,0 +1,101 @@
+//! Transactional outbox for event delivery.
+//!
+//! Mutations record their events in the `outbox` table within the same transaction as the
+//! change itself, so an event exists if and only if the change was committed. The relay then
+//! publishes pending events in order and marks them as sent, retrying whatever couldn't be
+//! delivered (e.g. while the broker is down) on its next poll.
+
+use std::{env, time::Duration};
+
+use sqlx::{PgConnection, PgPool};
+use tracing::{error, info};
+
+use crate::events::{CloudEvent, Event, EventPublisher};
+
+const BATCH_SIZE: i64 = 100;
+
+/// Records the event to be published once the surrounding transaction commits.
+///
+/// The CloudEvents envelope is built here, so its `id` and `time` are those of the change and
+/// stay the same however many attempts delivery takes.
+pub async fn enqueue(conn: &mut PgConnection, event: &Event) -> Result<(), sqlx::Error> {
+    let payload = serde_json::to_value(CloudEvent::new(event))
+        .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO outbox (event_type, payload)
+            VALUES ($1, $2);
+        "#,
+        event.name(),
+        payload
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Publishes the oldest pending events, returning how many were sent.
+///
+/// Rows are locked with `SKIP LOCKED` so several instances can relay concurrently, and the
+/// batch stops at the first failure so events are never delivered out of order.
+pub async fn relay_batch(db: &PgPool, publisher: &EventPublisher) -> Result<usize, sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    let pending = sqlx::query!(
+        r#"
+            SELECT id, event_type, payload FROM outbox
+            WHERE sent IS NULL
+            ORDER BY id
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED;
+        "#,
+        BATCH_SIZE
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut sent = 0;
+
+    for event in pending {
+        if let Err(e) = publisher.publish(&event.event_type, &event.payload).await {
+            error!(
+                "Failed to publish event {} '{}', will retry: {}",
+                event.id, event.event_type, e
+            );
+            break;
+        }
+
+        sqlx::query!("UPDATE outbox SET sent = now() WHERE id = $1;", event.id)
+            .execute(&mut *tx)
+            .await?;
+
+        sent += 1;
+    }
+
+    tx.commit().await?;
+
+    Ok(sent)
+}
+
+/// Relays events forever, polling every `OUTBOX_POLL_INTERVAL_MS` (default 1000) when idle
+pub async fn relay(db: PgPool, publisher: EventPublisher) {
+    let poll_interval = env::var("OUTBOX_POLL_INTERVAL_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(Duration::from_secs(1));
+
+    info!("Relaying events from the outbox every {poll_interval:?}");
+
+    loop {
+        match relay_batch(&db, &publisher).await {
+            Ok(sent) if sent as i64 == BATCH_SIZE => continue,
+            Ok(_) => {}
+            Err(e) => error!("Failed to relay events from the outbox: {e}"),
+        }
+
+        tokio::time::sleep(poll_interval).await;
+    }
+}
@@ -0,0 +1,312 @@
+//! Cron-style scheduling of periodic work.
+//!
+//! Each task runs on its own schedule, configured with a cron expression (with a leading
+//! seconds field) in its environment variable, or `off` to disable it. Schedules are kept per
+//! instance, so the tasks here are either local to the instance, like refreshing its cached
+//! JWKS, or safe to run from several instances at once, like queueing a retention purge.
+
+use std::{
+    env,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use chrono::Utc;
+use cron::Schedule;
+use reqwest::Client;
+use serde::Serialize;
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use crate::{
+    http::{auth, timestamp},
+    jobs::{self, Job},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum SchedulerError {
+    #[error("Invalid schedule '{expression}' in {variable}: {source}")]
+    InvalidSchedule {
+        variable: &'static str,
+        expression: String,
+        source: cron::error::Error,
+    },
+}
+
+/// Periodic work the scheduler knows how to do
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Task {
+    /// Queues a purge of delivered events older than `OUTBOX_RETENTION_DAYS` (default 7)
+    PurgeOutbox,
+    /// Fetches the auth server's signing keys ahead of them being needed by a request
+    RefreshJwks,
+    /// Queues an import of people from the LDAP directory, when `LDAP_URL` is set
+    SyncLdap,
+    /// Queues the deletion of people whose scheduled deletion is due
+    DeleteDuePeople,
+    /// Queues the archiving of people unchanged for `ARCHIVE_INACTIVE_DAYS` (default 730)
+    ArchiveInactivePeople,
+    /// Queues the discarding of export files whose download has expired
+    PurgeExports,
+}
+
+impl Task {
+    const ALL: [Task; 6] = [
+        Task::PurgeOutbox,
+        Task::RefreshJwks,
+        Task::SyncLdap,
+        Task::DeleteDuePeople,
+        Task::ArchiveInactivePeople,
+        Task::PurgeExports,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Task::PurgeOutbox => "outbox.purge",
+            Task::RefreshJwks => "jwks.refresh",
+            Task::SyncLdap => "ldap.sync",
+            Task::DeleteDuePeople => "person.delete_due",
+            Task::ArchiveInactivePeople => "person.archive_inactive",
+            Task::PurgeExports => "person.export_purge",
+        }
+    }
+
+    fn variable(self) -> &'static str {
+        match self {
+            Task::PurgeOutbox => "SCHEDULE_OUTBOX_PURGE",
+            Task::RefreshJwks => "SCHEDULE_JWKS_REFRESH",
+            Task::SyncLdap => "SCHEDULE_LDAP_SYNC",
+            Task::DeleteDuePeople => "SCHEDULE_PERSON_DELETION",
+            Task::ArchiveInactivePeople => "SCHEDULE_PERSON_ARCHIVAL",
+            Task::PurgeExports => "SCHEDULE_EXPORT_PURGE",
+        }
+    }
+
+    fn default_schedule(self) -> &'static str {
+        match self {
+            // daily at 03:00 UTC
+            Task::PurgeOutbox => "0 0 3 * * *",
+            // every five minutes, comfortably inside the JWKS cache lifetime
+            Task::RefreshJwks => "0 */5 * * * *",
+            // hourly, on the hour
+            Task::SyncLdap => "0 0 * * * *",
+            // every fifteen minutes, as deletions are scheduled with days of grace
+            Task::DeleteDuePeople => "0 */15 * * * *",
+            // daily at 04:00 UTC, after the outbox purge
+            Task::ArchiveInactivePeople => "0 0 4 * * *",
+            // hourly, at half past, as downloads last hours rather than days
+            Task::PurgeExports => "0 30 * * * *",
+        }
+    }
+
+    /// Whether the task has anything to do in this deployment
+    fn applicable(self) -> bool {
+        match self {
+            Task::SyncLdap => env::var("LDAP_URL").is_ok(),
+            _ => true,
+        }
+    }
+
+    async fn run(self, db: &PgPool, client: &Client) -> Result<(), String> {
+        match self {
+            Task::PurgeOutbox => {
+                let older_than_days = env::var("OUTBOX_RETENTION_DAYS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(7);
+
+                jobs::enqueue(db, &Job::PurgeOutbox { older_than_days })
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            }
+            Task::RefreshJwks => auth::refresh_jwks(client)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            Task::SyncLdap => jobs::enqueue(db, &Job::SyncLdap)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            Task::DeleteDuePeople => jobs::enqueue(db, &Job::DeleteDuePeople)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            Task::ArchiveInactivePeople => {
+                let inactive_days = env::var("ARCHIVE_INACTIVE_DAYS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(730);
+
+                jobs::enqueue(db, &Job::ArchiveInactivePeople { inactive_days })
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            }
+            Task::PurgeExports => jobs::enqueue(db, &Job::PurgeExports)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// When a scheduled task last ran and will next run
+#[derive(Clone, Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledTaskStatus {
+    pub name: &'static str,
+    pub schedule: String,
+    #[serde(with = "timestamp::option")]
+    pub last_run: Option<OffsetDateTime>,
+    /// Why the last run failed, if it did
+    pub last_error: Option<String>,
+    #[serde(with = "timestamp::option")]
+    pub next_run: Option<OffsetDateTime>,
+}
+
+struct ScheduledTask {
+    task: Task,
+    schedule: Schedule,
+    status: Mutex<ScheduledTaskStatus>,
+}
+
+impl ScheduledTask {
+    fn next_run(&self) -> Option<OffsetDateTime> {
+        self.schedule
+            .upcoming(Utc)
+            .next()
+            .and_then(|next| OffsetDateTime::from_unix_timestamp(next.timestamp()).ok())
+    }
+}
+
+/// The enabled periodic tasks, shared with the admin API for reporting
+#[derive(Clone, Default)]
+pub struct Scheduler {
+    tasks: Arc<Vec<ScheduledTask>>,
+}
+
+impl Scheduler {
+    /// Reads each task's schedule from its environment variable, falling back to its default
+    pub fn from_env() -> Result<Self, SchedulerError> {
+        let mut tasks = vec![];
+
+        for task in Task::ALL {
+            let expression =
+                env::var(task.variable()).unwrap_or_else(|_| task.default_schedule().to_owned());
+
+            if expression == "off" || !task.applicable() {
+                continue;
+            }
+
+            let schedule = Schedule::from_str(&expression).map_err(|source| {
+                SchedulerError::InvalidSchedule {
+                    variable: task.variable(),
+                    expression: expression.clone(),
+                    source,
+                }
+            })?;
+
+            let scheduled = ScheduledTask {
+                task,
+                schedule,
+                status: Mutex::new(ScheduledTaskStatus {
+                    name: task.name(),
+                    schedule: expression,
+                    last_run: None,
+                    last_error: None,
+                    next_run: None,
+                }),
+            };
+            scheduled.status.lock().unwrap().next_run = scheduled.next_run();
+
+            tasks.push(scheduled);
+        }
+
+        Ok(Scheduler {
+            tasks: Arc::new(tasks),
+        })
+    }
+
+    pub fn status(&self) -> Vec<ScheduledTaskStatus> {
+        self.tasks
+            .iter()
+            .map(|t| t.status.lock().unwrap().clone())
+            .collect()
+    }
+
+    /// Runs every task on its schedule, forever
+    pub async fn run(self, db: PgPool, client: Client) {
+        for index in 0..self.tasks.len() {
+            let tasks = self.tasks.clone();
+            let db = db.clone();
+            let client = client.clone();
+
+            tokio::spawn(async move {
+                let scheduled = &tasks[index];
+                info!(
+                    "Scheduled '{}' to run on '{}'",
+                    scheduled.task.name(),
+                    scheduled.status.lock().unwrap().schedule
+                );
+
+                while let Some(next_run) = scheduled.next_run() {
+                    scheduled.status.lock().unwrap().next_run = Some(next_run);
+
+                    let wait = next_run - OffsetDateTime::now_utc();
+                    tokio::time::sleep(wait.try_into().unwrap_or_default()).await;
+
+                    let outcome = scheduled.task.run(&db, &client).await;
+
+                    if let Err(e) = &outcome {
+                        error!("Scheduled task '{}' failed: {e}", scheduled.task.name());
+                    }
+
+                    let mut status = scheduled.status.lock().unwrap();
+                    status.last_run = Some(OffsetDateTime::now_utc());
+                    status.last_error = outcome.err();
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::Scheduler;
+
+    // both cases share the environment, so run as one test
+    #[test]
+    fn schedules_are_read_from_the_environment() {
+        env::set_var("SCHEDULE_OUTBOX_PURGE", "off");
+        env::set_var("SCHEDULE_PERSON_DELETION", "off");
+        env::set_var("SCHEDULE_PERSON_ARCHIVAL", "off");
+        env::set_var("SCHEDULE_EXPORT_PURGE", "off");
+        env::set_var("SCHEDULE_JWKS_REFRESH", "0 0 * * * *");
+
+        let status = Scheduler::from_env().unwrap().status();
+
+        assert_eq!(status.len(), 1, "Disabled tasks should not be scheduled");
+        assert_eq!(status[0].name, "jwks.refresh");
+        assert_eq!(status[0].schedule, "0 0 * * * *");
+        assert!(status[0].next_run.is_some());
+        assert!(status[0].last_run.is_none());
+
+        env::set_var("SCHEDULE_JWKS_REFRESH", "every so often");
+
+        assert!(
+            Scheduler::from_env().is_err(),
+            "Invalid schedules should be rejected"
+        );
+
+        env::remove_var("SCHEDULE_OUTBOX_PURGE");
+        env::remove_var("SCHEDULE_PERSON_DELETION");
+        env::remove_var("SCHEDULE_PERSON_ARCHIVAL");
+        env::remove_var("SCHEDULE_EXPORT_PURGE");
+        env::remove_var("SCHEDULE_JWKS_REFRESH");
+    }
+}
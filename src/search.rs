@@ -0,0 +1,212 @@
+//! Mirrors people into an Elasticsearch (or OpenSearch) index for fuzzy, ranked search.
+//!
+//! The index is kept up to date from the outbox, in the same way as events are relayed to the
+//! transport: person events not yet `indexed` are applied in order, and the batch stops at the
+//! first failure so a change is never overwritten by an older one. Only the document APIs
+//! common to both Elasticsearch and OpenSearch are used.
+
+use std::{env, sync::Arc, time::Duration};
+
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{events::Event, http::person::Person};
+
+const BATCH_SIZE: i64 = 100;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SearchError {
+    #[error("{0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Search index responded with {status}: {body}")]
+    Status { status: StatusCode, body: String },
+    #[error("{0}")]
+    Database(#[from] sqlx::Error),
+    #[error("{0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+#[derive(Debug)]
+pub struct ElasticsearchIndex {
+    client: Client,
+    url: String,
+    index: String,
+}
+
+impl ElasticsearchIndex {
+    fn document_url(&self, id: Uuid) -> String {
+        format!("{}/{}/_doc/{id}", self.url, self.index)
+    }
+
+    async fn check(response: reqwest::Response) -> Result<reqwest::Response, SearchError> {
+        let status = response.status();
+        if status.is_success() {
+            Ok(response)
+        } else {
+            Err(SearchError::Status {
+                status,
+                body: response.text().await.unwrap_or_default(),
+            })
+        }
+    }
+
+    async fn upsert(&self, person: &Person) -> Result<(), SearchError> {
+        let response = self
+            .client
+            .put(self.document_url(person.id))
+            .json(person)
+            .send()
+            .await?;
+
+        Self::check(response).await?;
+
+        Ok(())
+    }
+
+    async fn remove(&self, id: Uuid) -> Result<(), SearchError> {
+        let response = self.client.delete(self.document_url(id)).send().await?;
+
+        // already gone, e.g. when the person was deleted before ever being indexed
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+
+        Self::check(response).await?;
+
+        Ok(())
+    }
+
+    async fn search(&self, query: &str, limit: i64) -> Result<Vec<Person>, SearchError> {
+        let response = self
+            .client
+            .post(format!("{}/{}/_search", self.url, self.index))
+            .json(&json!({
+                "size": limit,
+                "query": {
+                    "multi_match": {
+                        "query": query,
+                        "fields": ["firstName", "familyName"],
+                        "fuzziness": "AUTO",
+                    }
+                }
+            }))
+            .send()
+            .await?;
+
+        let body: Value = Self::check(response).await?.json().await?;
+
+        body["hits"]["hits"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|hit| Ok(serde_json::from_value(hit["_source"].clone())?))
+            .collect()
+    }
+}
+
+/// Where people are searched, enabled by setting `ELASTICSEARCH_URL`
+#[derive(Clone, Debug)]
+pub enum SearchIndex {
+    Disabled,
+    Elasticsearch(Arc<ElasticsearchIndex>),
+}
+
+impl SearchIndex {
+    /// Reads `ELASTICSEARCH_URL` and `ELASTICSEARCH_INDEX` (default `people`)
+    pub fn from_env(client: Client) -> Self {
+        match env::var("ELASTICSEARCH_URL") {
+            Ok(url) => SearchIndex::Elasticsearch(Arc::new(ElasticsearchIndex {
+                client,
+                url: url.trim_end_matches('/').to_owned(),
+                index: env::var("ELASTICSEARCH_INDEX").unwrap_or_else(|_| "people".to_owned()),
+            })),
+            Err(_) => SearchIndex::Disabled,
+        }
+    }
+
+    /// The people best matching `query`, allowing for typos, most relevant first.
+    ///
+    /// Returns `None` when no index is configured.
+    pub async fn search(
+        &self,
+        query: &str,
+        limit: i64,
+    ) -> Option<Result<Vec<Person>, SearchError>> {
+        match self {
+            SearchIndex::Disabled => None,
+            SearchIndex::Elasticsearch(index) => Some(index.search(query, limit).await),
+        }
+    }
+}
+
+/// Applies the oldest person events not yet indexed, returning how many were applied
+pub async fn index_batch(db: &PgPool, index: &ElasticsearchIndex) -> Result<usize, SearchError> {
+    let mut tx = db.begin().await?;
+
+    let pending = sqlx::query!(
+        r#"
+            SELECT id, payload FROM outbox
+            WHERE indexed IS NULL AND event_type LIKE 'person.%'
+            ORDER BY id
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED;
+        "#,
+        BATCH_SIZE
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut indexed = 0;
+
+    for row in pending {
+        let outcome = match serde_json::from_value(row.payload)? {
+            Event::PersonCreated { person }
+            | Event::PersonUpdated { person }
+            | Event::PersonRestored { person } => index.upsert(&person).await,
+            // archived people are left out of search along with everything else
+            Event::PersonDeleted { person_id } | Event::PersonArchived { person_id } => {
+                index.remove(person_id).await
+            }
+            _ => Ok(()),
+        };
+
+        if let Err(e) = outcome {
+            error!("Failed to index event {}, will retry: {}", row.id, e);
+            break;
+        }
+
+        sqlx::query!("UPDATE outbox SET indexed = now() WHERE id = $1;", row.id)
+            .execute(&mut *tx)
+            .await?;
+
+        indexed += 1;
+    }
+
+    tx.commit().await?;
+
+    Ok(indexed)
+}
+
+/// Keeps the index in sync forever, polling every `OUTBOX_POLL_INTERVAL_MS` (default 1000)
+pub async fn sync(db: PgPool, index: Arc<ElasticsearchIndex>) {
+    let poll_interval = env::var("OUTBOX_POLL_INTERVAL_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(Duration::from_secs(1));
+
+    info!("Indexing people into '{}'", index.index);
+
+    loop {
+        match index_batch(&db, &index).await {
+            Ok(indexed) if indexed as i64 == BATCH_SIZE => continue,
+            Ok(_) => {}
+            Err(e) => error!("Failed to index people: {e}"),
+        }
+
+        tokio::time::sleep(poll_interval).await;
+    }
+}
@@ -0,0 +1,190 @@
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    clock,
+    events::Event,
+    http::{
+        address::{Address, NewAddress},
+        cache,
+        error::{ApiError, Context},
+    },
+    outbox,
+};
+
+/// Adding addresses to people and removing them
+#[derive(Clone, Debug)]
+pub struct AddressService {
+    db: PgPool,
+}
+
+impl AddressService {
+    pub fn new(db: PgPool) -> Self {
+        AddressService { db }
+    }
+
+    /// Creates an address and sets it as the person's current address on behalf of `actor`,
+    /// after validating the request
+    pub async fn add(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        request: &NewAddress,
+    ) -> Result<Address, ApiError> {
+        request.validate()?;
+
+        let mut tx = self.db.begin().await?;
+
+        let address = sqlx::query_as!(
+            Address,
+            r#"
+                WITH new_address AS (
+                    INSERT INTO address(building, street, town_or_city, postcode, created, last_edited)
+                    VALUES ($1, $2, $3, $4, $5, $5)
+                    RETURNING uuid, created, last_edited, building, street, town_or_city, postcode
+                )
+                UPDATE person p
+                SET address = new_address.uuid, last_edited = $5
+                FROM new_address
+                WHERE p.uuid = $6 AND p.deleted_at IS NULL
+                RETURNING new_address.uuid AS "id!", new_address.building AS "building!", new_address.street,
+                    new_address.town_or_city, new_address.postcode AS "postcode!",
+                    new_address.created AS "created!", new_address.last_edited AS "last_edited!";
+            "#,
+            request.building,
+            request.street,
+            request.town_or_city,
+            request.postcode.as_str(),
+            clock::now(),
+            person_uuid,
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to insert an address for person '{person_uuid}'"))?
+        .ok_or_else(|| ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}")))?;
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::AddressAdded {
+                person_id: person_uuid,
+                address_id: address.id,
+            },
+        )
+        .await
+        .context("Failed to queue the address added event")?;
+
+        tx.commit().await?;
+        // the person's last edited time has moved on
+        cache::invalidate(person_uuid);
+
+        info!("Client '{actor}' created an address for the person '{person_uuid}'");
+
+        Ok(address)
+    }
+
+    /// The current address of a person, if they have one
+    pub async fn find_for_person(&self, person_uuid: Uuid) -> Result<Option<Address>, ApiError> {
+        let address = sqlx::query_as!(
+            Address,
+            r#"
+                SELECT a.uuid AS id, a.building, a.street, a.town_or_city, a.postcode, a.created, a.last_edited
+                FROM address a JOIN person p ON p.address = a.uuid
+                WHERE p.uuid = $1 AND p.deleted_at IS NULL;
+            "#,
+            person_uuid
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find the address of person '{person_uuid}'"))?;
+
+        Ok(address)
+    }
+
+    /// Detaches the address from anyone living there and removes it, on behalf of `actor`
+    pub async fn remove(&self, actor: &str, address_uuid: Uuid) -> Result<(), ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        delete(&mut tx, address_uuid).await?;
+
+        tx.commit().await?;
+
+        info!("Client '{actor}' deleted the address '{address_uuid}'");
+
+        Ok(())
+    }
+
+    /// Removes the person's current address as [`remove`](Self::remove) does, for callers who
+    /// only know the person
+    pub async fn remove_for_person(&self, actor: &str, person_uuid: Uuid) -> Result<(), ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        // locked, so the address can't be changed before it's removed
+        let person = sqlx::query!(
+            r#"
+                SELECT address FROM person WHERE uuid = $1 AND deleted_at IS NULL
+                FOR UPDATE;
+            "#,
+            person_uuid
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to find person '{person_uuid}'"))?
+        .ok_or_else(|| {
+            ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}"))
+        })?;
+
+        let address_uuid = person
+            .address
+            .ok_or_else(|| ApiError::NotFound(format!("Person '{person_uuid}' has no address")))?;
+
+        delete(&mut tx, address_uuid).await?;
+
+        tx.commit().await?;
+        cache::invalidate(person_uuid);
+
+        info!("Client '{actor}' deleted the address '{address_uuid}' of person '{person_uuid}'");
+
+        Ok(())
+    }
+}
+
+/// Moves everyone out of the address and deletes it, queueing the address removed event
+async fn delete(tx: &mut PgConnection, address_uuid: Uuid) -> Result<(), ApiError> {
+    sqlx::query!(
+        r#"
+            UPDATE person SET address = NULL WHERE address = $1
+            RETURNING id;
+        "#,
+        address_uuid
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .with_context(|| format!("Failed to move people out of address '{address_uuid}'"))?;
+
+    sqlx::query!(
+        r#"
+            DELETE FROM address WHERE uuid = $1
+            RETURNING id;
+        "#,
+        address_uuid
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .with_context(|| format!("Failed to delete address '{address_uuid}'"))?
+    .ok_or_else(|| ApiError::NotFound(format!("Address not found for the UUID: {address_uuid}")))?;
+
+    outbox::enqueue(
+        tx,
+        &Event::AddressRemoved {
+            address_id: address_uuid,
+        },
+    )
+    .await
+    .context("Failed to queue the address removed event")?;
+
+    Ok(())
+}
+
+extractor!(AddressService);
@@ -0,0 +1,414 @@
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
+use time::{Duration, OffsetDateTime};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    clock,
+    events::Event,
+    http::{
+        archive::ArchivedPerson,
+        cache,
+        error::{ApiError, Context},
+        limit::Limit,
+        person::Person,
+    },
+    outbox,
+    service::{person_event, person_history},
+};
+
+/// The most people archived by one run of [`ArchiveService::archive_inactive`], the rest being
+/// left for the next
+const ARCHIVE_BATCH: i64 = 500;
+
+/// Moving people nobody has changed in a long time out of the `person` table, so they no longer
+/// weigh on every query, and bringing them back when they're needed again.
+///
+/// A person's employment, contact details, emergency contacts, consent, photo, tags, notes and
+/// relationships go with them into the archive, as JSON, since deleting the person would otherwise
+/// remove them. Any new table referencing `person` needs to be carried along the same way.
+/// Relationships are only brought back while the other person is still there to be related to.
+#[derive(Clone, Debug)]
+pub struct ArchiveService {
+    db: PgPool,
+}
+
+impl ArchiveService {
+    pub fn new(db: PgPool) -> Self {
+        ArchiveService { db }
+    }
+
+    /// The most recently archived people, up to the limit
+    pub async fn list(&self, limit: Limit) -> Result<Vec<ArchivedPerson>, ApiError> {
+        let people = sqlx::query_as!(
+            ArchivedPerson,
+            r#"
+                SELECT uuid AS id, first_name, family_name, date_of_birth, created, last_edited, archived
+                FROM person_archive
+                ORDER BY archived DESC, id DESC
+                LIMIT $1;
+            "#,
+            limit.get()
+        )
+        .fetch_all(&self.db)
+        .await
+        .context("Failed to list archived people")?;
+
+        Ok(people)
+    }
+
+    /// One archived person
+    pub async fn find(&self, person_uuid: Uuid) -> Result<ArchivedPerson, ApiError> {
+        let person = sqlx::query_as!(
+            ArchivedPerson,
+            r#"
+                SELECT uuid AS id, first_name, family_name, date_of_birth, created, last_edited, archived
+                FROM person_archive
+                WHERE uuid = $1;
+            "#,
+            person_uuid
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find archived person '{person_uuid}'"))?
+        .ok_or_else(|| not_archived(person_uuid))?;
+
+        Ok(person)
+    }
+
+    /// Archives a person on behalf of `actor`, unless they're scheduled to be deleted
+    pub async fn archive(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+    ) -> Result<ArchivedPerson, ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        let delete_at = sqlx::query_scalar!(
+            "SELECT delete_at FROM person WHERE uuid = $1 AND deleted_at IS NULL FOR UPDATE;",
+            person_uuid
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to find person '{person_uuid}'"))?
+        .ok_or_else(|| {
+            ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}"))
+        })?;
+
+        if delete_at.is_some() {
+            return Err(ApiError::Conflict(format!(
+                "Person '{person_uuid}' is scheduled to be deleted, so can't be archived"
+            )));
+        }
+
+        let archived = move_to_archive(&mut tx, actor, person_uuid).await?;
+
+        tx.commit().await?;
+        cache::invalidate(person_uuid);
+
+        info!("Client '{actor}' archived person '{person_uuid}'");
+
+        Ok(archived)
+    }
+
+    /// Moves an archived person back into the `person` table on behalf of `actor`, along with
+    /// everything archived with them. Restoring counts as a change, so they aren't archived
+    /// again straight away.
+    pub async fn restore(&self, actor: &str, person_uuid: Uuid) -> Result<Person, ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        let related = sqlx::query_scalar!(
+            "SELECT related FROM person_archive WHERE uuid = $1 FOR UPDATE;",
+            person_uuid
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to find archived person '{person_uuid}'"))?
+        .ok_or_else(|| not_archived(person_uuid))?;
+
+        let person = sqlx::query_as!(
+            Person,
+            r#"
+                WITH archived AS (
+                    DELETE FROM person_archive WHERE uuid = $1
+                    RETURNING *
+                )
+                INSERT INTO person (id, uuid, created, last_edited, first_name, family_name,
+                    date_of_birth, address, external_id, user_name, legal_hold, version)
+                SELECT id, uuid, created, $2, first_name, family_name, date_of_birth, address,
+                    external_id, user_name, legal_hold, version + 1
+                FROM archived
+                RETURNING uuid AS id, created, last_edited, first_name, family_name, date_of_birth, version;
+            "#,
+            person_uuid,
+            clock::now()
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| match e {
+            // someone else has since taken their user name or external ID
+            sqlx::Error::Database(dbe) if dbe.constraint().is_some() => {
+                ApiError::Conflict(format!(
+                    "Unable to restore person due to constraint: {}",
+                    dbe.constraint().unwrap()
+                ))
+            }
+            _ => ApiError::from(e).context(format!("Failed to restore person '{person_uuid}'")),
+        })?;
+
+        restore_related(&mut tx, &related)
+            .await
+            .with_context(|| format!("Failed to restore the details of person '{person_uuid}'"))?;
+
+        let event = Event::PersonRestored {
+            person: person.clone(),
+        };
+        person_event::append(&mut tx, actor, &event).await?;
+        person_history::record(&mut tx, actor, &event).await?;
+        outbox::enqueue(&mut tx, &event)
+            .await
+            .context("Failed to queue the person restored event")?;
+
+        tx.commit().await?;
+
+        info!("Client '{actor}' restored person '{person_uuid}' from the archive");
+
+        Ok(person)
+    }
+
+    /// Archives people who haven't been changed for the given number of days, besides those
+    /// scheduled to be deleted, returning how many were archived
+    pub async fn archive_inactive(&self, inactive_days: i32) -> Result<usize, ApiError> {
+        let cutoff = clock::now() - Duration::days(inactive_days.into());
+
+        let inactive = sqlx::query_scalar!(
+            r#"
+                SELECT uuid FROM person
+                WHERE last_edited < $1 AND delete_at IS NULL AND deleted_at IS NULL
+                ORDER BY last_edited
+                LIMIT $2;
+            "#,
+            cutoff,
+            ARCHIVE_BATCH
+        )
+        .fetch_all(&self.db)
+        .await
+        .context("Failed to find inactive people")?;
+
+        let mut archived = 0;
+        for person_uuid in inactive {
+            if self.archive_if_inactive(person_uuid, cutoff).await? {
+                archived += 1;
+            }
+        }
+
+        Ok(archived)
+    }
+
+    /// Archives the person, so long as they're still inactive once locked
+    async fn archive_if_inactive(
+        &self,
+        person_uuid: Uuid,
+        cutoff: OffsetDateTime,
+    ) -> Result<bool, ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        // skips anyone locked by a request, as it may be about to change them
+        let inactive = sqlx::query_scalar!(
+            r#"
+                SELECT id FROM person
+                WHERE uuid = $1 AND last_edited < $2 AND delete_at IS NULL AND deleted_at IS NULL
+                FOR UPDATE SKIP LOCKED;
+            "#,
+            person_uuid,
+            cutoff
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to lock person '{person_uuid}' for archiving"))?;
+
+        if inactive.is_none() {
+            return Ok(false);
+        }
+
+        move_to_archive(&mut tx, "person.archive_inactive", person_uuid).await?;
+
+        tx.commit().await?;
+        cache::invalidate(person_uuid);
+
+        info!("Archived inactive person '{person_uuid}'");
+
+        Ok(true)
+    }
+}
+
+fn not_archived(person_uuid: Uuid) -> ApiError {
+    ApiError::NotFound(format!(
+        "Archived person not found for the UUID: {person_uuid}"
+    ))
+}
+
+/// Copies a person, with everything referencing them, into the archive and deletes them within
+/// the transaction on behalf of `actor`, queueing the event saying so
+async fn move_to_archive(
+    conn: &mut PgConnection,
+    actor: &str,
+    person_uuid: Uuid,
+) -> Result<ArchivedPerson, ApiError> {
+    let archived = sqlx::query_as!(
+        ArchivedPerson,
+        r#"
+            INSERT INTO person_archive (id, uuid, created, last_edited, first_name, family_name,
+                date_of_birth, address, external_id, user_name, legal_hold, version, related, archived)
+            SELECT p.id, p.uuid, p.created, p.last_edited, p.first_name, p.family_name,
+                p.date_of_birth, p.address, p.external_id, p.user_name, p.legal_hold, p.version,
+                jsonb_build_object(
+                    'employment', (SELECT COALESCE(jsonb_agg(to_jsonb(e)), '[]')
+                        FROM person_employment e WHERE e.person = p.uuid),
+                    'emergencyContacts', (SELECT COALESCE(jsonb_agg(to_jsonb(c)), '[]')
+                        FROM person_emergency_contact c WHERE c.person = p.uuid),
+                    'consent', (SELECT COALESCE(jsonb_agg(to_jsonb(c)), '[]')
+                        FROM person_consent c WHERE c.person = p.uuid),
+                    'emailAddresses', (SELECT COALESCE(jsonb_agg(to_jsonb(e)), '[]')
+                        FROM person_email_address e WHERE e.person = p.uuid),
+                    'phoneNumbers', (SELECT COALESCE(jsonb_agg(to_jsonb(n)), '[]')
+                        FROM person_phone_number n WHERE n.person = p.uuid),
+                    'photo', (SELECT COALESCE(jsonb_agg(to_jsonb(f)), '[]')
+                        FROM person_photo f WHERE f.person = p.uuid),
+                    'tags', (SELECT COALESCE(jsonb_agg(to_jsonb(t)), '[]')
+                        FROM person_tag t WHERE t.person = p.uuid),
+                    'notes', (SELECT COALESCE(jsonb_agg(to_jsonb(n)), '[]')
+                        FROM person_note n WHERE n.person = p.uuid),
+                    'relationships', (SELECT COALESCE(jsonb_agg(to_jsonb(r)), '[]')
+                        FROM relationship r WHERE p.uuid IN (r.person, r.related))
+                ),
+                $2
+            FROM person p
+            WHERE p.uuid = $1
+            RETURNING uuid AS id, first_name, family_name, date_of_birth, created, last_edited, archived;
+        "#,
+        person_uuid,
+        clock::now()
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .with_context(|| format!("Failed to archive person '{person_uuid}'"))?;
+
+    sqlx::query!("DELETE FROM person WHERE uuid = $1;", person_uuid)
+        .execute(&mut *conn)
+        .await
+        .with_context(|| format!("Failed to remove archived person '{person_uuid}'"))?;
+
+    let event = Event::PersonArchived {
+        person_id: person_uuid,
+    };
+    person_event::append(conn, actor, &event).await?;
+    person_history::record(conn, actor, &event).await?;
+    outbox::enqueue(conn, &event)
+        .await
+        .context("Failed to queue the person archived event")?;
+
+    Ok(archived)
+}
+
+/// Puts back what was archived along with a person, once the person themselves is back
+async fn restore_related(conn: &mut PgConnection, related: &Value) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            INSERT INTO person_employment
+            SELECT * FROM jsonb_populate_recordset(NULL::person_employment, $1::JSONB -> 'employment');
+        "#,
+        related
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO person_emergency_contact
+            SELECT * FROM jsonb_populate_recordset(NULL::person_emergency_contact, $1::JSONB -> 'emergencyContacts');
+        "#,
+        related
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO person_consent
+            SELECT * FROM jsonb_populate_recordset(NULL::person_consent, $1::JSONB -> 'consent');
+        "#,
+        related
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO person_email_address
+            SELECT * FROM jsonb_populate_recordset(NULL::person_email_address, $1::JSONB -> 'emailAddresses');
+        "#,
+        related
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO person_phone_number
+            SELECT * FROM jsonb_populate_recordset(NULL::person_phone_number, $1::JSONB -> 'phoneNumbers');
+        "#,
+        related
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO person_photo
+            SELECT * FROM jsonb_populate_recordset(NULL::person_photo, $1::JSONB -> 'photo');
+        "#,
+        related
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO person_tag
+            SELECT * FROM jsonb_populate_recordset(NULL::person_tag, $1::JSONB -> 'tags');
+        "#,
+        related
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO person_note
+            SELECT * FROM jsonb_populate_recordset(NULL::person_note, $1::JSONB -> 'notes');
+        "#,
+        related
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // the other person may have been deleted or archived since, or linked again the same way
+    sqlx::query!(
+        r#"
+            INSERT INTO relationship
+            SELECT r.* FROM jsonb_populate_recordset(NULL::relationship, $1::JSONB -> 'relationships') r
+            WHERE EXISTS (SELECT 1 FROM person WHERE uuid = r.person)
+                AND EXISTS (SELECT 1 FROM person WHERE uuid = r.related)
+            ON CONFLICT DO NOTHING;
+        "#,
+        related
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+extractor!(ArchiveService);
@@ -0,0 +1,142 @@
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    clock,
+    events::Event,
+    http::{
+        consent::{Consent, ConsentChannel, ConsentPurpose, NewConsent},
+        error::{ApiError, Context},
+    },
+    outbox,
+    service::person::lock_person,
+};
+
+/// Recording what people have consented to their details being used for
+#[derive(Clone, Debug)]
+pub struct ConsentService {
+    db: PgPool,
+}
+
+impl ConsentService {
+    pub fn new(db: PgPool) -> Self {
+        ConsentService { db }
+    }
+
+    /// The latest consent recorded for each purpose the person has been asked about
+    pub async fn current(&self, person_uuid: Uuid) -> Result<Vec<Consent>, ApiError> {
+        let mut conn = self.db.acquire().await?;
+        lock_person(&mut conn, person_uuid, false).await?;
+
+        let consents = sqlx::query_as!(
+            Consent,
+            r#"
+                SELECT DISTINCT ON (purpose)
+                    uuid AS id, person AS person_id, purpose AS "purpose: ConsentPurpose", granted,
+                    channel AS "channel: ConsentChannel", recorded
+                FROM person_consent
+                WHERE person = $1
+                ORDER BY purpose, recorded DESC, id DESC;
+            "#,
+            person_uuid
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .with_context(|| format!("Failed to list the consent of person '{person_uuid}'"))?;
+
+        Ok(consents)
+    }
+
+    /// Every consent recorded for the person, optionally only for one purpose, newest first
+    pub async fn history(
+        &self,
+        person_uuid: Uuid,
+        purpose: Option<ConsentPurpose>,
+    ) -> Result<Vec<Consent>, ApiError> {
+        let mut conn = self.db.acquire().await?;
+        lock_person(&mut conn, person_uuid, false).await?;
+
+        let consents = sqlx::query_as!(
+            Consent,
+            r#"
+                SELECT uuid AS id, person AS person_id, purpose AS "purpose: ConsentPurpose", granted,
+                    channel AS "channel: ConsentChannel", recorded
+                FROM person_consent
+                WHERE person = $1 AND ($2::TEXT IS NULL OR purpose = $2)
+                ORDER BY recorded DESC, id DESC;
+            "#,
+            person_uuid,
+            purpose as Option<ConsentPurpose>
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .with_context(|| {
+            format!("Failed to list the consent history of person '{person_uuid}'")
+        })?;
+
+        Ok(consents)
+    }
+
+    /// Records a person giving or withdrawing their consent on behalf of `actor`
+    pub async fn record(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        request: &NewConsent,
+    ) -> Result<Consent, ApiError> {
+        request.validate()?;
+
+        let mut tx = self.db.begin().await?;
+        lock_person(&mut tx, person_uuid, false).await?;
+
+        let consent = sqlx::query_as!(
+            Consent,
+            r#"
+                INSERT INTO person_consent (person, purpose, granted, channel, recorded)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING uuid AS id, person AS person_id, purpose AS "purpose: ConsentPurpose",
+                    granted, channel AS "channel: ConsentChannel", recorded;
+            "#,
+            person_uuid,
+            request.purpose as ConsentPurpose,
+            request.granted,
+            request.channel as ConsentChannel,
+            clock::now()
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to record consent for person '{person_uuid}'"))?;
+
+        let event = if consent.granted {
+            Event::ConsentGranted {
+                person_id: person_uuid,
+                consent_id: consent.id,
+                purpose: consent.purpose,
+            }
+        } else {
+            Event::ConsentRevoked {
+                person_id: person_uuid,
+                consent_id: consent.id,
+                purpose: consent.purpose,
+            }
+        };
+
+        outbox::enqueue(&mut tx, &event)
+            .await
+            .context("Failed to queue the consent event")?;
+
+        tx.commit().await?;
+
+        info!(
+            "Client '{actor}' recorded that person '{person_uuid}' {} consent for {}",
+            if consent.granted { "gave" } else { "withdrew" },
+            consent.purpose.as_str()
+        );
+
+        Ok(consent)
+    }
+}
+
+extractor!(ConsentService);
@@ -0,0 +1,217 @@
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    clock,
+    events::Event,
+    http::{
+        contact_detail::{ContactDetail, ContactType, NewContactDetail},
+        error::{ApiError, Context},
+    },
+    outbox,
+    service::person::lock_person,
+};
+
+/// Recording the email addresses and phone numbers people can be contacted on, kept in a table
+/// each but served together
+#[derive(Clone, Debug)]
+pub struct ContactDetailService {
+    db: PgPool,
+}
+
+impl ContactDetailService {
+    pub fn new(db: PgPool) -> Self {
+        ContactDetailService { db }
+    }
+
+    /// A person's contact details, in the order they were added
+    pub async fn list(&self, person_uuid: Uuid) -> Result<Vec<ContactDetail>, ApiError> {
+        let mut conn = self.db.acquire().await?;
+        lock_person(&mut conn, person_uuid, false).await?;
+
+        let details = sqlx::query_as!(
+            ContactDetail,
+            r#"
+                SELECT uuid AS "id!", person AS "person_id!", 'email' AS "contact_type!: ContactType",
+                    address AS "value!", label, created AS "created!"
+                FROM person_email_address
+                WHERE person = $1
+                UNION ALL
+                SELECT uuid, person, 'phone', number, label, created
+                FROM person_phone_number
+                WHERE person = $1
+                ORDER BY 6, 1;
+            "#,
+            person_uuid
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .with_context(|| format!("Failed to list the contact details of person '{person_uuid}'"))?;
+
+        Ok(details)
+    }
+
+    /// One of a person's contact details
+    pub async fn find(
+        &self,
+        person_uuid: Uuid,
+        detail_uuid: Uuid,
+    ) -> Result<ContactDetail, ApiError> {
+        let detail = sqlx::query_as!(
+            ContactDetail,
+            r#"
+                SELECT uuid AS "id!", person AS "person_id!", 'email' AS "contact_type!: ContactType",
+                    address AS "value!", label, created AS "created!"
+                FROM person_email_address
+                WHERE uuid = $1 AND person = $2
+                UNION ALL
+                SELECT uuid, person, 'phone', number, label, created
+                FROM person_phone_number
+                WHERE uuid = $1 AND person = $2;
+            "#,
+            detail_uuid,
+            person_uuid
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find contact detail '{detail_uuid}'"))?
+        .ok_or_else(|| not_found(detail_uuid))?;
+
+        Ok(detail)
+    }
+
+    /// Adds an email address or phone number for a person on behalf of `actor`, after
+    /// validating the request, unless they already have it
+    pub async fn add(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        request: &NewContactDetail,
+    ) -> Result<ContactDetail, ApiError> {
+        request.validate()?;
+        let value = request.stored_value()?;
+
+        let mut tx = self.db.begin().await?;
+        lock_person(&mut tx, person_uuid, true).await?;
+
+        let inserted = match request.contact_type {
+            ContactType::Email => {
+                sqlx::query_as!(
+                    ContactDetail,
+                    r#"
+                        INSERT INTO person_email_address (person, address, label, created)
+                        VALUES ($1, $2, $3, $4)
+                        RETURNING uuid AS id, person AS person_id,
+                            'email' AS "contact_type!: ContactType", address AS value, label, created;
+                    "#,
+                    person_uuid,
+                    value,
+                    request.label,
+                    clock::now()
+                )
+                .fetch_one(&mut *tx)
+                .await
+            }
+            ContactType::Phone => {
+                sqlx::query_as!(
+                    ContactDetail,
+                    r#"
+                        INSERT INTO person_phone_number (person, number, label, created)
+                        VALUES ($1, $2, $3, $4)
+                        RETURNING uuid AS id, person AS person_id,
+                            'phone' AS "contact_type!: ContactType", number AS value, label, created;
+                    "#,
+                    person_uuid,
+                    value,
+                    request.label,
+                    clock::now()
+                )
+                .fetch_one(&mut *tx)
+                .await
+            }
+        };
+        let detail = inserted.map_err(|e| match e {
+            sqlx::Error::Database(dbe) if dbe.is_unique_violation() => ApiError::Conflict(format!(
+                "Person '{person_uuid}' already has the contact detail '{value}'"
+            )),
+            _ => ApiError::from(e).context(format!(
+                "Failed to insert a contact detail for person '{person_uuid}'"
+            )),
+        })?;
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::ContactDetailAdded {
+                person_id: person_uuid,
+                detail_id: detail.id,
+            },
+        )
+        .await
+        .context("Failed to queue the contact detail added event")?;
+
+        tx.commit().await?;
+
+        info!(
+            "Client '{actor}' added contact detail '{}' for the person '{person_uuid}'",
+            detail.id
+        );
+
+        Ok(detail)
+    }
+
+    /// Removes one of a person's contact details on behalf of `actor`
+    pub async fn remove(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        detail_uuid: Uuid,
+    ) -> Result<(), ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        let deleted = sqlx::query_scalar!(
+            r#"
+                WITH email AS (
+                    DELETE FROM person_email_address WHERE uuid = $1 AND person = $2 RETURNING id
+                ), phone AS (
+                    DELETE FROM person_phone_number WHERE uuid = $1 AND person = $2 RETURNING id
+                )
+                SELECT (SELECT COUNT(*) FROM email) + (SELECT COUNT(*) FROM phone) AS "deleted!";
+            "#,
+            detail_uuid,
+            person_uuid
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to delete contact detail '{detail_uuid}'"))?;
+
+        if deleted == 0 {
+            return Err(not_found(detail_uuid));
+        }
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::ContactDetailRemoved {
+                person_id: person_uuid,
+                detail_id: detail_uuid,
+            },
+        )
+        .await
+        .context("Failed to queue the contact detail removed event")?;
+
+        tx.commit().await?;
+
+        info!("Client '{actor}' deleted contact detail '{detail_uuid}'");
+
+        Ok(())
+    }
+}
+
+fn not_found(detail_uuid: Uuid) -> ApiError {
+    ApiError::NotFound(format!(
+        "Contact detail not found for the UUID: {detail_uuid}"
+    ))
+}
+
+extractor!(ContactDetailService);
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    clock,
+    events::Event,
+    http::{
+        emergency_contact::{EmergencyContact, NewEmergencyContact, MAX_EMERGENCY_CONTACTS},
+        error::{ApiError, Context},
+        person::{ExpandedPerson, Person},
+    },
+    outbox,
+    service::person::lock_person,
+};
+
+/// Recording who to contact about people in an emergency
+#[derive(Clone, Debug)]
+pub struct EmergencyContactService {
+    db: PgPool,
+}
+
+impl EmergencyContactService {
+    pub fn new(db: PgPool) -> Self {
+        EmergencyContactService { db }
+    }
+
+    /// A person's emergency contacts, in the order they were added
+    pub async fn list(&self, person_uuid: Uuid) -> Result<Vec<EmergencyContact>, ApiError> {
+        let mut conn = self.db.acquire().await?;
+        lock_person(&mut conn, person_uuid, false).await?;
+
+        let contacts = sqlx::query_as!(
+            EmergencyContact,
+            r#"
+                SELECT uuid AS id, person AS person_id, name, relationship, phone, created, last_edited
+                FROM person_emergency_contact
+                WHERE person = $1
+                ORDER BY created, id;
+            "#,
+            person_uuid
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .with_context(|| format!("Failed to list the emergency contacts of person '{person_uuid}'"))?;
+
+        Ok(contacts)
+    }
+
+    /// The people along with their emergency contacts, in a single query
+    pub async fn expand(&self, people: Vec<Person>) -> Result<Vec<ExpandedPerson>, ApiError> {
+        let person_uuids: Vec<_> = people.iter().map(|person| person.id).collect();
+
+        let rows = sqlx::query_as!(
+            EmergencyContact,
+            r#"
+                SELECT uuid AS id, person AS person_id, name, relationship, phone, created, last_edited
+                FROM person_emergency_contact
+                WHERE person = ANY($1)
+                ORDER BY created, id;
+            "#,
+            &person_uuids
+        )
+        .fetch_all(&self.db)
+        .await
+        .context("Failed to list the emergency contacts of people")?;
+
+        let mut contacts: HashMap<Uuid, Vec<EmergencyContact>> = HashMap::new();
+        for contact in rows {
+            contacts.entry(contact.person_id).or_default().push(contact);
+        }
+
+        Ok(people
+            .into_iter()
+            .map(|person| ExpandedPerson {
+                emergency_contacts: Some(contacts.remove(&person.id).unwrap_or_default()),
+                person,
+                address: None,
+            })
+            .collect())
+    }
+
+    /// One of a person's emergency contacts
+    pub async fn find(
+        &self,
+        person_uuid: Uuid,
+        contact_uuid: Uuid,
+    ) -> Result<EmergencyContact, ApiError> {
+        let contact = sqlx::query_as!(
+            EmergencyContact,
+            r#"
+                SELECT uuid AS id, person AS person_id, name, relationship, phone, created, last_edited
+                FROM person_emergency_contact
+                WHERE uuid = $1 AND person = $2;
+            "#,
+            contact_uuid,
+            person_uuid
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find emergency contact '{contact_uuid}'"))?
+        .ok_or_else(|| not_found(contact_uuid))?;
+
+        Ok(contact)
+    }
+
+    /// Adds an emergency contact for a person on behalf of `actor`, after validating the
+    /// request, unless they already have as many as they can
+    pub async fn add(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        request: &NewEmergencyContact,
+    ) -> Result<EmergencyContact, ApiError> {
+        request.validate()?;
+
+        let mut tx = self.db.begin().await?;
+        // held until the contact is added, so concurrent requests can't both take the last place
+        lock_person(&mut tx, person_uuid, true).await?;
+
+        let existing = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM person_emergency_contact WHERE person = $1;"#,
+            person_uuid
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .with_context(|| {
+            format!("Failed to count the emergency contacts of person '{person_uuid}'")
+        })?;
+
+        if existing >= MAX_EMERGENCY_CONTACTS {
+            return Err(ApiError::Conflict(format!(
+                "A person can have at most {MAX_EMERGENCY_CONTACTS} emergency contacts"
+            )));
+        }
+
+        let contact = sqlx::query_as!(
+            EmergencyContact,
+            r#"
+                INSERT INTO person_emergency_contact (person, name, relationship, phone, created, last_edited)
+                VALUES ($1, $2, $3, $4, $5, $5)
+                RETURNING uuid AS id, person AS person_id, name, relationship, phone, created, last_edited;
+            "#,
+            person_uuid,
+            request.name,
+            request.relationship,
+            request.phone.as_str(),
+            clock::now()
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .with_context(|| {
+            format!("Failed to insert an emergency contact for person '{person_uuid}'")
+        })?;
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::EmergencyContactAdded {
+                person_id: person_uuid,
+                contact_id: contact.id,
+            },
+        )
+        .await
+        .context("Failed to queue the emergency contact added event")?;
+
+        tx.commit().await?;
+
+        info!(
+            "Client '{actor}' added emergency contact '{}' for the person '{person_uuid}'",
+            contact.id
+        );
+
+        Ok(contact)
+    }
+
+    /// Replaces one of a person's emergency contacts on behalf of `actor`
+    pub async fn update(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        contact_uuid: Uuid,
+        request: &NewEmergencyContact,
+    ) -> Result<EmergencyContact, ApiError> {
+        request.validate()?;
+
+        let mut tx = self.db.begin().await?;
+
+        let contact = sqlx::query_as!(
+            EmergencyContact,
+            r#"
+                UPDATE person_emergency_contact
+                SET name = $1, relationship = $2, phone = $3, last_edited = $4
+                WHERE uuid = $5 AND person = $6
+                RETURNING uuid AS id, person AS person_id, name, relationship, phone, created, last_edited;
+            "#,
+            request.name,
+            request.relationship,
+            request.phone.as_str(),
+            clock::now(),
+            contact_uuid,
+            person_uuid
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to update emergency contact '{contact_uuid}'"))?
+        .ok_or_else(|| not_found(contact_uuid))?;
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::EmergencyContactUpdated {
+                person_id: person_uuid,
+                contact_id: contact_uuid,
+            },
+        )
+        .await
+        .context("Failed to queue the emergency contact updated event")?;
+
+        tx.commit().await?;
+
+        info!("Client '{actor}' updated emergency contact '{contact_uuid}'");
+
+        Ok(contact)
+    }
+
+    /// Removes one of a person's emergency contacts on behalf of `actor`
+    pub async fn remove(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        contact_uuid: Uuid,
+    ) -> Result<(), ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM person_emergency_contact WHERE uuid = $1 AND person = $2
+                RETURNING id;
+            "#,
+            contact_uuid,
+            person_uuid
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to delete emergency contact '{contact_uuid}'"))?
+        .ok_or_else(|| not_found(contact_uuid))?;
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::EmergencyContactRemoved {
+                person_id: person_uuid,
+                contact_id: contact_uuid,
+            },
+        )
+        .await
+        .context("Failed to queue the emergency contact removed event")?;
+
+        tx.commit().await?;
+
+        info!("Client '{actor}' deleted emergency contact '{contact_uuid}'");
+
+        Ok(())
+    }
+}
+
+fn not_found(contact_uuid: Uuid) -> ApiError {
+    ApiError::NotFound(format!(
+        "Emergency contact not found for the UUID: {contact_uuid}"
+    ))
+}
+
+extractor!(EmergencyContactService);
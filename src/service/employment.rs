@@ -0,0 +1,288 @@
+use sqlx::{PgConnection, PgPool};
+use time::Date;
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    clock,
+    events::Event,
+    http::{
+        employment::{Employment, NewEmployment},
+        error::{ApiError, Context},
+    },
+    outbox,
+    service::person::lock_person,
+};
+
+/// Recording where people have worked
+#[derive(Clone, Debug)]
+pub struct EmploymentService {
+    db: PgPool,
+}
+
+impl EmploymentService {
+    pub fn new(db: PgPool) -> Self {
+        EmploymentService { db }
+    }
+
+    /// A person's employment history, earliest first
+    pub async fn list(&self, person_uuid: Uuid) -> Result<Vec<Employment>, ApiError> {
+        let mut conn = self.db.acquire().await?;
+        lock_person(&mut conn, person_uuid, false).await?;
+
+        let employments = sqlx::query_as!(
+            Employment,
+            r#"
+                SELECT uuid AS id, person AS person_id, employer, role, start_date, end_date, created, last_edited
+                FROM person_employment
+                WHERE person = $1
+                ORDER BY start_date, created;
+            "#,
+            person_uuid
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .with_context(|| format!("Failed to list the employment of person '{person_uuid}'"))?;
+
+        Ok(employments)
+    }
+
+    /// One of a person's employments
+    pub async fn find(
+        &self,
+        person_uuid: Uuid,
+        employment_uuid: Uuid,
+    ) -> Result<Employment, ApiError> {
+        let employment = sqlx::query_as!(
+            Employment,
+            r#"
+                SELECT uuid AS id, person AS person_id, employer, role, start_date, end_date, created, last_edited
+                FROM person_employment
+                WHERE uuid = $1 AND person = $2;
+            "#,
+            employment_uuid,
+            person_uuid
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find employment '{employment_uuid}'"))?
+        .ok_or_else(|| not_found(employment_uuid))?;
+
+        Ok(employment)
+    }
+
+    /// Adds to a person's employment history on behalf of `actor`, after validating the
+    /// request and checking it doesn't overlap any of their other employment
+    pub async fn add(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        request: &NewEmployment,
+    ) -> Result<Employment, ApiError> {
+        request.validate()?;
+
+        let mut tx = self.db.begin().await?;
+        lock_person(&mut tx, person_uuid, true).await?;
+        check_overlap(&mut tx, person_uuid, None, request).await?;
+
+        let employment = sqlx::query_as!(
+            Employment,
+            r#"
+                INSERT INTO person_employment (person, employer, role, start_date, end_date, created, last_edited)
+                VALUES ($1, $2, $3, $4, $5, $6, $6)
+                RETURNING uuid AS id, person AS person_id, employer, role, start_date, end_date, created, last_edited;
+            "#,
+            person_uuid,
+            request.employer,
+            request.role,
+            request.start_date,
+            request.end_date,
+            clock::now()
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to insert employment for person '{person_uuid}'"))?;
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::EmploymentAdded {
+                person_id: person_uuid,
+                employment_id: employment.id,
+            },
+        )
+        .await
+        .context("Failed to queue the employment added event")?;
+
+        tx.commit().await?;
+
+        info!(
+            "Client '{actor}' added employment '{}' for the person '{person_uuid}'",
+            employment.id
+        );
+
+        Ok(employment)
+    }
+
+    /// Replaces one of a person's employments on behalf of `actor`, with the same checks as
+    /// [`add`](Self::add)
+    pub async fn update(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        employment_uuid: Uuid,
+        request: &NewEmployment,
+    ) -> Result<Employment, ApiError> {
+        request.validate()?;
+
+        let mut tx = self.db.begin().await?;
+        lock_person(&mut tx, person_uuid, true).await?;
+        check_overlap(&mut tx, person_uuid, Some(employment_uuid), request).await?;
+
+        let employment = sqlx::query_as!(
+            Employment,
+            r#"
+                UPDATE person_employment
+                SET employer = $1, role = $2, start_date = $3, end_date = $4, last_edited = $5
+                WHERE uuid = $6 AND person = $7
+                RETURNING uuid AS id, person AS person_id, employer, role, start_date, end_date, created, last_edited;
+            "#,
+            request.employer,
+            request.role,
+            request.start_date,
+            request.end_date,
+            clock::now(),
+            employment_uuid,
+            person_uuid
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to update employment '{employment_uuid}'"))?
+        .ok_or_else(|| not_found(employment_uuid))?;
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::EmploymentUpdated {
+                person_id: person_uuid,
+                employment_id: employment_uuid,
+            },
+        )
+        .await
+        .context("Failed to queue the employment updated event")?;
+
+        tx.commit().await?;
+
+        info!("Client '{actor}' updated employment '{employment_uuid}'");
+
+        Ok(employment)
+    }
+
+    /// Removes one of a person's employments on behalf of `actor`
+    pub async fn remove(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        employment_uuid: Uuid,
+    ) -> Result<(), ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM person_employment WHERE uuid = $1 AND person = $2
+                RETURNING id;
+            "#,
+            employment_uuid,
+            person_uuid
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to delete employment '{employment_uuid}'"))?
+        .ok_or_else(|| not_found(employment_uuid))?;
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::EmploymentRemoved {
+                person_id: person_uuid,
+                employment_id: employment_uuid,
+            },
+        )
+        .await
+        .context("Failed to queue the employment removed event")?;
+
+        tx.commit().await?;
+
+        info!("Client '{actor}' deleted employment '{employment_uuid}'");
+
+        Ok(())
+    }
+}
+
+fn not_found(employment_uuid: Uuid) -> ApiError {
+    ApiError::NotFound(format!(
+        "Employment not found for the UUID: {employment_uuid}"
+    ))
+}
+
+/// Refuses employment overlapping any of the person's others, besides the one being replaced
+async fn check_overlap(
+    conn: &mut PgConnection,
+    person_uuid: Uuid,
+    replacing: Option<Uuid>,
+    request: &NewEmployment,
+) -> Result<(), ApiError> {
+    let overlapping = find_overlap(
+        conn,
+        person_uuid,
+        replacing,
+        request.start_date,
+        request.end_date,
+    )
+    .await?;
+
+    match overlapping {
+        Some(other) => Err(ApiError::Conflict(format!(
+            "The employment overlaps with '{}' at {}",
+            other.uuid, other.employer
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// One of a person's employments found to overlap another
+pub(crate) struct Overlap {
+    pub uuid: Uuid,
+    pub employer: String,
+}
+
+/// The person's earliest employment overlapping the given dates, besides the one being
+/// replaced. Both ends are inclusive, and employment without an end is ongoing.
+pub(crate) async fn find_overlap(
+    conn: &mut PgConnection,
+    person_uuid: Uuid,
+    replacing: Option<Uuid>,
+    start_date: Date,
+    end_date: Option<Date>,
+) -> Result<Option<Overlap>, ApiError> {
+    let overlapping = sqlx::query_as!(
+        Overlap,
+        r#"
+            SELECT uuid, employer FROM person_employment
+            WHERE person = $1
+                AND ($2::UUID IS NULL OR uuid <> $2)
+                AND daterange(start_date, end_date, '[]') && daterange($3, $4, '[]')
+            ORDER BY start_date
+            LIMIT 1;
+        "#,
+        person_uuid,
+        replacing,
+        start_date,
+        end_date
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .with_context(|| format!("Failed to check the employment of person '{person_uuid}'"))?;
+
+    Ok(overlapping)
+}
+
+extractor!(EmploymentService);
@@ -0,0 +1,166 @@
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    events::Event,
+    http::{
+        error::{ApiError, Context},
+        legal_hold::{LegalHold, NewLegalHold},
+    },
+    outbox,
+};
+
+/// Placing people under legal hold, keeping them from being deleted until it's lifted
+#[derive(Clone, Debug)]
+pub struct LegalHoldService {
+    db: PgPool,
+}
+
+impl LegalHoldService {
+    pub fn new(db: PgPool) -> Self {
+        LegalHoldService { db }
+    }
+
+    /// Whether the person is under legal hold
+    pub async fn find(&self, person_uuid: Uuid) -> Result<LegalHold, ApiError> {
+        let held = sqlx::query_scalar!(
+            "SELECT legal_hold FROM person WHERE uuid = $1 AND deleted_at IS NULL;",
+            person_uuid
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find the legal hold of person '{person_uuid}'"))?
+        .ok_or_else(|| not_found(person_uuid))?;
+
+        Ok(LegalHold {
+            person_id: person_uuid,
+            held,
+        })
+    }
+
+    /// Places the person under legal hold on behalf of `actor`, doing nothing when they
+    /// already are
+    pub async fn place(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        request: &NewLegalHold,
+    ) -> Result<LegalHold, ApiError> {
+        request.validate()?;
+
+        let mut tx = self.db.begin().await?;
+
+        if !set(&mut tx, person_uuid, true).await? {
+            return Ok(LegalHold {
+                person_id: person_uuid,
+                held: true,
+            });
+        }
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::LegalHoldPlaced {
+                person_id: person_uuid,
+                reason: request.reason.clone(),
+            },
+        )
+        .await
+        .context("Failed to queue the legal hold placed event")?;
+
+        tx.commit().await?;
+
+        info!(
+            "Client '{actor}' placed person '{person_uuid}' under legal hold: {}",
+            request.reason
+        );
+
+        Ok(LegalHold {
+            person_id: person_uuid,
+            held: true,
+        })
+    }
+
+    /// Lifts the person's legal hold on behalf of `actor`, doing nothing when they aren't
+    /// under one
+    pub async fn lift(&self, actor: &str, person_uuid: Uuid) -> Result<(), ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        if !set(&mut tx, person_uuid, false).await? {
+            return Ok(());
+        }
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::LegalHoldLifted {
+                person_id: person_uuid,
+            },
+        )
+        .await
+        .context("Failed to queue the legal hold lifted event")?;
+
+        tx.commit().await?;
+
+        info!("Client '{actor}' lifted the legal hold on person '{person_uuid}'");
+
+        Ok(())
+    }
+}
+
+fn not_found(person_uuid: Uuid) -> ApiError {
+    ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}"))
+}
+
+/// Holds or releases the person, returning whether that changed anything
+async fn set(conn: &mut PgConnection, person_uuid: Uuid, held: bool) -> Result<bool, ApiError> {
+    let was_held = sqlx::query_scalar!(
+        "SELECT legal_hold FROM person WHERE uuid = $1 AND deleted_at IS NULL FOR UPDATE;",
+        person_uuid
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .with_context(|| format!("Failed to find the legal hold of person '{person_uuid}'"))?
+    .ok_or_else(|| not_found(person_uuid))?;
+
+    if was_held == held {
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        "UPDATE person SET legal_hold = $1 WHERE uuid = $2;",
+        held,
+        person_uuid
+    )
+    .execute(&mut *conn)
+    .await
+    .with_context(|| format!("Failed to change the legal hold of person '{person_uuid}'"))?;
+
+    Ok(true)
+}
+
+/// Locks the person for the rest of the transaction, refusing with `423 Locked` when they're
+/// under legal hold. Anything removing a person's details checks this first.
+pub(crate) async fn check_not_held(
+    conn: &mut PgConnection,
+    person_uuid: Uuid,
+) -> Result<(), ApiError> {
+    let held = sqlx::query_scalar!(
+        "SELECT legal_hold FROM person WHERE uuid = $1 AND deleted_at IS NULL FOR UPDATE;",
+        person_uuid
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .with_context(|| format!("Failed to find the legal hold of person '{person_uuid}'"))?
+    .ok_or_else(|| not_found(person_uuid))?;
+
+    if held {
+        return Err(ApiError::Locked(format!(
+            "Person '{person_uuid}' is under legal hold"
+        )));
+    }
+
+    Ok(())
+}
+
+extractor!(LegalHoldService);
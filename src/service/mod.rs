@@ -0,0 +1,60 @@
+//! The business logic for people, their addresses, employment, contact details, emergency
+//! contacts and consent, shared by every way in. REST handlers, GraphQL resolvers and SCIM
+//! provisioning each adapt their requests onto a service, and its results back into their own
+//! responses.
+//!
+//! Services own the transactions, validating what they are given, turning constraint
+//! violations into conflicts, queueing an event for every change and logging who made it.
+//! Reads are left to the callers to log, as what's worth recording differs between them.
+//!
+//! Handlers take a service as an extractor, built around the database pool in the app's state.
+
+/// Lets handlers take the service as an extractor, built around the database pool in the app's
+/// state, or refused as unavailable when there's no pool
+macro_rules! extractor {
+    ($service:ident) => {
+        #[axum::async_trait]
+        impl<S> axum::extract::FromRequestParts<S> for $service
+        where
+            S: Send + Sync,
+        {
+            type Rejection = $crate::http::error::ApiError;
+
+            async fn from_request_parts(
+                parts: &mut axum::http::request::Parts,
+                state: &S,
+            ) -> Result<Self, Self::Rejection> {
+                use axum::Extension;
+
+                let Extension(db) = Extension::<sqlx::PgPool>::from_request_parts(parts, state)
+                    .await
+                    .map_err(|_| {
+                        $crate::http::error::ApiError::Unavailable(
+                            "The database is not available".to_owned(),
+                        )
+                    })?;
+
+                Ok($service::new(db))
+            }
+        }
+    };
+}
+
+pub mod address;
+pub mod archive;
+pub mod consent;
+pub mod contact_detail;
+pub mod emergency_contact;
+pub mod employment;
+pub mod legal_hold;
+pub mod person;
+pub mod person_event;
+pub mod person_export;
+pub mod person_history;
+pub mod person_merge;
+pub mod person_note;
+pub mod person_photo;
+pub mod person_stats;
+pub mod person_tag;
+pub mod relationship;
+pub mod scheduled_deletion;
@@ -0,0 +1,972 @@
+use std::collections::{HashMap, HashSet};
+
+use futures::StreamExt;
+use sqlx::{Connection, PgConnection, PgPool, Postgres, QueryBuilder};
+use time::{Date, OffsetDateTime};
+use tokio::sync::mpsc;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    clock,
+    events::Event,
+    http::{
+        address::Address,
+        cache,
+        cursor::Cursor,
+        error::{ApiError, Context},
+        fields::{DateOfBirth, PersonName},
+        filter::{Filter, Sort},
+        limit::Limit,
+        merge_patch::MergePatch,
+        person::{
+            BulkDeleteResult, DuplicateReason, ExpandedPerson, NewPerson, Person,
+            PersonLookupResult, PossibleDuplicate, UpdatePerson,
+        },
+        precondition::{IfMatch, Validators},
+    },
+    outbox,
+    service::{legal_hold::check_not_held, person_event, person_history},
+};
+
+/// How similar a name must be to a search, from 0 to 1, for the person to be found
+const SEARCH_THRESHOLD: &str = "0.3";
+
+/// Creating, reading, changing and deleting people
+#[derive(Clone, Debug)]
+pub struct PersonService {
+    db: PgPool,
+}
+
+impl PersonService {
+    pub fn new(db: PgPool) -> Self {
+        PersonService { db }
+    }
+
+    /// Inserts a new person on behalf of `actor`
+    pub async fn create(&self, actor: &str, request: &NewPerson) -> Result<Person, ApiError> {
+        let mut tx = self.db.begin().await?;
+        let person = insert(&mut tx, actor, request).await?;
+        tx.commit().await?;
+
+        info!("Client '{actor}' created person '{}'", person.id);
+
+        Ok(person)
+    }
+
+    /// Inserts many people in one transaction on behalf of `actor`, giving the result of each
+    /// in turn. A person who can't be inserted doesn't stop the others, but a failure of the
+    /// database fails them all.
+    pub async fn create_each(
+        &self,
+        actor: &str,
+        requests: &[NewPerson],
+    ) -> Result<Vec<Result<Person, ApiError>>, ApiError> {
+        let mut tx = self.db.begin().await?;
+        let mut results = Vec::with_capacity(requests.len());
+
+        for request in requests {
+            let mut savepoint = tx.begin().await?;
+
+            match insert(&mut savepoint, actor, request).await {
+                Ok(person) => {
+                    savepoint.commit().await?;
+                    results.push(Ok(person));
+                }
+                Err(e) if e.status_code().is_server_error() => return Err(e),
+                Err(e) => {
+                    savepoint.rollback().await?;
+                    results.push(Err(e));
+                }
+            }
+        }
+
+        tx.commit().await?;
+
+        info!(
+            "Client '{actor}' created {} of {} person(s) in bulk",
+            results.iter().flatten().count(),
+            requests.len()
+        );
+
+        Ok(results)
+    }
+
+    /// The first people to have been created, up to the limit
+    pub async fn list(&self, limit: Limit) -> Result<Vec<Person>, ApiError> {
+        let people = sqlx::query_as!(
+            Person,
+            r#"
+                SELECT uuid AS id, created, last_edited, first_name, family_name, date_of_birth, version FROM person
+                WHERE deleted_at IS NULL
+                ORDER BY created, uuid
+                LIMIT $1;
+            "#,
+            limit.get()
+        )
+        .fetch_all(&self.db)
+        .await
+        .context("Failed to list people")?;
+
+        Ok(people)
+    }
+
+    /// How many people there are, or match the filter
+    pub async fn count(&self, filter: Option<&Filter>) -> Result<i64, ApiError> {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM person p");
+        push_filter(&mut query, filter);
+
+        let count = query
+            .build_query_scalar()
+            .fetch_one(&self.db)
+            .await
+            .context("Failed to count people")?;
+
+        Ok(count)
+    }
+
+    /// Validators for lists of people, which change whenever anyone is created, changed,
+    /// deleted or archived, whatever the list is filtered to
+    pub async fn list_validators(&self) -> Result<Validators, ApiError> {
+        // deleted people are included, so deleting someone moves the last change on
+        let row = sqlx::query!(
+            r#"
+                SELECT COUNT(*) AS "count!", COALESCE(SUM(version), 0) AS "versions!",
+                    MAX(GREATEST(last_edited, deleted_at)) AS last_modified
+                FROM person;
+            "#
+        )
+        .fetch_one(&self.db)
+        .await
+        .context("Failed to find when people were last changed")?;
+
+        let changed = row
+            .last_modified
+            .map_or(0, OffsetDateTime::unix_timestamp_nanos);
+
+        Ok(Validators {
+            etag: format!("W/\"{}-{}-{changed}\"", row.count, row.versions),
+            last_modified: row.last_modified,
+        })
+    }
+
+    /// People matching the filter, sorted by `order` and then as for [`list`](Self::list),
+    /// skipping the first `offset`
+    pub async fn list_from(
+        &self,
+        filter: Option<&Filter>,
+        order: &[Sort],
+        offset: i64,
+        limit: Limit,
+    ) -> Result<Vec<Person>, ApiError> {
+        let mut query = QueryBuilder::new(
+            "SELECT p.uuid AS id, p.created, p.last_edited, p.first_name, p.family_name, p.date_of_birth, p.version FROM person p",
+        );
+        push_filter(&mut query, filter);
+        push_order(&mut query, order);
+        query
+            .push(" OFFSET ")
+            .push_bind(offset)
+            .push(" LIMIT ")
+            .push_bind(limit.get());
+
+        let people = query
+            .build_query_as()
+            .fetch_all(&self.db)
+            .await
+            .with_context(|| format!("Failed to list people from {offset}"))?;
+
+        Ok(people)
+    }
+
+    /// The people whose names best match `query` by their trigrams, allowing for typos, most
+    /// similar first
+    pub async fn search(&self, query: &str, limit: i64) -> Result<Vec<Person>, ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query!(
+            "SELECT set_config('pg_trgm.word_similarity_threshold', $1, true)",
+            SEARCH_THRESHOLD
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to set the search threshold")?;
+
+        let people = sqlx::query_as!(
+            Person,
+            r#"
+                SELECT uuid AS id, created, last_edited, first_name, family_name, date_of_birth, version FROM person
+                WHERE deleted_at IS NULL AND $1 OPERATOR(public.<%) (first_name || ' ' || family_name)
+                ORDER BY public.word_similarity($1, first_name || ' ' || family_name) DESC, created, uuid
+                LIMIT $2;
+            "#,
+            query,
+            limit
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to search people for '{query}'"))?;
+
+        tx.commit().await?;
+
+        Ok(people)
+    }
+
+    /// Existing people who may be the new person, those with the same name and date of birth
+    /// first and then by how similar their names are
+    pub async fn find_duplicates(
+        &self,
+        person: &NewPerson,
+    ) -> Result<Vec<PossibleDuplicate>, ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query!(
+            "SELECT set_config('pg_trgm.similarity_threshold', $1, true)",
+            SEARCH_THRESHOLD
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to set the duplicate threshold")?;
+
+        let first_name = person.first_name.as_str();
+        let family_name = person.family_name.as_str();
+        let date_of_birth = person.date_of_birth.date();
+        let name = format!("{first_name} {family_name}");
+
+        let rows = sqlx::query!(
+            r#"
+                SELECT uuid AS id, created, last_edited, first_name, family_name, date_of_birth, version,
+                    lower(first_name) = lower($1) AND lower(family_name) = lower($2) AS "same_name!",
+                    public.similarity($4, first_name || ' ' || family_name) AS "similarity!"
+                FROM person
+                WHERE deleted_at IS NULL AND (
+                    (lower(first_name) = lower($1) AND lower(family_name) = lower($2) AND date_of_birth = $3)
+                    OR $4 OPERATOR(public.%) (first_name || ' ' || family_name)
+                )
+                ORDER BY lower(first_name) = lower($1) AND lower(family_name) = lower($2) AND date_of_birth = $3 DESC,
+                    date_of_birth = $3 DESC, "similarity!" DESC, created, uuid
+                LIMIT 20;
+            "#,
+            first_name,
+            family_name,
+            date_of_birth,
+            name
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to find possible duplicates of a new person")?;
+
+        tx.commit().await?;
+
+        let duplicates = rows
+            .into_iter()
+            .map(|row| {
+                let reason = match (row.same_name, row.date_of_birth == date_of_birth) {
+                    (true, true) => DuplicateReason::SameNameAndDateOfBirth,
+                    (false, true) => DuplicateReason::SimilarNameSameDateOfBirth,
+                    (_, false) => DuplicateReason::SimilarName,
+                };
+
+                PossibleDuplicate {
+                    person: Person {
+                        id: row.id,
+                        first_name: row.first_name,
+                        family_name: row.family_name,
+                        date_of_birth: row.date_of_birth,
+                        created: row.created,
+                        last_edited: row.last_edited,
+                        version: row.version,
+                    },
+                    reason,
+                    similarity: row.similarity,
+                }
+            })
+            .collect();
+
+        Ok(duplicates)
+    }
+
+    /// People matching the filter, in the same order as [`list`](Self::list), starting after the
+    /// cursor. One more than the limit is returned when there is one, telling whether there's
+    /// another page.
+    pub async fn list_after(
+        &self,
+        filter: Option<&Filter>,
+        after: Option<Cursor>,
+        limit: Limit,
+    ) -> Result<Vec<Person>, ApiError> {
+        let mut query = QueryBuilder::new(
+            "SELECT p.uuid AS id, p.created, p.last_edited, p.first_name, p.family_name, p.date_of_birth, p.version FROM person p WHERE p.deleted_at IS NULL",
+        );
+        if let Some(filter) = filter {
+            query.push(" AND ");
+            filter.push_sql(&mut query);
+        }
+        if let Some(after) = after {
+            query
+                .push(" AND (p.created, p.uuid) > (")
+                .push_bind(after.created)
+                .push(", ")
+                .push_bind(after.id)
+                .push(")");
+        }
+        query
+            .push(" ORDER BY p.created, p.uuid LIMIT ")
+            .push_bind(limit.get() + 1);
+
+        let people = query
+            .build_query_as()
+            .fetch_all(&self.db)
+            .await
+            .context("Failed to list people after a cursor")?;
+
+        Ok(people)
+    }
+
+    /// Streams the same people as [`list`](Self::list) from the database a row at a time.
+    ///
+    /// Rows are sent through a bounded channel, so a slow client holds up the query rather than
+    /// rows piling up in memory. The query stops early if the receiver is dropped.
+    pub fn stream(&self, limit: Limit) -> mpsc::Receiver<Result<Person, sqlx::Error>> {
+        let (sender, receiver) = mpsc::channel(64);
+        let db = self.db.clone();
+
+        tokio::spawn(async move {
+            let mut rows = sqlx::query_as!(
+                Person,
+                r#"
+                    SELECT uuid AS id, created, last_edited, first_name, family_name, date_of_birth, version FROM person
+                    WHERE deleted_at IS NULL
+                    ORDER BY created, uuid
+                    LIMIT $1;
+                "#,
+                limit.get()
+            )
+            .fetch(&db);
+
+            while let Some(row) = rows.next().await {
+                if sender.send(row).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        receiver
+    }
+
+    /// Streams the people matching the filter, in the same order as
+    /// [`list_from`](Self::list_from), a row at a time as for [`stream`](Self::stream). Without
+    /// a limit everyone is streamed, however many there are.
+    pub fn stream_from(
+        &self,
+        filter: Option<Filter>,
+        order: Vec<Sort>,
+        offset: i64,
+        limit: Option<Limit>,
+    ) -> mpsc::Receiver<Result<Person, sqlx::Error>> {
+        let (sender, receiver) = mpsc::channel(64);
+        let db = self.db.clone();
+
+        tokio::spawn(async move {
+            let mut query = QueryBuilder::new(
+                "SELECT p.uuid AS id, p.created, p.last_edited, p.first_name, p.family_name, p.date_of_birth, p.version FROM person p",
+            );
+            push_filter(&mut query, filter.as_ref());
+            push_order(&mut query, &order);
+            query.push(" OFFSET ").push_bind(offset);
+            if let Some(limit) = limit {
+                query.push(" LIMIT ").push_bind(limit.get());
+            }
+
+            let mut rows = query.build_query_as().fetch(&db);
+
+            while let Some(row) = rows.next().await {
+                if sender.send(row).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        receiver
+    }
+
+    /// The same people as [`list_from`](Self::list_from) with their current address, in a
+    /// single query
+    pub async fn list_expanded(
+        &self,
+        filter: Option<&Filter>,
+        order: &[Sort],
+        offset: i64,
+        limit: Limit,
+    ) -> Result<Vec<ExpandedPerson>, ApiError> {
+        let mut query = QueryBuilder::new(
+            r#"
+                SELECT p.uuid AS id, p.created, p.last_edited, p.first_name, p.family_name, p.date_of_birth, p.version,
+                    a.uuid AS address_id, a.building, a.street, a.town_or_city, a.postcode,
+                    a.created AS address_created, a.last_edited AS address_last_edited
+                FROM person p LEFT JOIN address a ON a.uuid = p.address
+            "#,
+        );
+        push_filter(&mut query, filter);
+        push_order(&mut query, order);
+        query
+            .push(" OFFSET ")
+            .push_bind(offset)
+            .push(" LIMIT ")
+            .push_bind(limit.get());
+
+        let rows: Vec<ExpandedRow> = query
+            .build_query_as()
+            .fetch_all(&self.db)
+            .await
+            .context("Failed to list people with their addresses")?;
+
+        Ok(rows.into_iter().map(ExpandedPerson::from).collect())
+    }
+
+    /// A person, from the cache when there's a fresh enough copy
+    pub async fn find(&self, person_uuid: Uuid) -> Result<Person, ApiError> {
+        if let Some(person) = cache::get(person_uuid) {
+            return Ok(person);
+        }
+
+        let person = sqlx::query_as!(
+            Person,
+            r#"
+                SELECT uuid AS id, created, last_edited, first_name, family_name, date_of_birth, version FROM person
+                WHERE uuid = $1 AND deleted_at IS NULL;
+            "#,
+            person_uuid
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find person '{person_uuid}'"))?
+        .ok_or_else(|| ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}")))?;
+
+        cache::insert(&person);
+
+        Ok(person)
+    }
+
+    /// Everyone with the given UUIDs in a single query, in the order they were given, along
+    /// with the UUIDs no person was found for
+    pub async fn find_each(&self, person_uuids: &[Uuid]) -> Result<PersonLookupResult, ApiError> {
+        let mut found = sqlx::query_as!(
+            Person,
+            r#"
+                SELECT uuid AS id, created, last_edited, first_name, family_name, date_of_birth, version FROM person
+                WHERE uuid = ANY($1) AND deleted_at IS NULL;
+            "#,
+            person_uuids
+        )
+        .fetch_all(&self.db)
+        .await
+        .context("Failed to look up people")?
+        .into_iter()
+        .map(|person| (person.id, person))
+        .collect::<HashMap<_, _>>();
+
+        let mut result = PersonLookupResult {
+            people: Vec::with_capacity(found.len()),
+            not_found: Vec::new(),
+        };
+
+        let mut seen = HashSet::with_capacity(person_uuids.len());
+
+        for person_uuid in person_uuids {
+            // the same person given twice is only returned or reported once
+            if !seen.insert(*person_uuid) {
+                continue;
+            }
+
+            match found.remove(person_uuid) {
+                Some(person) => result.people.push(person),
+                None => result.not_found.push(*person_uuid),
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// A person with their current address, in a single query
+    pub async fn find_expanded(&self, person_uuid: Uuid) -> Result<ExpandedPerson, ApiError> {
+        let row = sqlx::query_as!(
+            ExpandedRow,
+            r#"
+                SELECT p.uuid AS "id!", p.created AS "created!", p.last_edited AS "last_edited!",
+                    p.first_name AS "first_name!", p.family_name AS "family_name!",
+                    p.date_of_birth AS "date_of_birth!", p.version AS "version!",
+                    a.uuid AS "address_id?", a.building AS "building?", a.street, a.town_or_city,
+                    a.postcode AS "postcode?", a.created AS "address_created?", a.last_edited AS "address_last_edited?"
+                FROM person p LEFT JOIN address a ON a.uuid = p.address
+                WHERE p.uuid = $1 AND p.deleted_at IS NULL;
+            "#,
+            person_uuid
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find person '{person_uuid}' with their address"))?
+        .ok_or_else(|| ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}")))?;
+
+        Ok(row.into())
+    }
+
+    /// Applies the given changes to a person on behalf of `actor`, leaving any fields not
+    /// provided as they are, if they're at a version the changes may be made to
+    pub async fn update(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        request: UpdatePerson,
+        if_match: &IfMatch,
+    ) -> Result<Person, ApiError> {
+        let mut tx = self.db.begin().await?;
+        check_version(&mut tx, person_uuid, if_match).await?;
+        let updated_person = apply(&mut tx, actor, person_uuid, &request).await?;
+        tx.commit().await?;
+        cache::invalidate(person_uuid);
+
+        info!("Client '{actor}' updated person '{person_uuid}'");
+
+        Ok(updated_person)
+    }
+
+    /// Applies a merge patch to a person on behalf of `actor` if they're at a version it may be
+    /// applied to, holding the person locked from reading them to saving the result so
+    /// concurrent changes aren't lost
+    pub async fn patch(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        patch: &MergePatch,
+        if_match: &IfMatch,
+    ) -> Result<Person, ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        let person = sqlx::query_as!(
+            Person,
+            r#"
+                SELECT uuid AS id, created, last_edited, first_name, family_name, date_of_birth, version FROM person
+                WHERE uuid = $1 AND deleted_at IS NULL
+                FOR UPDATE;
+            "#,
+            person_uuid
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to find person '{person_uuid}'"))?
+        .ok_or_else(|| ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}")))?;
+        if_match.check(person.version)?;
+
+        let request: NewPerson = patch.apply(person.changeable_fields())?;
+        let patched_person = apply(&mut tx, actor, person_uuid, &request.into()).await?;
+
+        tx.commit().await?;
+        cache::invalidate(person_uuid);
+
+        info!("Client '{actor}' patched person '{person_uuid}'");
+
+        Ok(patched_person)
+    }
+
+    /// Applies changes to many people in one transaction on behalf of `actor`, each if they're
+    /// at the version given with them, giving the result of each in turn. A person who can't be
+    /// changed, such as one who doesn't exist, doesn't stop the others being changed, but a
+    /// failure of the database fails them all.
+    pub async fn update_each(
+        &self,
+        actor: &str,
+        changes: Vec<(Uuid, i32, UpdatePerson)>,
+    ) -> Result<Vec<Result<Person, ApiError>>, ApiError> {
+        let mut tx = self.db.begin().await?;
+        let mut results = Vec::with_capacity(changes.len());
+
+        for (person_uuid, version, request) in &changes {
+            // each person is changed within a savepoint, so one failing leaves the rest intact
+            let mut savepoint = tx.begin().await?;
+            let if_match = IfMatch::Versions(vec![*version]);
+
+            let result = match check_version(&mut savepoint, *person_uuid, &if_match).await {
+                Ok(()) => apply(&mut savepoint, actor, *person_uuid, request).await,
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(person) => {
+                    savepoint.commit().await?;
+                    results.push(Ok(person));
+                }
+                Err(e) if e.status_code().is_server_error() => return Err(e),
+                Err(e) => {
+                    savepoint.rollback().await?;
+                    results.push(Err(e));
+                }
+            }
+        }
+
+        tx.commit().await?;
+
+        let updated: Vec<_> = results.iter().flatten().map(|person| person.id).collect();
+        for person_uuid in &updated {
+            cache::invalidate(*person_uuid);
+        }
+
+        info!(
+            "Client '{actor}' updated {} of {} person(s) in bulk",
+            updated.len(),
+            changes.len()
+        );
+
+        Ok(results)
+    }
+
+    /// Deletes a person on behalf of `actor` if they're at a version that may be deleted,
+    /// unless they're under legal hold
+    pub async fn delete(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        if_match: &IfMatch,
+    ) -> Result<(), ApiError> {
+        let mut tx = self.db.begin().await?;
+        check_not_held(&mut tx, person_uuid).await?;
+        check_version(&mut tx, person_uuid, if_match).await?;
+        remove(&mut tx, actor, person_uuid).await?;
+
+        tx.commit().await?;
+        cache::invalidate(person_uuid);
+
+        info!("Client '{actor}' deleted person '{person_uuid}'");
+
+        Ok(())
+    }
+
+    /// Deletes many people in one transaction on behalf of `actor`, saying how many were and
+    /// which weren't found. Anyone under legal hold fails them all, so nobody is deleted.
+    pub async fn delete_each(
+        &self,
+        actor: &str,
+        person_uuids: &[Uuid],
+    ) -> Result<BulkDeleteResult, ApiError> {
+        let mut tx = self.db.begin().await?;
+        let mut deleted = Vec::with_capacity(person_uuids.len());
+        let mut not_found = Vec::new();
+
+        for person_uuid in person_uuids {
+            // the same person given twice has been dealt with by the time of the second
+            if deleted.contains(person_uuid) || not_found.contains(person_uuid) {
+                continue;
+            }
+
+            match check_not_held(&mut tx, *person_uuid).await {
+                Ok(()) => {
+                    remove(&mut tx, actor, *person_uuid).await?;
+                    deleted.push(*person_uuid);
+                }
+                Err(ApiError::NotFound(_)) => not_found.push(*person_uuid),
+                Err(e) => return Err(e),
+            }
+        }
+
+        tx.commit().await?;
+
+        for person_uuid in &deleted {
+            cache::invalidate(*person_uuid);
+        }
+
+        info!(
+            "Client '{actor}' deleted {} of {} person(s) in bulk",
+            deleted.len(),
+            person_uuids.len()
+        );
+
+        Ok(BulkDeleteResult {
+            deleted: deleted.len() as u64,
+            not_found,
+        })
+    }
+
+    /// Brings back a deleted person on behalf of `actor`, along with everything still kept for
+    /// them. Any deletion they were scheduled for is dropped, so they aren't deleted again.
+    pub async fn restore(&self, actor: &str, person_uuid: Uuid) -> Result<Person, ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        let person = sqlx::query_as!(
+            Person,
+            r#"
+                UPDATE person SET deleted_at = NULL, delete_at = NULL, last_edited = $2, version = version + 1
+                WHERE uuid = $1 AND deleted_at IS NOT NULL
+                RETURNING uuid AS id, created, last_edited, first_name, family_name, date_of_birth, version;
+            "#,
+            person_uuid,
+            clock::now()
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to restore person '{person_uuid}'"))?
+        .ok_or_else(|| {
+            ApiError::NotFound(format!(
+                "Deleted person not found for the UUID: {person_uuid}"
+            ))
+        })?;
+
+        let event = Event::PersonRestored {
+            person: person.clone(),
+        };
+        person_event::append(&mut tx, actor, &event).await?;
+        person_history::record(&mut tx, actor, &event).await?;
+        outbox::enqueue(&mut tx, &event)
+            .await
+            .context("Failed to queue the person restored event")?;
+
+        tx.commit().await?;
+        cache::invalidate(person_uuid);
+
+        info!("Client '{actor}' restored person '{person_uuid}'");
+
+        Ok(person)
+    }
+}
+
+/// Inserts a person within the transaction on behalf of `actor` and queues the event saying so
+async fn insert(
+    conn: &mut PgConnection,
+    actor: &str,
+    request: &NewPerson,
+) -> Result<Person, ApiError> {
+    let person = sqlx::query_as!(
+        Person,
+        r#"
+            INSERT INTO person (first_name, family_name, date_of_birth, created, last_edited)
+            VALUES ($1, $2, $3, $4, $4)
+            RETURNING uuid AS id, created, last_edited, first_name, family_name, date_of_birth, version;
+        "#,
+        request.first_name.as_str(),
+        request.family_name.as_str(),
+        request.date_of_birth.date(),
+        clock::now()
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(dbe) if dbe.constraint().is_some() => ApiError::Conflict(format!(
+            "Unable to create person due to constraint: {}",
+            dbe.constraint().unwrap()
+        )),
+        _ => ApiError::from(e).context("Failed to insert person"),
+    })?;
+
+    let event = Event::PersonCreated {
+        person: person.clone(),
+    };
+    person_event::append(conn, actor, &event).await?;
+    person_history::record(conn, actor, &event).await?;
+    outbox::enqueue(conn, &event)
+        .await
+        .context("Failed to queue the person created event")?;
+
+    Ok(person)
+}
+
+/// Deletes a person within the transaction on behalf of `actor` and queues the event saying so.
+/// The row is kept, marked as deleted, so the person can be restored.
+pub(crate) async fn remove(
+    conn: &mut PgConnection,
+    actor: &str,
+    person_uuid: Uuid,
+) -> Result<(), ApiError> {
+    sqlx::query!(
+        r#"
+            UPDATE person SET deleted_at = $2, version = version + 1
+            WHERE uuid = $1 AND deleted_at IS NULL
+            RETURNING uuid as id;
+        "#,
+        person_uuid,
+        clock::now()
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .with_context(|| format!("Failed to delete person '{person_uuid}'"))?
+    .ok_or_else(|| ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}")))?;
+
+    let event = Event::PersonDeleted {
+        person_id: person_uuid,
+    };
+    person_event::append(conn, actor, &event).await?;
+    person_history::record(conn, actor, &event).await?;
+    outbox::enqueue(conn, &event)
+        .await
+        .context("Failed to queue the person deleted event")?;
+
+    Ok(())
+}
+
+/// Changes a person within the transaction on behalf of `actor` and queues the event saying so
+async fn apply(
+    conn: &mut PgConnection,
+    actor: &str,
+    person_uuid: Uuid,
+    request: &UpdatePerson,
+) -> Result<Person, ApiError> {
+    let updated_person = sqlx::query_as!(
+        Person,
+        r#"
+            UPDATE person SET first_name = COALESCE($1, first_name), family_name = COALESCE($2, family_name),
+                date_of_birth = COALESCE($3, date_of_birth), last_edited = $4, version = version + 1
+            WHERE uuid = $5 AND deleted_at IS NULL
+            RETURNING uuid AS id, created, last_edited, first_name, family_name, date_of_birth, version;
+        "#,
+        request.first_name.as_ref().map(PersonName::as_str),
+        request.family_name.as_ref().map(PersonName::as_str),
+        request.date_of_birth.map(DateOfBirth::date),
+        clock::now(),
+        person_uuid
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .with_context(|| format!("Failed to update person '{person_uuid}'"))?
+    .ok_or_else(|| ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}")))?;
+
+    let event = Event::PersonUpdated {
+        person: updated_person.clone(),
+    };
+    person_event::append(conn, actor, &event).await?;
+    person_history::record(conn, actor, &event).await?;
+    outbox::enqueue(conn, &event)
+        .await
+        .context("Failed to queue the person updated event")?;
+
+    Ok(updated_person)
+}
+
+/// Checks the person is at a version the change may be made to, locking them until the
+/// transaction ends so they can't be changed in the meantime
+async fn check_version(
+    conn: &mut PgConnection,
+    person_uuid: Uuid,
+    if_match: &IfMatch,
+) -> Result<(), ApiError> {
+    let version = sqlx::query_scalar!(
+        "SELECT version FROM person WHERE uuid = $1 AND deleted_at IS NULL FOR UPDATE;",
+        person_uuid
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .with_context(|| format!("Failed to find person '{person_uuid}'"))?
+    .ok_or_else(|| ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}")))?;
+
+    if_match.check(version)
+}
+
+/// Checks the person exists, locking them when `for_update` until the transaction ends, so
+/// concurrent changes to what belongs to them can't conflict with each other
+pub(crate) async fn lock_person(
+    conn: &mut PgConnection,
+    person_uuid: Uuid,
+    for_update: bool,
+) -> Result<(), ApiError> {
+    let found = if for_update {
+        sqlx::query_scalar!(
+            "SELECT id FROM person WHERE uuid = $1 AND deleted_at IS NULL FOR UPDATE;",
+            person_uuid
+        )
+        .fetch_optional(&mut *conn)
+        .await
+    } else {
+        sqlx::query_scalar!(
+            "SELECT id FROM person WHERE uuid = $1 AND deleted_at IS NULL;",
+            person_uuid
+        )
+        .fetch_optional(&mut *conn)
+        .await
+    };
+
+    found
+        .with_context(|| format!("Failed to find person '{person_uuid}'"))?
+        .ok_or_else(|| {
+            ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}"))
+        })?;
+
+    Ok(())
+}
+
+/// Restricts a query of people, aliased `p`, to those who haven't been deleted and match the
+/// filter
+fn push_filter(query: &mut QueryBuilder<'_, Postgres>, filter: Option<&Filter>) {
+    query.push(" WHERE p.deleted_at IS NULL");
+    if let Some(filter) = filter {
+        query.push(" AND ");
+        filter.push_sql(query);
+    }
+}
+
+/// Sorts a query of people, aliased `p`, oldest first after any fields asked for
+fn push_order(query: &mut QueryBuilder<'_, Postgres>, order: &[Sort]) {
+    query.push(" ORDER BY ");
+    for sort in order {
+        query
+            .push(sort.field.column())
+            .push(if sort.descending { " DESC, " } else { " ASC, " });
+    }
+    query.push("p.created, p.uuid");
+}
+
+extractor!(PersonService);
+
+/// A person joined with their current address, if any
+#[derive(sqlx::FromRow)]
+struct ExpandedRow {
+    id: Uuid,
+    first_name: String,
+    family_name: String,
+    date_of_birth: Date,
+    created: OffsetDateTime,
+    last_edited: OffsetDateTime,
+    version: i32,
+    address_id: Option<Uuid>,
+    building: Option<String>,
+    street: Option<String>,
+    town_or_city: Option<String>,
+    postcode: Option<String>,
+    address_created: Option<OffsetDateTime>,
+    address_last_edited: Option<OffsetDateTime>,
+}
+
+impl From<ExpandedRow> for ExpandedPerson {
+    fn from(row: ExpandedRow) -> Self {
+        let address = match (
+            row.address_id,
+            row.building,
+            row.postcode,
+            row.address_created,
+            row.address_last_edited,
+        ) {
+            (Some(id), Some(building), Some(postcode), Some(created), Some(last_edited)) => {
+                Some(Address {
+                    id,
+                    building,
+                    street: row.street,
+                    town_or_city: row.town_or_city,
+                    postcode,
+                    created,
+                    last_edited,
+                })
+            }
+            _ => None,
+        };
+
+        ExpandedPerson {
+            person: Person {
+                id: row.id,
+                first_name: row.first_name,
+                family_name: row.family_name,
+                date_of_birth: row.date_of_birth,
+                created: row.created,
+                last_edited: row.last_edited,
+                version: row.version,
+            },
+            address,
+            emergency_contacts: None,
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::env;
+
+use sqlx::{types::Json, PgConnection, PgExecutor, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    clock,
+    events::Event,
+    http::{
+        cache,
+        error::{ApiError, Context},
+        person::Person,
+        person_event::PersonEvent,
+    },
+    outbox,
+    service::person_history,
+};
+
+/// Whether changes to people are recorded in the `person_event` stream, which is opted into by
+/// setting `PERSON_EVENT_SOURCING` to `true`
+pub fn enabled() -> bool {
+    env::var("PERSON_EVENT_SOURCING").is_ok_and(|v| v == "true")
+}
+
+/// Appends the change to the person's event stream on behalf of `actor`, within the
+/// transaction making it, when event sourcing is enabled. Events other than changes to the
+/// person themselves are ignored.
+///
+/// As the stream is only ever written alongside the person table, the table is always what
+/// replaying the stream would give, and can be rebuilt from it with
+/// [`PersonEventService::replay`].
+pub(crate) async fn append(
+    conn: &mut PgConnection,
+    actor: &str,
+    event: &Event,
+) -> Result<(), ApiError> {
+    if !enabled() {
+        return Ok(());
+    }
+
+    let (person_uuid, person) = match event {
+        Event::PersonCreated { person }
+        | Event::PersonUpdated { person }
+        | Event::PersonRestored { person } => (person.id, Some(person)),
+        Event::PersonDeleted { person_id } | Event::PersonArchived { person_id } => {
+            (*person_id, None)
+        }
+        _ => return Ok(()),
+    };
+
+    // the person's row is locked by the change being recorded, so versions can't race
+    sqlx::query!(
+        r#"
+            INSERT INTO person_event (person, version, event_type, data, actor, recorded)
+            SELECT $1, COALESCE(MAX(version), 0) + 1, $2, $3, $4, $5
+            FROM person_event WHERE person = $1;
+        "#,
+        person_uuid,
+        event.name(),
+        person.map(Json) as Option<Json<&Person>>,
+        actor,
+        clock::now()
+    )
+    .execute(&mut *conn)
+    .await
+    .with_context(|| {
+        format!(
+            "Failed to record the {} event of person '{person_uuid}'",
+            event.name()
+        )
+    })?;
+
+    Ok(())
+}
+
+/// Reading a person's event stream and rebuilding the person from it
+#[derive(Clone, Debug)]
+pub struct PersonEventService {
+    db: PgPool,
+}
+
+impl PersonEventService {
+    pub fn new(db: PgPool) -> Self {
+        PersonEventService { db }
+    }
+
+    /// Everything recorded as happening to the person, oldest first. People who have since
+    /// been deleted still have their events, while people who never had any recorded, and
+    /// don't exist, aren't found.
+    pub async fn list(&self, person_uuid: Uuid) -> Result<Vec<PersonEvent>, ApiError> {
+        let events = stream(&self.db, person_uuid).await?;
+
+        if events.is_empty() {
+            let exists = sqlx::query_scalar!(
+                r#"SELECT EXISTS (SELECT 1 FROM person WHERE uuid = $1) AS "exists!";"#,
+                person_uuid
+            )
+            .fetch_one(&self.db)
+            .await
+            .with_context(|| format!("Failed to find person '{person_uuid}'"))?;
+
+            if !exists {
+                return Err(ApiError::NotFound(format!(
+                    "Person not found for the UUID: {person_uuid}"
+                )));
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Rebuilds the person's row from their event stream on behalf of `actor`, returning them
+    /// as they now are, or nothing if the stream ends with them deleted or archived. Only the
+    /// details held in the events are rebuilt, so their address and the like are kept.
+    pub async fn replay(&self, actor: &str, person_uuid: Uuid) -> Result<Option<Person>, ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        // holds off changes to the person, which would otherwise append past the replay
+        sqlx::query!(
+            "SELECT id FROM person WHERE uuid = $1 FOR UPDATE;",
+            person_uuid
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to lock person '{person_uuid}'"))?;
+
+        let last = stream(&mut *tx, person_uuid).await?.pop().ok_or_else(|| {
+            ApiError::NotFound(format!("No events recorded for the person: {person_uuid}"))
+        })?;
+
+        let (event, replayed) = match &last.person {
+            Some(person) => {
+                let replayed = sqlx::query_as!(
+                    Person,
+                    r#"
+                        INSERT INTO person (uuid, first_name, family_name, date_of_birth, created, last_edited)
+                        VALUES ($1, $2, $3, $4, $5, $6)
+                        ON CONFLICT (uuid) DO UPDATE
+                        SET first_name = EXCLUDED.first_name, family_name = EXCLUDED.family_name,
+                            date_of_birth = EXCLUDED.date_of_birth, created = EXCLUDED.created,
+                            last_edited = EXCLUDED.last_edited, deleted_at = NULL,
+                            version = person.version + 1
+                        RETURNING uuid AS id, created, last_edited, first_name, family_name, date_of_birth, version;
+                    "#,
+                    person.id,
+                    person.first_name,
+                    person.family_name,
+                    person.date_of_birth,
+                    person.created,
+                    person.last_edited
+                )
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| match e {
+                    sqlx::Error::Database(dbe) if dbe.constraint().is_some() => {
+                        ApiError::Conflict(format!(
+                            "Unable to replay person due to constraint: {}",
+                            dbe.constraint().unwrap()
+                        ))
+                    }
+                    _ => ApiError::from(e).context(format!("Failed to replay person '{person_uuid}'")),
+                })?;
+
+                let event = Event::PersonUpdated {
+                    person: replayed.clone(),
+                };
+
+                (event, Some(replayed))
+            }
+            None => {
+                sqlx::query!(
+                    r#"
+                        UPDATE person SET deleted_at = $2, version = version + 1
+                        WHERE uuid = $1 AND deleted_at IS NULL;
+                    "#,
+                    person_uuid,
+                    clock::now()
+                )
+                .execute(&mut *tx)
+                .await
+                .with_context(|| format!("Failed to replay person '{person_uuid}'"))?;
+
+                let event = Event::PersonDeleted {
+                    person_id: person_uuid,
+                };
+
+                (event, None)
+            }
+        };
+
+        person_history::record(&mut tx, actor, &event).await?;
+        // subscribers are told, as the person may have differed from the stream before
+        outbox::enqueue(&mut tx, &event)
+            .await
+            .context("Failed to queue the person replayed event")?;
+
+        tx.commit().await?;
+        cache::invalidate(person_uuid);
+
+        info!(
+            "Client '{actor}' replayed person '{person_uuid}' from {} event(s)",
+            last.version
+        );
+
+        Ok(replayed)
+    }
+}
+
+/// The person's events, oldest first
+async fn stream<'c>(
+    conn: impl PgExecutor<'c>,
+    person_uuid: Uuid,
+) -> Result<Vec<PersonEvent>, ApiError> {
+    let events = sqlx::query!(
+        r#"
+            SELECT version, event_type, data AS "person: Json<Person>", actor, recorded
+            FROM person_event
+            WHERE person = $1
+            ORDER BY version;
+        "#,
+        person_uuid
+    )
+    .fetch_all(conn)
+    .await
+    .with_context(|| format!("Failed to find the events of person '{person_uuid}'"))?
+    .into_iter()
+    .map(|row| PersonEvent {
+        person_id: person_uuid,
+        version: row.version,
+        event_type: row.event_type,
+        person: row.person.map(|Json(person)| person),
+        actor: row.actor,
+        recorded: row.recorded,
+    })
+    .collect();
+
+    Ok(events)
+}
+
+extractor!(PersonEventService);
@@ -0,0 +1,297 @@
+use std::env;
+
+use sqlx::PgPool;
+use time::{Duration, OffsetDateTime};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    clock,
+    http::{
+        error::{ApiError, Context},
+        export,
+        export_job::{ExportFormat, ExportJob, ExportStatus, NewExportJob},
+        filter::Filter,
+        limit::{Limit, MAX_LIMIT},
+        person::Person,
+        v1,
+    },
+    jobs::{self, Job, JobError},
+    service::person::PersonService,
+};
+
+/// How long a completed export can be downloaded for, from `EXPORT_DOWNLOAD_HOURS`
+fn download_hours() -> i64 {
+    env::var("EXPORT_DOWNLOAD_HOURS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(24)
+}
+
+struct ExportRow {
+    uuid: Uuid,
+    format: ExportFormat,
+    filter: Option<String>,
+    status: ExportStatus,
+    row_count: Option<i64>,
+    error: Option<String>,
+    expires: Option<OffsetDateTime>,
+    created: OffsetDateTime,
+    last_edited: OffsetDateTime,
+}
+
+impl From<ExportRow> for ExportJob {
+    fn from(row: ExportRow) -> Self {
+        // the link is only given while there's a file to download
+        let download_url = (row.status == ExportStatus::Completed
+            && row.expires.is_some_and(|expires| expires > clock::now()))
+        .then(|| format!("{}/person/export-jobs/{}/download", v1::PREFIX, row.uuid));
+
+        ExportJob {
+            id: row.uuid,
+            format: row.format,
+            filter: row.filter,
+            status: row.status,
+            row_count: row.row_count,
+            error: row.error,
+            download_url,
+            expires: row.expires,
+            created: row.created,
+            last_edited: row.last_edited,
+        }
+    }
+}
+
+/// Exporting people to a file in the background, and handing the file out until it expires
+#[derive(Clone, Debug)]
+pub struct PersonExportService {
+    db: PgPool,
+}
+
+impl PersonExportService {
+    pub fn new(db: PgPool) -> Self {
+        PersonExportService { db }
+    }
+
+    /// Queues an export on behalf of `actor`, to be run by the `person.export` job
+    pub async fn create(&self, actor: &str, request: &NewExportJob) -> Result<ExportJob, ApiError> {
+        request.validate()?;
+        // rejects an invalid filter now, rather than failing the job later
+        request.filter.as_deref().map(Filter::parse).transpose()?;
+
+        let mut tx = self.db.begin().await?;
+
+        let export = sqlx::query_as!(
+            ExportRow,
+            r#"
+                INSERT INTO person_export (requested_by, format, filter, created, last_edited)
+                VALUES ($1, $2, $3, $4, $4)
+                RETURNING uuid, format AS "format: ExportFormat", filter,
+                    status AS "status: ExportStatus", row_count, error, expires, created, last_edited;
+            "#,
+            actor,
+            request.format as ExportFormat,
+            request.filter,
+            clock::now()
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to insert export")?;
+
+        jobs::enqueue(
+            &mut *tx,
+            &Job::ExportPeople {
+                export_id: export.uuid,
+            },
+        )
+        .await
+        .map_err(|e| match e {
+            JobError::Database(e) => ApiError::from(e),
+            e => ApiError::ExportError(e.into()),
+        })
+        .context("Failed to queue the export job")?;
+
+        tx.commit().await?;
+
+        info!(
+            "Client '{actor}' queued export '{}' of people as {}",
+            export.uuid,
+            request.format.extension()
+        );
+
+        Ok(export.into())
+    }
+
+    /// An export and how far it has got
+    pub async fn find(&self, export_uuid: Uuid) -> Result<ExportJob, ApiError> {
+        let export = sqlx::query_as!(
+            ExportRow,
+            r#"
+                SELECT uuid, format AS "format: ExportFormat", filter,
+                    status AS "status: ExportStatus", row_count, error, expires, created, last_edited
+                FROM person_export
+                WHERE uuid = $1;
+            "#,
+            export_uuid
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find export '{export_uuid}'"))?
+        .ok_or_else(|| not_found(export_uuid))?;
+
+        Ok(export.into())
+    }
+
+    /// The file of a completed export, so long as it hasn't expired
+    pub async fn download(&self, export_uuid: Uuid) -> Result<(ExportFormat, Vec<u8>), ApiError> {
+        let export = sqlx::query!(
+            r#"
+                SELECT format AS "format: ExportFormat", status AS "status: ExportStatus",
+                    expires, content
+                FROM person_export
+                WHERE uuid = $1;
+            "#,
+            export_uuid
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find export '{export_uuid}'"))?
+        .ok_or_else(|| not_found(export_uuid))?;
+
+        if export.status != ExportStatus::Completed {
+            return Err(ApiError::Conflict(format!(
+                "Export '{export_uuid}' has not completed"
+            )));
+        }
+
+        match (export.expires, export.content) {
+            (Some(expires), Some(content)) if expires > clock::now() => {
+                Ok((export.format, content))
+            }
+            _ => Err(ApiError::Gone(format!(
+                "Export '{export_uuid}' has expired"
+            ))),
+        }
+    }
+
+    /// Writes the people asked for to the export's file, marking it failed if they can't be
+    /// written. Database failures are left to the job to retry.
+    pub async fn run(&self, export_uuid: Uuid) -> Result<(), ApiError> {
+        let export = sqlx::query!(
+            r#"
+                SELECT format AS "format: ExportFormat", filter
+                FROM person_export
+                WHERE uuid = $1 AND status = 'pending';
+            "#,
+            export_uuid
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find export '{export_uuid}'"))?;
+
+        // already run, or no longer wanted
+        let Some(export) = export else {
+            return Ok(());
+        };
+
+        let filter = export.filter.as_deref().map(Filter::parse).transpose()?;
+        let people = self.everyone(filter.as_ref()).await?;
+        let row_count = people.len() as i64;
+
+        let content = match export.format {
+            ExportFormat::Csv => Ok(export::csv(&people)),
+            ExportFormat::Json => serde_json::to_vec(&people).map_err(|e| e.to_string()),
+            ExportFormat::Xlsx => tokio::task::spawn_blocking(move || export::spreadsheet(&people))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|result| result.map_err(|e| e.to_string())),
+        };
+
+        let now = clock::now();
+
+        match content {
+            Ok(content) => {
+                sqlx::query!(
+                    r#"
+                        UPDATE person_export
+                        SET status = 'completed', row_count = $2, content = $3, expires = $4,
+                            last_edited = $5
+                        WHERE uuid = $1;
+                    "#,
+                    export_uuid,
+                    row_count,
+                    content,
+                    now + Duration::hours(download_hours()),
+                    now
+                )
+                .execute(&self.db)
+                .await
+                .with_context(|| format!("Failed to save export '{export_uuid}'"))?;
+
+                info!("Exported {row_count} person(s) to export '{export_uuid}'");
+            }
+            Err(error) => {
+                sqlx::query!(
+                    r#"
+                        UPDATE person_export SET status = 'failed', error = $2, last_edited = $3
+                        WHERE uuid = $1;
+                    "#,
+                    export_uuid,
+                    error,
+                    now
+                )
+                .execute(&self.db)
+                .await
+                .with_context(|| format!("Failed to fail export '{export_uuid}'"))?;
+
+                info!("Export '{export_uuid}' failed: {error}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Discards the files of expired exports, returning how many were discarded. The exports
+    /// themselves are kept, so following one says it has expired.
+    pub async fn purge_expired(&self) -> Result<u64, ApiError> {
+        let purged = sqlx::query!(
+            r#"
+                UPDATE person_export SET content = NULL
+                WHERE expires <= $1 AND content IS NOT NULL;
+            "#,
+            clock::now()
+        )
+        .execute(&self.db)
+        .await
+        .context("Failed to purge expired exports")?
+        .rows_affected();
+
+        Ok(purged)
+    }
+
+    /// Every person matching the filter, read a page at a time
+    async fn everyone(&self, filter: Option<&Filter>) -> Result<Vec<Person>, ApiError> {
+        let people = PersonService::new(self.db.clone());
+        let page = Limit::new(Some(MAX_LIMIT));
+        let mut everyone = vec![];
+
+        loop {
+            let found = people
+                .list_from(filter, &[], everyone.len() as i64, page)
+                .await?;
+            let last = (found.len() as i64) < page.get();
+            everyone.extend(found);
+
+            if last {
+                return Ok(everyone);
+            }
+        }
+    }
+}
+
+fn not_found(export_uuid: Uuid) -> ApiError {
+    ApiError::NotFound(format!("Export not found for the UUID: {export_uuid}"))
+}
+
+extractor!(PersonExportService);
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use sqlx::{types::Json, PgConnection, PgPool};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::{
+    clock,
+    events::Event,
+    http::{
+        error::{ApiError, Context},
+        limit::Limit,
+        person::Person,
+        person_history::{ChangedField, FieldChange, HistoryEntry, PersonDiff},
+    },
+};
+
+/// The fields of a person recorded as changed, and compared between revisions, leaving out
+/// those only ever set by the service itself
+pub(crate) const DIFFED_FIELDS: [&str; 3] = ["firstName", "familyName", "dateOfBirth"];
+
+/// Records the change in the person's history on behalf of `actor`, within the transaction
+/// making it, giving each field it changed with its old and new value. Events other than
+/// changes to the person themselves are ignored.
+///
+/// Old values are taken from the person as the last entry left them, so the first change
+/// recorded to someone who existed before the history was kept has none.
+pub(crate) async fn record(
+    conn: &mut PgConnection,
+    actor: &str,
+    event: &Event,
+) -> Result<(), ApiError> {
+    let (person_uuid, person) = match event {
+        Event::PersonCreated { person }
+        | Event::PersonUpdated { person }
+        | Event::PersonRestored { person } => (person.id, Some(person)),
+        Event::PersonDeleted { person_id } | Event::PersonArchived { person_id } => {
+            (*person_id, None)
+        }
+        _ => return Ok(()),
+    };
+
+    // the person's row is locked by the change being recorded, so the last entry can't race
+    let previous = sqlx::query_scalar!(
+        r#"
+            SELECT data FROM person_history
+            WHERE person = $1
+            ORDER BY id DESC
+            LIMIT 1;
+        "#,
+        person_uuid
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .with_context(|| format!("Failed to find the history of person '{person_uuid}'"))?
+    .flatten();
+
+    let current = person.map(|person| serde_json::json!(person));
+    let field = |data: &Option<Value>, name: &str| {
+        data.as_ref()
+            .and_then(|data| data.get(name))
+            .cloned()
+            .unwrap_or(Value::Null)
+    };
+
+    let changes: Vec<_> = DIFFED_FIELDS
+        .into_iter()
+        .filter_map(|name| {
+            let old_value = field(&previous, name);
+            let new_value = field(&current, name);
+
+            (old_value != new_value).then(|| ChangedField {
+                field: name.to_owned(),
+                old_value,
+                new_value,
+            })
+        })
+        .collect();
+
+    sqlx::query!(
+        r#"
+            INSERT INTO person_history (person, change_type, data, changes, actor, changed)
+            VALUES ($1, $2, $3, $4, $5, $6);
+        "#,
+        person_uuid,
+        event.name(),
+        person.map(Json) as Option<Json<&Person>>,
+        Json(&changes) as Json<&Vec<ChangedField>>,
+        actor,
+        clock::now()
+    )
+    .execute(&mut *conn)
+    .await
+    .with_context(|| {
+        format!(
+            "Failed to record the {} change to person '{person_uuid}'",
+            event.name()
+        )
+    })?;
+
+    Ok(())
+}
+
+/// Reading the audit of changes made to people, which is always recorded and so is what
+/// looking back at a person is built on, whether or not event sourcing is enabled
+#[derive(Clone, Debug)]
+pub struct PersonHistoryService {
+    db: PgPool,
+}
+
+impl PersonHistoryService {
+    pub fn new(db: PgPool) -> Self {
+        PersonHistoryService { db }
+    }
+
+    /// Every change recorded to the person, oldest first. People who have since been deleted
+    /// or archived still have their history, while people who never had a change recorded,
+    /// and don't exist, aren't found.
+    pub async fn list(&self, person_uuid: Uuid) -> Result<Vec<HistoryEntry>, ApiError> {
+        let entries: Vec<_> = sqlx::query!(
+            r#"
+                SELECT (ROW_NUMBER() OVER (ORDER BY id))::int AS "revision!", change_type,
+                    changes AS "changes: Json<Vec<ChangedField>>", actor, changed
+                FROM person_history
+                WHERE person = $1
+                ORDER BY id;
+            "#,
+            person_uuid
+        )
+        .fetch_all(&self.db)
+        .await
+        .with_context(|| format!("Failed to find the history of person '{person_uuid}'"))?
+        .into_iter()
+        .map(|row| HistoryEntry {
+            person_id: person_uuid,
+            revision: row.revision,
+            change_type: row.change_type,
+            changes: row.changes.0,
+            changed_by: row.actor,
+            changed: row.changed,
+        })
+        .collect();
+
+        if entries.is_empty() {
+            let exists = sqlx::query_scalar!(
+                r#"SELECT EXISTS (SELECT 1 FROM person WHERE uuid = $1) AS "exists!";"#,
+                person_uuid
+            )
+            .fetch_one(&self.db)
+            .await
+            .with_context(|| format!("Failed to find person '{person_uuid}'"))?;
+
+            if !exists {
+                return Err(ApiError::NotFound(format!(
+                    "Person not found for the UUID: {person_uuid}"
+                )));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// The most recent changes recorded to the person, oldest first, numbered as they are in
+    /// the whole history
+    pub async fn recent(
+        &self,
+        person_uuid: Uuid,
+        limit: Limit,
+    ) -> Result<Vec<HistoryEntry>, ApiError> {
+        let entries = sqlx::query!(
+            r#"
+                SELECT revision AS "revision!", change_type AS "change_type!",
+                    changes AS "changes!: Json<Vec<ChangedField>>", actor AS "actor!",
+                    changed AS "changed!"
+                FROM (
+                    SELECT id, (ROW_NUMBER() OVER (ORDER BY id))::int AS revision, change_type,
+                        changes, actor, changed
+                    FROM person_history
+                    WHERE person = $1
+                    ORDER BY id DESC
+                    LIMIT $2
+                ) recent
+                ORDER BY id;
+            "#,
+            person_uuid,
+            limit.get()
+        )
+        .fetch_all(&self.db)
+        .await
+        .with_context(|| format!("Failed to find the history of person '{person_uuid}'"))?
+        .into_iter()
+        .map(|row| HistoryEntry {
+            person_id: person_uuid,
+            revision: row.revision,
+            change_type: row.change_type,
+            changes: row.changes.0,
+            changed_by: row.actor,
+            changed: row.changed,
+        })
+        .collect();
+
+        Ok(entries)
+    }
+
+    /// The person as they were left by the last change recorded to them by the given time
+    pub async fn as_of(&self, person_uuid: Uuid, at: OffsetDateTime) -> Result<Person, ApiError> {
+        let entry = sqlx::query!(
+            r#"
+                SELECT change_type, data AS "person: Json<Person>"
+                FROM person_history
+                WHERE person = $1 AND changed <= $2
+                ORDER BY id DESC
+                LIMIT 1;
+            "#,
+            person_uuid,
+            at
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find the history of person '{person_uuid}'"))?
+        .ok_or_else(|| {
+            ApiError::NotFound(format!(
+                "No history of the person '{person_uuid}' recorded by {at}"
+            ))
+        })?;
+
+        let Json(person) = entry.person.ok_or_else(|| {
+            ApiError::NotFound(format!(
+                "Person '{person_uuid}' was gone by {at}, following a {} change",
+                entry.change_type
+            ))
+        })?;
+
+        Ok(person)
+    }
+
+    /// The fields differing between two revisions of the person, along with who last changed
+    /// each of them in between
+    pub async fn diff(
+        &self,
+        person_uuid: Uuid,
+        rev_a: i32,
+        rev_b: i32,
+    ) -> Result<PersonDiff, ApiError> {
+        let (earlier, later) = (rev_a.min(rev_b), rev_a.max(rev_b));
+
+        let revisions = sqlx::query!(
+            r#"
+                SELECT revision AS "revision!", data, actor AS "actor!", changed AS "changed!"
+                FROM (
+                    SELECT (ROW_NUMBER() OVER (ORDER BY id))::int AS revision, data, actor, changed
+                    FROM person_history
+                    WHERE person = $1
+                ) history
+                WHERE revision BETWEEN $2 AND $3
+                ORDER BY revision;
+            "#,
+            person_uuid,
+            earlier,
+            later
+        )
+        .fetch_all(&self.db)
+        .await
+        .with_context(|| format!("Failed to find the history of person '{person_uuid}'"))?;
+
+        // revisions have no gaps, so only the ends need checking
+        for revision in [earlier, later] {
+            if !revisions.iter().any(|r| r.revision == revision) {
+                return Err(ApiError::NotFound(format!(
+                    "Revision {revision} not found for the person: {person_uuid}"
+                )));
+            }
+        }
+
+        let field = |data: &Option<Value>, name: &str| {
+            data.as_ref()
+                .and_then(|data| data.get(name))
+                .cloned()
+                .unwrap_or(Value::Null)
+        };
+
+        let mut last_changed = HashMap::new();
+        for pair in revisions.windows(2) {
+            for name in DIFFED_FIELDS {
+                if field(&pair[0].data, name) != field(&pair[1].data, name) {
+                    last_changed.insert(name, (&pair[1].actor, pair[1].changed));
+                }
+            }
+        }
+
+        let data_at = |revision: i32| &revisions[(revision - earlier) as usize].data;
+        let changes = DIFFED_FIELDS
+            .into_iter()
+            .filter_map(|name| {
+                let old_value = field(data_at(rev_a), name);
+                let new_value = field(data_at(rev_b), name);
+                let (changed_by, changed) = last_changed.get(name)?;
+
+                (old_value != new_value).then(|| FieldChange {
+                    field: name.to_owned(),
+                    old_value,
+                    new_value,
+                    changed_by: changed_by.to_string(),
+                    changed: *changed,
+                })
+            })
+            .collect();
+
+        Ok(PersonDiff {
+            person_id: person_uuid,
+            from: rev_a,
+            to: rev_b,
+            changes,
+        })
+    }
+}
+
+extractor!(PersonHistoryService);
@@ -0,0 +1,360 @@
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    events::Event,
+    http::{
+        cache,
+        emergency_contact::MAX_EMERGENCY_CONTACTS,
+        error::{ApiError, Context},
+        person_merge::PersonMerge,
+        relationship::RelationshipType,
+    },
+    outbox,
+    service::{employment, person::remove, relationship::leads_to},
+};
+
+/// Merging duplicate people into one
+#[derive(Clone, Debug)]
+pub struct PersonMergeService {
+    db: PgPool,
+}
+
+impl PersonMergeService {
+    pub fn new(db: PgPool) -> Self {
+        PersonMergeService { db }
+    }
+
+    /// Merges the source person into the target on behalf of `actor`, in one transaction.
+    /// The source's employment, emergency contacts, consent and notes are moved to the target,
+    /// as are any contact details, tags and relationships the target doesn't already have and
+    /// their address and photo when the target has none, and the source is then deleted. The
+    /// merge is refused if it would leave the target with more emergency contacts than allowed
+    /// or overlapping employment. The merge is recorded, along with what was moved.
+    pub async fn merge(
+        &self,
+        actor: &str,
+        target_uuid: Uuid,
+        source_uuid: Uuid,
+    ) -> Result<PersonMerge, ApiError> {
+        if target_uuid == source_uuid {
+            return Err(ApiError::Conflict(format!(
+                "Person '{target_uuid}' can't be merged into themselves"
+            )));
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        // both are locked in the same order, so merges of the same people can't deadlock
+        let people = sqlx::query!(
+            r#"
+                SELECT uuid, address, legal_hold FROM person
+                WHERE uuid = ANY($1) AND deleted_at IS NULL
+                ORDER BY uuid
+                FOR UPDATE;
+            "#,
+            &[target_uuid, source_uuid]
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to find people '{target_uuid}' and '{source_uuid}'"))?;
+
+        let find = |person_uuid: Uuid| {
+            people
+                .iter()
+                .find(|person| person.uuid == person_uuid)
+                .ok_or_else(|| {
+                    ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}"))
+                })
+        };
+        let target = find(target_uuid)?;
+        let source = find(source_uuid)?;
+
+        if source.legal_hold {
+            return Err(ApiError::Locked(format!(
+                "Person '{source_uuid}' is under legal hold"
+            )));
+        }
+
+        // refused like adding them one at a time would be, rather than moving only some
+        let emergency_contacts = sqlx::query_scalar!(
+            r#"
+                SELECT COUNT(*) AS "count!" FROM person_emergency_contact
+                WHERE person IN ($1, $2);
+            "#,
+            target_uuid,
+            source_uuid
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .with_context(|| {
+            format!("Failed to count the emergency contacts of people '{target_uuid}' and '{source_uuid}'")
+        })?;
+
+        if emergency_contacts > MAX_EMERGENCY_CONTACTS {
+            return Err(ApiError::Conflict(format!(
+                "Merging would give person '{target_uuid}' {emergency_contacts} emergency \
+                 contacts, but a person can have at most {MAX_EMERGENCY_CONTACTS}"
+            )));
+        }
+
+        let moving = sqlx::query!(
+            r#"
+                SELECT uuid, start_date, end_date FROM person_employment
+                WHERE person = $1
+                ORDER BY start_date;
+            "#,
+            source_uuid
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to find the employment of person '{source_uuid}'"))?;
+
+        // checked as if each were added to the target, so the same rules apply
+        for employment in moving {
+            let overlapping = employment::find_overlap(
+                &mut tx,
+                target_uuid,
+                None,
+                employment.start_date,
+                employment.end_date,
+            )
+            .await?;
+
+            if let Some(kept) = overlapping {
+                return Err(ApiError::Conflict(format!(
+                    "The employment '{}' overlaps with '{}' at {}",
+                    employment.uuid, kept.uuid, kept.employer
+                )));
+            }
+        }
+
+        let moved_address = match (target.address, source.address) {
+            (None, Some(address)) => Some(address),
+            _ => None,
+        };
+        if let Some(address) = moved_address {
+            // given to the target and taken from the source
+            sqlx::query!(
+                r#"
+                    UPDATE person SET address = CASE WHEN uuid = $1 THEN $3::uuid END
+                    WHERE uuid IN ($1, $2);
+                "#,
+                target_uuid,
+                source_uuid,
+                address
+            )
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to move the address of person '{source_uuid}'"))?;
+        }
+
+        let employments_moved = sqlx::query!(
+            "UPDATE person_employment SET person = $1 WHERE person = $2;",
+            target_uuid,
+            source_uuid
+        )
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to move the employment of person '{source_uuid}'"))?
+        .rows_affected();
+
+        let emergency_contacts_moved = sqlx::query!(
+            "UPDATE person_emergency_contact SET person = $1 WHERE person = $2;",
+            target_uuid,
+            source_uuid
+        )
+        .execute(&mut *tx)
+        .await
+        .with_context(|| {
+            format!("Failed to move the emergency contacts of person '{source_uuid}'")
+        })?
+        .rows_affected();
+
+        // the target keeps their own where both have the same email address or phone number
+        let emails_moved = sqlx::query!(
+            r#"
+                UPDATE person_email_address SET person = $1
+                WHERE person = $2 AND lower(address) NOT IN (
+                    SELECT lower(address) FROM person_email_address WHERE person = $1
+                );
+            "#,
+            target_uuid,
+            source_uuid
+        )
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to move the email addresses of person '{source_uuid}'"))?
+        .rows_affected();
+
+        let phones_moved = sqlx::query!(
+            r#"
+                UPDATE person_phone_number SET person = $1
+                WHERE person = $2 AND number NOT IN (
+                    SELECT number FROM person_phone_number WHERE person = $1
+                );
+            "#,
+            target_uuid,
+            source_uuid
+        )
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to move the phone numbers of person '{source_uuid}'"))?
+        .rows_affected();
+
+        let consents_moved = sqlx::query!(
+            "UPDATE person_consent SET person = $1 WHERE person = $2;",
+            target_uuid,
+            source_uuid
+        )
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to move the consent of person '{source_uuid}'"))?
+        .rows_affected();
+
+        let tags_moved = sqlx::query!(
+            r#"
+                UPDATE person_tag SET person = $1
+                WHERE person = $2 AND tag NOT IN (SELECT tag FROM person_tag WHERE person = $1);
+            "#,
+            target_uuid,
+            source_uuid
+        )
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to move the tags of person '{source_uuid}'"))?
+        .rows_affected();
+
+        let notes_moved = sqlx::query!(
+            "UPDATE person_note SET person = $1 WHERE person = $2;",
+            target_uuid,
+            source_uuid
+        )
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to move the notes about person '{source_uuid}'"))?
+        .rows_affected();
+
+        let photo_moved = sqlx::query!(
+            r#"
+                UPDATE person_photo SET person = $1
+                WHERE person = $2 AND NOT EXISTS (SELECT 1 FROM person_photo WHERE person = $1);
+            "#,
+            target_uuid,
+            source_uuid
+        )
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to move the photo of person '{source_uuid}'"))?
+        .rows_affected()
+            > 0;
+
+        // links between the two of them and those the target already has stay with the source
+        let relationships_moved = sqlx::query!(
+            r#"
+                UPDATE relationship r SET person = moved.person, related = moved.related
+                FROM (
+                    SELECT id, kind, CASE WHEN person = $2 THEN $1 ELSE person END AS person,
+                        CASE WHEN related = $2 THEN $1 ELSE related END AS related
+                    FROM relationship
+                    WHERE $2 IN (person, related) AND $1 NOT IN (person, related)
+                ) moved
+                WHERE r.id = moved.id AND NOT EXISTS (
+                    SELECT 1 FROM relationship t
+                    WHERE t.kind = moved.kind AND (
+                        (t.person, t.related) = (moved.person, moved.related)
+                        OR (t.kind IN ('spouse', 'sibling')
+                            AND (t.person, t.related) = (moved.related, moved.person))
+                    )
+                );
+            "#,
+            target_uuid,
+            source_uuid
+        )
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to move the relationships of person '{source_uuid}'"))?
+        .rows_affected();
+
+        for relationship_type in [RelationshipType::Parent, RelationshipType::Guardian] {
+            if leads_to(&mut tx, target_uuid, target_uuid, relationship_type).await? {
+                return Err(ApiError::Conflict(format!(
+                    "Person '{source_uuid}' can't be merged into person '{target_uuid}', as \
+                     their relationships would form a cycle"
+                )));
+            }
+        }
+
+        remove(&mut tx, actor, source_uuid).await?;
+
+        let merge = sqlx::query_as!(
+            PersonMerge,
+            r#"
+                INSERT INTO person_merge (target, source, address_moved, employments_moved,
+                    emergency_contacts_moved, contact_details_moved, consents_moved, tags_moved,
+                    notes_moved, relationships_moved, photo_moved, merged_by)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                RETURNING uuid AS id, target AS target_id, source AS source_id, address_moved,
+                    employments_moved, emergency_contacts_moved, contact_details_moved,
+                    consents_moved, tags_moved, notes_moved, relationships_moved, photo_moved,
+                    merged_by, merged;
+            "#,
+            target_uuid,
+            source_uuid,
+            moved_address.is_some(),
+            employments_moved as i64,
+            emergency_contacts_moved as i64,
+            (emails_moved + phones_moved) as i64,
+            consents_moved as i64,
+            tags_moved as i64,
+            notes_moved as i64,
+            relationships_moved as i64,
+            photo_moved,
+            actor
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to record the merge of person '{source_uuid}'"))?;
+
+        if let Some(address_id) = moved_address {
+            outbox::enqueue(
+                &mut tx,
+                &Event::AddressAdded {
+                    person_id: target_uuid,
+                    address_id,
+                },
+            )
+            .await
+            .context("Failed to queue the address added event")?;
+        }
+        if photo_moved {
+            outbox::enqueue(
+                &mut tx,
+                &Event::PhotoUpdated {
+                    person_id: target_uuid,
+                },
+            )
+            .await
+            .context("Failed to queue the photo updated event")?;
+        }
+        outbox::enqueue(
+            &mut tx,
+            &Event::PersonMerged {
+                person_id: target_uuid,
+                merged_id: source_uuid,
+            },
+        )
+        .await
+        .context("Failed to queue the person merged event")?;
+
+        tx.commit().await?;
+        cache::invalidate(source_uuid);
+
+        info!("Client '{actor}' merged person '{source_uuid}' into '{target_uuid}'");
+
+        Ok(merge)
+    }
+}
+
+extractor!(PersonMergeService);
@@ -0,0 +1,137 @@
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    clock,
+    events::Event,
+    http::{
+        error::{ApiError, Context},
+        limit::Limit,
+        person_note::{NewNote, Note},
+    },
+    outbox,
+    service::person::lock_person,
+};
+
+/// Writing case notes about people, which are kept as written
+#[derive(Clone, Debug)]
+pub struct PersonNoteService {
+    db: PgPool,
+}
+
+impl PersonNoteService {
+    pub fn new(db: PgPool) -> Self {
+        PersonNoteService { db }
+    }
+
+    /// A page of the notes about a person, newest first, along with how many there are in all
+    pub async fn list(
+        &self,
+        person_uuid: Uuid,
+        offset: i64,
+        limit: Limit,
+    ) -> Result<(i64, Vec<Note>), ApiError> {
+        let mut conn = self.db.acquire().await?;
+        lock_person(&mut conn, person_uuid, false).await?;
+
+        let total = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM person_note WHERE person = $1;"#,
+            person_uuid
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .with_context(|| format!("Failed to count the notes about person '{person_uuid}'"))?;
+
+        let notes = sqlx::query_as!(
+            Note,
+            r#"
+                SELECT uuid AS id, person AS person_id, body, author, created
+                FROM person_note
+                WHERE person = $1
+                ORDER BY created DESC, id DESC
+                OFFSET $2
+                LIMIT $3;
+            "#,
+            person_uuid,
+            offset,
+            limit.get()
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .with_context(|| format!("Failed to list the notes about person '{person_uuid}'"))?;
+
+        Ok((total, notes))
+    }
+
+    /// One of the notes about a person
+    pub async fn find(&self, person_uuid: Uuid, note_uuid: Uuid) -> Result<Note, ApiError> {
+        let note = sqlx::query_as!(
+            Note,
+            r#"
+                SELECT uuid AS id, person AS person_id, body, author, created
+                FROM person_note
+                WHERE uuid = $1 AND person = $2;
+            "#,
+            note_uuid,
+            person_uuid
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find note '{note_uuid}'"))?
+        .ok_or_else(|| ApiError::NotFound(format!("Note not found for the UUID: {note_uuid}")))?;
+
+        Ok(note)
+    }
+
+    /// Writes a note about a person by `author`, after validating the request
+    pub async fn add(
+        &self,
+        author: &str,
+        person_uuid: Uuid,
+        request: &NewNote,
+    ) -> Result<Note, ApiError> {
+        request.validate()?;
+
+        let mut tx = self.db.begin().await?;
+        lock_person(&mut tx, person_uuid, true).await?;
+
+        let note = sqlx::query_as!(
+            Note,
+            r#"
+                INSERT INTO person_note (person, body, author, created)
+                VALUES ($1, $2, $3, $4)
+                RETURNING uuid AS id, person AS person_id, body, author, created;
+            "#,
+            person_uuid,
+            request.body,
+            author,
+            clock::now()
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to insert a note about person '{person_uuid}'"))?;
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::NoteAdded {
+                person_id: person_uuid,
+                note_id: note.id,
+            },
+        )
+        .await
+        .context("Failed to queue the note added event")?;
+
+        tx.commit().await?;
+
+        info!(
+            "Client '{author}' wrote note '{}' about person '{person_uuid}'",
+            note.id
+        );
+
+        Ok(note)
+    }
+}
+
+extractor!(PersonNoteService);
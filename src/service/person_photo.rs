@@ -0,0 +1,98 @@
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    clock,
+    events::Event,
+    http::{
+        error::{ApiError, Context},
+        person_photo::{PersonPhoto, PhotoType},
+    },
+    outbox,
+    service::person::lock_person,
+};
+
+/// Keeping a profile photo of each person, held in the database as exports are
+#[derive(Clone, Debug)]
+pub struct PersonPhotoService {
+    db: PgPool,
+}
+
+impl PersonPhotoService {
+    pub fn new(db: PgPool) -> Self {
+        PersonPhotoService { db }
+    }
+
+    /// The person's photo, as its content type and the image itself
+    pub async fn download(&self, person_uuid: Uuid) -> Result<(String, Vec<u8>), ApiError> {
+        let mut conn = self.db.acquire().await?;
+        lock_person(&mut conn, person_uuid, false).await?;
+
+        let photo = sqlx::query!(
+            "SELECT content_type, content FROM person_photo WHERE person = $1;",
+            person_uuid
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .with_context(|| format!("Failed to find the photo of person '{person_uuid}'"))?
+        .ok_or_else(|| {
+            ApiError::NotFound(format!("Person '{person_uuid}' doesn't have a photo"))
+        })?;
+
+        Ok((photo.content_type, photo.content))
+    }
+
+    /// Gives the person the photo on behalf of `actor`, replacing any they already have
+    pub async fn replace(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        photo_type: PhotoType,
+        content: Vec<u8>,
+    ) -> Result<PersonPhoto, ApiError> {
+        let mut tx = self.db.begin().await?;
+        lock_person(&mut tx, person_uuid, true).await?;
+
+        let photo = sqlx::query_as!(
+            PersonPhoto,
+            r#"
+                INSERT INTO person_photo (person, content_type, content, uploaded_by, uploaded)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (person) DO UPDATE SET content_type = EXCLUDED.content_type,
+                    content = EXCLUDED.content, uploaded_by = EXCLUDED.uploaded_by,
+                    uploaded = EXCLUDED.uploaded
+                RETURNING person AS person_id, content_type, length(content)::BIGINT AS "size!",
+                    uploaded;
+            "#,
+            person_uuid,
+            photo_type.content_type(),
+            content,
+            actor,
+            clock::now()
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to save the photo of person '{person_uuid}'"))?;
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::PhotoUpdated {
+                person_id: person_uuid,
+            },
+        )
+        .await
+        .context("Failed to queue the photo updated event")?;
+
+        tx.commit().await?;
+
+        info!(
+            "Client '{actor}' uploaded a {} byte photo of person '{person_uuid}'",
+            photo.size
+        );
+
+        Ok(photo)
+    }
+}
+
+extractor!(PersonPhotoService);
@@ -0,0 +1,86 @@
+use sqlx::PgPool;
+use time::UtcOffset;
+
+use crate::{
+    clock,
+    http::{
+        error::{ApiError, Context},
+        person_stats::{AgeBand, DailyCount, PersonStats},
+    },
+};
+
+/// Summarising people for reporting, counted by the database rather than fetched
+#[derive(Clone, Debug)]
+pub struct PersonStatsService {
+    db: PgPool,
+}
+
+impl PersonStatsService {
+    pub fn new(db: PgPool) -> Self {
+        PersonStatsService { db }
+    }
+
+    /// Statistics about everyone who hasn't been deleted, as of today in UTC
+    pub async fn summarise(&self) -> Result<PersonStats, ApiError> {
+        let today = clock::now().to_offset(UtcOffset::UTC).date();
+
+        // every band is joined to the ages within it, so empty bands are counted as 0
+        let age_bands = sqlx::query_as!(
+            AgeBand,
+            r#"
+                SELECT b.min_age AS "min_age!", b.max_age, COUNT(p.id) AS "count!"
+                FROM (VALUES (0, 17), (18, 24), (25, 34), (35, 44), (45, 54), (55, 64), (65, NULL))
+                    AS b (min_age, max_age)
+                LEFT JOIN person p ON p.deleted_at IS NULL
+                    AND date_part('year', age($1::DATE, p.date_of_birth)) >= b.min_age
+                    AND (b.max_age IS NULL OR date_part('year', age($1::DATE, p.date_of_birth)) <= b.max_age)
+                GROUP BY b.min_age, b.max_age
+                ORDER BY b.min_age;
+            "#,
+            today
+        )
+        .fetch_all(&self.db)
+        .await
+        .context("Failed to count people by age")?;
+
+        let created_per_day = sqlx::query_as!(
+            DailyCount,
+            r#"
+                SELECT d.date AS "date!", COUNT(p.id) AS "count!"
+                FROM generate_series(29, 0, -1) AS n
+                CROSS JOIN LATERAL (SELECT $1::DATE - n AS date) d
+                LEFT JOIN person p ON p.deleted_at IS NULL
+                    AND p.created >= d.date::TIMESTAMP AT TIME ZONE 'UTC'
+                    AND p.created < (d.date + 1)::TIMESTAMP AT TIME ZONE 'UTC'
+                GROUP BY d.date
+                ORDER BY d.date;
+            "#,
+            today
+        )
+        .fetch_all(&self.db)
+        .await
+        .context("Failed to count the people created each day")?;
+
+        let totals = sqlx::query!(
+            r#"
+                SELECT COUNT(*) AS "total!",
+                    COUNT(*) FILTER (WHERE address IS NOT NULL) AS "with_address!"
+                FROM person
+                WHERE deleted_at IS NULL;
+            "#
+        )
+        .fetch_one(&self.db)
+        .await
+        .context("Failed to count people")?;
+
+        Ok(PersonStats {
+            total: totals.total,
+            age_bands,
+            created_per_day,
+            with_address: totals.with_address,
+            without_address: totals.total - totals.with_address,
+        })
+    }
+}
+
+extractor!(PersonStatsService);
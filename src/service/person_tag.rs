@@ -0,0 +1,158 @@
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    clock,
+    events::Event,
+    http::{
+        error::{ApiError, Context},
+        fields::Tag,
+    },
+    outbox,
+    service::person::lock_person,
+};
+
+/// Tagging people, each tag being kept once in the `tag` table however many people have it
+#[derive(Clone, Debug)]
+pub struct PersonTagService {
+    db: PgPool,
+}
+
+impl PersonTagService {
+    pub fn new(db: PgPool) -> Self {
+        PersonTagService { db }
+    }
+
+    /// A person's tags, alphabetically
+    pub async fn list(&self, person_uuid: Uuid) -> Result<Vec<String>, ApiError> {
+        let mut conn = self.db.acquire().await?;
+        lock_person(&mut conn, person_uuid, false).await?;
+
+        tags_of(&mut conn, person_uuid).await
+    }
+
+    /// Tags a person on behalf of `actor`, unless they already have the tag, returning all of
+    /// their tags
+    pub async fn add(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        tag: &Tag,
+    ) -> Result<Vec<String>, ApiError> {
+        let mut tx = self.db.begin().await?;
+        lock_person(&mut tx, person_uuid, true).await?;
+
+        // the no-op update returns the tag's id when it already exists
+        let tag_id = sqlx::query_scalar!(
+            r#"
+                INSERT INTO tag (name, created) VALUES ($1, $2)
+                ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name
+                RETURNING id;
+            "#,
+            tag.as_str(),
+            clock::now()
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to find or create the tag '{tag}'"))?;
+
+        let tagged = sqlx::query!(
+            r#"
+                INSERT INTO person_tag (person, tag, tagged_by, tagged) VALUES ($1, $2, $3, $4)
+                ON CONFLICT DO NOTHING;
+            "#,
+            person_uuid,
+            tag_id,
+            actor,
+            clock::now()
+        )
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to tag person '{person_uuid}'"))?
+        .rows_affected()
+            > 0;
+
+        if tagged {
+            outbox::enqueue(
+                &mut tx,
+                &Event::PersonTagged {
+                    person_id: person_uuid,
+                    tag: tag.to_string(),
+                },
+            )
+            .await
+            .context("Failed to queue the person tagged event")?;
+        }
+
+        let tags = tags_of(&mut tx, person_uuid).await?;
+
+        tx.commit().await?;
+
+        if tagged {
+            info!("Client '{actor}' tagged person '{person_uuid}' '{tag}'");
+        }
+
+        Ok(tags)
+    }
+
+    /// Removes a tag from a person on behalf of `actor`
+    pub async fn remove(&self, actor: &str, person_uuid: Uuid, tag: &Tag) -> Result<(), ApiError> {
+        let mut tx = self.db.begin().await?;
+        lock_person(&mut tx, person_uuid, true).await?;
+
+        let removed = sqlx::query!(
+            r#"
+                DELETE FROM person_tag
+                WHERE person = $1 AND tag = (SELECT id FROM tag WHERE name = $2);
+            "#,
+            person_uuid,
+            tag.as_str()
+        )
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to remove tag '{tag}' from person '{person_uuid}'"))?
+        .rows_affected();
+
+        if removed == 0 {
+            return Err(ApiError::NotFound(format!(
+                "Person '{person_uuid}' isn't tagged '{tag}'"
+            )));
+        }
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::PersonUntagged {
+                person_id: person_uuid,
+                tag: tag.to_string(),
+            },
+        )
+        .await
+        .context("Failed to queue the person untagged event")?;
+
+        tx.commit().await?;
+
+        info!("Client '{actor}' removed tag '{tag}' from person '{person_uuid}'");
+
+        Ok(())
+    }
+}
+
+async fn tags_of(conn: &mut PgConnection, person_uuid: Uuid) -> Result<Vec<String>, ApiError> {
+    let tags = sqlx::query_scalar!(
+        r#"
+            SELECT t.name FROM person_tag pt
+            JOIN tag t ON t.id = pt.tag
+            WHERE pt.person = $1
+            ORDER BY t.name;
+        "#,
+        person_uuid
+    )
+    .fetch_all(conn)
+    .await
+    .with_context(|| format!("Failed to list the tags of person '{person_uuid}'"))?;
+
+    Ok(tags)
+}
+
+extractor!(PersonTagService);
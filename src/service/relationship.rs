@@ -0,0 +1,245 @@
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::{
+    clock,
+    events::Event,
+    http::{
+        error::{ApiError, Context},
+        relationship::{NewRelationship, Relationship, RelationshipType},
+    },
+    outbox,
+    service::person::lock_person,
+};
+
+/// Linking people to their parents, guardians, spouses, siblings and next of kin, each link
+/// being listed from both sides
+#[derive(Clone, Debug)]
+pub struct RelationshipService {
+    db: PgPool,
+}
+
+impl RelationshipService {
+    pub fn new(db: PgPool) -> Self {
+        RelationshipService { db }
+    }
+
+    /// A person's relationships made from either side, in the order they were made, leaving out
+    /// those to people who have been deleted
+    pub async fn list(&self, person_uuid: Uuid) -> Result<Vec<Relationship>, ApiError> {
+        let mut conn = self.db.acquire().await?;
+        lock_person(&mut conn, person_uuid, false).await?;
+
+        let relationships = sqlx::query_as!(
+            Relationship,
+            r#"
+                SELECT r.uuid AS id, r.person AS person_id, r.related AS related_id,
+                    r.kind AS "relationship_type: RelationshipType", r.created_by, r.created
+                FROM relationship r
+                JOIN person other
+                    ON other.uuid = CASE WHEN r.person = $1 THEN r.related ELSE r.person END
+                WHERE (r.person = $1 OR r.related = $1) AND other.deleted_at IS NULL
+                ORDER BY r.created, r.id;
+            "#,
+            person_uuid
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .with_context(|| format!("Failed to list the relationships of person '{person_uuid}'"))?;
+
+        Ok(relationships)
+    }
+
+    /// One of a person's relationships, made from either side
+    pub async fn find(
+        &self,
+        person_uuid: Uuid,
+        relationship_uuid: Uuid,
+    ) -> Result<Relationship, ApiError> {
+        let relationship = sqlx::query_as!(
+            Relationship,
+            r#"
+                SELECT uuid AS id, person AS person_id, related AS related_id,
+                    kind AS "relationship_type: RelationshipType", created_by, created
+                FROM relationship
+                WHERE uuid = $1 AND (person = $2 OR related = $2);
+            "#,
+            relationship_uuid,
+            person_uuid
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find relationship '{relationship_uuid}'"))?
+        .ok_or_else(|| not_found(relationship_uuid))?;
+
+        Ok(relationship)
+    }
+
+    /// Links a person to someone related to them on behalf of `actor`, unless they're already
+    /// linked that way or the link would lead back round to the person
+    pub async fn add(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        request: &NewRelationship,
+    ) -> Result<Relationship, ApiError> {
+        let related_uuid = request.related_id;
+        if related_uuid == person_uuid {
+            let mut errors = ValidationErrors::new();
+            errors.add(
+                "relatedId",
+                ValidationError::new("self")
+                    .with_message("a person can't be related to themselves".into()),
+            );
+            return Err(errors.into());
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        // both are locked in the same order, so links between the same people can't deadlock
+        let (first, second) = if person_uuid < related_uuid {
+            (person_uuid, related_uuid)
+        } else {
+            (related_uuid, person_uuid)
+        };
+        lock_person(&mut tx, first, true).await?;
+        lock_person(&mut tx, second, true).await?;
+
+        if request.relationship_type.is_lineal()
+            && leads_to(
+                &mut tx,
+                related_uuid,
+                person_uuid,
+                request.relationship_type,
+            )
+            .await?
+        {
+            return Err(ApiError::Conflict(format!(
+                "Person '{related_uuid}' can't be linked to person '{person_uuid}' that way, \
+                 as it would form a cycle"
+            )));
+        }
+
+        let relationship = sqlx::query_as!(
+            Relationship,
+            r#"
+                INSERT INTO relationship (person, related, kind, created_by, created)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING uuid AS id, person AS person_id, related AS related_id,
+                    kind AS "relationship_type: RelationshipType", created_by, created;
+            "#,
+            person_uuid,
+            related_uuid,
+            request.relationship_type as RelationshipType,
+            actor,
+            clock::now()
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(dbe) if dbe.is_unique_violation() => ApiError::Conflict(format!(
+                "Persons '{person_uuid}' and '{related_uuid}' are already related that way"
+            )),
+            _ => ApiError::from(e).context(format!(
+                "Failed to insert a relationship for person '{person_uuid}'"
+            )),
+        })?;
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::RelationshipAdded {
+                person_id: person_uuid,
+                relationship_id: relationship.id,
+            },
+        )
+        .await
+        .context("Failed to queue the relationship added event")?;
+
+        tx.commit().await?;
+
+        info!(
+            "Client '{actor}' linked person '{person_uuid}' to person '{related_uuid}' with relationship '{}'",
+            relationship.id
+        );
+
+        Ok(relationship)
+    }
+
+    /// Removes one of a person's relationships, made from either side, on behalf of `actor`
+    pub async fn remove(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        relationship_uuid: Uuid,
+    ) -> Result<(), ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        let deleted = sqlx::query!(
+            "DELETE FROM relationship WHERE uuid = $1 AND (person = $2 OR related = $2);",
+            relationship_uuid,
+            person_uuid
+        )
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to delete relationship '{relationship_uuid}'"))?;
+
+        if deleted.rows_affected() == 0 {
+            return Err(not_found(relationship_uuid));
+        }
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::RelationshipRemoved {
+                person_id: person_uuid,
+                relationship_id: relationship_uuid,
+            },
+        )
+        .await
+        .context("Failed to queue the relationship removed event")?;
+
+        tx.commit().await?;
+
+        info!("Client '{actor}' deleted relationship '{relationship_uuid}'");
+
+        Ok(())
+    }
+}
+
+/// Whether following one or more links of the given type from person to related person,
+/// starting at `from`, reaches `to`
+pub(crate) async fn leads_to(
+    conn: &mut PgConnection,
+    from: Uuid,
+    to: Uuid,
+    relationship_type: RelationshipType,
+) -> Result<bool, ApiError> {
+    let found = sqlx::query_scalar!(
+        r#"
+            WITH RECURSIVE reached (uuid) AS (
+                SELECT related FROM relationship WHERE person = $1 AND kind = $3
+                UNION
+                SELECT r.related FROM relationship r JOIN reached ON r.person = reached.uuid
+                WHERE r.kind = $3
+            )
+            SELECT EXISTS (SELECT 1 FROM reached WHERE uuid = $2) AS "found!";
+        "#,
+        from,
+        to,
+        relationship_type as RelationshipType
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .with_context(|| format!("Failed to follow the relationships of person '{from}'"))?;
+
+    Ok(found)
+}
+
+fn not_found(relationship_uuid: Uuid) -> ApiError {
+    ApiError::NotFound(format!(
+        "Relationship not found for the UUID: {relationship_uuid}"
+    ))
+}
+
+extractor!(RelationshipService);
@@ -0,0 +1,215 @@
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    clock,
+    events::Event,
+    http::{
+        cache,
+        error::{ApiError, Context},
+        scheduled_deletion::{ScheduleDeletion, ScheduledDeletion},
+    },
+    outbox,
+    service::{legal_hold::check_not_held, person},
+};
+
+/// The most people deleted by one run of [`ScheduledDeletionService::delete_due`], the rest
+/// being left for the next
+const DELETION_BATCH: i64 = 500;
+
+/// Deleting people at a time decided in advance, giving a grace period in which the deletion
+/// can still be called off
+#[derive(Clone, Debug)]
+pub struct ScheduledDeletionService {
+    db: PgPool,
+}
+
+impl ScheduledDeletionService {
+    pub fn new(db: PgPool) -> Self {
+        ScheduledDeletionService { db }
+    }
+
+    /// When the person is due to be deleted
+    pub async fn find(&self, person_uuid: Uuid) -> Result<ScheduledDeletion, ApiError> {
+        let delete_at = sqlx::query_scalar!(
+            "SELECT delete_at FROM person WHERE uuid = $1 AND deleted_at IS NULL;",
+            person_uuid
+        )
+        .fetch_optional(&self.db)
+        .await
+        .with_context(|| format!("Failed to find the deletion of person '{person_uuid}'"))?
+        .ok_or_else(|| person_not_found(person_uuid))?
+        .ok_or_else(|| not_scheduled(person_uuid))?;
+
+        Ok(ScheduledDeletion {
+            person_id: person_uuid,
+            delete_at,
+        })
+    }
+
+    /// Schedules the person to be deleted on behalf of `actor`, replacing any time they were
+    /// already due to be, unless they're under legal hold
+    pub async fn schedule(
+        &self,
+        actor: &str,
+        person_uuid: Uuid,
+        request: &ScheduleDeletion,
+    ) -> Result<ScheduledDeletion, ApiError> {
+        request.validate()?;
+
+        let mut tx = self.db.begin().await?;
+        check_not_held(&mut tx, person_uuid).await?;
+
+        sqlx::query!(
+            "UPDATE person SET delete_at = $1 WHERE uuid = $2;",
+            request.delete_at,
+            person_uuid
+        )
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to schedule the deletion of person '{person_uuid}'"))?;
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::DeletionScheduled {
+                person_id: person_uuid,
+                delete_at: request.delete_at,
+            },
+        )
+        .await
+        .context("Failed to queue the deletion scheduled event")?;
+
+        tx.commit().await?;
+
+        info!(
+            "Client '{actor}' scheduled person '{person_uuid}' to be deleted at {}",
+            request.delete_at
+        );
+
+        Ok(ScheduledDeletion {
+            person_id: person_uuid,
+            delete_at: request.delete_at,
+        })
+    }
+
+    /// Calls off the person's scheduled deletion on behalf of `actor`
+    pub async fn cancel(&self, actor: &str, person_uuid: Uuid) -> Result<(), ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        let delete_at = sqlx::query_scalar!(
+            "SELECT delete_at FROM person WHERE uuid = $1 AND deleted_at IS NULL FOR UPDATE;",
+            person_uuid
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to find the deletion of person '{person_uuid}'"))?
+        .ok_or_else(|| person_not_found(person_uuid))?;
+
+        if delete_at.is_none() {
+            return Err(not_scheduled(person_uuid));
+        }
+
+        sqlx::query!(
+            "UPDATE person SET delete_at = NULL WHERE uuid = $1;",
+            person_uuid
+        )
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to cancel the deletion of person '{person_uuid}'"))?;
+
+        outbox::enqueue(
+            &mut tx,
+            &Event::DeletionCancelled {
+                person_id: person_uuid,
+            },
+        )
+        .await
+        .context("Failed to queue the deletion cancelled event")?;
+
+        tx.commit().await?;
+
+        info!("Client '{actor}' cancelled the deletion of person '{person_uuid}'");
+
+        Ok(())
+    }
+
+    /// Deletes people whose time has come, returning how many were deleted. Those under legal
+    /// hold are kept, still scheduled, until the hold is lifted.
+    pub async fn delete_due(&self) -> Result<usize, ApiError> {
+        let now = clock::now();
+
+        let due = sqlx::query_scalar!(
+            r#"
+                SELECT uuid FROM person
+                WHERE delete_at <= $1 AND NOT legal_hold AND deleted_at IS NULL
+                ORDER BY delete_at
+                LIMIT $2;
+            "#,
+            now,
+            DELETION_BATCH
+        )
+        .fetch_all(&self.db)
+        .await
+        .context("Failed to find the people due to be deleted")?;
+
+        let mut deleted = 0;
+        for person_uuid in due {
+            if self.delete_if_due(person_uuid, now).await? {
+                deleted += 1;
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Deletes the person, so long as they're still due to be once locked
+    async fn delete_if_due(
+        &self,
+        person_uuid: Uuid,
+        now: OffsetDateTime,
+    ) -> Result<bool, ApiError> {
+        let mut tx = self.db.begin().await?;
+
+        // skips anyone locked by a request, as it may be about to cancel their deletion
+        let due = sqlx::query_scalar!(
+            r#"
+                SELECT id FROM person
+                WHERE uuid = $1 AND delete_at <= $2 AND NOT legal_hold AND deleted_at IS NULL
+                FOR UPDATE SKIP LOCKED;
+            "#,
+            person_uuid,
+            now
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to lock person '{person_uuid}' for deletion"))?;
+
+        if due.is_none() {
+            return Ok(false);
+        }
+
+        person::remove(&mut tx, "person.delete_due", person_uuid).await?;
+
+        tx.commit().await?;
+        cache::invalidate(person_uuid);
+
+        info!("Deleted person '{person_uuid}' as scheduled");
+
+        Ok(true)
+    }
+}
+
+fn person_not_found(person_uuid: Uuid) -> ApiError {
+    ApiError::NotFound(format!("Person not found for the UUID: {person_uuid}"))
+}
+
+fn not_scheduled(person_uuid: Uuid) -> ApiError {
+    ApiError::NotFound(format!(
+        "Person '{person_uuid}' is not scheduled to be deleted"
+    ))
+}
+
+extractor!(ScheduledDeletionService);
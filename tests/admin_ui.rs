@@ -0,0 +1,158 @@
+mod common;
+
+use axum::http::{
+    header::{CONTENT_TYPE, COOKIE, LOCATION, SET_COOKIE},
+    StatusCode,
+};
+use common::{client::TestResponse, TestApp};
+use serde_json::{json, Value};
+
+const FORM: &str = "application/x-www-form-urlencoded";
+
+/// Creates an API client with the scopes, returning its key
+async fn api_key(app: &TestApp, scopes: &[&str]) -> String {
+    let created: Value = app
+        .client()
+        .post("/api/v1/admin/clients")
+        .as_user(&["admin"])
+        .json(&json!({ "name": "operator", "scopes": scopes }))
+        .await
+        .json();
+
+    created["key"].as_str().unwrap().to_owned()
+}
+
+async fn sign_in(app: &TestApp, key: &str) -> TestResponse {
+    app.client()
+        .post("/admin/login")
+        .header(CONTENT_TYPE, FORM)
+        .body(format!("key={key}"))
+        .await
+}
+
+async fn create_person(app: &TestApp, first_name: &str, family_name: &str) -> String {
+    let person: Value = app
+        .client()
+        .post("/api/v1/person")
+        .as_user(&["write"])
+        .json(&json!({
+            "firstName": first_name,
+            "familyName": family_name,
+            "dateOfBirth": "1815-12-10"
+        }))
+        .await
+        .json();
+
+    person["id"].as_str().unwrap().to_owned()
+}
+
+#[tokio::test]
+async fn operators_are_sent_to_sign_in() {
+    let app = TestApp::new().await;
+
+    let response = app.client().get("/admin/people").await;
+
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(response.header(LOCATION), "/admin/login");
+}
+
+#[tokio::test]
+async fn keys_without_the_admin_scope_cannot_sign_in() {
+    let app = TestApp::new().await;
+    let key = api_key(&app, &["read", "write"]).await;
+
+    let response = sign_in(&app, &key).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(response.headers().get(SET_COOKIE).is_none());
+}
+
+#[tokio::test]
+async fn operators_can_search_and_edit_people() {
+    let app = TestApp::new().await;
+    let key = api_key(&app, &["admin"]).await;
+    create_person(&app, "Ada", "Lovelace").await;
+    let id = create_person(&app, "Charles", "Babbage").await;
+
+    let signed_in = sign_in(&app, &key).await;
+    assert_eq!(signed_in.status(), StatusCode::SEE_OTHER);
+    assert_eq!(signed_in.header(LOCATION), "/admin/people");
+
+    let cookie = signed_in.header(SET_COOKIE);
+    assert!(cookie.contains("HttpOnly"));
+    assert!(cookie.contains("SameSite=Strict"));
+    let session = cookie.split(';').next().unwrap().to_owned();
+
+    let found = app
+        .client()
+        .get("/admin/people?q=babb")
+        .header(COOKIE, &session)
+        .await;
+    assert_eq!(found.status(), StatusCode::OK);
+    assert!(found.text().contains("Babbage"));
+    assert!(!found.text().contains("Lovelace"));
+
+    let updated = app
+        .client()
+        .post(&format!("/admin/people/{id}"))
+        .header(COOKIE, &session)
+        .header(CONTENT_TYPE, FORM)
+        .body("version=1&firstName=Charlie&familyName=")
+        .await;
+    assert_eq!(updated.status(), StatusCode::SEE_OTHER);
+
+    let person: Value = app
+        .client()
+        .get(&format!("/api/v1/person/{id}"))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(person["firstName"], "Charlie");
+    assert_eq!(person["familyName"], "Babbage");
+}
+
+#[tokio::test]
+async fn edits_to_people_changed_since_are_refused() {
+    let app = TestApp::new().await;
+    let key = api_key(&app, &["admin"]).await;
+    let id = create_person(&app, "Charles", "Babbage").await;
+    let signed_in = sign_in(&app, &key).await;
+    let session = signed_in
+        .header(SET_COOKIE)
+        .split(';')
+        .next()
+        .unwrap()
+        .to_owned();
+
+    let page = app
+        .client()
+        .get(&format!("/admin/people/{id}"))
+        .header(COOKIE, &session)
+        .await;
+    assert!(page
+        .text()
+        .contains(r#"<input type="hidden" name="version" value="1">"#));
+
+    for (body, expected) in [
+        ("version=1&firstName=Charlie", StatusCode::SEE_OTHER),
+        ("version=1&firstName=Chuck", StatusCode::PRECONDITION_FAILED),
+        ("firstName=Chuck", StatusCode::PRECONDITION_REQUIRED),
+    ] {
+        let response = app
+            .client()
+            .post(&format!("/admin/people/{id}"))
+            .header(COOKIE, &session)
+            .header(CONTENT_TYPE, FORM)
+            .body(body)
+            .await;
+        assert_eq!(response.status(), expected, "{body}");
+    }
+
+    let person: Value = app
+        .client()
+        .get(&format!("/api/v1/person/{id}"))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(person["firstName"], "Charlie");
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{
+        header::{AUTHORIZATION, CACHE_CONTROL, VARY},
+        Request, StatusCode,
+    },
+};
+use common::{auth::token, factories, json_body, TestApp};
+use serde_json::Value;
+
+#[tokio::test]
+async fn person_routes_require_a_token() {
+    let app = TestApp::new().await;
+
+    let response = app.get("/api/v1/person").await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let body: Value = json_body(response).await;
+    assert_eq!(body["message"], "Missing token");
+}
+
+#[tokio::test]
+async fn openapi_document_is_served() {
+    let app = TestApp::new().await;
+
+    let response = app.get("/api-doc/v1/openapi.json").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = json_body(response).await;
+    assert_eq!(body["servers"][0]["url"], "/api/v1");
+}
+
+#[tokio::test]
+async fn openapi_document_may_be_cached_publicly() {
+    let app = TestApp::new().await;
+
+    let response = app.get("/api-doc/v1/openapi.json").await;
+
+    assert_eq!(response.headers()[CACHE_CONTROL], "public, max-age=86400");
+}
+
+#[tokio::test]
+async fn reads_are_cached_privately_and_writes_are_not_stored() {
+    let app = TestApp::new().await;
+
+    let read = app
+        .request(
+            Request::get("/api/v1/person")
+                .header(AUTHORIZATION, format!("Bearer {}", token(&["read"])))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(read.status(), StatusCode::OK);
+    assert_eq!(read.headers()[CACHE_CONTROL], "private, max-age=0");
+    assert!(
+        read.headers()
+            .get_all(VARY)
+            .iter()
+            .any(|value| value == "authorization"),
+        "Should vary by the caller's token"
+    );
+
+    let write = app
+        .request(
+            Request::post("/api/v1/person")
+                .header(AUTHORIZATION, format!("Bearer {}", token(&["write"])))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    r#"{"first_name":"Jane","family_name":"Doe","date_of_birth":"1990-01-01"}"#,
+                ))
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(write.status(), StatusCode::CREATED);
+    assert_eq!(write.headers()[CACHE_CONTROL], "no-store");
+
+    let error = app.get("/api/v1/person").await;
+
+    assert_eq!(error.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(error.headers()[CACHE_CONTROL], "no-store");
+}
+
+#[tokio::test]
+async fn apps_are_isolated_in_their_own_schema() {
+    let app = TestApp::new().await;
+    let other = TestApp::new().await;
+
+    factories::PersonFactory::default()
+        .insert(&other.pool)
+        .await;
+
+    let people: i64 = sqlx::query_scalar("SELECT count(*) FROM person")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+
+    assert_eq!(people, 0);
+    assert_ne!(app.schema, other.schema);
+}
+
+#[tokio::test]
+async fn paths_are_normalized_before_routing() {
+    let app = TestApp::new().await;
+    let person = factories::PersonFactory::default().insert(&app.pool).await;
+
+    for uri in [
+        "/api/v1/person/".to_owned(),
+        "/api//v1/person".to_owned(),
+        format!("//api/v1/person/{}//", person.uuid),
+    ] {
+        let response = app.client().get(&uri).as_user(&["read"]).await;
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+    }
+
+    let response = app.get("/swagger-ui").await;
+    assert_eq!(
+        response.status(),
+        StatusCode::SEE_OTHER,
+        "Should still redirect to the trailing slash"
+    );
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use axum::http::{HeaderName, StatusCode};
+use common::TestApp;
+use serde_json::{json, Value};
+
+const API_KEY: HeaderName = HeaderName::from_static("x-api-key");
+
+#[tokio::test]
+async fn api_keys_grant_their_scopes_until_disabled() {
+    let app = TestApp::new().await;
+    let client = app.client();
+
+    let created = client
+        .post("/api/v1/admin/clients")
+        .as_user(&["admin"])
+        .json(&json!({ "name": "nightly-import", "scopes": ["read"] }))
+        .await;
+    assert_eq!(created.status(), StatusCode::CREATED);
+
+    let created: Value = created.json();
+    let key = created["key"].as_str().unwrap().to_owned();
+    assert!(key.starts_with(created["keyPrefix"].as_str().unwrap()));
+
+    let read = client.get("/api/v1/person").header(API_KEY, &key).await;
+    assert_eq!(read.status(), StatusCode::OK);
+
+    let write = client
+        .post("/api/v1/person")
+        .header(API_KEY, &key)
+        .json(&json!({ "first_name": "Jane", "family_name": "Doe", "date_of_birth": "1990-01-01" }))
+        .await;
+    assert_eq!(write.status(), StatusCode::FORBIDDEN);
+
+    let disabled = client
+        .post(&format!(
+            "/api/v1/admin/clients/{}/disable",
+            created["id"].as_str().unwrap()
+        ))
+        .as_user(&["admin"])
+        .await;
+    assert_eq!(disabled.status(), StatusCode::OK);
+    assert!(disabled.json::<Value>()["disabled"].is_string());
+
+    let read = client.get("/api/v1/person").header(API_KEY, &key).await;
+    assert_eq!(read.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn keys_are_never_listed() {
+    let app = TestApp::new().await;
+    let client = app.client();
+
+    client
+        .post("/api/v1/admin/clients")
+        .as_user(&["admin"])
+        .json(&json!({ "name": "reporting", "scopes": ["read"] }))
+        .await;
+
+    let clients: Value = client
+        .get("/api/v1/admin/clients")
+        .as_user(&["admin"])
+        .await
+        .json();
+
+    let clients = clients.as_array().unwrap();
+    assert_eq!(clients.len(), 1);
+    assert_eq!(clients[0]["name"], "reporting");
+    assert!(clients[0].get("key").is_none());
+}
+
+#[tokio::test]
+async fn clients_need_unique_names_and_known_scopes() {
+    let app = TestApp::new().await;
+    let client = app.client();
+
+    let create = |body: Value| {
+        client
+            .post("/api/v1/admin/clients")
+            .as_user(&["admin"])
+            .json(&body)
+    };
+
+    let first = create(json!({ "name": "Sync", "scopes": ["read"] })).await;
+    assert_eq!(first.status(), StatusCode::CREATED);
+
+    let duplicate = create(json!({ "name": "sync", "scopes": ["read"] })).await;
+    assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+
+    let unknown = create(json!({ "name": "other", "scopes": ["root"] })).await;
+    assert_eq!(unknown.status(), StatusCode::BAD_REQUEST);
+
+    let expired =
+        create(json!({ "name": "other", "scopes": ["read"], "expires": "2000-01-01T00:00:00Z" }))
+            .await;
+    assert_eq!(expired.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn unknown_keys_are_rejected() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .client()
+        .get("/api/v1/person")
+        .header(API_KEY, "rwa_not-a-key")
+        .await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json::<Value>()["message"], "Invalid API key");
+}
+
+#[tokio::test]
+async fn managing_clients_requires_the_admin_scope() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .client()
+        .get("/api/v1/admin/clients")
+        .as_user(&["read", "write"])
+        .await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{
+    factories::{EmergencyContactFactory, EmploymentFactory, PersonFactory},
+    TestApp,
+};
+use rust_web_app::jobs::{self, Job};
+use serde_json::{json, Value};
+use time::macros::datetime;
+
+#[tokio::test]
+async fn people_can_be_archived_and_restored_with_their_details() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    EmploymentFactory::default()
+        .insert(&app.pool, person.uuid)
+        .await;
+    EmergencyContactFactory::default()
+        .insert(&app.pool, person.uuid)
+        .await;
+    client
+        .post(&format!("/api/v1/person/{}/consents", person.uuid))
+        .as_user(&["write"])
+        .json(&json!({ "purpose": "email", "granted": true, "channel": "web" }))
+        .await;
+    client
+        .post(&format!("/api/v1/person/{}/contacts", person.uuid))
+        .as_user(&["write"])
+        .json(&json!({ "type": "email", "value": "ada@example.com" }))
+        .await;
+    client
+        .post(&format!("/api/v1/person/{}/tags", person.uuid))
+        .as_user(&["write"])
+        .json(&json!({ "tag": "cohort" }))
+        .await;
+    client
+        .post(&format!("/api/v1/person/{}/notes", person.uuid))
+        .as_user(&["write"])
+        .json(&json!({ "body": "Moving house" }))
+        .await;
+    let sibling = PersonFactory::default().insert(&app.pool).await;
+    client
+        .post(&format!("/api/v1/person/{}/relationships", person.uuid))
+        .as_user(&["write"])
+        .json(&json!({ "relatedId": sibling.uuid, "type": "sibling" }))
+        .await;
+
+    let response = client
+        .post(&format!("/api/v1/person/{}/archive", person.uuid))
+        .as_user(&["write"])
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = client
+        .get(&format!("/api/v1/person/{}", person.uuid))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let archived: Value = client
+        .get("/api/v1/person/archive")
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(archived[0]["id"], person.uuid.to_string());
+
+    let response = client
+        .post(&format!("/api/v1/person/archive/{}/restore", person.uuid))
+        .as_user(&["write"])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .get(&format!("/api/v1/person/archive/{}", person.uuid))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    for details in [
+        "employments",
+        "emergency-contacts",
+        "consents",
+        "contacts",
+        "tags",
+        "notes",
+        "relationships",
+    ] {
+        let restored: Value = client
+            .get(&format!("/api/v1/person/{}/{details}", person.uuid))
+            .as_user(&["read"])
+            .await
+            .json();
+        assert_eq!(
+            restored.as_array().map(Vec::len),
+            Some(1),
+            "The {details} of the person should be restored"
+        );
+    }
+}
+
+#[tokio::test]
+async fn people_scheduled_for_deletion_cannot_be_archived() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default()
+        .with_delete_at(datetime!(2100-01-01 0:00 UTC))
+        .insert(&app.pool)
+        .await;
+
+    let response = app
+        .client()
+        .post(&format!("/api/v1/person/{}/archive", person.uuid))
+        .as_user(&["write"])
+        .await;
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn inactive_people_are_archived_in_the_background() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let inactive = PersonFactory::default().insert(&app.pool).await;
+    let active = PersonFactory::default().insert(&app.pool).await;
+
+    sqlx::query("UPDATE person SET last_edited = now() - interval '3 years' WHERE uuid = $1")
+        .bind(inactive.uuid)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    jobs::enqueue(
+        &app.pool,
+        &Job::ArchiveInactivePeople { inactive_days: 730 },
+    )
+    .await
+    .unwrap();
+    assert!(jobs::run_next(&app.pool).await.unwrap(), "Job should run");
+
+    let response = client
+        .get(&format!("/api/v1/person/archive/{}", inactive.uuid))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .get(&format!("/api/v1/person/{}", active.uuid))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(
+        response.status(),
+        StatusCode::OK,
+        "Active people should be kept"
+    );
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header::AUTHORIZATION, Request, StatusCode},
+    response::Response,
+};
+use common::{
+    auth::{mint_with_key_id, token, TestClaims},
+    json_body, TestApp,
+};
+use serde_json::{json, Value};
+
+async fn request_as(app: &TestApp, method: &str, uri: &str, token: &str, body: Value) -> Response {
+    app.request(
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn read_scope_allows_listing_people() {
+    let app = TestApp::new().await;
+
+    let response = request_as(
+        &app,
+        "GET",
+        "/api/v1/person",
+        &token(&["read"]),
+        json!(null),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn write_requires_the_write_scope() {
+    let app = TestApp::new().await;
+    let person = json!({"first_name": "John", "family_name": "Doe", "date_of_birth": "1990-01-01"});
+
+    let response = request_as(&app, "POST", "/api/v1/person", &token(&["read"]), person).await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let body: Value = json_body(response).await;
+    assert_eq!(body["message"], "Client requires the scope: write");
+}
+
+#[tokio::test]
+async fn expired_tokens_are_rejected() {
+    let app = TestApp::new().await;
+    let expired = TestClaims::with_scopes(&["read"]).expired().mint();
+
+    let response = request_as(&app, "GET", "/api/v1/person", &expired, json!(null)).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let body: Value = json_body(response).await;
+    assert_eq!(body["message"], "Token expired");
+}
+
+#[tokio::test]
+async fn tokens_signed_with_an_unknown_key_are_rejected() {
+    let app = TestApp::new().await;
+    let unknown = mint_with_key_id(&TestClaims::with_scopes(&["read"]), "unknown-key");
+
+    let response = request_as(&app, "GET", "/api/v1/person", &unknown, json!(null)).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let body: Value = json_body(response).await;
+    assert_eq!(body["message"], "Invalid token");
+}
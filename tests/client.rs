@@ -0,0 +1,77 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{auth::token, TestApp};
+use rust_web_app::client::{ClientError, PersonClient};
+use serde_json::json;
+
+#[tokio::test]
+async fn people_make_the_round_trip_through_the_client() {
+    let app = TestApp::new().await;
+    let client = PersonClient::new(app.listen().await).with_bearer_token(token(&["read", "write"]));
+
+    let created = client
+        .create(
+            &serde_json::from_value(json!({
+                "firstName": "Ada",
+                "familyName": "Byron",
+                "dateOfBirth": "1815-12-10",
+            }))
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(created.version, 1);
+
+    let listed = client.list().await.unwrap();
+    assert_eq!(
+        listed.iter().map(|person| person.id).collect::<Vec<_>>(),
+        [created.id]
+    );
+
+    let found = client.get(created.id).await.unwrap();
+    assert_eq!(found.family_name, "Byron");
+
+    let changes = serde_json::from_value(json!({ "familyName": "Lovelace" })).unwrap();
+    let updated = client
+        .update(created.id, created.version, &changes)
+        .await
+        .unwrap();
+    assert_eq!(updated.family_name, "Lovelace");
+    assert_eq!(updated.version, 2);
+
+    // the version sent in If-Match is checked, so a stale one is refused
+    match client.update(created.id, created.version, &changes).await {
+        Err(ClientError::Api { status, .. }) => {
+            assert_eq!(status, reqwest::StatusCode::PRECONDITION_FAILED)
+        }
+        result => panic!("Expected the stale update to be refused, got: {result:?}"),
+    }
+
+    client.delete(created.id, updated.version).await.unwrap();
+
+    match client.get(created.id).await {
+        Err(ClientError::Api { status, .. }) => {
+            assert_eq!(status, reqwest::StatusCode::NOT_FOUND)
+        }
+        result => panic!("Expected the person to be gone, got: {result:?}"),
+    }
+    assert!(client.list().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn client_surfaces_api_errors() {
+    let app = TestApp::new().await;
+    let client = PersonClient::new(app.listen().await);
+
+    let error = client.list().await.unwrap_err();
+
+    match error {
+        ClientError::Api { status, message } => {
+            assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+            assert_eq!(message, "Missing token");
+        }
+        e => panic!("Expected an API error, got: {e}"),
+    }
+}
@@ -0,0 +1,129 @@
+//! Test doubles for the OAuth provider.
+//!
+//! An RSA keypair is generated once per test binary and its public half served as a JWKS by a
+//! wiremock server, which `AUTH_URL` is pointed at. Tokens signed with the private half can
+//! then be minted with arbitrary scopes to exercise the auth extractors end to end.
+
+use std::{
+    env,
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use rsa::{pkcs1::EncodeRsaPrivateKey, pkcs8::LineEnding, traits::PublicKeyParts, RsaPrivateKey};
+use serde::Serialize;
+use serde_json::json;
+use tokio::sync::OnceCell;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+pub const KEY_ID: &str = "test-key";
+pub const CLIENT: &str = "test-client";
+
+static KEY: OnceLock<RsaPrivateKey> = OnceLock::new();
+static JWKS_SERVER: OnceCell<MockServer> = OnceCell::const_new();
+
+fn key() -> &'static RsaPrivateKey {
+    KEY.get_or_init(|| {
+        RsaPrivateKey::new(&mut rand::thread_rng(), 2048).expect("Failed to generate RSA key")
+    })
+}
+
+/// Starts the mock JWKS endpoint, if not already running, and points `AUTH_URL` at it
+pub async fn mock_jwks() -> &'static MockServer {
+    JWKS_SERVER
+        .get_or_init(|| async {
+            let server = MockServer::start().await;
+            let public_key = key().to_public_key();
+
+            Mock::given(method("GET"))
+                .and(path("/auth/.well-known/jwks.json"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "keys": [{
+                        "kty": "RSA",
+                        "use": "sig",
+                        "alg": "RS256",
+                        "kid": KEY_ID,
+                        "n": URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be()),
+                        "e": URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be()),
+                    }]
+                })))
+                .mount(&server)
+                .await;
+
+            env::set_var("AUTH_URL", format!("{}/auth", server.uri()));
+
+            server
+        })
+        .await
+}
+
+#[derive(Serialize)]
+pub struct TestClaims {
+    pub iss: String,
+    pub sub: String,
+    pub exp: u64,
+    pub scope: Vec<String>,
+    pub authorities: Vec<String>,
+}
+
+impl TestClaims {
+    /// Claims for the default test client, valid for an hour
+    pub fn with_scopes(scopes: &[&str]) -> Self {
+        TestClaims {
+            iss: "http://localhost/auth/issuer".to_owned(),
+            sub: CLIENT.to_owned(),
+            exp: now() + 3600,
+            scope: scopes.iter().map(|s| s.to_string()).collect(),
+            authorities: vec![],
+        }
+    }
+
+    pub fn expired(mut self) -> Self {
+        self.exp = now() - 3600;
+        self
+    }
+
+    pub fn for_client(mut self, client: &str) -> Self {
+        self.sub = client.to_owned();
+        self
+    }
+
+    /// Signs the claims with the key served by the mock JWKS
+    pub fn mint(&self) -> String {
+        mint_with_key_id(self, KEY_ID)
+    }
+}
+
+/// Signs the claims with the test key, advertising the given key ID in the header
+pub fn mint_with_key_id(claims: &TestClaims, key_id: &str) -> String {
+    let pem = key()
+        .to_pkcs1_pem(LineEnding::LF)
+        .expect("Failed to encode RSA key");
+
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(key_id.to_owned());
+
+    encode(
+        &header,
+        claims,
+        &EncodingKey::from_rsa_pem(pem.as_bytes()).unwrap(),
+    )
+    .expect("Failed to mint token")
+}
+
+/// A valid token for the default test client holding the given scopes
+pub fn token(scopes: &[&str]) -> String {
+    TestClaims::with_scopes(scopes).mint()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
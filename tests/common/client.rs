@@ -0,0 +1,166 @@
+//! A request builder for the app under test, cutting out the boilerplate of building
+//! `http::Request`s and reading bodies in every test.
+//!
+//! ```ignore
+//! let response = app.client().post("/api/v1/person").as_user(&["write"]).json(&body).await;
+//! assert_eq!(response.status(), StatusCode::CREATED);
+//! let person: Value = response.json();
+//! ```
+
+use std::{
+    future::{Future, IntoFuture},
+    pin::Pin,
+};
+
+use axum::{
+    body::{Body, Bytes},
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        request, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode,
+    },
+};
+use http_body_util::BodyExt;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{auth::token, TestApp};
+
+/// Starts requests against a [`TestApp`]
+#[derive(Clone, Copy)]
+pub struct TestClient<'a> {
+    app: &'a TestApp,
+}
+
+impl<'a> TestClient<'a> {
+    pub fn new(app: &'a TestApp) -> Self {
+        TestClient { app }
+    }
+
+    pub fn request(&self, method: Method, uri: &str) -> TestRequest<'a> {
+        TestRequest {
+            app: self.app,
+            builder: Request::builder().method(method).uri(uri),
+            body: Body::empty(),
+        }
+    }
+
+    pub fn get(&self, uri: &str) -> TestRequest<'a> {
+        self.request(Method::GET, uri)
+    }
+
+    pub fn post(&self, uri: &str) -> TestRequest<'a> {
+        self.request(Method::POST, uri)
+    }
+
+    pub fn put(&self, uri: &str) -> TestRequest<'a> {
+        self.request(Method::PUT, uri)
+    }
+
+    pub fn patch(&self, uri: &str) -> TestRequest<'a> {
+        self.request(Method::PATCH, uri)
+    }
+
+    pub fn delete(&self, uri: &str) -> TestRequest<'a> {
+        self.request(Method::DELETE, uri)
+    }
+}
+
+/// A request being built, sent by awaiting it
+pub struct TestRequest<'a> {
+    app: &'a TestApp,
+    builder: request::Builder,
+    body: Body,
+}
+
+impl TestRequest<'_> {
+    /// Authenticates with a token granting the given scopes
+    #[allow(clippy::wrong_self_convention)]
+    pub fn as_user(self, scopes: &[&str]) -> Self {
+        self.header(AUTHORIZATION, format!("Bearer {}", token(scopes)))
+    }
+
+    pub fn header<V>(mut self, name: HeaderName, value: V) -> Self
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: std::fmt::Debug,
+    {
+        self.builder = self
+            .builder
+            .header(name, value.try_into().expect("Invalid header value"));
+        self
+    }
+
+    /// Sends the value as a JSON body
+    pub fn json<T: Serialize + ?Sized>(self, value: &T) -> Self {
+        let body = serde_json::to_vec(value).expect("Failed to serialize the request body");
+        self.header(CONTENT_TYPE, "application/json").body(body)
+    }
+
+    pub fn body(mut self, body: impl Into<Body>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub async fn send(self) -> TestResponse {
+        let request = self.builder.body(self.body).unwrap();
+        let response = self.app.request(request).await;
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        TestResponse {
+            status,
+            headers,
+            body,
+        }
+    }
+}
+
+impl<'a> IntoFuture for TestRequest<'a> {
+    type Output = TestResponse;
+    type IntoFuture = Pin<Box<dyn Future<Output = TestResponse> + Send + 'a>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.send())
+    }
+}
+
+/// A response, with its body already read
+#[derive(Debug)]
+pub struct TestResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl TestResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The value of a header, which must be present
+    pub fn header(&self, name: HeaderName) -> &str {
+        self.headers
+            .get(&name)
+            .unwrap_or_else(|| panic!("Response should have a {name} header"))
+            .to_str()
+            .unwrap()
+    }
+
+    /// Deserializes the body as JSON
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        serde_json::from_slice(&self.body).expect("Response body should be valid JSON")
+    }
+
+    pub fn text(&self) -> &str {
+        std::str::from_utf8(&self.body).expect("Response body should be UTF-8")
+    }
+
+    pub fn bytes(&self) -> &Bytes {
+        &self.body
+    }
+}
@@ -0,0 +1,326 @@
+//! Builders inserting rows straight into the database for test setup, e.g.
+//!
+//! ```ignore
+//! let person = PersonFactory::default()
+//!     .with_family_name("Smith")
+//!     .with_address(AddressFactory::default())
+//!     .insert(&app.pool)
+//!     .await;
+//! ```
+
+use sqlx::PgPool;
+use time::{macros::date, Date, OffsetDateTime};
+use uuid::Uuid;
+
+#[derive(Clone, Debug)]
+pub struct AddressFixture {
+    pub uuid: Uuid,
+    pub building: String,
+    pub street: Option<String>,
+    pub town_or_city: Option<String>,
+    pub postcode: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct AddressFactory {
+    building: String,
+    street: Option<String>,
+    town_or_city: Option<String>,
+    postcode: String,
+}
+
+impl Default for AddressFactory {
+    fn default() -> Self {
+        AddressFactory {
+            building: "10".to_owned(),
+            street: Some("Downing Street".to_owned()),
+            town_or_city: Some("London".to_owned()),
+            postcode: "SW1A 2AA".to_owned(),
+        }
+    }
+}
+
+impl AddressFactory {
+    pub fn with_building(mut self, building: &str) -> Self {
+        self.building = building.to_owned();
+        self
+    }
+
+    pub fn with_street(mut self, street: Option<&str>) -> Self {
+        self.street = street.map(str::to_owned);
+        self
+    }
+
+    pub fn with_town_or_city(mut self, town_or_city: Option<&str>) -> Self {
+        self.town_or_city = town_or_city.map(str::to_owned);
+        self
+    }
+
+    pub fn with_postcode(mut self, postcode: &str) -> Self {
+        self.postcode = postcode.to_owned();
+        self
+    }
+
+    pub async fn insert(self, pool: &PgPool) -> AddressFixture {
+        let uuid = sqlx::query_scalar(
+            r#"
+                INSERT INTO address (building, street, town_or_city, postcode)
+                VALUES ($1, $2, $3, $4)
+                RETURNING uuid;
+            "#,
+        )
+        .bind(&self.building)
+        .bind(&self.street)
+        .bind(&self.town_or_city)
+        .bind(&self.postcode)
+        .fetch_one(pool)
+        .await
+        .expect("Failed to insert address fixture");
+
+        AddressFixture {
+            uuid,
+            building: self.building,
+            street: self.street,
+            town_or_city: self.town_or_city,
+            postcode: self.postcode,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PersonFixture {
+    pub uuid: Uuid,
+    pub first_name: String,
+    pub family_name: String,
+    pub date_of_birth: Date,
+    pub address: Option<AddressFixture>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PersonFactory {
+    first_name: String,
+    family_name: String,
+    date_of_birth: Date,
+    address: Option<AddressFactory>,
+    delete_at: Option<OffsetDateTime>,
+}
+
+impl Default for PersonFactory {
+    fn default() -> Self {
+        PersonFactory {
+            first_name: "John".to_owned(),
+            family_name: "Doe".to_owned(),
+            date_of_birth: date!(1990 - 01 - 01),
+            address: None,
+            delete_at: None,
+        }
+    }
+}
+
+impl PersonFactory {
+    pub fn with_first_name(mut self, first_name: &str) -> Self {
+        self.first_name = first_name.to_owned();
+        self
+    }
+
+    pub fn with_family_name(mut self, family_name: &str) -> Self {
+        self.family_name = family_name.to_owned();
+        self
+    }
+
+    pub fn with_date_of_birth(mut self, date_of_birth: Date) -> Self {
+        self.date_of_birth = date_of_birth;
+        self
+    }
+
+    /// Inserts the address alongside the person and links the two
+    pub fn with_address(mut self, address: AddressFactory) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Schedules the person to be deleted, at a time which may already have passed
+    pub fn with_delete_at(mut self, delete_at: OffsetDateTime) -> Self {
+        self.delete_at = Some(delete_at);
+        self
+    }
+
+    pub async fn insert(self, pool: &PgPool) -> PersonFixture {
+        let address = match self.address {
+            Some(factory) => Some(factory.insert(pool).await),
+            None => None,
+        };
+
+        let uuid = sqlx::query_scalar(
+            r#"
+                INSERT INTO person (first_name, family_name, date_of_birth, address, delete_at)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING uuid;
+            "#,
+        )
+        .bind(&self.first_name)
+        .bind(&self.family_name)
+        .bind(self.date_of_birth)
+        .bind(address.as_ref().map(|a| a.uuid))
+        .bind(self.delete_at)
+        .fetch_one(pool)
+        .await
+        .expect("Failed to insert person fixture");
+
+        PersonFixture {
+            uuid,
+            first_name: self.first_name,
+            family_name: self.family_name,
+            date_of_birth: self.date_of_birth,
+            address,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ApiClientFixture {
+    pub uuid: Uuid,
+    pub name: String,
+}
+
+/// An API client with a random name and an unusable key
+#[derive(Clone, Debug)]
+pub struct ApiClientFactory {
+    name: String,
+    scopes: Vec<String>,
+}
+
+impl Default for ApiClientFactory {
+    fn default() -> Self {
+        ApiClientFactory {
+            name: format!("client-{}", Uuid::new_v4()),
+            scopes: vec!["read".to_owned()],
+        }
+    }
+}
+
+impl ApiClientFactory {
+    pub async fn insert(self, pool: &PgPool) -> ApiClientFixture {
+        let uuid = sqlx::query_scalar(
+            r#"
+                INSERT INTO api_client (name, scopes, key_prefix, key_hash)
+                VALUES ($1, $2, 'rwa_fixture', $3)
+                RETURNING uuid;
+            "#,
+        )
+        .bind(&self.name)
+        .bind(&self.scopes)
+        .bind(Uuid::new_v4().as_bytes().to_vec())
+        .fetch_one(pool)
+        .await
+        .expect("Failed to insert API client fixture");
+
+        ApiClientFixture {
+            uuid,
+            name: self.name,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EmploymentFixture {
+    pub uuid: Uuid,
+    pub person: Uuid,
+}
+
+/// Ongoing employment, starting in 2015
+#[derive(Clone, Debug)]
+pub struct EmploymentFactory {
+    employer: String,
+    role: String,
+    start_date: Date,
+    end_date: Option<Date>,
+}
+
+impl Default for EmploymentFactory {
+    fn default() -> Self {
+        EmploymentFactory {
+            employer: "Acme".to_owned(),
+            role: "Engineer".to_owned(),
+            start_date: date!(2015 - 01 - 01),
+            end_date: None,
+        }
+    }
+}
+
+impl EmploymentFactory {
+    pub fn with_dates(mut self, start_date: Date, end_date: Option<Date>) -> Self {
+        self.start_date = start_date;
+        self.end_date = end_date;
+        self
+    }
+
+    pub async fn insert(self, pool: &PgPool, person: Uuid) -> EmploymentFixture {
+        let uuid = sqlx::query_scalar(
+            r#"
+                INSERT INTO person_employment (person, employer, role, start_date, end_date)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING uuid;
+            "#,
+        )
+        .bind(person)
+        .bind(&self.employer)
+        .bind(&self.role)
+        .bind(self.start_date)
+        .bind(self.end_date)
+        .fetch_one(pool)
+        .await
+        .expect("Failed to insert employment fixture");
+
+        EmploymentFixture { uuid, person }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EmergencyContactFixture {
+    pub uuid: Uuid,
+    pub person: Uuid,
+}
+
+#[derive(Clone, Debug)]
+pub struct EmergencyContactFactory {
+    name: String,
+    relationship: String,
+    phone: String,
+}
+
+impl Default for EmergencyContactFactory {
+    fn default() -> Self {
+        EmergencyContactFactory {
+            name: "Jane Doe".to_owned(),
+            relationship: "Sister".to_owned(),
+            phone: "+442079460000".to_owned(),
+        }
+    }
+}
+
+impl EmergencyContactFactory {
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+
+    pub async fn insert(self, pool: &PgPool, person: Uuid) -> EmergencyContactFixture {
+        let uuid = sqlx::query_scalar(
+            r#"
+                INSERT INTO person_emergency_contact (person, name, relationship, phone)
+                VALUES ($1, $2, $3, $4)
+                RETURNING uuid;
+            "#,
+        )
+        .bind(person)
+        .bind(&self.name)
+        .bind(&self.relationship)
+        .bind(&self.phone)
+        .fetch_one(pool)
+        .await
+        .expect("Failed to insert emergency contact fixture");
+
+        EmergencyContactFixture { uuid, person }
+    }
+}
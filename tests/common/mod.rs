@@ -0,0 +1,203 @@
+//! Shared harness for the integration tests.
+//!
+//! A disposable Postgres is started once per test binary using testcontainers. Set
+//! `TEST_DATABASE_URL` to run against an existing database instead, e.g. when Docker isn't
+//! available. Every `TestApp` is given its own freshly migrated schema, dropped again when the
+//! app is, so tests can run in parallel against the one database without seeing each other's
+//! rows.
+#![allow(dead_code)]
+
+pub mod auth;
+pub mod client;
+pub mod factories;
+pub mod search;
+
+use std::{env, sync::Arc, thread};
+
+use axum::{body::Body, http::Request, response::Response, Router};
+use client::TestClient;
+use http_body_util::BodyExt;
+use rust_web_app::{
+    clock::{SharedClock, SystemClock},
+    outbound,
+    scheduler::Scheduler,
+    search::SearchIndex,
+};
+use serde::de::DeserializeOwned;
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    Connection, PgConnection, PgPool,
+};
+use testcontainers_modules::{
+    postgres::Postgres,
+    testcontainers::{runners::AsyncRunner, ContainerAsync},
+};
+use tokio::{net::TcpListener, sync::OnceCell};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+struct Database {
+    url: String,
+    _container: Option<ContainerAsync<Postgres>>,
+}
+
+static DATABASE: OnceCell<Database> = OnceCell::const_new();
+
+async fn database() -> &'static Database {
+    DATABASE
+        .get_or_init(|| async {
+            if let Ok(url) = env::var("TEST_DATABASE_URL") {
+                return Database {
+                    url,
+                    _container: None,
+                };
+            }
+
+            let container = Postgres::default()
+                .start()
+                .await
+                .expect("Failed to start Postgres container, is Docker running?");
+
+            let url = format!(
+                "postgres://postgres:postgres@{}:{}/postgres",
+                container.get_host().await.unwrap(),
+                container.get_host_port_ipv4(5432).await.unwrap()
+            );
+
+            Database {
+                url,
+                _container: Some(container),
+            }
+        })
+        .await
+}
+
+pub struct TestApp {
+    pub router: Router,
+    pub pool: PgPool,
+    pub schema: String,
+    database_url: String,
+}
+
+impl TestApp {
+    /// Builds the application against its own freshly migrated schema
+    pub async fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock)).await
+    }
+
+    /// Builds the application telling the time by the given clock
+    pub async fn with_clock(clock: SharedClock) -> Self {
+        let database = database().await;
+        auth::mock_jwks().await;
+
+        let schema = format!("test_{}", Uuid::new_v4().simple());
+
+        let mut connection = PgConnection::connect(&database.url)
+            .await
+            .expect("Failed to connect to the test database");
+
+        sqlx::query(&format!("CREATE SCHEMA {schema}"))
+            .execute(&mut connection)
+            .await
+            .expect("Failed to create the test schema");
+
+        let connect_options = database
+            .url
+            .parse::<PgConnectOptions>()
+            .unwrap()
+            .options([("search_path", schema.as_str())])
+            .application_name(&schema);
+
+        let pool = PgPoolOptions::new()
+            .connect_with(connect_options)
+            .await
+            .expect("Failed to connect to the test database");
+
+        sqlx::migrate!("db/migrations")
+            .run(&pool)
+            .await
+            .expect("Failed to migrate the test database");
+
+        let client = outbound::from_env().expect("Failed to build the HTTP client");
+
+        TestApp {
+            router: rust_web_app::app(
+                pool.clone(),
+                client.clone(),
+                clock,
+                Scheduler::from_env().expect("Invalid schedule in the environment"),
+                SearchIndex::from_env(client),
+            ),
+            pool,
+            schema,
+            database_url: database.url.clone(),
+        }
+    }
+
+    /// Builds requests against the application, see [`TestClient`]
+    pub fn client(&self) -> TestClient<'_> {
+        TestClient::new(self)
+    }
+
+    /// Issues a request against the application without binding to a port
+    pub async fn request(&self, request: Request<Body>) -> Response {
+        self.router.clone().oneshot(request).await.unwrap()
+    }
+
+    pub async fn get(&self, uri: &str) -> Response {
+        self.request(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+    }
+
+    /// Serves the application on a random local port, returning its base URL
+    pub async fn listen(&self) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = self.router.clone();
+
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        format!("http://{addr}")
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let url = self.database_url.clone();
+        let schema = self.schema.clone();
+
+        // the test's runtime may already be shutting down, so the schema is dropped on a
+        // runtime of its own
+        let cleanup = thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async {
+                    if let Ok(mut connection) = PgConnection::connect(&url).await {
+                        // a transaction abandoned on an error path is only rolled back once its
+                        // connection is next used, which never happens with the test runtime
+                        // blocked here, so the app's connections are closed before dropping
+                        let _ = sqlx::query(
+                            "SELECT pg_terminate_backend(pid) FROM pg_stat_activity \
+                             WHERE application_name = $1",
+                        )
+                        .bind(&schema)
+                        .execute(&mut connection)
+                        .await;
+                        let _ = sqlx::query(&format!("DROP SCHEMA IF EXISTS {schema} CASCADE"))
+                            .execute(&mut connection)
+                            .await;
+                    }
+                })
+        });
+
+        cleanup.join().ok();
+    }
+}
+
+/// Reads the whole response body and deserializes it as JSON
+pub async fn json_body<T: DeserializeOwned>(response: Response) -> T {
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&bytes).expect("Response body should be valid JSON")
+}
@@ -0,0 +1,63 @@
+//! Test double for the search index.
+//!
+//! A wiremock server stands in for Elasticsearch, with `ELASTICSEARCH_URL` pointed at it. By
+//! default every document write succeeds and searches find nothing; tests wanting particular
+//! results mount their own search mock, which takes precedence over the default.
+
+use std::env;
+
+use serde_json::{json, Value};
+use tokio::sync::OnceCell;
+use wiremock::{
+    matchers::{body_partial_json, method, path, path_regex},
+    Mock, MockServer, ResponseTemplate,
+};
+
+static SEARCH_SERVER: OnceCell<MockServer> = OnceCell::const_new();
+
+/// Starts the mock search index, if not already running, and points `ELASTICSEARCH_URL` at it
+pub async fn mock_elasticsearch() -> &'static MockServer {
+    SEARCH_SERVER
+        .get_or_init(|| async {
+            let server = MockServer::start().await;
+
+            Mock::given(path_regex("^/people/_doc/[^/]+$"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "result": "ok" })))
+                .with_priority(10)
+                .mount(&server)
+                .await;
+
+            Mock::given(method("POST"))
+                .and(path("/people/_search"))
+                .respond_with(search_hits(vec![]))
+                .with_priority(10)
+                .mount(&server)
+                .await;
+
+            env::set_var("ELASTICSEARCH_URL", server.uri());
+
+            server
+        })
+        .await
+}
+
+/// Makes searches for `query` return the given documents, in order
+pub async fn mock_search(query: &str, documents: Vec<Value>) {
+    Mock::given(method("POST"))
+        .and(path("/people/_search"))
+        .and(body_partial_json(
+            json!({ "query": { "multi_match": { "query": query } } }),
+        ))
+        .respond_with(search_hits(documents))
+        .mount(mock_elasticsearch().await)
+        .await;
+}
+
+fn search_hits(documents: Vec<Value>) -> ResponseTemplate {
+    let hits: Vec<_> = documents
+        .into_iter()
+        .map(|document| json!({ "_source": document }))
+        .collect();
+
+    ResponseTemplate::new(200).set_body_json(json!({ "hits": { "hits": hits } }))
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use axum::http::{header::LOCATION, StatusCode};
+use common::{factories::PersonFactory, TestApp};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn the_latest_consent_for_each_purpose_is_current() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let consents = format!("/api/v1/person/{}/consents", person.uuid);
+
+    for (purpose, granted, channel) in [
+        ("email", true, "web"),
+        ("marketing", true, "paper"),
+        ("email", false, "phone"),
+    ] {
+        let response = client
+            .post(&consents)
+            .as_user(&["write"])
+            .json(&json!({ "purpose": purpose, "granted": granted, "channel": channel }))
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    let current: Value = client.get(&consents).as_user(&["read"]).await.json();
+    let current: Vec<_> = current
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| {
+            (
+                c["purpose"].clone(),
+                c["granted"].clone(),
+                c["channel"].clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        current,
+        vec![
+            (json!("email"), json!(false), json!("phone")),
+            (json!("marketing"), json!(true), json!("paper")),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn every_consent_is_kept_in_the_history() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let consents = format!("/api/v1/person/{}/consents", person.uuid);
+
+    let mut location = String::new();
+    for (purpose, granted) in [("sms", true), ("post", true), ("sms", false)] {
+        let response = client
+            .post(&consents)
+            .as_user(&["write"])
+            .json(&json!({ "purpose": purpose, "granted": granted, "channel": "verbal" }))
+            .await;
+        location = response.header(LOCATION).to_owned();
+    }
+    assert_eq!(
+        location,
+        format!(
+            "/api/v1/person/{}/consents/history?purpose=sms",
+            person.uuid
+        )
+    );
+
+    let sms: Value = client.get(&location).as_user(&["read"]).await.json();
+    let granted: Vec<_> = sms
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| &c["granted"])
+        .collect();
+    assert_eq!(granted, [&json!(false), &json!(true)]);
+
+    let all: Value = client
+        .get(&format!("{consents}/history"))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(all.as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn unknown_purposes_are_rejected() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    let response = app
+        .client()
+        .post(&format!("/api/v1/person/{}/consents", person.uuid))
+        .as_user(&["write"])
+        .json(&json!({ "purpose": "anything", "granted": true, "channel": "web" }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn consent_cannot_be_recorded_for_missing_people() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .client()
+        .post(&format!("/api/v1/person/{}/consents", Uuid::new_v4()))
+        .as_user(&["write"])
+        .json(&json!({ "purpose": "email", "granted": true, "channel": "web" }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use axum::http::{header::LOCATION, StatusCode};
+use common::{factories::PersonFactory, TestApp};
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn email_addresses_and_phone_numbers_can_be_added_and_removed() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let contacts = format!("/api/v1/person/{}/contacts", person.uuid);
+
+    let response = client
+        .post(&contacts)
+        .as_user(&["write"])
+        .json(&json!({"type": "email", "value": " ada@example.com ", "label": " Work "}))
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let location = response.header(LOCATION).to_owned();
+    let email: Value = response.json();
+    assert_eq!(email["type"], "email");
+    assert_eq!(email["value"], "ada@example.com");
+    assert_eq!(email["label"], "Work");
+    assert_eq!(email["personId"], person.uuid.to_string());
+
+    let response = client
+        .post(&contacts)
+        .as_user(&["write"])
+        .json(&json!({"type": "phone", "value": "+44 20 7946-0000"}))
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let phone: Value = response.json();
+    assert_eq!(phone["value"], "+442079460000");
+    assert_eq!(phone["label"], Value::Null);
+
+    let listed: Value = client.get(&contacts).as_user(&["read"]).await.json();
+    assert_eq!(listed, json!([email, phone]), "First added first");
+
+    let fetched: Value = client.get(&location).as_user(&["read"]).await.json();
+    assert_eq!(fetched, email);
+
+    let response = client.delete(&location).as_user(&["write"]).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client.get(&location).as_user(&["read"]).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let listed: Value = client.get(&contacts).as_user(&["read"]).await.json();
+    assert_eq!(listed, json!([phone]));
+}
+
+#[tokio::test]
+async fn invalid_contact_details_are_rejected() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let contacts = format!("/api/v1/person/{}/contacts", person.uuid);
+
+    for (contact_type, value) in [
+        ("email", "ada.example.com"),
+        ("phone", "020 7946 0000"),
+        ("phone", "ada@example.com"),
+    ] {
+        let response = client
+            .post(&contacts)
+            .as_user(&["write"])
+            .json(&json!({"type": contact_type, "value": value}))
+            .await;
+
+        assert_eq!(
+            response.status(),
+            StatusCode::BAD_REQUEST,
+            "{contact_type} {value}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn the_same_contact_detail_cant_be_added_twice() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let contacts = format!("/api/v1/person/{}/contacts", person.uuid);
+
+    let response = client
+        .post(&contacts)
+        .as_user(&["write"])
+        .json(&json!({"type": "email", "value": "ada@example.com"}))
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = client
+        .post(&contacts)
+        .as_user(&["write"])
+        .json(&json!({"type": "email", "value": "Ada@Example.com"}))
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
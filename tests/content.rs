@@ -0,0 +1,374 @@
+mod common;
+
+use std::io::Write;
+
+use axum::{
+    body::Body,
+    http::{
+        header::{ACCEPT, AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE, IF_MATCH},
+        Request, StatusCode,
+    },
+    response::Response,
+};
+use common::{auth::token, factories::PersonFactory, json_body, TestApp};
+use flate2::{write::GzEncoder, Compression};
+use http_body_util::BodyExt;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+async fn msgpack_body(response: Response) -> Value {
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    rmp_serde::from_slice(&bytes).expect("Response body should be valid MessagePack")
+}
+
+fn msgpack(value: &impl Serialize) -> Vec<u8> {
+    let mut body = vec![];
+    value
+        .serialize(&mut rmp_serde::Serializer::new(&mut body).with_struct_map())
+        .unwrap();
+    body
+}
+
+#[tokio::test]
+async fn people_can_be_created_and_read_as_msgpack() {
+    let app = TestApp::new().await;
+
+    let new_person = json!({
+        "first_name": "Ada",
+        "family_name": "Lovelace",
+        "date_of_birth": "1815-12-10",
+    });
+
+    let response = app
+        .request(
+            Request::post("/api/v1/person")
+                .header(AUTHORIZATION, format!("Bearer {}", token(&["write"])))
+                .header(CONTENT_TYPE, "application/msgpack")
+                .header(ACCEPT, "application/msgpack")
+                .body(Body::from(msgpack(&new_person)))
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/msgpack");
+
+    let created = msgpack_body(response).await;
+    assert_eq!(created["firstName"], "Ada");
+    assert_eq!(created["dateOfBirth"], "1815-12-10");
+
+    let response = app
+        .request(
+            Request::get(format!(
+                "/api/v1/person/{}",
+                created["id"].as_str().unwrap()
+            ))
+            .header(AUTHORIZATION, format!("Bearer {}", token(&["read"])))
+            .header(ACCEPT, "application/msgpack")
+            .body(Body::empty())
+            .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(msgpack_body(response).await, created);
+}
+
+#[tokio::test]
+async fn json_is_returned_by_default() {
+    let app = TestApp::new().await;
+    PersonFactory::default().insert(&app.pool).await;
+
+    let response = app
+        .request(
+            Request::get("/api/v1/person")
+                .header(AUTHORIZATION, format!("Bearer {}", token(&["read"])))
+                .header(ACCEPT, "text/html, */*;q=0.8")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+
+    let body: Value = json_body(response).await;
+    assert_eq!(body.as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn malformed_msgpack_is_rejected() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    let response = app
+        .request(
+            Request::put(format!("/api/v1/person/{}", person.uuid))
+                .header(AUTHORIZATION, format!("Bearer {}", token(&["write"])))
+                .header(IF_MATCH, "*")
+                .header(CONTENT_TYPE, "application/msgpack")
+                .header(ACCEPT, "application/msgpack")
+                .body(Body::from(vec![0xc1]))
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+
+    let body: Value = json_body(response).await;
+    assert!(body["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Failed to parse the request body as MessagePack"));
+}
+
+#[tokio::test]
+async fn people_can_be_listed_as_xml() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default()
+        .with_first_name("Ada")
+        .insert(&app.pool)
+        .await;
+
+    let response = app
+        .request(
+            Request::get("/api/v1/person")
+                .header(AUTHORIZATION, format!("Bearer {}", token(&["read"])))
+                .header(ACCEPT, "application/xml")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/xml");
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8(bytes.to_vec()).unwrap();
+
+    assert!(
+        body.starts_with("<people><person>"),
+        "Unexpected XML: {body}"
+    );
+    assert!(body.contains(&format!("<id>{}</id>", person.uuid)));
+    assert!(body.contains("<firstName>Ada</firstName>"));
+}
+
+#[tokio::test]
+async fn xml_request_bodies_are_rejected() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .request(
+            Request::post("/api/v1/person")
+                .header(AUTHORIZATION, format!("Bearer {}", token(&["write"])))
+                .header(CONTENT_TYPE, "application/xml")
+                .body(Body::from("<person/>"))
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn people_carry_links_when_hateoas_is_requested() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let person_uri = format!("/api/v1/person/{}", person.uuid);
+
+    let response = app
+        .request(
+            Request::get(format!("{person_uri}?hateoas=true"))
+                .header(AUTHORIZATION, format!("Bearer {}", token(&["read"])))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/hal+json");
+
+    let body: Value = json_body(response).await;
+    assert_eq!(body["id"], person.uuid.to_string());
+    assert_eq!(body["_links"]["self"]["href"], person_uri);
+    assert_eq!(body["_links"]["delete"]["href"], person_uri);
+    assert_eq!(
+        body["_links"]["address"]["href"],
+        format!("{person_uri}/address")
+    );
+
+    let response = app
+        .request(
+            Request::get("/api/v1/person")
+                .header(AUTHORIZATION, format!("Bearer {}", token(&["read"])))
+                .header(ACCEPT, "application/hal+json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+    let body: Value = json_body(response).await;
+    assert_eq!(body["_links"]["self"]["href"], "/api/v1/person");
+    assert_eq!(
+        body["_embedded"]["people"][0]["_links"]["self"]["href"],
+        person_uri
+    );
+}
+
+async fn json_api_post(app: &TestApp, uri: &str, document: Value) -> Response {
+    app.request(
+        Request::post(uri)
+            .header(AUTHORIZATION, format!("Bearer {}", token(&["write"])))
+            .header(CONTENT_TYPE, "application/vnd.api+json")
+            .header(ACCEPT, "application/vnd.api+json")
+            .body(Body::from(document.to_string()))
+            .unwrap(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn people_and_addresses_can_be_created_with_json_api() {
+    let app = TestApp::new().await;
+
+    let response = json_api_post(
+        &app,
+        "/api/v1/person",
+        json!({
+            "data": {
+                "type": "people",
+                "attributes": {
+                    "first_name": "Ada",
+                    "family_name": "Lovelace",
+                    "date_of_birth": "1815-12-10",
+                }
+            }
+        }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/vnd.api+json");
+
+    let body: Value = json_body(response).await;
+    let id = body["data"]["id"].as_str().unwrap();
+    assert_eq!(body["data"]["type"], "people");
+    assert_eq!(body["data"]["attributes"]["firstName"], "Ada");
+    assert!(body["data"]["attributes"].get("id").is_none());
+    assert_eq!(
+        body["data"]["links"]["self"],
+        format!("/api/v1/person/{id}")
+    );
+    assert_eq!(
+        body["data"]["relationships"]["address"]["links"]["related"],
+        format!("/api/v1/person/{id}/address")
+    );
+
+    let response = json_api_post(
+        &app,
+        &format!("/api/v1/person/{id}/address"),
+        json!({
+            "data": {
+                "type": "addresses",
+                "attributes": { "building": "1", "postcode": "SW1A 1AA" }
+            }
+        }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn errors_are_json_api_error_objects() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    let response = json_api_post(
+        &app,
+        &format!("/api/v1/person/{}/address", person.uuid),
+        json!({
+            "data": {
+                "type": "addresses",
+                "attributes": { "building": "", "postcode": "SW1A 1AA" }
+            }
+        }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/vnd.api+json");
+
+    let body: Value = json_body(response).await;
+    assert_eq!(body["errors"][0]["status"], "400");
+    assert_eq!(body["errors"][0]["code"], "length");
+    assert_eq!(
+        body["errors"][0]["source"]["pointer"],
+        "/data/attributes/building"
+    );
+
+    let response = json_api_post(
+        &app,
+        "/api/v1/person",
+        json!({
+            "data": {
+                "type": "people",
+                "attributes": {
+                    "first_name": "",
+                    "family_name": "Lovelace",
+                    "date_of_birth": "1815-12-10",
+                }
+            }
+        }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body: Value = json_body(response).await;
+    assert_eq!(body["errors"][0]["status"], "400");
+    assert!(body["errors"][0]["detail"]
+        .as_str()
+        .unwrap()
+        .contains("between 1 and 64 characters"));
+}
+
+#[tokio::test]
+async fn gzipped_bodies_are_decompressed() {
+    let app = TestApp::new().await;
+
+    let gzip = |content_encoding: &str| {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder
+            .write_all(
+                json!({
+                    "first_name": "Ada",
+                    "family_name": "Lovelace",
+                    "date_of_birth": "1815-12-10",
+                })
+                .to_string()
+                .as_bytes(),
+            )
+            .unwrap();
+
+        Request::post("/api/v1/person")
+            .header(AUTHORIZATION, format!("Bearer {}", token(&["write"])))
+            .header(CONTENT_TYPE, "application/json")
+            .header(CONTENT_ENCODING, content_encoding)
+            .body(Body::from(encoder.finish().unwrap()))
+            .unwrap()
+    };
+
+    let response = app.request(gzip("gzip")).await;
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let body: Value = json_body(response).await;
+    assert_eq!(body["firstName"], "Ada");
+
+    let response = app.request(gzip("br")).await;
+
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
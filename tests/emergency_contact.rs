@@ -0,0 +1,149 @@
+mod common;
+
+use axum::http::{header::LOCATION, StatusCode};
+use common::{
+    factories::{EmergencyContactFactory, PersonFactory},
+    TestApp,
+};
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn emergency_contacts_can_be_added_changed_and_removed() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let contacts = format!("/api/v1/person/{}/emergency-contacts", person.uuid);
+
+    let response = client
+        .post(&contacts)
+        .as_user(&["write"])
+        .json(&json!({
+            "name": " Jane  Doe ",
+            "relationship": "Sister",
+            "phone": "+44 (0)20 7946-0000"
+        }))
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let location = response.header(LOCATION).to_owned();
+    let added: Value = response.json();
+    assert_eq!(added["name"], "Jane Doe");
+    assert_eq!(added["phone"], "+4402079460000");
+    assert_eq!(added["personId"], person.uuid.to_string());
+
+    let response = client
+        .put(&location)
+        .as_user(&["write"])
+        .json(&json!({
+            "name": "Jane Doe",
+            "relationship": "Neighbour",
+            "phone": "020 7946 0001"
+        }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let fetched: Value = client.get(&location).as_user(&["read"]).await.json();
+    assert_eq!(fetched["relationship"], "Neighbour");
+    assert_eq!(fetched["phone"], "02079460001");
+
+    let response = client.delete(&location).as_user(&["write"]).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let listed: Value = client.get(&contacts).as_user(&["read"]).await.json();
+    assert_eq!(listed, json!([]));
+}
+
+#[tokio::test]
+async fn invalid_phone_numbers_are_rejected() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    let response = app
+        .client()
+        .post(&format!(
+            "/api/v1/person/{}/emergency-contacts",
+            person.uuid
+        ))
+        .as_user(&["write"])
+        .json(&json!({
+            "name": "Jane Doe",
+            "relationship": "Sister",
+            "phone": "call the office"
+        }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn people_have_a_limited_number_of_emergency_contacts() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    for i in 0..5 {
+        EmergencyContactFactory::default()
+            .with_name(&format!("Contact {i}"))
+            .insert(&app.pool, person.uuid)
+            .await;
+    }
+
+    let response = app
+        .client()
+        .post(&format!(
+            "/api/v1/person/{}/emergency-contacts",
+            person.uuid
+        ))
+        .as_user(&["write"])
+        .json(&json!({
+            "name": "One Too Many",
+            "relationship": "Friend",
+            "phone": "+442079460000"
+        }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn people_can_be_expanded_with_their_emergency_contacts() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let without = PersonFactory::default().insert(&app.pool).await;
+    EmergencyContactFactory::default()
+        .insert(&app.pool, person.uuid)
+        .await;
+
+    let fetched: Value = client
+        .get(&format!(
+            "/api/v1/person/{}?expand=emergencyContacts",
+            person.uuid
+        ))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(fetched["emergencyContacts"][0]["name"], "Jane Doe");
+
+    let listed: Value = client
+        .get("/api/v1/person?expand=emergencyContacts")
+        .as_user(&["read"])
+        .await
+        .json();
+    let listed = listed.as_array().unwrap();
+    let contacts_of = |uuid: String| {
+        listed
+            .iter()
+            .find(|p| p["id"] == uuid)
+            .map(|p| p["emergencyContacts"].as_array().unwrap().len())
+    };
+    assert_eq!(contacts_of(person.uuid.to_string()), Some(1));
+    assert_eq!(contacts_of(without.uuid.to_string()), Some(0));
+
+    // contacts are only included when asked for
+    let plain: Value = client
+        .get(&format!("/api/v1/person/{}", person.uuid))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert!(plain.get("emergencyContacts").is_none());
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use axum::http::{header::LOCATION, StatusCode};
+use common::{
+    factories::{EmploymentFactory, PersonFactory},
+    TestApp,
+};
+use serde_json::{json, Value};
+use time::macros::date;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn employment_can_be_added_changed_and_removed() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let employments = format!("/api/v1/person/{}/employments", person.uuid);
+
+    let response = client
+        .post(&employments)
+        .as_user(&["write"])
+        .json(&json!({
+            "employer": "  Analytical  Engines ",
+            "role": "Programmer",
+            "startDate": "1842-01-01",
+            "endDate": "1843-12-31"
+        }))
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let location = response.header(LOCATION).to_owned();
+    let added: Value = response.json();
+    assert_eq!(added["employer"], "Analytical Engines");
+    assert_eq!(added["personId"], person.uuid.to_string());
+    assert_eq!(
+        location,
+        format!("{employments}/{}", added["id"].as_str().unwrap())
+    );
+
+    let fetched: Value = client.get(&location).as_user(&["read"]).await.json();
+    assert_eq!(fetched["role"], "Programmer");
+
+    let response = client
+        .put(&location)
+        .as_user(&["write"])
+        .json(&json!({
+            "employer": "Analytical Engines",
+            "role": "Lead Programmer",
+            "startDate": "1842-01-01"
+        }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let updated: Value = response.json();
+    assert_eq!(updated["role"], "Lead Programmer");
+    assert_eq!(updated["endDate"], Value::Null);
+
+    let listed: Value = client.get(&employments).as_user(&["read"]).await.json();
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+
+    let response = client.delete(&location).as_user(&["write"]).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client.get(&location).as_user(&["read"]).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn overlapping_employment_is_refused() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let earlier = EmploymentFactory::default()
+        .with_dates(date!(2010 - 01 - 01), Some(date!(2014 - 12 - 31)))
+        .insert(&app.pool, person.uuid)
+        .await;
+    // ongoing since 2015
+    EmploymentFactory::default()
+        .insert(&app.pool, person.uuid)
+        .await;
+
+    let employments = format!("/api/v1/person/{}/employments", person.uuid);
+    let add = |start: &str, end: Option<&str>| {
+        client.post(&employments).as_user(&["write"]).json(&json!({
+            "employer": "Initech",
+            "role": "Analyst",
+            "startDate": start,
+            "endDate": end
+        }))
+    };
+
+    let overlapping = add("2014-12-31", Some("2014-12-31")).await;
+    assert_eq!(overlapping.status(), StatusCode::CONFLICT);
+
+    let ongoing = add("2030-01-01", None).await;
+    assert_eq!(ongoing.status(), StatusCode::CONFLICT);
+
+    let before = add("2000-01-01", Some("2009-12-31")).await;
+    assert_eq!(before.status(), StatusCode::CREATED);
+
+    // an employment doesn't overlap the one it replaces
+    let response = client
+        .put(&format!("{employments}/{}", earlier.uuid))
+        .as_user(&["write"])
+        .json(&json!({
+            "employer": "Acme",
+            "role": "Engineer",
+            "startDate": "2010-06-01",
+            "endDate": "2014-12-31"
+        }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let listed: Value = client.get(&employments).as_user(&["read"]).await.json();
+    let starts: Vec<_> = listed
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["startDate"].as_str().unwrap())
+        .collect();
+    assert_eq!(starts, ["2000-01-01", "2010-06-01", "2015-01-01"]);
+}
+
+#[tokio::test]
+async fn employment_must_end_after_it_starts() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    let response = app
+        .client()
+        .post(&format!("/api/v1/person/{}/employments", person.uuid))
+        .as_user(&["write"])
+        .json(&json!({
+            "employer": "Initech",
+            "role": "Analyst",
+            "startDate": "2020-01-01",
+            "endDate": "2019-12-31"
+        }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn employment_of_unknown_people_is_not_found() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let employment = EmploymentFactory::default()
+        .insert(&app.pool, person.uuid)
+        .await;
+
+    let response = client
+        .get(&format!("/api/v1/person/{}/employments", Uuid::new_v4()))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // employment is only found under the person it belongs to
+    let other = PersonFactory::default().insert(&app.pool).await;
+    let response = client
+        .get(&format!(
+            "/api/v1/person/{}/employments/{}",
+            other.uuid, employment.uuid
+        ))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
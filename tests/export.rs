@@ -0,0 +1,134 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{
+        header::{AUTHORIZATION, CONTENT_DISPOSITION, CONTENT_TYPE},
+        Request, StatusCode,
+    },
+    response::Response,
+};
+use common::{
+    auth::token,
+    factories::{AddressFactory, PersonFactory},
+    json_body, TestApp,
+};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+async fn export(app: &TestApp, uri: &str) -> Response {
+    app.request(
+        Request::get(uri)
+            .header(AUTHORIZATION, format!("Bearer {}", token(&["read"])))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn person_can_be_exported_as_a_pdf() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default()
+        .with_address(AddressFactory::default())
+        .insert(&app.pool)
+        .await;
+
+    let response = export(&app, &format!("/api/v1/person/{}/export.pdf", person.uuid)).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/pdf");
+    assert_eq!(
+        response.headers()[CONTENT_DISPOSITION],
+        format!("attachment; filename=\"person-{}.pdf\"", person.uuid)
+    );
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(bytes.starts_with(b"%PDF-"), "Should be a PDF document");
+}
+
+#[tokio::test]
+async fn people_with_a_history_can_be_exported_as_a_pdf() {
+    let app = TestApp::new().await;
+    let person: Value = app
+        .client()
+        .post("/api/v1/person")
+        .as_user(&["write"])
+        .json(&json!({
+            "firstName": "Ada",
+            "familyName": "Byron",
+            "dateOfBirth": "1815-12-10",
+        }))
+        .await
+        .json();
+
+    let response = export(
+        &app,
+        &format!(
+            "/api/v1/person/{}/export.pdf",
+            person["id"].as_str().unwrap()
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(bytes.starts_with(b"%PDF-"), "Should be a PDF document");
+}
+
+#[tokio::test]
+async fn exporting_an_unknown_person_is_not_found() {
+    let app = TestApp::new().await;
+
+    let response = export(
+        &app,
+        &format!("/api/v1/person/{}/export.pdf", Uuid::new_v4()),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn people_can_be_exported_as_a_spreadsheet() {
+    let app = TestApp::new().await;
+    PersonFactory::default().insert(&app.pool).await;
+    PersonFactory::default().insert(&app.pool).await;
+
+    let response = export(&app, "/api/v1/person/export.xlsx").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[CONTENT_TYPE],
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+    );
+
+    // workbooks are zip archives
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(
+        bytes.starts_with(b"PK\x03\x04"),
+        "Should be an XLSX workbook"
+    );
+}
+
+#[tokio::test]
+async fn spreadsheets_of_too_many_people_are_refused() {
+    let app = TestApp::new().await;
+    for _ in 0..1_001 {
+        PersonFactory::default().insert(&app.pool).await;
+    }
+
+    let response = export(&app, "/api/v1/person/export.xlsx").await;
+
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let body: Value = json_body(response).await;
+    assert!(
+        body["message"]
+            .as_str()
+            .unwrap()
+            .contains("POST /api/v1/person/export-jobs"),
+        "{body}"
+    );
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use axum::http::{
+    header::{CONTENT_TYPE, LOCATION},
+    StatusCode,
+};
+use common::{factories::PersonFactory, TestApp};
+use rust_web_app::jobs;
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn exports_are_generated_in_the_background() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    PersonFactory::default()
+        .with_family_name("Lovelace")
+        .insert(&app.pool)
+        .await;
+    PersonFactory::default()
+        .with_family_name("Babbage")
+        .insert(&app.pool)
+        .await;
+
+    let response = client
+        .post("/api/v1/person/export-jobs")
+        .as_user(&["read"])
+        .json(&json!({ "format": "csv", "filter": "familyName==Lovelace" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let location = response.header(LOCATION).to_owned();
+
+    let export: Value = client.get(&location).as_user(&["read"]).await.json();
+    assert_eq!(export["status"], "pending");
+    assert!(export.get("downloadUrl").is_none());
+
+    assert!(jobs::run_next(&app.pool).await.unwrap(), "Job should run");
+
+    let export: Value = client.get(&location).as_user(&["read"]).await.json();
+    assert_eq!(export["status"], "completed");
+    assert_eq!(export["rowCount"], 1);
+
+    let response = client
+        .get(export["downloadUrl"].as_str().unwrap())
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.header(CONTENT_TYPE), "text/csv");
+
+    let lines: Vec<_> = response.text().lines().collect();
+    assert_eq!(lines.len(), 2, "Should be a header and one person");
+    assert!(lines[1].contains("Lovelace"));
+}
+
+#[tokio::test]
+async fn exports_can_only_be_downloaded_once_completed_and_until_they_expire() {
+    let app = TestApp::new().await;
+    let client = app.client();
+
+    let export: Value = client
+        .post("/api/v1/person/export-jobs")
+        .as_user(&["read"])
+        .json(&json!({ "format": "xlsx" }))
+        .await
+        .json();
+    let download = format!(
+        "/api/v1/person/export-jobs/{}/download",
+        export["id"].as_str().unwrap()
+    );
+
+    let response = client.get(&download).as_user(&["read"]).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    jobs::run_next(&app.pool).await.unwrap();
+
+    let response = client.get(&download).as_user(&["read"]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    sqlx::query("UPDATE person_export SET expires = now() - interval '1 minute'")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let response = client.get(&download).as_user(&["read"]).await;
+    assert_eq!(response.status(), StatusCode::GONE);
+}
+
+#[tokio::test]
+async fn exports_with_an_invalid_filter_are_rejected() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .client()
+        .post("/api/v1/person/export-jobs")
+        .as_user(&["read"])
+        .json(&json!({ "format": "json", "filter": "shoeSize==9" }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header::AUTHORIZATION, Request, StatusCode},
+};
+use common::{
+    auth::token,
+    factories::{AddressFactory, PersonFactory},
+    json_body, TestApp,
+};
+use serde_json::{json, Value};
+
+async fn execute(app: &TestApp, scopes: &[&str], query: &str) -> Value {
+    let response = app
+        .request(
+            Request::post("/graphql")
+                .header(AUTHORIZATION, format!("Bearer {}", token(scopes)))
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "query": query }).to_string()))
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    json_body(response).await
+}
+
+#[tokio::test]
+async fn person_query_resolves_the_address() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default()
+        .with_first_name("Ada")
+        .with_address(AddressFactory::default().with_postcode("N1 9GU"))
+        .insert(&app.pool)
+        .await;
+
+    let response = execute(
+        &app,
+        &["read"],
+        &format!(
+            r#"{{ person(id: "{}") {{ firstName address {{ postcode }} }} }}"#,
+            person.uuid
+        ),
+    )
+    .await;
+
+    assert_eq!(
+        response["data"]["person"],
+        json!({ "firstName": "Ada", "address": { "postcode": "N1 9GU" } })
+    );
+}
+
+#[tokio::test]
+async fn mutations_require_the_write_scope() {
+    let app = TestApp::new().await;
+    let mutation = r#"mutation {
+        createPerson(person: { firstName: "Grace", familyName: "Hopper", dateOfBirth: "1906-12-09" }) { id }
+    }"#;
+
+    let response = execute(&app, &["read"], mutation).await;
+
+    assert_eq!(
+        response["errors"][0]["message"],
+        "Client requires the scope: write"
+    );
+
+    let response = execute(&app, &["write"], mutation).await;
+
+    assert!(response["errors"].is_null(), "{response}");
+    assert!(response["data"]["createPerson"]["id"].is_string());
+}
+
+#[tokio::test]
+async fn mutations_are_validated() {
+    let app = TestApp::new().await;
+
+    let response = execute(
+        &app,
+        &["write"],
+        r#"mutation {
+            createPerson(person: { firstName: "", familyName: "Hopper", dateOfBirth: "1906-12-09" }) { id }
+        }"#,
+    )
+    .await;
+
+    assert!(response["errors"][0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("must be between 1 and 64 characters"));
+}
+
+#[tokio::test]
+async fn stale_versions_are_refused_by_mutations() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    let update = |version| {
+        format!(
+            r#"mutation {{
+                updatePerson(id: "{}", version: {version}, changes: {{ familyName: "Lovelace" }}) {{ version }}
+            }}"#,
+            person.uuid
+        )
+    };
+
+    let response = execute(&app, &["write"], &update(1)).await;
+    assert_eq!(response["data"]["updatePerson"]["version"], 2, "{response}");
+
+    let response = execute(&app, &["write"], &update(1)).await;
+    assert_eq!(
+        response["errors"][0]["message"],
+        "The resource has been changed, and is now at version 2"
+    );
+
+    let delete = |version| {
+        format!(
+            r#"mutation {{ deletePerson(id: "{}", version: {version}) }}"#,
+            person.uuid
+        )
+    };
+
+    let response = execute(&app, &["write"], &delete(1)).await;
+    assert!(response["errors"][0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("now at version 2"));
+
+    let response = execute(&app, &["write"], &delete(2)).await;
+    assert_eq!(
+        response["data"]["deletePerson"],
+        json!(person.uuid),
+        "{response}"
+    );
+}
+
+#[tokio::test]
+async fn graphql_requires_a_token() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .request(
+            Request::post("/graphql")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({ "query": "{ people { id } }" }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
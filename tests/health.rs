@@ -0,0 +1,56 @@
+mod common;
+
+use axum::http::{header::RETRY_AFTER, StatusCode};
+use common::TestApp;
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn instances_are_live_and_ready_while_the_database_answers() {
+    let app = TestApp::new().await;
+    let client = app.client();
+
+    let response = client.get("/health/live").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.get("/health/ready").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let readiness: Value = response.json();
+    assert_eq!(readiness["ready"], true);
+    assert_eq!(readiness["database"]["reachable"], true);
+    assert_eq!(readiness["database"]["failingOver"], false);
+    assert_eq!(readiness["auth"]["available"], true);
+}
+
+#[tokio::test]
+async fn instances_are_not_ready_once_the_database_cannot_be_reached() {
+    let app = TestApp::new().await;
+    app.pool.close().await;
+
+    let response = app.client().get("/health/ready").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let readiness: Value = response.json();
+    assert_eq!(readiness["ready"], false);
+    assert_eq!(readiness["database"]["reachable"], false);
+}
+
+#[tokio::test]
+async fn writes_are_not_retried_when_the_database_cannot_be_reached() {
+    let app = TestApp::new().await;
+    app.pool.close().await;
+
+    let response = app
+        .client()
+        .post("/api/v1/person")
+        .as_user(&["write"])
+        .json(&json!({
+            "firstName": "Ada",
+            "familyName": "Lovelace",
+            "dateOfBirth": "1815-12-10"
+        }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.header(RETRY_AFTER), "1");
+}
@@ -0,0 +1,166 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header::CONTENT_TYPE, StatusCode},
+};
+use common::TestApp;
+use futures::stream;
+use serde_json::{json, Value};
+
+fn person(first_name: &str) -> String {
+    json!({
+        "firstName": first_name,
+        "familyName": "Lovelace",
+        "dateOfBirth": "1815-12-10",
+    })
+    .to_string()
+}
+
+/// A form uploading the file as its `file` field
+fn upload(file_name: &str, content_type: &str, file: &str) -> (String, String) {
+    let boundary = "person-import-boundary";
+    let body = format!(
+        "--{boundary}\r\n\
+         Content-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\n\
+         Content-Type: {content_type}\r\n\r\n\
+         {file}\r\n\
+         --{boundary}--\r\n"
+    );
+
+    (format!("multipart/form-data; boundary={boundary}"), body)
+}
+
+async fn count_people(app: &TestApp) -> i64 {
+    sqlx::query_scalar("SELECT count(*) FROM person")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn people_are_imported_line_by_line() {
+    let app = TestApp::new().await;
+    let body = format!(
+        "{}\n{{not json\n\n{}\n{}",
+        person("Ada"),
+        person(""),
+        person("Augusta")
+    );
+
+    // split mid-line, as a body arriving over the network would be
+    let (first, rest) = body.split_at(10);
+    let chunks = vec![
+        Ok::<_, std::io::Error>(first.to_owned()),
+        Ok(rest.to_owned()),
+    ];
+
+    let response = app
+        .client()
+        .post("/api/v1/person/import")
+        .as_user(&["write"])
+        .header(CONTENT_TYPE, "application/x-ndjson")
+        .body(Body::from_stream(stream::iter(chunks)))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let summary: Value = response.json();
+    assert_eq!(summary["imported"], 2);
+    assert_eq!(summary["failed"], 2);
+    let lines: Vec<_> = summary["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["line"].clone())
+        .collect();
+    assert_eq!(lines, [json!(2), json!(4)]);
+
+    assert_eq!(count_people(&app).await, 2);
+}
+
+#[tokio::test]
+async fn imports_span_many_batches() {
+    let app = TestApp::new().await;
+    let body: String = (0..1_201).map(|_| person("Ada") + "\n").collect();
+
+    let summary: Value = app
+        .client()
+        .post("/api/v1/person/import")
+        .as_user(&["write"])
+        .header(CONTENT_TYPE, "application/x-ndjson")
+        .body(body)
+        .await
+        .json();
+
+    assert_eq!(summary["imported"], 1_201);
+    assert_eq!(count_people(&app).await, 1_201);
+}
+
+#[tokio::test]
+async fn imports_must_be_newline_delimited_json() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .client()
+        .post("/api/v1/person/import")
+        .as_user(&["write"])
+        .json(&json!([]))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn people_are_imported_from_uploaded_files() {
+    let app = TestApp::new().await;
+    let csv = "firstName,familyName,dateOfBirth\r\n\
+               Ada,Lovelace,1815-12-10\r\n\
+               Grace,,1906-12-09\r\n\
+               Alan,Turing,1912-06-23\r\n";
+    let json = json!([
+        { "firstName": "Katherine", "familyName": "Johnson", "dateOfBirth": "1918-08-26" },
+        { "firstName": "Margaret", "dateOfBirth": "1936-08-17" },
+    ])
+    .to_string();
+
+    for ((content_type, body), imported, line) in [
+        (upload("people.csv", "text/csv", csv), 2, 3),
+        (
+            upload("people.json", "application/octet-stream", &json),
+            1,
+            2,
+        ),
+    ] {
+        let response = app
+            .client()
+            .post("/api/v1/person/import")
+            .as_user(&["write"])
+            .header(CONTENT_TYPE, content_type)
+            .body(body)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let summary: Value = response.json();
+        assert_eq!(summary["imported"], imported);
+        assert_eq!(summary["failed"], 1);
+        assert_eq!(summary["errors"][0]["line"], line);
+    }
+
+    assert_eq!(count_people(&app).await, 3);
+}
+
+#[tokio::test]
+async fn uploaded_files_must_be_csv_or_json() {
+    let app = TestApp::new().await;
+    let (content_type, body) = upload("people.xlsx", "application/vnd.ms-excel", "");
+
+    let response = app
+        .client()
+        .post("/api/v1/person/import")
+        .as_user(&["write"])
+        .header(CONTENT_TYPE, content_type)
+        .body(body)
+        .await;
+
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
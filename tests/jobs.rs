@@ -0,0 +1,141 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header::AUTHORIZATION, Request, StatusCode},
+    response::Response,
+};
+use common::{auth::token, json_body, TestApp};
+use rust_web_app::jobs::{self, Job};
+use serde_json::Value;
+
+async fn list_jobs(app: &TestApp, query: &str, scopes: &[&str]) -> Response {
+    app.request(
+        Request::get(format!("/api/v1/admin/jobs{query}"))
+            .header(AUTHORIZATION, format!("Bearer {}", token(scopes)))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn purge_job_removes_old_delivered_events() {
+    let app = TestApp::new().await;
+
+    sqlx::query(
+        "INSERT INTO outbox (event_type, payload, created, sent) VALUES \
+         ('person.deleted', '{}', now() - interval '40 days', now() - interval '40 days'), \
+         ('person.deleted', '{}', now(), now()), \
+         ('person.deleted', '{}', now() - interval '40 days', NULL)",
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    let job = jobs::enqueue(
+        &app.pool,
+        &Job::PurgeOutbox {
+            older_than_days: 30,
+        },
+    )
+    .await
+    .unwrap();
+
+    assert!(jobs::run_next(&app.pool).await.unwrap(), "Job should run");
+    assert!(
+        !jobs::run_next(&app.pool).await.unwrap(),
+        "Nothing else should be due"
+    );
+
+    let (remaining,): (i64,) = sqlx::query_as("SELECT count(*) FROM outbox")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+
+    assert_eq!(remaining, 2, "Recent and undelivered events should be kept");
+
+    let response = list_jobs(&app, "?status=completed", &["admin"]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = json_body(response).await;
+    assert_eq!(body[0]["id"], job.to_string());
+    assert_eq!(body[0]["kind"], "outbox.purge");
+    assert_eq!(body[0]["attempts"], 1);
+}
+
+#[tokio::test]
+async fn failing_jobs_are_retried_until_out_of_attempts() {
+    let app = TestApp::new().await;
+
+    sqlx::query("INSERT INTO job (kind, payload, max_attempts) VALUES ('unknown', '{}', 2)")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    assert!(jobs::run_next(&app.pool).await.unwrap());
+
+    let body: Value = json_body(list_jobs(&app, "", &["admin"]).await).await;
+    assert_eq!(body[0]["status"], "pending");
+    assert!(body[0]["lastError"]
+        .as_str()
+        .unwrap()
+        .starts_with("Unreadable job payload"));
+
+    sqlx::query("UPDATE job SET run_at = now()")
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    assert!(jobs::run_next(&app.pool).await.unwrap());
+
+    let body: Value = json_body(list_jobs(&app, "", &["admin"]).await).await;
+    assert_eq!(body[0]["status"], "failed");
+    assert_eq!(body[0]["attempts"], 2);
+}
+
+#[tokio::test]
+async fn listing_jobs_requires_the_admin_scope() {
+    let app = TestApp::new().await;
+
+    let response = list_jobs(&app, "", &["read", "write"]).await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn schedule_reports_when_tasks_next_run() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .request(
+            Request::get("/api/v1/admin/schedule")
+                .header(AUTHORIZATION, format!("Bearer {}", token(&["admin"])))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = json_body(response).await;
+    let names: Vec<_> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|task| task["name"].as_str().unwrap())
+        .collect();
+
+    assert_eq!(
+        names,
+        [
+            "outbox.purge",
+            "jwks.refresh",
+            "person.delete_due",
+            "person.archive_inactive",
+            "person.export_purge"
+        ]
+    );
+    assert!(body[0]["nextRun"].is_string());
+    assert!(body[0]["lastRun"].is_null());
+}
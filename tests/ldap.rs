@@ -0,0 +1,67 @@
+mod common;
+
+use common::TestApp;
+use rust_web_app::ldap::{self, DirectoryPerson, SyncSummary};
+use time::macros::date;
+
+fn directory_person(external_id: &str, family_name: &str) -> DirectoryPerson {
+    DirectoryPerson {
+        external_id: external_id.to_owned(),
+        first_name: "Grace".to_owned(),
+        family_name: family_name.to_owned(),
+        date_of_birth: date!(1906 - 12 - 09),
+    }
+}
+
+#[tokio::test]
+async fn directory_people_are_matched_on_their_external_id() {
+    let app = TestApp::new().await;
+
+    let summary = ldap::import(&app.pool, &[directory_person("grace", "Murray")])
+        .await
+        .unwrap();
+    assert_eq!(summary.created, 1);
+
+    let summary = ldap::import(
+        &app.pool,
+        &[
+            directory_person("grace", "Hopper"),
+            directory_person("invalid", ""),
+        ],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        summary,
+        SyncSummary {
+            created: 0,
+            updated: 1,
+            unchanged: 0,
+            skipped: 1,
+        }
+    );
+
+    let summary = ldap::import(&app.pool, &[directory_person("grace", "Hopper")])
+        .await
+        .unwrap();
+    assert_eq!(summary.unchanged, 1);
+
+    let people: Vec<(String, String)> =
+        sqlx::query_as("SELECT external_id, family_name FROM person")
+            .fetch_all(&app.pool)
+            .await
+            .unwrap();
+    assert_eq!(people, [("grace".to_owned(), "Hopper".to_owned())]);
+
+    let events: Vec<(String,)> = sqlx::query_as("SELECT event_type FROM outbox ORDER BY id")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        events,
+        [
+            ("person.created".to_owned(),),
+            ("person.updated".to_owned(),)
+        ]
+    );
+}
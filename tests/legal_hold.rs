@@ -0,0 +1,104 @@
+mod common;
+
+use axum::http::{header::IF_MATCH, StatusCode};
+use common::{factories::PersonFactory, TestApp};
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn people_under_legal_hold_cannot_be_deleted() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let hold = format!("/api/v1/admin/person/{}/legal-hold", person.uuid);
+    let uri = format!("/api/v1/person/{}", person.uuid);
+
+    let response = client
+        .put(&hold)
+        .as_user(&["admin"])
+        .json(&json!({ "reason": "Case 1234" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let placed: Value = response.json();
+    assert_eq!(placed["held"], true);
+
+    let response = client
+        .delete(&uri)
+        .as_user(&["write"])
+        .header(IF_MATCH, "*")
+        .await;
+    assert_eq!(response.status(), StatusCode::LOCKED);
+
+    let response = client.get(&uri).as_user(&["read"]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.delete(&hold).as_user(&["admin"]).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let lifted: Value = client.get(&hold).as_user(&["admin"]).await.json();
+    assert_eq!(lifted["held"], false);
+
+    let response = client
+        .delete(&uri)
+        .as_user(&["write"])
+        .header(IF_MATCH, "*")
+        .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn bulk_deletes_including_a_held_person_delete_nobody() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let held = PersonFactory::default().insert(&app.pool).await;
+
+    let response = client
+        .put(&format!("/api/v1/admin/person/{}/legal-hold", held.uuid))
+        .as_user(&["admin"])
+        .json(&json!({ "reason": "Case 1234" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .delete("/api/v1/person")
+        .as_user(&["write"])
+        .json(&json!([person.uuid, held.uuid]))
+        .await;
+    assert_eq!(response.status(), StatusCode::LOCKED);
+
+    let response = client
+        .get(&format!("/api/v1/person/{}", person.uuid))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn legal_holds_need_a_reason() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    let response = app
+        .client()
+        .put(&format!("/api/v1/admin/person/{}/legal-hold", person.uuid))
+        .as_user(&["admin"])
+        .json(&json!({ "reason": " " }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn only_admins_can_place_legal_holds() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    let response = app
+        .client()
+        .put(&format!("/api/v1/admin/person/{}/legal-hold", person.uuid))
+        .as_user(&["write"])
+        .json(&json!({ "reason": "Case 1234" }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,10 @@
+/// Guards the published API contract, any change to the generated OpenAPI document must be
+/// accepted by updating the snapshot in `tests/snapshots`
+#[test]
+fn openapi_document_matches_snapshot() {
+    let document = rust_web_app::openapi()
+        .to_pretty_json()
+        .expect("OpenAPI document should serialize");
+
+    insta::assert_snapshot!("openapi_v1", document);
+}
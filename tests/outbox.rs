@@ -0,0 +1,86 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{
+        header::{AUTHORIZATION, IF_MATCH},
+        Request, StatusCode,
+    },
+};
+use common::{auth::token, factories::PersonFactory, TestApp};
+use rust_web_app::{events::EventPublisher, outbox};
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn changes_are_recorded_in_the_outbox_and_relayed() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .request(
+            Request::post("/api/v1/person")
+                .header(AUTHORIZATION, format!("Bearer {}", token(&["write"])))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "first_name": "Grace",
+                        "family_name": "Hopper",
+                        "date_of_birth": "1906-12-09",
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let (event_type, payload): (String, Value) =
+        sqlx::query_as("SELECT event_type, payload FROM outbox WHERE sent IS NULL")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+
+    assert_eq!(event_type, "person.created");
+    assert_eq!(payload["specversion"], "1.0");
+    assert_eq!(payload["type"], "person.created");
+    assert_eq!(payload["data"]["person"]["firstName"], "Grace");
+
+    let sent = outbox::relay_batch(&app.pool, &EventPublisher::Disabled)
+        .await
+        .unwrap();
+
+    assert_eq!(sent, 1);
+    assert_eq!(
+        outbox::relay_batch(&app.pool, &EventPublisher::Disabled)
+            .await
+            .unwrap(),
+        0,
+        "Sent events should not be relayed again"
+    );
+}
+
+#[tokio::test]
+async fn failed_changes_leave_no_event_behind() {
+    let app = TestApp::new().await;
+    let missing = uuid::Uuid::new_v4();
+    PersonFactory::default().insert(&app.pool).await;
+
+    let response = app
+        .request(
+            Request::delete(format!("/api/v1/person/{missing}"))
+                .header(AUTHORIZATION, format!("Bearer {}", token(&["write"])))
+                .header(IF_MATCH, "*")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let (pending,): (i64,) = sqlx::query_as("SELECT count(*) FROM outbox")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+
+    assert_eq!(pending, 0);
+}
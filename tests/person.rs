@@ -0,0 +1,1495 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use axum::http::{
+    header::{
+        ACCEPT, ACCEPT_RANGES, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_MATCH, IF_MODIFIED_SINCE,
+        IF_NONE_MATCH, LAST_MODIFIED, LOCATION, RANGE,
+    },
+    HeaderName, StatusCode,
+};
+use common::{
+    factories::{AddressFactory, PersonFactory},
+    TestApp,
+};
+use rust_web_app::clock::FixedClock;
+use serde_json::{json, Value};
+use time::{
+    macros::{date, datetime},
+    OffsetDateTime,
+};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    Layer,
+};
+use uuid::Uuid;
+
+const TOTAL_COUNT: HeaderName = HeaderName::from_static("x-total-count");
+const NEXT_CURSOR: HeaderName = HeaderName::from_static("x-next-cursor");
+
+/// Counts the statements sqlx executes while it's the thread's default subscriber
+#[derive(Clone, Default)]
+struct QueryCounter(Arc<AtomicUsize>);
+
+impl QueryCounter {
+    fn count(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl<S: Subscriber> Layer<S> for QueryCounter {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target() == "sqlx::query" {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// How many statements handling the request took
+async fn queries_for(app: &TestApp, uri: &str) -> usize {
+    let counter = QueryCounter::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(counter.clone()));
+
+    let response = app.client().get(uri).as_user(&["read"]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    counter.count()
+}
+
+#[tokio::test]
+async fn get_person_returns_the_person() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default()
+        .with_first_name("Ada")
+        .with_family_name("Lovelace")
+        .insert(&app.pool)
+        .await;
+
+    let response = app
+        .client()
+        .get(&format!("/api/v1/person/{}", person.uuid))
+        .as_user(&["read"])
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = response.json();
+    assert_eq!(body["id"], person.uuid.to_string());
+    assert_eq!(body["firstName"], "Ada");
+    assert_eq!(body["familyName"], "Lovelace");
+}
+
+#[tokio::test]
+async fn delete_person_removes_the_person() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let uri = format!("/api/v1/person/{}", person.uuid);
+
+    let response = app
+        .client()
+        .delete(&uri)
+        .as_user(&["write"])
+        .header(IF_MATCH, r#""1""#)
+        .await;
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = app.client().get(&uri).as_user(&["read"]).await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn deleted_people_are_kept_and_can_be_restored() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let uri = format!("/api/v1/person/{}", person.uuid);
+    let restore = format!("{uri}/restore");
+
+    let response = app.client().post(&restore).as_user(&["write"]).await;
+
+    assert_eq!(
+        response.status(),
+        StatusCode::NOT_FOUND,
+        "Only deleted people can be restored"
+    );
+
+    app.client()
+        .delete(&uri)
+        .as_user(&["write"])
+        .header(IF_MATCH, "*")
+        .await;
+
+    let deleted_at: Option<OffsetDateTime> =
+        sqlx::query_scalar("SELECT deleted_at FROM person WHERE uuid = $1")
+            .bind(person.uuid)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+
+    assert!(deleted_at.is_some(), "The person should be kept");
+
+    let people: Vec<Value> = app
+        .client()
+        .get("/api/v1/person")
+        .as_user(&["read"])
+        .await
+        .json();
+
+    assert!(people.is_empty(), "Deleted people shouldn't be listed");
+
+    let response = app
+        .client()
+        .delete(&uri)
+        .as_user(&["write"])
+        .header(IF_MATCH, "*")
+        .await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app.client().post(&restore).as_user(&["write"]).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[LOCATION], uri);
+
+    let response = app.client().get(&uri).as_user(&["read"]).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.client().post(&restore).as_user(&["write"]).await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn changes_must_be_made_to_the_current_version() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let uri = format!("/api/v1/person/{}", person.uuid);
+
+    let patch = |if_match: Option<&'static str>, body: Value| {
+        let request = app
+            .client()
+            .patch(&uri)
+            .as_user(&["write"])
+            .header(CONTENT_TYPE, "application/merge-patch+json")
+            .body(body.to_string());
+        match if_match {
+            Some(if_match) => request.header(IF_MATCH, if_match),
+            None => request,
+        }
+    };
+
+    let response = patch(None, json!({"familyName": "Lovelace"})).await;
+
+    assert_eq!(response.status(), StatusCode::PRECONDITION_REQUIRED);
+
+    let response = patch(Some(r#""1""#), json!({"familyName": "Lovelace"})).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<Value>()["version"], 2);
+
+    let response = patch(Some(r#""1""#), json!({"familyName": "Byron"})).await;
+
+    assert_eq!(
+        response.status(),
+        StatusCode::PRECONDITION_FAILED,
+        "The person has been changed since version 1"
+    );
+
+    let response = app
+        .client()
+        .delete(&uri)
+        .as_user(&["write"])
+        .header(IF_MATCH, r#""1""#)
+        .await;
+
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+    let response = app
+        .client()
+        .delete(&uri)
+        .as_user(&["write"])
+        .header(IF_MATCH, r#""1", "2""#)
+        .await;
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn unchanged_people_are_not_sent_again() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let uri = format!("/api/v1/person/{}", person.uuid);
+
+    let response = client.get(&uri).as_user(&["read"]).await;
+
+    assert_eq!(response.header(ETAG), r#""1""#);
+    let last_modified = response.header(LAST_MODIFIED).to_owned();
+
+    let response = client
+        .get(&uri)
+        .as_user(&["read"])
+        .header(IF_NONE_MATCH, r#""1""#)
+        .await;
+
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert!(response.bytes().is_empty());
+    assert_eq!(response.header(ETAG), r#""1""#);
+
+    let response = client
+        .get(&uri)
+        .as_user(&["read"])
+        .header(IF_MODIFIED_SINCE, last_modified.as_str())
+        .await;
+
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let people = client.get("/api/v1/person").as_user(&["read"]).await;
+    let list_etag = people.header(ETAG).to_owned();
+
+    let response = client
+        .get("/api/v1/person")
+        .as_user(&["read"])
+        .header(IF_NONE_MATCH, list_etag.as_str())
+        .await;
+
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    client
+        .patch(&uri)
+        .as_user(&["write"])
+        .header(IF_MATCH, r#""1""#)
+        .json(&json!({"familyName": "Lovelace"}))
+        .await;
+
+    let response = client
+        .get(&uri)
+        .as_user(&["read"])
+        .header(IF_NONE_MATCH, r#""1""#)
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK, "The person has changed");
+    assert_eq!(response.header(ETAG), r#""2""#);
+
+    let response = client
+        .get("/api/v1/person")
+        .as_user(&["read"])
+        .header(IF_NONE_MATCH, list_etag.as_str())
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK, "Someone has changed");
+}
+
+#[tokio::test]
+async fn put_replaces_the_whole_person() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default()
+        .with_first_name("Ada")
+        .with_family_name("Byron")
+        .insert(&app.pool)
+        .await;
+
+    let replace = |id: Uuid, body: Value| {
+        app.client()
+            .put(&format!("/api/v1/person/{id}"))
+            .as_user(&["write"])
+            .header(IF_MATCH, "*")
+            .json(&body)
+    };
+
+    let response = replace(person.uuid, json!({"family_name": "Lovelace"})).await;
+
+    assert_eq!(
+        response.status(),
+        StatusCode::BAD_REQUEST,
+        "Every field must be given"
+    );
+
+    let lovelace = json!({
+        "firstName": "Augusta",
+        "familyName": "Lovelace",
+        "dateOfBirth": "1815-12-10",
+    });
+    let response = replace(person.uuid, lovelace.clone()).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = response.json();
+    assert_eq!(body["firstName"], "Augusta");
+    assert_eq!(body["familyName"], "Lovelace");
+    assert_eq!(body["dateOfBirth"], "1815-12-10");
+
+    let response = replace(Uuid::new_v4(), lovelace).await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn patch_only_changes_the_given_fields() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default()
+        .with_first_name("Ada")
+        .with_family_name("Byron")
+        .insert(&app.pool)
+        .await;
+
+    let patch = |id: Uuid, content_type: &'static str, body: Value| {
+        app.client()
+            .patch(&format!("/api/v1/person/{id}"))
+            .as_user(&["write"])
+            .header(IF_MATCH, "*")
+            .header(CONTENT_TYPE, content_type)
+            .body(body.to_string())
+    };
+
+    let response = patch(
+        person.uuid,
+        "application/merge-patch+json",
+        json!({"familyName": "Lovelace"}),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = response.json();
+    assert_eq!(body["firstName"], "Ada");
+    assert_eq!(body["familyName"], "Lovelace");
+
+    let response = patch(
+        person.uuid,
+        "application/merge-patch+json",
+        json!({"familyName": null}),
+    )
+    .await;
+
+    assert_eq!(
+        response.status(),
+        StatusCode::BAD_REQUEST,
+        "Required fields can't be cleared"
+    );
+
+    let response = patch(person.uuid, "text/plain", json!({"familyName": "Byron"})).await;
+
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let response = patch(
+        Uuid::new_v4(),
+        "application/merge-patch+json",
+        json!({"familyName": "Lovelace"}),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn people_can_be_updated_in_bulk() {
+    let app = TestApp::new().await;
+    let ada = PersonFactory::default()
+        .with_first_name("Ada")
+        .insert(&app.pool)
+        .await;
+    let grace = PersonFactory::default()
+        .with_first_name("Grace")
+        .insert(&app.pool)
+        .await;
+    let missing = Uuid::new_v4();
+
+    let response = app
+        .client()
+        .patch("/api/v1/person")
+        .as_user(&["write"])
+        .json(&json!([
+            { "id": ada.uuid, "version": 1, "changes": { "familyName": "Lovelace" } },
+            { "id": missing, "version": 1, "changes": { "familyName": "Nobody" } },
+            { "id": grace.uuid, "version": 1, "changes": { "familyName": "" } },
+            { "id": grace.uuid, "version": 1, "changes": { "familyName": "Hopper" } },
+            { "id": grace.uuid, "version": 1, "changes": { "familyName": "Stale" } },
+        ]))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let results: Vec<Value> = response.json();
+    let statuses: Vec<_> = results
+        .iter()
+        .map(|r| r["status"].as_u64().unwrap())
+        .collect();
+    assert_eq!(
+        statuses,
+        [200, 404, 400, 200, 412],
+        "Grace was at version 2 by the last change"
+    );
+    assert_eq!(results[0]["person"]["familyName"], "Lovelace");
+    assert_eq!(results[1]["id"], missing.to_string());
+    assert!(results[2]["error"].is_string());
+    assert_eq!(results[3]["person"]["firstName"], "Grace");
+    assert_eq!(results[3]["person"]["familyName"], "Hopper");
+    assert_eq!(results[3]["person"]["version"], 2);
+
+    let (events,): (i64,) =
+        sqlx::query_as("SELECT count(*) FROM outbox WHERE event_type = 'person.updated'")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+    assert_eq!(events, 2, "Only the people changed should have events");
+}
+
+#[tokio::test]
+async fn bulk_updates_are_limited() {
+    let app = TestApp::new().await;
+
+    let changes: Vec<_> = (0..1001)
+        .map(|_| json!({ "id": Uuid::new_v4(), "version": 1, "changes": {} }))
+        .collect();
+
+    for body in [json!([]), json!(changes)] {
+        let response = app
+            .client()
+            .patch("/api/v1/person")
+            .as_user(&["write"])
+            .json(&body)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn people_can_be_deleted_in_bulk() {
+    let app = TestApp::new().await;
+    let ada = PersonFactory::default().insert(&app.pool).await;
+    let grace = PersonFactory::default().insert(&app.pool).await;
+    let kept = PersonFactory::default().insert(&app.pool).await;
+    let missing = Uuid::new_v4();
+
+    let response = app
+        .client()
+        .delete("/api/v1/person")
+        .as_user(&["write"])
+        .json(&json!([ada.uuid, missing, grace.uuid, ada.uuid]))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = response.json();
+    assert_eq!(body, json!({ "deleted": 2, "notFound": [missing] }));
+
+    for (person, status) in [
+        (ada.uuid, StatusCode::NOT_FOUND),
+        (grace.uuid, StatusCode::NOT_FOUND),
+        (kept.uuid, StatusCode::OK),
+    ] {
+        let response = app
+            .client()
+            .get(&format!("/api/v1/person/{person}"))
+            .as_user(&["read"])
+            .await;
+        assert_eq!(response.status(), status);
+    }
+
+    let response = app
+        .client()
+        .delete("/api/v1/person")
+        .as_user(&["write"])
+        .json(&json!([]))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn people_can_be_looked_up_in_bulk() {
+    let app = TestApp::new().await;
+    let ada = PersonFactory::default().insert(&app.pool).await;
+    let grace = PersonFactory::default().insert(&app.pool).await;
+    let deleted = PersonFactory::default().insert(&app.pool).await;
+    let missing = Uuid::new_v4();
+    app.client()
+        .delete(&format!("/api/v1/person/{}", deleted.uuid))
+        .as_user(&["write"])
+        .header(IF_MATCH, "*")
+        .await;
+
+    let response = app
+        .client()
+        .post("/api/v1/person/lookup")
+        .as_user(&["read"])
+        .json(&json!([
+            grace.uuid,
+            missing,
+            ada.uuid,
+            deleted.uuid,
+            grace.uuid
+        ]))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = response.json();
+    let found: Vec<_> = body["people"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|person| person["id"].clone())
+        .collect();
+    assert_eq!(
+        found,
+        [json!(grace.uuid), json!(ada.uuid)],
+        "In the order given"
+    );
+    assert_eq!(body["notFound"], json!([missing, deleted.uuid]));
+
+    let ids: Vec<_> = (0..1001).map(|_| Uuid::new_v4()).collect();
+
+    for body in [json!([]), json!(ids)] {
+        let response = app
+            .client()
+            .post("/api/v1/person/lookup")
+            .as_user(&["read"])
+            .json(&body)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn people_are_created_by_the_app_clock() {
+    let now = datetime!(2000-01-01 12:00 UTC);
+    let app = TestApp::with_clock(Arc::new(FixedClock(now))).await;
+
+    let create = |date_of_birth: &str| {
+        app.client()
+            .post("/api/v1/person")
+            .as_user(&["write"])
+            .json(&json!({
+                "first_name": "Ada",
+                "family_name": "Lovelace",
+                "date_of_birth": date_of_birth,
+            }))
+    };
+
+    let response = create("2010-01-01").await;
+    assert_eq!(
+        response.status(),
+        StatusCode::BAD_REQUEST,
+        "Born after the clock's today"
+    );
+
+    let response = create("1990-01-01").await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let body: Value = response.json();
+    assert_eq!(body["created"], "2000-01-01T12:00:00Z");
+    assert_eq!(body["lastEdited"], "2000-01-01T12:00:00Z");
+
+    let (created, last_edited): (OffsetDateTime, OffsetDateTime) =
+        sqlx::query_as("SELECT created, last_edited FROM person")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+
+    assert_eq!(created, now);
+    assert_eq!(last_edited, now);
+}
+
+#[tokio::test]
+async fn names_and_addresses_are_normalized() {
+    let app = TestApp::new().await;
+
+    let post = |uri: &str, body: Value| app.client().post(uri).as_user(&["write"]).json(&body);
+
+    let response = post(
+        "/api/v1/person",
+        json!({"firstName": "  Mary   Jane ", "familyName": "Rene\u{301}e", "dateOfBirth": "1990-01-01"}),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let person: Value = response.json();
+    assert_eq!(person["firstName"], "Mary Jane");
+    assert_eq!(person["familyName"], "Ren\u{e9}e");
+
+    let response = post(
+        &format!("/api/v1/person/{}/address", person["id"].as_str().unwrap()),
+        json!({"building": " 1  Main Street ", "townOrCity": "London", "postcode": " SW1A  1AA"}),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let (building, town_or_city, postcode): (String, String, String) =
+        sqlx::query_as("SELECT building, town_or_city, postcode FROM address")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+
+    assert_eq!(building, "1 Main Street");
+    assert_eq!(town_or_city, "London");
+    assert_eq!(postcode, "SW1A 1AA");
+}
+
+#[tokio::test]
+async fn created_resources_are_returned_with_their_location() {
+    let app = TestApp::new().await;
+
+    let post = |uri: &str, body: Value| app.client().post(uri).as_user(&["write"]).json(&body);
+
+    let response = post(
+        "/api/v1/person",
+        json!({"firstName": "Ada", "familyName": "Lovelace", "dateOfBirth": "1815-12-10"}),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let location = response.header(LOCATION).to_owned();
+    let person: Value = response.json();
+    assert_eq!(
+        location,
+        format!("/api/v1/person/{}", person["id"].as_str().unwrap())
+    );
+
+    let response = app.client().get(&location).as_user(&["read"]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = post(
+        &format!("{location}/address"),
+        json!({"building": "1", "postcode": "SW1A 1AA"}),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(response.header(LOCATION), format!("{location}/address"));
+
+    let address: Value = response.json();
+    assert_eq!(address["building"], "1");
+    assert_eq!(address["postcode"], "SW1A 1AA");
+}
+
+#[tokio::test]
+async fn remove_address_detaches_it_from_the_person() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default()
+        .with_address(AddressFactory::default())
+        .insert(&app.pool)
+        .await;
+    let address = person.address.unwrap();
+
+    let response = app
+        .client()
+        .delete(&format!("/api/v1/address/{}", address.uuid))
+        .as_user(&["write"])
+        .await;
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let linked: Option<uuid::Uuid> =
+        sqlx::query_scalar("SELECT address FROM person WHERE uuid = $1")
+            .bind(person.uuid)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+
+    assert_eq!(linked, None);
+}
+
+#[tokio::test]
+async fn a_persons_address_can_be_removed_without_knowing_its_uuid() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default()
+        .with_address(AddressFactory::default())
+        .insert(&app.pool)
+        .await;
+    let address = person.address.unwrap();
+
+    let response = app
+        .client()
+        .delete(&format!("/api/v1/person/{}/address", person.uuid))
+        .as_user(&["write"])
+        .await;
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let remaining: i64 = sqlx::query_scalar("SELECT count(*) FROM address WHERE uuid = $1")
+        .bind(address.uuid)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+
+    assert_eq!(remaining, 0);
+
+    let response = app
+        .client()
+        .delete(&format!("/api/v1/person/{}/address", person.uuid))
+        .as_user(&["write"])
+        .await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn search_matches_names_in_the_database_without_an_index() {
+    let app = TestApp::new().await;
+
+    for (first_name, family_name) in [("Jane", "Smithson"), ("John", "Smith"), ("Ada", "Lovelace")]
+    {
+        PersonFactory::default()
+            .with_first_name(first_name)
+            .with_family_name(family_name)
+            .insert(&app.pool)
+            .await;
+    }
+
+    let names = |uri: &'static str| {
+        let client = app.client();
+        async move {
+            let response = client.get(uri).as_user(&["read"]).await;
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            let people: Vec<Value> = response.json();
+            people
+                .iter()
+                .map(|p| p["firstName"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(names("/api/v1/person/search?q=Jon%20Smtih").await, ["John"]);
+    assert_eq!(
+        names("/api/v1/person/search?q=smith").await,
+        ["John", "Jane"]
+    );
+    assert_eq!(
+        names("/api/v1/person/search?q=Lovlace&limit=1").await,
+        ["Ada"]
+    );
+    assert!(names("/api/v1/person/search?q=Turing").await.is_empty());
+}
+
+#[tokio::test]
+async fn possible_duplicates_are_found_before_creating_a_person() {
+    let app = TestApp::new().await;
+
+    for (first_name, family_name, date_of_birth) in [
+        ("Jon", "Smith", date!(1980 - 01 - 01)),
+        ("John", "Smith", date!(1990 - 06 - 15)),
+        ("John", "Smith", date!(1980 - 01 - 01)),
+        ("Ada", "Lovelace", date!(1980 - 01 - 01)),
+    ] {
+        PersonFactory::default()
+            .with_first_name(first_name)
+            .with_family_name(family_name)
+            .with_date_of_birth(date_of_birth)
+            .insert(&app.pool)
+            .await;
+    }
+
+    let response = app
+        .client()
+        .post("/api/v1/person/check-duplicates")
+        .as_user(&["read"])
+        .json(&json!({
+            "firstName": "john",
+            "familyName": "smith",
+            "dateOfBirth": "1980-01-01",
+        }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let duplicates: Vec<Value> = response.json();
+    let found: Vec<_> = duplicates
+        .iter()
+        .map(|d| {
+            (
+                d["firstName"].as_str().unwrap(),
+                d["reason"].as_str().unwrap(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        found,
+        [
+            ("John", "sameNameAndDateOfBirth"),
+            ("Jon", "similarNameSameDateOfBirth"),
+            ("John", "similarName"),
+        ]
+    );
+    let people: i64 = sqlx::query_scalar("SELECT count(*) FROM person")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(people, 4, "Checking for duplicates doesn't create anyone");
+}
+
+#[tokio::test]
+async fn list_people_streams_every_person() {
+    let app = TestApp::new().await;
+
+    let response = app.client().get("/api/v1/person").as_user(&["read"]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body, serde_json::json!([]));
+
+    for _ in 0..150 {
+        PersonFactory::default().insert(&app.pool).await;
+    }
+
+    let response = app
+        .client()
+        .get("/api/v1/person?limit=150")
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/json");
+
+    let body: Value = response.json();
+    let people = body.as_array().unwrap();
+    assert_eq!(people.len(), 150);
+    assert!(people.iter().all(|person| person["id"].is_string()));
+}
+
+#[tokio::test]
+async fn every_person_can_be_streamed_as_ndjson() {
+    let app = TestApp::new().await;
+    let ndjson = |uri: &'static str| {
+        app.client()
+            .get(uri)
+            .as_user(&["read"])
+            .header(ACCEPT, "application/x-ndjson")
+    };
+
+    let response = ndjson("/api/v1/person").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text(), "");
+
+    for _ in 0..1_050 {
+        PersonFactory::default()
+            .with_family_name("Lovelace")
+            .insert(&app.pool)
+            .await;
+    }
+    PersonFactory::default()
+        .with_family_name("Hopper")
+        .insert(&app.pool)
+        .await;
+
+    let response = ndjson("/api/v1/person").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+    assert_eq!(response.header(TOTAL_COUNT), "1051");
+
+    let people: Vec<Value> = response
+        .text()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(people.len(), 1_051, "Should not be limited unless asked");
+
+    let response = ndjson("/api/v1/person?sort=family_name&limit=2").await;
+    let names: Vec<_> = response
+        .text()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap()["familyName"].clone())
+        .collect();
+    assert_eq!(names, [json!("Hopper"), json!("Lovelace")]);
+}
+
+#[tokio::test]
+async fn list_people_is_limited() {
+    let app = TestApp::new().await;
+
+    for _ in 0..105 {
+        PersonFactory::default().insert(&app.pool).await;
+    }
+
+    let response = app.client().get("/api/v1/person").as_user(&["read"]).await;
+    let body: Value = response.json();
+    assert_eq!(body.as_array().unwrap().len(), 100, "Should default to 100");
+
+    let response = app
+        .client()
+        .get("/api/v1/person?limit=10")
+        .as_user(&["read"])
+        .await;
+    let body: Value = response.json();
+    assert_eq!(body.as_array().unwrap().len(), 10);
+}
+
+#[tokio::test]
+async fn people_can_be_paged_by_offset() {
+    let app = TestApp::new().await;
+
+    for i in 0..5 {
+        PersonFactory::default()
+            .with_first_name(&format!("Person {i}"))
+            .insert(&app.pool)
+            .await;
+    }
+
+    let response = app
+        .client()
+        .get("/api/v1/person?offset=3&limit=10")
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.header(TOTAL_COUNT), "5");
+
+    let people: Vec<Value> = response.json();
+    let names: Vec<_> = people.iter().map(|p| &p["firstName"]).collect();
+    assert_eq!(names, ["Person 3", "Person 4"]);
+
+    let filtered = app
+        .client()
+        .get("/api/v1/person?filter=firstName==%22Person%201%22&offset=0")
+        .as_user(&["read"])
+        .await;
+    assert_eq!(filtered.header(TOTAL_COUNT), "1");
+
+    let negative = app
+        .client()
+        .get("/api/v1/person?offset=-1")
+        .as_user(&["read"])
+        .await;
+    assert_eq!(negative.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn people_can_be_paged_by_range() {
+    let app = TestApp::new().await;
+
+    for i in 0..5 {
+        PersonFactory::default()
+            .with_first_name(&format!("Person {i}"))
+            .insert(&app.pool)
+            .await;
+    }
+
+    let response = app
+        .client()
+        .get("/api/v1/person")
+        .as_user(&["read"])
+        .header(RANGE, "items=1-2")
+        .await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.header(CONTENT_RANGE), "items 1-2/5");
+
+    let people: Vec<Value> = response.json();
+    let names: Vec<_> = people.iter().map(|p| &p["firstName"]).collect();
+    assert_eq!(names, ["Person 1", "Person 2"]);
+
+    let rest = app
+        .client()
+        .get("/api/v1/person")
+        .as_user(&["read"])
+        .header(RANGE, "items=3-")
+        .await;
+    assert_eq!(rest.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(rest.header(CONTENT_RANGE), "items 3-4/5");
+
+    let beyond = app
+        .client()
+        .get("/api/v1/person")
+        .as_user(&["read"])
+        .header(RANGE, "items=5-9")
+        .await;
+    assert_eq!(beyond.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(beyond.header(CONTENT_RANGE), "items */5");
+}
+
+#[tokio::test]
+async fn every_person_can_be_walked_with_cursors() {
+    let app = TestApp::new().await;
+
+    for i in 0..5 {
+        PersonFactory::default()
+            .with_first_name(&format!("Person {i}"))
+            .insert(&app.pool)
+            .await;
+    }
+
+    let mut names = vec![];
+    let mut cursor = String::new();
+    loop {
+        let response = app
+            .client()
+            .get(&format!("/api/v1/person?limit=2&cursor={cursor}"))
+            .as_user(&["read"])
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let next = response
+            .headers()
+            .get(NEXT_CURSOR)
+            .map(|next| next.to_str().unwrap().to_owned());
+        let people: Vec<Value> = response.json();
+        names.extend(people.iter().map(|p| p["firstName"].clone()));
+
+        match next {
+            Some(next) => cursor = next,
+            None => break,
+        }
+    }
+
+    assert_eq!(
+        names,
+        ["Person 0", "Person 1", "Person 2", "Person 3", "Person 4"]
+    );
+
+    for uri in [
+        "/api/v1/person?cursor=nonsense",
+        "/api/v1/person?cursor=&sort=family_name",
+    ] {
+        let response = app.client().get(uri).as_user(&["read"]).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn lists_without_a_range_advertise_them() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .client()
+        .get("/api/v1/person")
+        .as_user(&["read"])
+        .header(RANGE, "bytes=0-99")
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.header(ACCEPT_RANGES), "items");
+}
+
+#[tokio::test]
+async fn people_can_be_filtered() {
+    let app = TestApp::new().await;
+
+    for (first_name, family_name, born) in [
+        ("Ada", "Smith", date!(1985 - 12 - 10)),
+        ("Grace", "Smith", date!(1976 - 12 - 09)),
+        ("Alan", "Turing", date!(1992 - 06 - 23)),
+    ] {
+        PersonFactory::default()
+            .with_first_name(first_name)
+            .with_family_name(family_name)
+            .with_date_of_birth(born)
+            .insert(&app.pool)
+            .await;
+    }
+
+    let names = |uri: &'static str| {
+        let client = app.client();
+        async move {
+            let response = client.get(uri).as_user(&["read"]).await;
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            let people: Vec<Value> = response.json();
+            people
+                .iter()
+                .map(|p| p["firstName"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(
+        names("/api/v1/person?filter=familyName==Smith;dateOfBirth=ge=1980-01-01").await,
+        ["Ada"]
+    );
+    assert_eq!(
+        names("/api/v1/person?filter=firstName==a*,dateOfBirth=lt=1980-01-01").await,
+        ["Ada", "Grace", "Alan"]
+    );
+    assert_eq!(
+        names("/api/v1/person?filter=familyName=out=(Smith)&expand=address").await,
+        ["Alan"]
+    );
+
+    // values are bound rather than written into the query
+    assert!(
+        names("/api/v1/person?filter=familyName==%22Smith'%20OR%20'1'='1%22")
+            .await
+            .is_empty()
+    );
+
+    let range = app
+        .client()
+        .get("/api/v1/person?filter=familyName==Smith")
+        .as_user(&["read"])
+        .header(RANGE, "items=1-")
+        .await;
+    assert_eq!(range.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(range.header(CONTENT_RANGE), "items 1-1/2");
+}
+
+#[tokio::test]
+async fn people_can_be_counted_without_listing_them() {
+    let app = TestApp::new().await;
+
+    for (first_name, family_name) in [("Ada", "Smith"), ("Grace", "Smith"), ("Alan", "Turing")] {
+        PersonFactory::default()
+            .with_first_name(first_name)
+            .with_family_name(family_name)
+            .insert(&app.pool)
+            .await;
+    }
+
+    let count = |uri: &'static str| {
+        let client = app.client();
+        async move {
+            let response = client.get(uri).as_user(&["read"]).await;
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            response.json::<Value>()
+        }
+    };
+
+    assert_eq!(count("/api/v1/person/count").await, json!({"count": 3}));
+    assert_eq!(
+        count("/api/v1/person/count?filter=familyName==Smith").await,
+        json!({"count": 2})
+    );
+    assert_eq!(
+        count("/api/v1/person/count?first_name=al&filter=familyName==Smith").await,
+        json!({"count": 0})
+    );
+
+    let response = app
+        .client()
+        .get("/api/v1/person/count?filter=height==2")
+        .as_user(&["read"])
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn only_the_fields_asked_for_are_listed() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let expected = json!([{
+        "id": person.uuid,
+        "firstName": person.first_name,
+        "familyName": person.family_name,
+    }]);
+
+    for uri in [
+        "/api/v1/person?fields=firstName,familyName",
+        "/api/v1/person?fields=firstName,familyName&sort=created",
+        "/api/v1/person?fields=firstName,familyName,address&expand=address",
+    ] {
+        let response = app.client().get(uri).as_user(&["read"]).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        assert_eq!(response.json::<Value>(), expected, "{uri}");
+    }
+
+    let response = app
+        .client()
+        .get("/api/v1/person?fields=firstName,height")
+        .as_user(&["read"])
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn people_can_be_found_by_their_names() {
+    let app = TestApp::new().await;
+
+    for (first_name, family_name) in [
+        ("Joanna", "Smith"),
+        ("Joseph", "Smithson"),
+        ("Jo", "Jones"),
+        ("Mary", "Smith"),
+        ("Jo_e", "O'Brien"),
+    ] {
+        PersonFactory::default()
+            .with_first_name(first_name)
+            .with_family_name(family_name)
+            .insert(&app.pool)
+            .await;
+    }
+
+    let names = |uri: &'static str| {
+        let client = app.client();
+        async move {
+            let response = client.get(uri).as_user(&["read"]).await;
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            let people: Vec<Value> = response.json();
+            people
+                .iter()
+                .map(|p| p["firstName"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(
+        names("/api/v1/person?family_name=Smith&first_name=jo").await,
+        ["Joanna", "Joseph"]
+    );
+    assert_eq!(
+        names("/api/v1/person?family_name=smith&filter=firstName==Mary").await,
+        ["Mary"]
+    );
+    // the pattern's own characters are matched literally
+    assert_eq!(names("/api/v1/person?first_name=Jo_").await, ["Jo_e"]);
+
+    let empty = app
+        .client()
+        .get("/api/v1/person?family_name=")
+        .as_user(&["read"])
+        .await;
+    assert_eq!(empty.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn people_can_be_sorted() {
+    let app = TestApp::new().await;
+
+    for (first_name, family_name) in [
+        ("Ada", "Smith"),
+        ("Grace", "Hopper"),
+        ("Alan", "Turing"),
+        ("Mary", "Smith"),
+    ] {
+        PersonFactory::default()
+            .with_first_name(first_name)
+            .with_family_name(family_name)
+            .insert(&app.pool)
+            .await;
+    }
+
+    let names = |uri: &'static str| {
+        let client = app.client();
+        async move {
+            let response = client.get(uri).as_user(&["read"]).await;
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            let people: Vec<Value> = response.json();
+            people
+                .iter()
+                .map(|p| p["firstName"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(
+        names("/api/v1/person?sort=family_name").await,
+        ["Grace", "Ada", "Mary", "Alan"]
+    );
+    assert_eq!(
+        names("/api/v1/person?sort=family_name,-created").await,
+        ["Grace", "Mary", "Ada", "Alan"]
+    );
+    assert_eq!(
+        names("/api/v1/person?sort=-family_name&offset=1&limit=2").await,
+        ["Ada", "Mary"]
+    );
+
+    let unknown = app
+        .client()
+        .get("/api/v1/person?sort=address")
+        .as_user(&["read"])
+        .await;
+    assert_eq!(unknown.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn filters_on_unknown_fields_are_rejected() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .client()
+        .get("/api/v1/person?filter=address==Anywhere")
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body: Value = response.json();
+    assert!(body["message"].as_str().unwrap().contains("address"));
+}
+
+#[tokio::test]
+async fn people_can_be_queried_with_odata_options() {
+    let app = TestApp::new().await;
+
+    for (first_name, family_name, born) in [
+        ("Ada", "Smith", date!(1985 - 12 - 10)),
+        ("Grace", "Smith", date!(1976 - 12 - 09)),
+        ("Alan", "Turing", date!(1992 - 06 - 23)),
+        ("Brian", "Smithson", date!(1988 - 03 - 01)),
+    ] {
+        PersonFactory::default()
+            .with_first_name(first_name)
+            .with_family_name(family_name)
+            .with_date_of_birth(born)
+            .insert(&app.pool)
+            .await;
+    }
+
+    let get = |uri: &'static str| {
+        let client = app.client();
+        async move {
+            let response = client.get(uri).as_user(&["read"]).await;
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            response.json::<Value>()
+        }
+    };
+    let names = |people: &Value| -> Vec<String> {
+        people
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["firstName"].as_str().unwrap().to_owned())
+            .collect()
+    };
+
+    let people = get(
+        "/api/v1/person?$filter=familyName%20eq%20'Smith'%20and%20dateOfBirth%20ge%201980-01-01",
+    )
+    .await;
+    assert_eq!(names(&people), ["Ada"]);
+
+    let people =
+        get("/api/v1/person?$filter=startswith(familyName,'smith')&$orderby=dateOfBirth%20desc")
+            .await;
+    assert_eq!(names(&people), ["Brian", "Ada", "Grace"]);
+
+    let people = get("/api/v1/person?$orderby=firstName&$skip=1&$top=2").await;
+    assert_eq!(names(&people), ["Alan", "Brian"]);
+
+    let counted =
+        get("/api/v1/person?$filter=not%20(familyName%20eq%20'Turing')&$top=1&$count=true").await;
+    assert_eq!(counted["@odata.count"], 3);
+    assert_eq!(names(&counted["value"]), ["Ada"]);
+}
+
+#[tokio::test]
+async fn invalid_odata_options_are_rejected() {
+    let app = TestApp::new().await;
+
+    for uri in [
+        "/api/v1/person?$filter=password%20eq%20'secret'",
+        "/api/v1/person?$orderby=password",
+        "/api/v1/person?$top=0",
+        "/api/v1/person?$skip=-1",
+    ] {
+        let response = app.client().get(uri).as_user(&["read"]).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn invalid_query_parameters_are_rejected_with_field_errors() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .client()
+        .get("/api/v1/person?limit=0")
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body: Value = response.json();
+    assert_eq!(body["errors"]["limit"][0]["code"], "range");
+
+    let response = app
+        .client()
+        .get("/api/v1/person?limit=lots")
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body: Value = response.json();
+    assert!(body["message"].is_string(), "Should be a JSON error");
+}
+
+#[tokio::test]
+async fn people_can_be_expanded_with_their_address() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default()
+        .with_address(AddressFactory::default())
+        .insert(&app.pool)
+        .await;
+    PersonFactory::default().insert(&app.pool).await;
+
+    let response = app
+        .client()
+        .get(&format!("/api/v1/person/{}?expand=address", person.uuid))
+        .as_user(&["read"])
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = response.json();
+    assert_eq!(body["id"], person.uuid.to_string());
+    let address = person.address.unwrap().uuid.to_string();
+    assert_eq!(body["address"]["id"], address);
+
+    let response = app
+        .client()
+        .get("/api/v1/person?expand=address")
+        .as_user(&["read"])
+        .await;
+    let body: Value = response.json();
+    let people = body.as_array().unwrap();
+
+    assert_eq!(people.len(), 2);
+    assert_eq!(
+        people.iter().filter(|p| p.get("address").is_some()).count(),
+        1,
+        "Only people with an address should have one"
+    );
+
+    let response = app
+        .client()
+        .get(&format!("/api/v1/person/{}?include=address", person.uuid))
+        .as_user(&["read"])
+        .await;
+    let body: Value = response.json();
+
+    assert_eq!(
+        body["address"]["id"], address,
+        "include is the same as expand"
+    );
+
+    let response = app
+        .client()
+        .get("/api/v1/person?expand=everything")
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn expanding_addresses_takes_a_single_query() {
+    let app = TestApp::new().await;
+    PersonFactory::default()
+        .with_address(AddressFactory::default())
+        .insert(&app.pool)
+        .await;
+
+    // the first request may also open connections
+    queries_for(&app, "/api/v1/person?expand=address").await;
+
+    let one_person = queries_for(&app, "/api/v1/person?expand=address").await;
+
+    for _ in 0..10 {
+        PersonFactory::default()
+            .with_address(AddressFactory::default())
+            .insert(&app.pool)
+            .await;
+    }
+
+    let many_people = queries_for(&app, "/api/v1/person?expand=address").await;
+
+    assert_eq!(one_person, 1, "Listing should take one query");
+    assert_eq!(
+        many_people, one_person,
+        "Queries should not grow with the number of people"
+    );
+}
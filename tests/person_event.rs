@@ -0,0 +1,151 @@
+mod common;
+
+use std::env;
+
+use axum::http::{header::IF_MATCH, StatusCode};
+use common::{auth::CLIENT, factories::PersonFactory, TestApp};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// Every test here runs with event sourcing enabled, which is why they have a binary to
+/// themselves
+fn event_sourcing() {
+    env::set_var("PERSON_EVENT_SOURCING", "true");
+}
+
+async fn create(app: &TestApp) -> String {
+    let person: Value = app
+        .client()
+        .post("/api/v1/person")
+        .as_user(&["write"])
+        .json(&json!({
+            "first_name": "Ada",
+            "family_name": "Byron",
+            "date_of_birth": "1815-12-10",
+        }))
+        .await
+        .json();
+
+    person["id"].as_str().unwrap().to_owned()
+}
+
+#[tokio::test]
+async fn changes_to_people_are_recorded_in_their_stream() {
+    event_sourcing();
+    let app = TestApp::new().await;
+    let client = app.client();
+    let id = create(&app).await;
+
+    client
+        .patch(&format!("/api/v1/person/{id}"))
+        .as_user(&["write"])
+        .header(IF_MATCH, r#""1""#)
+        .json(&json!({ "familyName": "Lovelace" }))
+        .await;
+    client
+        .delete(&format!("/api/v1/person/{id}"))
+        .as_user(&["write"])
+        .header(IF_MATCH, r#""2""#)
+        .await;
+
+    let response = client
+        .get(&format!("/api/v1/person/{id}/events"))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(
+        response.status(),
+        StatusCode::OK,
+        "Events should outlive the person"
+    );
+
+    let events: Value = response.json();
+    let types: Vec<_> = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| (event["version"].clone(), event["type"].clone()))
+        .collect();
+    assert_eq!(
+        types,
+        [
+            (json!(1), json!("person.created")),
+            (json!(2), json!("person.updated")),
+            (json!(3), json!("person.deleted")),
+        ]
+    );
+    assert_eq!(events[1]["person"]["familyName"], "Lovelace");
+    assert_eq!(events[1]["actor"], CLIENT);
+    assert_eq!(events[2]["person"], Value::Null);
+}
+
+#[tokio::test]
+async fn people_can_be_rebuilt_from_their_stream() {
+    event_sourcing();
+    let app = TestApp::new().await;
+    let client = app.client();
+    let id = create(&app).await;
+
+    sqlx::query("UPDATE person SET first_name = 'Augusta' WHERE uuid = $1")
+        .bind(Uuid::parse_str(&id).unwrap())
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let response = client
+        .post(&format!("/api/v1/admin/person/{id}/replay"))
+        .as_user(&["admin"])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let person: Value = client
+        .get(&format!("/api/v1/person/{id}"))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(person["firstName"], "Ada");
+
+    sqlx::query("INSERT INTO person_event (person, version, event_type, actor) VALUES ($1, 2, 'person.deleted', 'test')")
+        .bind(Uuid::parse_str(&id).unwrap())
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let response = client
+        .post(&format!("/api/v1/admin/person/{id}/replay"))
+        .as_user(&["admin"])
+        .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client
+        .get(&format!("/api/v1/person/{id}"))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn people_without_events_cannot_be_replayed() {
+    event_sourcing();
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    let events: Value = client
+        .get(&format!("/api/v1/person/{}/events", person.uuid))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(events, json!([]));
+
+    let response = client
+        .post(&format!("/api/v1/admin/person/{}/replay", person.uuid))
+        .as_user(&["admin"])
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client
+        .get(&format!("/api/v1/person/{}/events", Uuid::new_v4()))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
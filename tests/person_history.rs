@@ -0,0 +1,234 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::http::{header::IF_MATCH, StatusCode};
+use common::{auth::CLIENT, TestApp};
+use rust_web_app::clock::Clock;
+use serde_json::{json, Value};
+use time::{macros::datetime, OffsetDateTime};
+use uuid::Uuid;
+
+/// A clock only moving when told to
+struct SettableClock(Mutex<OffsetDateTime>);
+
+impl Clock for SettableClock {
+    fn now(&self) -> OffsetDateTime {
+        *self.0.lock().unwrap()
+    }
+}
+
+#[tokio::test]
+async fn every_change_to_a_person_is_recorded_in_their_history() {
+    let app = TestApp::new().await;
+    let client = app.client();
+
+    let person: Value = client
+        .post("/api/v1/person")
+        .as_user(&["write"])
+        .json(&json!({
+            "firstName": "Ada",
+            "familyName": "Byron",
+            "dateOfBirth": "1815-12-10",
+        }))
+        .await
+        .json();
+    let uri = format!("/api/v1/person/{}", person["id"].as_str().unwrap());
+
+    client
+        .patch(&uri)
+        .as_user(&["write"])
+        .header(IF_MATCH, r#""1""#)
+        .json(&json!({ "familyName": "Lovelace" }))
+        .await;
+    client
+        .delete(&uri)
+        .as_user(&["write"])
+        .header(IF_MATCH, r#""2""#)
+        .await;
+
+    let response = client
+        .get(&format!("{uri}/history"))
+        .as_user(&["read"])
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let history: Value = response.json();
+    let history = history.as_array().unwrap();
+    let types: Vec<_> = history.iter().map(|entry| &entry["type"]).collect();
+
+    assert_eq!(
+        types,
+        ["person.created", "person.updated", "person.deleted"],
+        "The history is kept after the person is deleted"
+    );
+    assert!(history.iter().all(|entry| entry["changedBy"] == CLIENT));
+    assert_eq!(
+        history
+            .iter()
+            .map(|entry| &entry["revision"])
+            .collect::<Vec<_>>(),
+        [1, 2, 3]
+    );
+    assert_eq!(
+        history[0]["changes"][1],
+        json!({ "field": "familyName", "oldValue": null, "newValue": "Byron" })
+    );
+    assert_eq!(
+        history[1]["changes"],
+        json!([{ "field": "familyName", "oldValue": "Byron", "newValue": "Lovelace" }]),
+        "Only the changed fields are given"
+    );
+    assert_eq!(
+        history[2]["changes"][0],
+        json!({ "field": "firstName", "oldValue": "Ada", "newValue": null })
+    );
+}
+
+#[tokio::test]
+async fn people_can_be_retrieved_as_they_were() {
+    let clock = Arc::new(SettableClock(Mutex::new(datetime!(2020-01-01 0:00 UTC))));
+    let app = TestApp::with_clock(clock.clone()).await;
+    let client = app.client();
+    let person: Value = client
+        .post("/api/v1/person")
+        .as_user(&["write"])
+        .json(&json!({
+            "firstName": "Ada",
+            "familyName": "Byron",
+            "dateOfBirth": "1815-12-10",
+        }))
+        .await
+        .json();
+    let id = person["id"].as_str().unwrap();
+
+    *clock.0.lock().unwrap() = datetime!(2021-01-01 0:00 UTC);
+    client
+        .patch(&format!("/api/v1/person/{id}"))
+        .as_user(&["write"])
+        .header(IF_MATCH, r#""1""#)
+        .json(&json!({ "familyName": "Lovelace" }))
+        .await;
+
+    *clock.0.lock().unwrap() = datetime!(2022-01-01 0:00 UTC);
+    client
+        .delete(&format!("/api/v1/person/{id}"))
+        .as_user(&["write"])
+        .header(IF_MATCH, r#""2""#)
+        .await;
+
+    let as_of = |time: &str| {
+        client
+            .get(&format!("/api/v1/person/{id}?as_of={time}"))
+            .as_user(&["read"])
+    };
+
+    let person: Value = as_of("2020-06-01T00:00:00Z").await.json();
+    assert_eq!(person["familyName"], "Byron");
+
+    let person: Value = as_of("2021-01-01T00:00:00Z").await.json();
+    assert_eq!(person["familyName"], "Lovelace");
+
+    for (time, reason) in [
+        ("2019-01-01T00:00:00Z", "Before they were created"),
+        ("2023-01-01T00:00:00Z", "After they were deleted"),
+    ] {
+        assert_eq!(
+            as_of(time).await.status(),
+            StatusCode::NOT_FOUND,
+            "{reason}"
+        );
+    }
+
+    let response = client
+        .get(&format!(
+            "/api/v1/person/{id}?as_of=2021-01-01T00:00:00Z&expand=address"
+        ))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn revisions_can_be_compared_field_by_field() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person: Value = client
+        .post("/api/v1/person")
+        .as_user(&["write"])
+        .json(&json!({
+            "firstName": "Ada",
+            "familyName": "Byron",
+            "dateOfBirth": "1815-12-10",
+        }))
+        .await
+        .json();
+    let id = person["id"].as_str().unwrap();
+
+    client
+        .patch(&format!("/api/v1/person/{id}"))
+        .as_user(&["write"])
+        .header(IF_MATCH, r#""1""#)
+        .json(&json!({ "familyName": "Lovelace" }))
+        .await;
+    client
+        .patch(&format!("/api/v1/person/{id}"))
+        .as_user(&["write"])
+        .header(IF_MATCH, r#""2""#)
+        .json(&json!({ "firstName": "Augusta" }))
+        .await;
+
+    let diff: Value = client
+        .get(&format!("/api/v1/person/{id}/history/1/diff/3"))
+        .as_user(&["read"])
+        .await
+        .json();
+    let changes: Vec<_> = diff["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|change| {
+            (
+                change["field"].clone(),
+                change["oldValue"].clone(),
+                change["newValue"].clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            (json!("firstName"), json!("Ada"), json!("Augusta")),
+            (json!("familyName"), json!("Byron"), json!("Lovelace")),
+        ]
+    );
+    assert_eq!(diff["changes"][0]["changedBy"], CLIENT);
+
+    let diff: Value = client
+        .get(&format!("/api/v1/person/{id}/history/3/diff/2"))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(diff["changes"][0]["oldValue"], "Augusta");
+    assert_eq!(diff["changes"][0]["newValue"], "Ada");
+
+    let response = client
+        .get(&format!("/api/v1/person/{id}/history/1/diff/4"))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn history_of_unknown_people_is_not_found() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .client()
+        .get(&format!("/api/v1/person/{}/history", Uuid::new_v4()))
+        .as_user(&["read"])
+        .await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
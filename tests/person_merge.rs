@@ -0,0 +1,399 @@
+mod common;
+
+use axum::http::{header::CONTENT_TYPE, StatusCode};
+use common::{
+    factories::{AddressFactory, EmergencyContactFactory, EmploymentFactory, PersonFactory},
+    TestApp,
+};
+use serde_json::{json, Value};
+use time::macros::date;
+
+#[tokio::test]
+async fn duplicates_are_merged_into_the_person_kept() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let target = PersonFactory::default().insert(&app.pool).await;
+    let source = PersonFactory::default()
+        .with_address(AddressFactory::default())
+        .insert(&app.pool)
+        .await;
+    EmploymentFactory::default()
+        .insert(&app.pool, source.uuid)
+        .await;
+    EmergencyContactFactory::default()
+        .insert(&app.pool, source.uuid)
+        .await;
+    client
+        .post(&format!("/api/v1/person/{}/notes", source.uuid))
+        .as_user(&["write"])
+        .json(&json!({ "body": "Registered twice" }))
+        .await;
+
+    let response = client
+        .post(&format!(
+            "/api/v1/person/{}/merge/{}",
+            target.uuid, source.uuid
+        ))
+        .as_user(&["write"])
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let merge: Value = response.json();
+    assert_eq!(merge["targetId"], json!(target.uuid));
+    assert_eq!(merge["sourceId"], json!(source.uuid));
+    assert_eq!(merge["addressMoved"], true);
+    assert_eq!(merge["employmentsMoved"], 1);
+    assert_eq!(merge["emergencyContactsMoved"], 1);
+    assert_eq!(merge["consentsMoved"], 0);
+    assert_eq!(merge["notesMoved"], 1);
+
+    let response = client
+        .get(&format!("/api/v1/person/{}", source.uuid))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(
+        response.status(),
+        StatusCode::NOT_FOUND,
+        "The source is deleted"
+    );
+
+    let response = client
+        .get(&format!("/api/v1/person/{}?expand=address", target.uuid))
+        .as_user(&["read"])
+        .await;
+    let person: Value = response.json();
+    assert_eq!(person["address"]["id"], json!(source.address.unwrap().uuid));
+
+    let employments: Vec<Value> = client
+        .get(&format!("/api/v1/person/{}/employments", target.uuid))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(employments.len(), 1);
+
+    let notes: Vec<Value> = client
+        .get(&format!("/api/v1/person/{}/notes", target.uuid))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(notes.len(), 1);
+
+    let recorded: i64 = sqlx::query_scalar("SELECT count(*) FROM person_merge WHERE target = $1")
+        .bind(target.uuid)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(recorded, 1);
+}
+
+#[tokio::test]
+async fn only_contact_details_the_person_kept_lacks_are_moved() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let target = PersonFactory::default().insert(&app.pool).await;
+    let source = PersonFactory::default().insert(&app.pool).await;
+    for (person, email) in [
+        (target.uuid, "ada@example.com"),
+        (source.uuid, "ADA@example.com"),
+        (source.uuid, "ada@work.example.com"),
+    ] {
+        let response = client
+            .post(&format!("/api/v1/person/{person}/contacts"))
+            .as_user(&["write"])
+            .json(&json!({"type": "email", "value": email}))
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    let merge: Value = client
+        .post(&format!(
+            "/api/v1/person/{}/merge/{}",
+            target.uuid, source.uuid
+        ))
+        .as_user(&["write"])
+        .await
+        .json();
+    assert_eq!(merge["contactDetailsMoved"], 1);
+
+    let details: Vec<Value> = client
+        .get(&format!("/api/v1/person/{}/contacts", target.uuid))
+        .as_user(&["read"])
+        .await
+        .json();
+    let emails: Vec<_> = details.iter().map(|detail| &detail["value"]).collect();
+    assert_eq!(
+        emails,
+        [&json!("ada@example.com"), &json!("ada@work.example.com")]
+    );
+}
+
+#[tokio::test]
+async fn only_tags_the_person_kept_lacks_are_moved() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let target = PersonFactory::default().insert(&app.pool).await;
+    let source = PersonFactory::default().insert(&app.pool).await;
+    for (person, tag) in [
+        (target.uuid, "cohort a"),
+        (source.uuid, "cohort a"),
+        (source.uuid, "cohort b"),
+    ] {
+        client
+            .post(&format!("/api/v1/person/{person}/tags"))
+            .as_user(&["write"])
+            .json(&json!({ "tag": tag }))
+            .await;
+    }
+
+    let merge: Value = client
+        .post(&format!(
+            "/api/v1/person/{}/merge/{}",
+            target.uuid, source.uuid
+        ))
+        .as_user(&["write"])
+        .await
+        .json();
+    assert_eq!(merge["tagsMoved"], 1);
+
+    let tags: Value = client
+        .get(&format!("/api/v1/person/{}/tags", target.uuid))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(tags, json!(["cohort a", "cohort b"]));
+}
+
+#[tokio::test]
+async fn only_relationships_the_person_kept_lacks_are_moved() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let target = PersonFactory::default().insert(&app.pool).await;
+    let source = PersonFactory::default().insert(&app.pool).await;
+    let parent = PersonFactory::default().insert(&app.pool).await;
+    let spouse = PersonFactory::default().insert(&app.pool).await;
+    let child = PersonFactory::default().insert(&app.pool).await;
+    for (person, related, relationship_type) in [
+        (target.uuid, parent.uuid, "parent"),
+        (source.uuid, parent.uuid, "parent"),
+        (spouse.uuid, source.uuid, "spouse"),
+        (child.uuid, source.uuid, "parent"),
+        (source.uuid, target.uuid, "sibling"),
+    ] {
+        let response = client
+            .post(&format!("/api/v1/person/{person}/relationships"))
+            .as_user(&["write"])
+            .json(&json!({ "relatedId": related, "type": relationship_type }))
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    let merge: Value = client
+        .post(&format!(
+            "/api/v1/person/{}/merge/{}",
+            target.uuid, source.uuid
+        ))
+        .as_user(&["write"])
+        .await
+        .json();
+    assert_eq!(merge["relationshipsMoved"], 2);
+
+    let relationships: Vec<Value> = client
+        .get(&format!("/api/v1/person/{}/relationships", target.uuid))
+        .as_user(&["read"])
+        .await
+        .json();
+    let links: Vec<_> = relationships
+        .iter()
+        .map(|r| (&r["personId"], &r["relatedId"], &r["type"]))
+        .collect();
+    assert_eq!(
+        links,
+        [
+            (&json!(target.uuid), &json!(parent.uuid), &json!("parent")),
+            (&json!(spouse.uuid), &json!(target.uuid), &json!("spouse")),
+            (&json!(child.uuid), &json!(target.uuid), &json!("parent")),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn people_whose_relationships_would_form_a_cycle_arent_merged() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let target = PersonFactory::default().insert(&app.pool).await;
+    let source = PersonFactory::default().insert(&app.pool).await;
+    let child = PersonFactory::default().insert(&app.pool).await;
+    // the target's child is named as the source's parent
+    for (person, related) in [(child.uuid, target.uuid), (source.uuid, child.uuid)] {
+        client
+            .post(&format!("/api/v1/person/{person}/relationships"))
+            .as_user(&["write"])
+            .json(&json!({ "relatedId": related, "type": "parent" }))
+            .await;
+    }
+
+    let response = client
+        .post(&format!(
+            "/api/v1/person/{}/merge/{}",
+            target.uuid, source.uuid
+        ))
+        .as_user(&["write"])
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let relationships: Vec<Value> = client
+        .get(&format!("/api/v1/person/{}/relationships", source.uuid))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(relationships.len(), 1, "Nothing is moved");
+}
+
+#[tokio::test]
+async fn photos_are_moved_when_the_person_kept_has_none() {
+    let app = TestApp::new().await;
+    let client = app.client();
+
+    for (kept_photo, expected) in [(None, "image/png"), (Some("image/jpeg"), "image/jpeg")] {
+        let target = PersonFactory::default().insert(&app.pool).await;
+        let source = PersonFactory::default().insert(&app.pool).await;
+        for (person, content_type) in [(source.uuid, Some("image/png")), (target.uuid, kept_photo)]
+        {
+            if let Some(content_type) = content_type {
+                sqlx::query(
+                    "INSERT INTO person_photo (person, content_type, content, uploaded_by) \
+                     VALUES ($1, $2, 'photo', 'test')",
+                )
+                .bind(person)
+                .bind(content_type)
+                .execute(&app.pool)
+                .await
+                .unwrap();
+            }
+        }
+
+        let merge: Value = client
+            .post(&format!(
+                "/api/v1/person/{}/merge/{}",
+                target.uuid, source.uuid
+            ))
+            .as_user(&["write"])
+            .await
+            .json();
+        assert_eq!(merge["photoMoved"], kept_photo.is_none());
+
+        let response = client
+            .get(&format!("/api/v1/person/{}/photo", target.uuid))
+            .as_user(&["read"])
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.header(CONTENT_TYPE), expected);
+    }
+}
+
+#[tokio::test]
+async fn merges_leaving_too_many_emergency_contacts_are_refused() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let target = PersonFactory::default().insert(&app.pool).await;
+    let source = PersonFactory::default().insert(&app.pool).await;
+    for person in [target.uuid, source.uuid] {
+        for _ in 0..3 {
+            EmergencyContactFactory::default()
+                .insert(&app.pool, person)
+                .await;
+        }
+    }
+
+    let response = client
+        .post(&format!(
+            "/api/v1/person/{}/merge/{}",
+            target.uuid, source.uuid
+        ))
+        .as_user(&["write"])
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = client
+        .get(&format!("/api/v1/person/{}", source.uuid))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK, "The source is kept");
+
+    let contacts: Vec<Value> = client
+        .get(&format!(
+            "/api/v1/person/{}/emergency-contacts",
+            target.uuid
+        ))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(contacts.len(), 3);
+}
+
+#[tokio::test]
+async fn merges_leaving_overlapping_employment_are_refused() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let target = PersonFactory::default().insert(&app.pool).await;
+    let source = PersonFactory::default().insert(&app.pool).await;
+    EmploymentFactory::default()
+        .with_dates(date!(2015 - 01 - 01), Some(date!(2019 - 12 - 31)))
+        .insert(&app.pool, target.uuid)
+        .await;
+    EmploymentFactory::default()
+        .with_dates(date!(2019 - 12 - 31), None)
+        .insert(&app.pool, source.uuid)
+        .await;
+
+    let response = client
+        .post(&format!(
+            "/api/v1/person/{}/merge/{}",
+            target.uuid, source.uuid
+        ))
+        .as_user(&["write"])
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let employments: Vec<Value> = client
+        .get(&format!("/api/v1/person/{}/employments", target.uuid))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(employments.len(), 1, "Nothing is moved");
+
+    // employment one after the other is moved
+    sqlx::query("UPDATE person_employment SET start_date = '2020-01-01' WHERE person = $1")
+        .bind(source.uuid)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let merge: Value = client
+        .post(&format!(
+            "/api/v1/person/{}/merge/{}",
+            target.uuid, source.uuid
+        ))
+        .as_user(&["write"])
+        .await
+        .json();
+    assert_eq!(merge["employmentsMoved"], 1);
+}
+
+#[tokio::test]
+async fn people_cant_be_merged_into_themselves() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    let response = app
+        .client()
+        .post(&format!(
+            "/api/v1/person/{}/merge/{}",
+            person.uuid, person.uuid
+        ))
+        .as_user(&["write"])
+        .await;
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use axum::http::{
+    header::{HeaderName, AUTHORIZATION, LOCATION},
+    StatusCode,
+};
+use common::{
+    auth::{TestClaims, CLIENT},
+    factories::PersonFactory,
+    TestApp,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+const TOTAL_COUNT: HeaderName = HeaderName::from_static("x-total-count");
+
+#[tokio::test]
+async fn notes_are_listed_newest_first_a_page_at_a_time() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let notes = format!("/api/v1/person/{}/notes", person.uuid);
+
+    let response = client
+        .post(&notes)
+        .as_user(&["write"])
+        .json(&json!({ "body": "First visit\nAll well" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let first: Value = response.json();
+    assert_eq!(first["body"], "First visit\nAll well");
+    assert_eq!(first["author"], CLIENT);
+
+    let response = client
+        .get(response.header(LOCATION))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<Value>(), first);
+
+    let case_worker = TestClaims::with_scopes(&["write"])
+        .for_client("case-worker")
+        .mint();
+    for body in ["Second visit", "Third visit"] {
+        let response = client
+            .post(&notes)
+            .header(AUTHORIZATION, format!("Bearer {case_worker}"))
+            .json(&json!({ "body": body }))
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    let response = client
+        .get(&format!("{notes}?limit=2"))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.header(TOTAL_COUNT), "3");
+
+    let page: Vec<Value> = response.json();
+    let bodies: Vec<_> = page.iter().map(|note| &note["body"]).collect();
+    assert_eq!(bodies, [&json!("Third visit"), &json!("Second visit")]);
+    assert!(page.iter().all(|note| note["author"] == "case-worker"));
+
+    let page: Vec<Value> = client
+        .get(&format!("{notes}?limit=2&offset=2"))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(page, [first]);
+}
+
+#[tokio::test]
+async fn invalid_notes_are_rejected() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    for body in [json!({ "body": "" }), json!({ "body": " \n\t" }), json!({})] {
+        let response = client
+            .post(&format!("/api/v1/person/{}/notes", person.uuid))
+            .as_user(&["write"])
+            .json(&body)
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{body}");
+    }
+
+    let response = client
+        .post(&format!("/api/v1/person/{}/notes", person.uuid))
+        .as_user(&["write"])
+        .json(&json!({ "body": "a".repeat(10_001) }))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = client
+        .post(&format!("/api/v1/person/{}/notes", Uuid::new_v4()))
+        .as_user(&["write"])
+        .json(&json!({ "body": "Nobody to write about" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client
+        .get(&format!("/api/v1/person/{}/notes", Uuid::new_v4()))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use axum::http::{header::CONTENT_TYPE, StatusCode};
+use common::{factories::PersonFactory, TestApp};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// The start of a PNG image, enough for it to be recognised as one
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+/// A form uploading the image as its `photo` field
+fn upload(content_type: &str, image: &[u8]) -> (String, Vec<u8>) {
+    let boundary = "person-photo-boundary";
+    let mut body = format!(
+        "--{boundary}\r\n\
+         Content-Disposition: form-data; name=\"photo\"; filename=\"photo\"\r\n\
+         Content-Type: {content_type}\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(image);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+    (format!("multipart/form-data; boundary={boundary}"), body)
+}
+
+#[tokio::test]
+async fn photos_can_be_uploaded_and_replaced() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let photo = format!("/api/v1/person/{}/photo", person.uuid);
+
+    let response = client.get(&photo).as_user(&["read"]).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let (content_type, body) = upload("image/png", PNG);
+    let response = client
+        .put(&photo)
+        .as_user(&["write"])
+        .header(CONTENT_TYPE, content_type)
+        .body(body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let uploaded: Value = response.json();
+    assert_eq!(uploaded["personId"], person.uuid.to_string());
+    assert_eq!(uploaded["contentType"], "image/png");
+    assert_eq!(uploaded["size"], PNG.len());
+
+    let response = client.get(&photo).as_user(&["read"]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.header(CONTENT_TYPE), "image/png");
+    assert_eq!(response.bytes()[..], *PNG);
+
+    let jpeg = [0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10];
+    let (content_type, body) = upload("image/jpeg", &jpeg);
+    let response = client
+        .put(&photo)
+        .as_user(&["write"])
+        .header(CONTENT_TYPE, content_type)
+        .body(body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.get(&photo).as_user(&["read"]).await;
+    assert_eq!(response.header(CONTENT_TYPE), "image/jpeg");
+    assert_eq!(response.bytes()[..], jpeg);
+}
+
+#[tokio::test]
+async fn only_images_matching_their_type_are_accepted() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let photo = format!("/api/v1/person/{}/photo", person.uuid);
+
+    for (content_type, image, status) in [
+        (
+            "image/gif",
+            b"GIF89a".as_slice(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        ),
+        ("image/jpeg", PNG, StatusCode::BAD_REQUEST),
+        (
+            "image/png",
+            b"not an image".as_slice(),
+            StatusCode::BAD_REQUEST,
+        ),
+    ] {
+        let (form_type, body) = upload(content_type, image);
+        let response = client
+            .put(&photo)
+            .as_user(&["write"])
+            .header(CONTENT_TYPE, form_type)
+            .body(body)
+            .await;
+
+        assert_eq!(response.status(), status, "{content_type}");
+    }
+
+    let response = client.get(&photo).as_user(&["read"]).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn photos_larger_than_allowed_are_refused() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    let mut image = PNG.to_vec();
+    image.resize(5 * 1024 * 1024 + 1, 0);
+    let (content_type, body) = upload("image/png", &image);
+
+    let response = client
+        .put(&format!("/api/v1/person/{}/photo", person.uuid))
+        .as_user(&["write"])
+        .header(CONTENT_TYPE, content_type)
+        .body(body)
+        .await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn photos_cant_be_uploaded_for_unknown_people() {
+    let app = TestApp::new().await;
+    let (content_type, body) = upload("image/png", PNG);
+
+    let response = app
+        .client()
+        .put(&format!("/api/v1/person/{}/photo", Uuid::new_v4()))
+        .as_user(&["write"])
+        .header(CONTENT_TYPE, content_type)
+        .body(body)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
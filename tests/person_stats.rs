@@ -0,0 +1,125 @@
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use common::{
+    factories::{AddressFactory, PersonFactory},
+    TestApp,
+};
+use rust_web_app::clock::FixedClock;
+use serde_json::{json, Value};
+use time::{
+    macros::{date, datetime},
+    Duration, OffsetDateTime,
+};
+
+#[tokio::test]
+async fn people_are_counted_by_age_creation_day_and_address() {
+    let app = TestApp::with_clock(Arc::new(FixedClock(datetime!(2020-06-15 12:00 UTC)))).await;
+
+    let child = PersonFactory::default()
+        .with_date_of_birth(date!(2010 - 01 - 01))
+        .insert(&app.pool)
+        .await;
+    // eighteen tomorrow, so still in the youngest band
+    let almost_adult = PersonFactory::default()
+        .with_date_of_birth(date!(2002 - 06 - 16))
+        .insert(&app.pool)
+        .await;
+    // thirty today
+    let adult = PersonFactory::default()
+        .with_date_of_birth(date!(1990 - 06 - 15))
+        .with_address(AddressFactory::default())
+        .insert(&app.pool)
+        .await;
+    let pensioner = PersonFactory::default()
+        .with_date_of_birth(date!(1950 - 01 - 01))
+        .insert(&app.pool)
+        .await;
+    let deleted = PersonFactory::default()
+        .with_date_of_birth(date!(2000 - 01 - 01))
+        .with_address(AddressFactory::default())
+        .insert(&app.pool)
+        .await;
+
+    for (person, created) in [
+        (child.uuid, datetime!(2020-06-15 00:00 UTC)),
+        (almost_adult.uuid, datetime!(2020-06-15 23:59 UTC)),
+        (adult.uuid, datetime!(2020-05-17 08:00 UTC)),
+        (pensioner.uuid, datetime!(2020-05-16 23:59 UTC)),
+        (deleted.uuid, datetime!(2020-06-15 08:00 UTC)),
+    ] {
+        sqlx::query("UPDATE person SET created = $2 WHERE uuid = $1")
+            .bind(person)
+            .bind(created)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+    }
+    sqlx::query("UPDATE person SET deleted_at = now() WHERE uuid = $1")
+        .bind(deleted.uuid)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .client()
+        .get("/api/v1/person/stats")
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let stats: Value = response.json();
+    assert_eq!(
+        stats["ageBands"],
+        json!([
+            { "minAge": 0, "maxAge": 17, "count": 2 },
+            { "minAge": 18, "maxAge": 24, "count": 0 },
+            { "minAge": 25, "maxAge": 34, "count": 1 },
+            { "minAge": 35, "maxAge": 44, "count": 0 },
+            { "minAge": 45, "maxAge": 54, "count": 0 },
+            { "minAge": 55, "maxAge": 64, "count": 0 },
+            { "minAge": 65, "maxAge": null, "count": 1 },
+        ])
+    );
+
+    assert_eq!(stats["total"], 4);
+    assert_eq!(stats["withAddress"], 1);
+    assert_eq!(stats["withoutAddress"], 3);
+
+    let days = stats["createdPerDay"].as_array().unwrap();
+    assert_eq!(days.len(), 30);
+    assert_eq!(days[0], json!({ "date": "2020-05-17", "count": 1 }));
+    assert_eq!(days[29], json!({ "date": "2020-06-15", "count": 2 }));
+    assert!(days[1..29].iter().all(|day| day["count"] == 0));
+}
+
+#[tokio::test]
+async fn the_last_30_days_end_today_with_nobody_to_count() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .client()
+        .get("/api/v1/person/stats")
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let stats: Value = response.json();
+    assert_eq!(stats["total"], 0);
+    assert_eq!(stats["withAddress"], 0);
+    assert_eq!(stats["withoutAddress"], 0);
+
+    let bands = stats["ageBands"].as_array().unwrap();
+    assert_eq!(bands.len(), 7);
+    assert!(bands.iter().all(|band| band["count"] == 0));
+    assert_eq!(bands[6]["maxAge"], Value::Null);
+
+    let days = stats["createdPerDay"].as_array().unwrap();
+    let today = OffsetDateTime::now_utc().date();
+    assert_eq!(days.len(), 30);
+    assert_eq!(days[29]["date"], today.to_string());
+    assert_eq!(days[0]["date"], (today - Duration::days(29)).to_string());
+    assert!(days.iter().all(|day| day["count"] == 0));
+}
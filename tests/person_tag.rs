@@ -0,0 +1,131 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{factories::PersonFactory, TestApp};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn people_can_be_tagged_and_untagged() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let tags = format!("/api/v1/person/{}/tags", person.uuid);
+
+    for (tag, expected) in [
+        (" At  Risk ", json!(["at risk"])),
+        ("housing", json!(["at risk", "housing"])),
+        ("AT RISK", json!(["at risk", "housing"])),
+    ] {
+        let response = client
+            .post(&tags)
+            .as_user(&["write"])
+            .json(&json!({ "tag": tag }))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: Value = response.json();
+        assert_eq!(body, expected, "After tagging '{tag}'");
+    }
+
+    let tagged: i64 =
+        sqlx::query_scalar("SELECT count(*) FROM outbox WHERE event_type = 'person.tagged'")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+    assert_eq!(tagged, 2, "Tagging with a tag already had changes nothing");
+
+    let response = client
+        .delete(&format!("{tags}/At%20Risk"))
+        .as_user(&["write"])
+        .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let listed: Value = client.get(&tags).as_user(&["read"]).await.json();
+    assert_eq!(listed, json!(["housing"]));
+
+    let response = client
+        .delete(&format!("{tags}/at%20risk"))
+        .as_user(&["write"])
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn people_can_be_listed_and_counted_by_tag() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let smith = PersonFactory::default()
+        .with_family_name("Smith")
+        .insert(&app.pool)
+        .await;
+    let jones = PersonFactory::default()
+        .with_family_name("Jones")
+        .insert(&app.pool)
+        .await;
+    PersonFactory::default().insert(&app.pool).await;
+
+    for person in [smith.uuid, jones.uuid] {
+        client
+            .post(&format!("/api/v1/person/{person}/tags"))
+            .as_user(&["write"])
+            .json(&json!({ "tag": "cohort a" }))
+            .await;
+    }
+
+    let people: Vec<Value> = client
+        .get("/api/v1/person?tag=Cohort%20A")
+        .as_user(&["read"])
+        .await
+        .json();
+    let ids: Vec<_> = people.iter().map(|person| &person["id"]).collect();
+    assert_eq!(ids, [&json!(smith.uuid), &json!(jones.uuid)]);
+
+    let people: Vec<Value> = client
+        .get("/api/v1/person?tag=cohort%20a&family_name=Jo")
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(people.len(), 1);
+    assert_eq!(people[0]["id"], json!(jones.uuid));
+
+    let count: Value = client
+        .get("/api/v1/person/count?tag=cohort%20a")
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(count["count"], 2);
+
+    let people: Vec<Value> = client
+        .get("/api/v1/person?tag=nobody")
+        .as_user(&["read"])
+        .await
+        .json();
+    assert!(people.is_empty());
+}
+
+#[tokio::test]
+async fn invalid_tags_are_rejected() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    for tag in [json!("  "), json!("a".repeat(65)), json!(42)] {
+        let response = client
+            .post(&format!("/api/v1/person/{}/tags", person.uuid))
+            .as_user(&["write"])
+            .json(&json!({ "tag": tag }))
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{tag}");
+    }
+
+    let response = client
+        .post(&format!("/api/v1/person/{}/tags", Uuid::new_v4()))
+        .as_user(&["write"])
+        .json(&json!({ "tag": "cohort" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client.get("/api/v1/person?tag=").as_user(&["read"]).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use axum::http::{header::LOCATION, StatusCode};
+use common::{auth::CLIENT, factories::PersonFactory, TestApp};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn relationships_are_listed_from_both_sides() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let child = PersonFactory::default().insert(&app.pool).await;
+    let mother = PersonFactory::default().insert(&app.pool).await;
+
+    let response = client
+        .post(&format!("/api/v1/person/{}/relationships", child.uuid))
+        .as_user(&["write"])
+        .json(&json!({ "relatedId": mother.uuid, "type": "parent" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let relationship: Value = response.json();
+    assert_eq!(relationship["personId"], json!(child.uuid));
+    assert_eq!(relationship["relatedId"], json!(mother.uuid));
+    assert_eq!(relationship["type"], "parent");
+    assert_eq!(relationship["createdBy"], CLIENT);
+
+    let response = client
+        .get(response.header(LOCATION))
+        .as_user(&["read"])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<Value>(), relationship);
+
+    for person in [child.uuid, mother.uuid] {
+        let listed: Value = client
+            .get(&format!("/api/v1/person/{person}/relationships"))
+            .as_user(&["read"])
+            .await
+            .json();
+        assert_eq!(listed, json!([relationship]), "As listed for '{person}'");
+    }
+
+    let response = client
+        .delete(&format!(
+            "/api/v1/person/{}/relationships/{}",
+            mother.uuid,
+            relationship["id"].as_str().unwrap()
+        ))
+        .as_user(&["write"])
+        .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let listed: Value = client
+        .get(&format!("/api/v1/person/{}/relationships", child.uuid))
+        .as_user(&["read"])
+        .await
+        .json();
+    assert_eq!(listed, json!([]));
+}
+
+#[tokio::test]
+async fn people_are_only_related_the_same_way_once() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let husband = PersonFactory::default().insert(&app.pool).await;
+    let wife = PersonFactory::default().insert(&app.pool).await;
+
+    for (person, related, expected) in [
+        (husband.uuid, wife.uuid, StatusCode::CREATED),
+        (husband.uuid, wife.uuid, StatusCode::CONFLICT),
+        (wife.uuid, husband.uuid, StatusCode::CONFLICT),
+    ] {
+        let response = client
+            .post(&format!("/api/v1/person/{person}/relationships"))
+            .as_user(&["write"])
+            .json(&json!({ "relatedId": related, "type": "spouse" }))
+            .await;
+        assert_eq!(response.status(), expected);
+    }
+
+    // next of kin goes one way, so each can name the other
+    for (person, related) in [(husband.uuid, wife.uuid), (wife.uuid, husband.uuid)] {
+        let response = client
+            .post(&format!("/api/v1/person/{person}/relationships"))
+            .as_user(&["write"])
+            .json(&json!({ "relatedId": related, "type": "next_of_kin" }))
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+}
+
+#[tokio::test]
+async fn parents_and_guardians_cant_form_a_cycle() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let child = PersonFactory::default().insert(&app.pool).await;
+    let parent = PersonFactory::default().insert(&app.pool).await;
+    let grandparent = PersonFactory::default().insert(&app.pool).await;
+
+    for (person, related) in [(child.uuid, parent.uuid), (parent.uuid, grandparent.uuid)] {
+        let response = client
+            .post(&format!("/api/v1/person/{person}/relationships"))
+            .as_user(&["write"])
+            .json(&json!({ "relatedId": related, "type": "parent" }))
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    let response = client
+        .post(&format!(
+            "/api/v1/person/{}/relationships",
+            grandparent.uuid
+        ))
+        .as_user(&["write"])
+        .json(&json!({ "relatedId": child.uuid, "type": "parent" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = client
+        .post(&format!(
+            "/api/v1/person/{}/relationships",
+            grandparent.uuid
+        ))
+        .as_user(&["write"])
+        .json(&json!({ "relatedId": child.uuid, "type": "next_of_kin" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn invalid_relationships_are_rejected() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let relationships = format!("/api/v1/person/{}/relationships", person.uuid);
+
+    for body in [
+        json!({ "relatedId": person.uuid, "type": "sibling" }),
+        json!({ "relatedId": Uuid::new_v4(), "type": "cousin" }),
+        json!({ "type": "sibling" }),
+    ] {
+        let response = client
+            .post(&relationships)
+            .as_user(&["write"])
+            .json(&body)
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{body}");
+    }
+
+    let response = client
+        .post(&relationships)
+        .as_user(&["write"])
+        .json(&json!({ "relatedId": Uuid::new_v4(), "type": "sibling" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client
+        .delete(&format!("{relationships}/{}", Uuid::new_v4()))
+        .as_user(&["write"])
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
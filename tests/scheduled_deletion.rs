@@ -0,0 +1,125 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{factories::PersonFactory, TestApp};
+use rust_web_app::jobs::{self, Job};
+use serde_json::{json, Value};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn deletions_can_be_scheduled_and_cancelled() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+    let uri = format!("/api/v1/person/{}/schedule-deletion", person.uuid);
+
+    let response = client
+        .post(&uri)
+        .as_user(&["write"])
+        .json(&json!({ "deleteAt": "2100-01-01T00:00:00Z" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let scheduled: Value = client.get(&uri).as_user(&["read"]).await.json();
+    assert_eq!(scheduled["deleteAt"], "2100-01-01T00:00:00Z");
+
+    let response = client.delete(&uri).as_user(&["write"]).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client.get(&uri).as_user(&["read"]).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client.delete(&uri).as_user(&["write"]).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn deletions_must_be_scheduled_in_the_future() {
+    let app = TestApp::new().await;
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    let response = app
+        .client()
+        .post(&format!("/api/v1/person/{}/schedule-deletion", person.uuid))
+        .as_user(&["write"])
+        .json(&json!({ "deleteAt": "2000-01-01T00:00:00Z" }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn people_under_legal_hold_cannot_be_scheduled_for_deletion() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let person = PersonFactory::default().insert(&app.pool).await;
+
+    client
+        .put(&format!("/api/v1/admin/person/{}/legal-hold", person.uuid))
+        .as_user(&["admin"])
+        .json(&json!({ "reason": "Case 1234" }))
+        .await;
+
+    let response = client
+        .post(&format!("/api/v1/person/{}/schedule-deletion", person.uuid))
+        .as_user(&["write"])
+        .json(&json!({ "deleteAt": "2100-01-01T00:00:00Z" }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::LOCKED);
+}
+
+#[tokio::test]
+async fn due_deletions_are_carried_out_in_the_background() {
+    let app = TestApp::new().await;
+    let client = app.client();
+    let past = OffsetDateTime::now_utc() - Duration::minutes(1);
+    let due = PersonFactory::default()
+        .with_delete_at(past)
+        .insert(&app.pool)
+        .await;
+    let held = PersonFactory::default()
+        .with_delete_at(past)
+        .insert(&app.pool)
+        .await;
+    let later = PersonFactory::default().insert(&app.pool).await;
+
+    sqlx::query("UPDATE person SET legal_hold = true WHERE uuid = $1")
+        .bind(held.uuid)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    client
+        .post(&format!("/api/v1/person/{}/schedule-deletion", later.uuid))
+        .as_user(&["write"])
+        .json(&json!({ "deleteAt": "2100-01-01T00:00:00Z" }))
+        .await;
+
+    jobs::enqueue(&app.pool, &Job::DeleteDuePeople)
+        .await
+        .unwrap();
+    assert!(jobs::run_next(&app.pool).await.unwrap(), "Job should run");
+
+    let status_of = |uuid: Uuid| {
+        let client = &client;
+        async move {
+            client
+                .get(&format!("/api/v1/person/{uuid}"))
+                .as_user(&["read"])
+                .await
+                .status()
+        }
+    };
+    assert_eq!(status_of(due.uuid).await, StatusCode::NOT_FOUND);
+    assert_eq!(
+        status_of(held.uuid).await,
+        StatusCode::OK,
+        "People under legal hold should be kept"
+    );
+    assert_eq!(
+        status_of(later.uuid).await,
+        StatusCode::OK,
+        "People not yet due should be kept"
+    );
+}
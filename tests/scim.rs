@@ -0,0 +1,231 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE, IF_NONE_MATCH, LOCATION},
+        Request, StatusCode,
+    },
+    response::Response,
+};
+use common::{auth::token, json_body, TestApp};
+use serde_json::{json, Value};
+
+const EXTENSION: &str = "urn:ietf:params:scim:schemas:extension:rust-web-app:2.0:User";
+
+async fn scim(app: &TestApp, method: &str, uri: &str, body: Option<Value>) -> Response {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(
+            AUTHORIZATION,
+            format!("Bearer {}", token(&["read", "write"])),
+        )
+        .header(CONTENT_TYPE, "application/scim+json");
+
+    let body = body.map_or(Body::empty(), |b| Body::from(b.to_string()));
+    app.request(request.body(body).unwrap()).await
+}
+
+async fn provision(app: &TestApp, user_name: &str, family_name: &str) -> Value {
+    let response = scim(
+        app,
+        "POST",
+        "/scim/v2/Users",
+        Some(json!({
+            "schemas": ["urn:ietf:params:scim:schemas:core:2.0:User", EXTENSION],
+            "userName": user_name,
+            "externalId": format!("idp-{user_name}"),
+            "name": { "givenName": "Jane", "familyName": family_name },
+            EXTENSION: { "dateOfBirth": "1990-01-01" },
+        })),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+    json_body(response).await
+}
+
+#[tokio::test]
+async fn users_can_be_provisioned_and_retrieved() {
+    let app = TestApp::new().await;
+
+    let response = scim(
+        &app,
+        "POST",
+        "/scim/v2/Users",
+        Some(json!({
+            "userName": "jdoe@example.com",
+            "name": { "givenName": "Jane", "familyName": "Doe" },
+            EXTENSION: { "dateOfBirth": "1990-01-01" },
+        })),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(
+        response.headers()[CONTENT_TYPE],
+        "application/scim+json",
+        "SCIM responses should use the SCIM media type"
+    );
+    let location = response.headers()[LOCATION].to_str().unwrap().to_owned();
+
+    let created: Value = json_body(response).await;
+    assert_eq!(created["userName"], "jdoe@example.com");
+    assert_eq!(created["meta"]["resourceType"], "User");
+    assert_eq!(created["meta"]["location"], location);
+
+    let response = scim(&app, "GET", &location, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let fetched: Value = json_body(response).await;
+    assert_eq!(fetched, created);
+
+    // people are visible through the REST API too
+    let id = created["id"].as_str().unwrap();
+    let response = app
+        .request(
+            Request::get(format!("/api/v1/person/{id}"))
+                .header(AUTHORIZATION, format!("Bearer {}", token(&["read"])))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn user_names_must_be_unique() {
+    let app = TestApp::new().await;
+    provision(&app, "jdoe", "Doe").await;
+
+    let response = scim(
+        &app,
+        "POST",
+        "/scim/v2/Users",
+        Some(json!({
+            "userName": "JDOE",
+            "name": { "givenName": "John", "familyName": "Doe" },
+            EXTENSION: { "dateOfBirth": "1990-01-01" },
+        })),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let error: Value = json_body(response).await;
+    assert_eq!(
+        error["schemas"][0],
+        "urn:ietf:params:scim:api:messages:2.0:Error"
+    );
+    assert_eq!(error["status"], "409");
+    assert_eq!(error["scimType"], "uniqueness");
+}
+
+#[tokio::test]
+async fn create_only_retries_fail_their_precondition() {
+    let app = TestApp::new().await;
+
+    let user = json!({
+        "userName": "jdoe",
+        "externalId": "idp-jdoe",
+        "name": { "givenName": "Jane", "familyName": "Doe" },
+        EXTENSION: { "dateOfBirth": "1990-01-01" },
+    });
+    let create = || {
+        app.client()
+            .post("/scim/v2/Users")
+            .as_user(&["write"])
+            .header(IF_NONE_MATCH, "*")
+            .json(&user)
+    };
+
+    assert_eq!(create().await.status(), StatusCode::CREATED);
+
+    let retry = create().await;
+    assert_eq!(retry.status(), StatusCode::PRECONDITION_FAILED);
+
+    let error: Value = retry.json();
+    assert_eq!(error["status"], "412");
+    assert!(error.get("scimType").is_none_or(Value::is_null));
+}
+
+#[tokio::test]
+async fn users_can_be_filtered() {
+    let app = TestApp::new().await;
+    provision(&app, "jdoe", "Doe").await;
+    provision(&app, "jsmith", "Smith").await;
+
+    let response = scim(
+        &app,
+        "GET",
+        "/scim/v2/Users?filter=userName%20eq%20%22JSmith%22",
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let list: Value = json_body(response).await;
+    assert_eq!(list["totalResults"], 1);
+    assert_eq!(list["Resources"][0]["userName"], "jsmith");
+
+    let response = scim(
+        &app,
+        "GET",
+        "/scim/v2/Users?filter=title%20eq%20%22Manager%22",
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let error: Value = json_body(response).await;
+    assert_eq!(error["scimType"], "invalidFilter");
+}
+
+#[tokio::test]
+async fn users_can_be_patched_and_deprovisioned() {
+    let app = TestApp::new().await;
+    let user = provision(&app, "jdoe", "Doe").await;
+    let location = format!("/scim/v2/Users/{}", user["id"].as_str().unwrap());
+
+    let response = scim(
+        &app,
+        "PATCH",
+        &location,
+        Some(json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+            "Operations": [
+                { "op": "Replace", "path": "name.familyName", "value": "Smith" },
+                { "op": "replace", "value": { "userName": "jsmith" } },
+                { "op": "remove", "path": "externalId" },
+            ],
+        })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let patched: Value = json_body(response).await;
+    assert_eq!(patched["name"]["familyName"], "Smith");
+    assert_eq!(patched["userName"], "jsmith");
+    assert!(patched.get("externalId").is_none());
+
+    let response = scim(
+        &app,
+        "PATCH",
+        &location,
+        Some(json!({
+            "Operations": [{ "op": "remove", "path": "userName" }],
+        })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = scim(&app, "DELETE", &location, None).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = scim(&app, "GET", &location, None).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let error: Value = json_body(response).await;
+    assert_eq!(error["status"], "404");
+}
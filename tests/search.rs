@@ -0,0 +1,103 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{
+        header::{AUTHORIZATION, IF_MATCH},
+        request::Builder,
+        Request, StatusCode,
+    },
+};
+use common::{
+    auth::token,
+    json_body,
+    search::{mock_elasticsearch, mock_search},
+    TestApp,
+};
+use rust_web_app::search::{self, SearchIndex};
+use serde_json::{json, Value};
+
+fn authorized(method: &str, uri: &str, scopes: &[&str]) -> Builder {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(AUTHORIZATION, format!("Bearer {}", token(scopes)))
+}
+
+async fn create_person(app: &TestApp, first_name: &str, family_name: &str) -> Value {
+    let response = app
+        .request(
+            authorized("POST", "/api/v1/person", &["write"])
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "first_name": first_name,
+                        "family_name": family_name,
+                        "date_of_birth": "1990-01-01",
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await;
+
+    json_body(response).await
+}
+
+#[tokio::test]
+async fn search_returns_matches_from_the_index() {
+    mock_elasticsearch().await;
+    let app = TestApp::new().await;
+
+    let person = create_person(&app, "John", "Smith").await;
+    mock_search("Jon Smtih", vec![person.clone()]).await;
+
+    let response = app
+        .request(
+            authorized("GET", "/api/v1/person/search?q=Jon%20Smtih", &["read"])
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = json_body(response).await;
+    assert_eq!(body, json!([person]));
+}
+
+#[tokio::test]
+async fn changes_to_people_are_indexed_in_order() {
+    let server = mock_elasticsearch().await;
+    let app = TestApp::new().await;
+
+    let person = create_person(&app, "Ada", "Lovelace").await;
+    let id = person["id"].as_str().unwrap();
+
+    app.request(
+        authorized("DELETE", &format!("/api/v1/person/{id}"), &["write"])
+            .header(IF_MATCH, r#""1""#)
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+
+    let SearchIndex::Elasticsearch(index) = SearchIndex::from_env(reqwest::Client::new()) else {
+        panic!("Search should be enabled");
+    };
+
+    assert_eq!(search::index_batch(&app.pool, &index).await.unwrap(), 2);
+    assert_eq!(search::index_batch(&app.pool, &index).await.unwrap(), 0);
+
+    let document = format!("/people/_doc/{id}");
+    let writes: Vec<_> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|r| r.url.path() == document)
+        .map(|r| r.method.to_string())
+        .collect();
+
+    assert_eq!(writes, ["PUT", "DELETE"]);
+}
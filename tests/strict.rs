@@ -0,0 +1,64 @@
+mod common;
+
+use std::env;
+
+use axum::http::StatusCode;
+use common::{json_body, TestApp};
+use serde_json::{json, Value};
+
+/// Every test in this binary runs in strict mode, as it's read once per process
+async fn strict_app() -> TestApp {
+    env::set_var("STRICT_REQUESTS", "true");
+    TestApp::new().await
+}
+
+#[tokio::test]
+async fn unknown_fields_are_rejected() {
+    let app = strict_app().await;
+
+    let response = app
+        .client()
+        .post("/api/v1/person")
+        .as_user(&["write"])
+        .json(&json!({
+            "firstName": "Ada",
+            "familyname": "Lovelace",
+            "dateOfBirth": "1815-12-10"
+        }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let error: Value = response.json();
+    assert_eq!(error["message"], "Unknown field(s): familyname");
+}
+
+#[tokio::test]
+async fn known_fields_and_their_aliases_are_accepted() {
+    let app = strict_app().await;
+
+    let response = app
+        .client()
+        .post("/api/v1/person")
+        .as_user(&["write"])
+        .json(&json!({
+            "first_name": "Ada",
+            "familyName": "Lovelace",
+            "dateOfBirth": "1815-12-10"
+        }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn request_schemas_do_not_allow_other_properties() {
+    let app = strict_app().await;
+
+    let document: Value = json_body(app.get("/api-doc/v1/openapi.json").await).await;
+
+    assert_eq!(
+        document["components"]["schemas"]["NewPerson"]["additionalProperties"],
+        false
+    );
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use axum::http::{header::RETRY_AFTER, HeaderName, StatusCode};
+use common::TestApp;
+use serde_json::{json, Value};
+
+const API_KEY: HeaderName = HeaderName::from_static("x-api-key");
+
+#[tokio::test]
+async fn requests_are_counted_per_client_per_day() {
+    let app = TestApp::new().await;
+    let client = app.client();
+
+    let created: Value = client
+        .post("/api/v1/admin/clients")
+        .as_user(&["admin"])
+        .json(&json!({ "name": "reporting", "scopes": ["read"] }))
+        .await
+        .json();
+    let key = created["key"].as_str().unwrap();
+
+    for _ in 0..3 {
+        let response = client.get("/api/v1/person").header(API_KEY, key).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let usage: Value = client
+        .get("/api/v1/admin/usage?client=reporting")
+        .as_user(&["admin"])
+        .await
+        .json();
+
+    assert_eq!(usage.as_array().unwrap().len(), 1);
+    assert_eq!(usage[0]["client"], "reporting");
+    assert_eq!(usage[0]["requests"], 3);
+}
+
+#[tokio::test]
+async fn clients_over_their_quota_are_refused_until_tomorrow() {
+    let app = TestApp::new().await;
+    let client = app.client();
+
+    let created: Value = client
+        .post("/api/v1/admin/clients")
+        .as_user(&["admin"])
+        .json(&json!({ "name": "limited", "scopes": ["read"], "dailyQuota": 2 }))
+        .await
+        .json();
+    assert_eq!(created["dailyQuota"], 2);
+    let key = created["key"].as_str().unwrap();
+
+    for _ in 0..2 {
+        let response = client.get("/api/v1/person").header(API_KEY, key).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let refused = client.get("/api/v1/person").header(API_KEY, key).await;
+    assert_eq!(refused.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(refused.header(RETRY_AFTER).parse::<u64>().unwrap() <= 24 * 60 * 60);
+
+    // refused requests aren't counted
+    let usage: Value = client
+        .get("/api/v1/admin/usage?client=limited")
+        .as_user(&["admin"])
+        .await
+        .json();
+    assert_eq!(usage[0]["requests"], 2);
+}
+
+#[tokio::test]
+async fn usage_requires_the_admin_scope() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .client()
+        .get("/api/v1/admin/usage")
+        .as_user(&["read"])
+        .await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}